@@ -0,0 +1,294 @@
+package bot
+
+import (
+	"context"
+	"encoding/json"
+	"fmt"
+	"net/http"
+	"net/http/httptest"
+	"strings"
+	"testing"
+)
+
+func TestCallAnthropicParsesFirstContentBlock(t *testing.T) {
+	client := &http.Client{Transport: roundTripFunc(func(req *http.Request) (*http.Response, error) {
+		if req.URL.String() != "https://api.anthropic.com/v1/messages" {
+			t.Fatalf("unexpected url: %s", req.URL)
+		}
+		if req.Header.Get("x-api-key") != "key" || req.Header.Get("anthropic-version") != "2023-06-01" {
+			t.Fatalf("unexpected headers: %v", req.Header)
+		}
+		return jsonResponse(http.StatusOK, `{"id":"msg_1","type":"message","role":"assistant","content":[{"type":"text","text":"  🚀 New coins  "}],"stop_reason":"end_turn"}`), nil
+	})}
+	cfg := Config{AIModel: "claude-3-5-haiku-20241022", AnthropicAPIKey: "key"}
+
+	text, err := callAnthropic(context.Background(), client, cfg, "prompt")
+	if err != nil {
+		t.Fatalf("callAnthropic error: %v", err)
+	}
+	if text != "🚀 New coins" {
+		t.Fatalf("unexpected text: %q", text)
+	}
+}
+
+func TestCallAnthropicReturnsErrorPayload(t *testing.T) {
+	client := &http.Client{Transport: roundTripFunc(func(req *http.Request) (*http.Response, error) {
+		return jsonResponse(http.StatusBadRequest, `{"type":"error","error":{"type":"invalid_request_error","message":"max_tokens: field required"}}`), nil
+	})}
+	cfg := Config{AIModel: "claude-3-5-haiku-20241022", AnthropicAPIKey: "key"}
+
+	_, err := callAnthropic(context.Background(), client, cfg, "prompt")
+	if err == nil || !strings.Contains(err.Error(), "invalid_request_error: max_tokens: field required") {
+		t.Fatalf("expected parsed anthropic error, got %v", err)
+	}
+}
+
+func TestCallOpenAIParsesFirstChoice(t *testing.T) {
+	client := &http.Client{Transport: roundTripFunc(func(req *http.Request) (*http.Response, error) {
+		if req.URL.String() != "https://api.openai.com/v1/chat/completions" {
+			t.Fatalf("unexpected url: %s", req.URL)
+		}
+		if req.Header.Get("Authorization") != "Bearer key" {
+			t.Fatalf("unexpected headers: %v", req.Header)
+		}
+		return jsonResponse(http.StatusOK, `{"id":"chatcmpl-1","object":"chat.completion","choices":[{"index":0,"message":{"role":"assistant","content":"  🚀 New coins  "},"finish_reason":"stop"}],"usage":{"prompt_tokens":12,"completion_tokens":3,"total_tokens":15}}`), nil
+	})}
+	cfg := Config{AIModel: "gpt-4o-mini", OpenAIAPIKey: "key"}
+
+	text, err := callOpenAI(context.Background(), client, cfg, "prompt")
+	if err != nil {
+		t.Fatalf("callOpenAI error: %v", err)
+	}
+	if text != "🚀 New coins" {
+		t.Fatalf("unexpected text: %q", text)
+	}
+}
+
+func TestCallOpenAIRetriesRateLimit(t *testing.T) {
+	calls := 0
+	client := &http.Client{Transport: roundTripFunc(func(req *http.Request) (*http.Response, error) {
+		calls++
+		if calls == 1 {
+			return jsonResponse(http.StatusTooManyRequests, `{"error":{"message":"Rate limit reached","type":"requests"}}`), nil
+		}
+		return jsonResponse(http.StatusOK, `{"choices":[{"index":0,"message":{"role":"assistant","content":"🚀 New coins"},"finish_reason":"stop"}]}`), nil
+	})}
+	cfg := Config{AIModel: "gpt-4o-mini", OpenAIAPIKey: "key", CMCMaxRetries: 2}
+
+	text, err := callOpenAI(context.Background(), client, cfg, "prompt")
+	if err != nil {
+		t.Fatalf("callOpenAI error: %v", err)
+	}
+	if text != "🚀 New coins" || calls != 2 {
+		t.Fatalf("expected the 429 to be retried once, got %q after %d calls", text, calls)
+	}
+}
+
+func TestProduceTelegramTextFallsBackWhenOpenAIFails(t *testing.T) {
+	client := &http.Client{Transport: roundTripFunc(func(req *http.Request) (*http.Response, error) {
+		return jsonResponse(http.StatusUnauthorized, `{"error":{"message":"Incorrect API key provided","type":"invalid_request_error"}}`), nil
+	})}
+	cfg := Config{AIEnabled: true, AIProvider: "openai", AIModel: "gpt-4o-mini", OpenAIAPIKey: "bad"}
+
+	gen, err := produceTelegramText(context.Background(), client, cfg, map[string]any{"top_n": 100, "new_coins": []Coin{{Name: "Sui", Symbol: "SUI", Rank: 42}}})
+	if err != nil {
+		t.Fatalf("produceTelegramText error: %v", err)
+	}
+	if !strings.Contains(gen.Text, "SUI") {
+		t.Fatalf("expected fallback template output, got %q", gen.Text)
+	}
+}
+
+func TestCallOllamaReadsResponseField(t *testing.T) {
+	srv := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		if r.URL.Path != "/api/generate" {
+			t.Errorf("unexpected request to %s", r.URL)
+		}
+		var body map[string]any
+		if err := json.NewDecoder(r.Body).Decode(&body); err != nil || body["model"] != "llama3.2" || body["prompt"] != "prompt" || body["stream"] != false {
+			t.Errorf("unexpected body: %v (%v)", body, err)
+		}
+		fmt.Fprint(w, `{"model":"llama3.2","created_at":"2026-01-15T10:00:00Z","response":"  🚀 New coins  ","done":true}`)
+	}))
+	defer srv.Close()
+	cfg := Config{AIModel: "llama3.2", OllamaBaseURL: srv.URL}
+
+	text, err := callOllama(context.Background(), srv.Client(), cfg, "prompt")
+	if err != nil {
+		t.Fatalf("callOllama error: %v", err)
+	}
+	if text != "🚀 New coins" {
+		t.Fatalf("unexpected text: %q", text)
+	}
+}
+
+func TestProduceTelegramTextFallsBackWhenOllamaFails(t *testing.T) {
+	srv := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		http.Error(w, `{"error":"model 'llama3.2' not found"}`, http.StatusNotFound)
+	}))
+	defer srv.Close()
+	cfg := Config{AIEnabled: true, AIProvider: "ollama", AIModel: "llama3.2", OllamaBaseURL: srv.URL}
+
+	gen, err := produceTelegramText(context.Background(), srv.Client(), cfg, map[string]any{"top_n": 100, "new_coins": []Coin{{Name: "Sui", Symbol: "SUI", Rank: 42}}})
+	if err != nil {
+		t.Fatalf("produceTelegramText error: %v", err)
+	}
+	if !strings.Contains(gen.Text, "SUI") {
+		t.Fatalf("expected fallback template output, got %q", gen.Text)
+	}
+}
+
+func TestCallGeminiSendsGenerationConfigFromEnv(t *testing.T) {
+	t.Setenv("CMC_API_KEY", "key")
+	t.Setenv("GEMINI_API_KEY", "gem")
+	t.Setenv("AI_TEMPERATURE", "0.3")
+	t.Setenv("AI_MAX_TOKENS", "512")
+	cfg, err := ConfigFromEnv(true, true)
+	if err != nil {
+		t.Fatalf("ConfigFromEnv error: %v", err)
+	}
+
+	var sent map[string]any
+	client := &http.Client{Transport: roundTripFunc(func(req *http.Request) (*http.Response, error) {
+		if err := json.NewDecoder(req.Body).Decode(&sent); err != nil {
+			t.Fatalf("decode request body: %v", err)
+		}
+		return jsonResponse(http.StatusOK, `{"candidates":[{"content":{"parts":[{"text":"ok"}]}}]}`), nil
+	})}
+	if _, err := callGemini(context.Background(), client, cfg, "prompt"); err != nil {
+		t.Fatalf("callGemini error: %v", err)
+	}
+	gen, ok := sent["generationConfig"].(map[string]any)
+	if !ok || gen["temperature"] != 0.3 || gen["maxOutputTokens"] != float64(512) {
+		t.Fatalf("unexpected generationConfig: %v", sent["generationConfig"])
+	}
+
+	if _, err := callGemini(context.Background(), client, Config{AIModel: "gemini-3-flash-preview", GeminiAPIKey: "gem"}, "prompt"); err != nil {
+		t.Fatalf("callGemini error: %v", err)
+	}
+	if _, ok := sent["generationConfig"]; ok {
+		t.Fatalf("generationConfig should be omitted when neither env var is set: %v", sent)
+	}
+}
+
+func TestCallGeminiRetriesMaxTokensWithHigherLimit(t *testing.T) {
+	var limits []any
+	client := &http.Client{Transport: roundTripFunc(func(req *http.Request) (*http.Response, error) {
+		var sent map[string]any
+		_ = json.NewDecoder(req.Body).Decode(&sent)
+		gen, _ := sent["generationConfig"].(map[string]any)
+		limits = append(limits, gen["maxOutputTokens"])
+		if len(limits) == 1 {
+			return jsonResponse(http.StatusOK, `{"candidates":[{"content":{"parts":[{"text":"🚀 New coins: BTC, E"}]},"finishReason":"MAX_TOKENS"}]}`), nil
+		}
+		return jsonResponse(http.StatusOK, `{"candidates":[{"content":{"parts":[{"text":"🚀 New coins: BTC, ETH"}]},"finishReason":"STOP"}]}`), nil
+	})}
+	cfg := Config{AIModel: "gemini-3-flash-preview", GeminiAPIKey: "gem", AIMaxTokens: 256}
+
+	text, err := callGemini(context.Background(), client, cfg, "prompt")
+	if err != nil {
+		t.Fatalf("callGemini error: %v", err)
+	}
+	if text != "🚀 New coins: BTC, ETH" || len(limits) != 2 || limits[0] != float64(256) || limits[1] != float64(512) {
+		t.Fatalf("expected one retry with a doubled limit, got text=%q limits=%v", text, limits)
+	}
+}
+
+func TestCallGeminiDoesNotRetrySafetyBlock(t *testing.T) {
+	calls := 0
+	client := &http.Client{Transport: roundTripFunc(func(req *http.Request) (*http.Response, error) {
+		calls++
+		return jsonResponse(http.StatusOK, `{"candidates":[{"finishReason":"SAFETY","safetyRatings":[{"category":"HARM_CATEGORY_DANGEROUS_CONTENT","probability":"HIGH"}]}]}`), nil
+	})}
+	cfg := Config{AIModel: "gemini-3-flash-preview", GeminiAPIKey: "gem"}
+
+	if _, err := callGemini(context.Background(), client, cfg, "prompt"); err == nil || !strings.Contains(err.Error(), "SAFETY") {
+		t.Fatalf("expected a safety error, got %v", err)
+	}
+	if calls != 1 {
+		t.Fatalf("a safety block should not be retried, got %d calls", calls)
+	}
+}
+
+func TestCallGeminiRetriesEmptyAnswerOnce(t *testing.T) {
+	calls := 0
+	client := &http.Client{Transport: roundTripFunc(func(req *http.Request) (*http.Response, error) {
+		calls++
+		return jsonResponse(http.StatusOK, `{"candidates":[{"content":{"parts":[]},"finishReason":"OTHER"}]}`), nil
+	})}
+	cfg := Config{AIModel: "gemini-3-flash-preview", GeminiAPIKey: "gem"}
+
+	if _, err := callGemini(context.Background(), client, cfg, "prompt"); err == nil {
+		t.Fatalf("expected an error after two empty answers")
+	}
+	if calls != 2 {
+		t.Fatalf("an empty answer should be retried once, got %d calls", calls)
+	}
+}
+
+func TestProduceTelegramTextReportsSource(t *testing.T) {
+	renderCtx := map[string]any{"top_n": 100, "new_coins": []Coin{{Name: "Sui", Symbol: "SUI", Rank: 42}}}
+	openAIReply := func(status int, body string) *http.Client {
+		return &http.Client{Transport: roundTripFunc(func(req *http.Request) (*http.Response, error) {
+			return jsonResponse(status, body), nil
+		})}
+	}
+	for _, tc := range []struct {
+		name   string
+		cfg    Config
+		client *http.Client
+		want   TextSource
+	}{
+		{"ai success", Config{AIEnabled: true, AIProvider: "openai", AIModel: "gpt-4o-mini", OpenAIAPIKey: "key"},
+			openAIReply(http.StatusOK, `{"choices":[{"index":0,"message":{"role":"assistant","content":"🚀 SUI joins the Top 100"}}]}`), TextSourceAI},
+		{"ai empty", Config{AIEnabled: true, AIProvider: "openai", AIModel: "gpt-4o-mini", OpenAIAPIKey: "key"},
+			openAIReply(http.StatusOK, `{"choices":[{"index":0,"message":{"role":"assistant","content":"  "}}]}`), TextSourceFallback},
+		{"ai error", Config{AIEnabled: true, AIProvider: "openai", AIModel: "gpt-4o-mini", OpenAIAPIKey: "key"},
+			openAIReply(http.StatusUnauthorized, `{"error":{"message":"Incorrect API key provided","type":"invalid_request_error"}}`), TextSourceFallback},
+		{"ai disabled", Config{AIProvider: "openai", AIModel: "gpt-4o-mini", OpenAIAPIKey: "key"},
+			&http.Client{Transport: roundTripFunc(func(req *http.Request) (*http.Response, error) {
+				t.Fatalf("unexpected request with AI disabled: %s", req.URL)
+				return nil, nil
+			})}, TextSourceFallback},
+	} {
+		gen, err := produceTelegramText(context.Background(), tc.client, tc.cfg, renderCtx)
+		if err != nil {
+			t.Fatalf("%s: produceTelegramText error: %v", tc.name, err)
+		}
+		if gen.Source != tc.want || gen.Text == "" {
+			t.Fatalf("%s: got source %q and text %q, want source %q", tc.name, gen.Source, gen.Text, tc.want)
+		}
+	}
+}
+
+func TestProduceTelegramTextWalksModelChain(t *testing.T) {
+	var tried []string
+	client := &http.Client{Transport: roundTripFunc(func(req *http.Request) (*http.Response, error) {
+		var payload struct {
+			Model string `json:"model"`
+		}
+		if err := json.NewDecoder(req.Body).Decode(&payload); err != nil {
+			t.Fatalf("decode request: %v", err)
+		}
+		tried = append(tried, payload.Model)
+		switch payload.Model {
+		case "gpt-4o":
+			return jsonResponse(http.StatusUnauthorized, `{"error":{"message":"model unavailable","type":"invalid_request_error"}}`), nil
+		case "gpt-4o-mini":
+			return jsonResponse(http.StatusOK, `{"choices":[{"index":0,"message":{"role":"assistant","content":"  "}}]}`), nil
+		}
+		return jsonResponse(http.StatusOK, `{"choices":[{"index":0,"message":{"role":"assistant","content":"🚀 SUI joins the Top 100"}}]}`), nil
+	})}
+	cfg := Config{AIEnabled: true, AIProvider: "openai", AIModel: "gpt-4o", AIModelChain: []string{"gpt-4o", "gpt-4o-mini", "gpt-3.5-turbo", "unused"}, OpenAIAPIKey: "key"}
+
+	gen, err := produceTelegramText(context.Background(), client, cfg, map[string]any{"top_n": 100, "new_coins": []Coin{{Name: "Sui", Symbol: "SUI", Rank: 42}}})
+	if err != nil {
+		t.Fatalf("produceTelegramText error: %v", err)
+	}
+	if gen.Source != TextSourceAI || gen.Model != "gpt-3.5-turbo" || gen.Text != "🚀 SUI joins the Top 100" {
+		t.Fatalf("unexpected result: %+v", gen)
+	}
+	if strings.Join(tried, ",") != "gpt-4o,gpt-4o-mini,gpt-3.5-turbo" {
+		t.Fatalf("unexpected attempts: %v", tried)
+	}
+}
@@ -0,0 +1,51 @@
+package bot
+
+import (
+	"context"
+	"time"
+)
+
+// ATHRecord is the highest market cap seen for a coin so far.
+type ATHRecord struct {
+	ID           int64
+	MaxMarketCap float64
+	AchievedAt   time.Time
+}
+
+// ATHCoin is a current coin whose market cap beat its stored record.
+type ATHCoin struct {
+	Coin
+	PrevMaxMarketCap float64   `json:"prev_max_market_cap"`
+	PrevAchievedAt   time.Time `json:"prev_achieved_at"`
+}
+
+// detectATHs compares current market caps with the stored records and returns
+// the coins that set a new high plus the records to upsert. A coin without a
+// record only gets a baseline; announcing it would flag every coin on the
+// first run.
+func detectATHs(current []Coin, records map[int64]ATHRecord, now time.Time) ([]ATHCoin, []ATHRecord) {
+	athCoins := []ATHCoin{}
+	updates := []ATHRecord{}
+	for _, c := range current {
+		if c.MarketCap == nil || *c.MarketCap <= 0 {
+			continue
+		}
+		rec, ok := records[c.ID]
+		switch {
+		case !ok:
+			updates = append(updates, ATHRecord{ID: c.ID, MaxMarketCap: *c.MarketCap, AchievedAt: now})
+		case *c.MarketCap > rec.MaxMarketCap:
+			athCoins = append(athCoins, ATHCoin{Coin: c, PrevMaxMarketCap: rec.MaxMarketCap, PrevAchievedAt: rec.AchievedAt})
+			updates = append(updates, ATHRecord{ID: c.ID, MaxMarketCap: *c.MarketCap, AchievedAt: now})
+		}
+	}
+	return athCoins, updates
+}
+
+// saveATHs upserts changed records; it is a no-op when nothing changed.
+func saveATHs(ctx context.Context, store StateStore, updates []ATHRecord) error {
+	if len(updates) == 0 {
+		return nil
+	}
+	return store.UpsertATHs(ctx, updates)
+}
@@ -0,0 +1,66 @@
+package bot
+
+import (
+	"context"
+	"testing"
+	"time"
+)
+
+func TestDetectATHsAlertsOnlyOnBrokenRecords(t *testing.T) {
+	mcap := func(v float64) *float64 { return &v }
+	before := time.Date(2025, 11, 1, 0, 0, 0, 0, time.UTC)
+	now := time.Date(2026, 1, 15, 10, 0, 0, 0, time.UTC)
+	records := map[int64]ATHRecord{
+		1: {ID: 1, MaxMarketCap: 1000, AchievedAt: before},
+		2: {ID: 2, MaxMarketCap: 500, AchievedAt: before},
+	}
+	current := []Coin{
+		{ID: 1, Symbol: "BTC", MarketCap: mcap(1200)},
+		{ID: 2, Symbol: "ETH", MarketCap: mcap(400)},
+		{ID: 3, Symbol: "NEW", MarketCap: mcap(50)},
+		{ID: 4, Symbol: "NOCAP"},
+	}
+
+	athCoins, updates := detectATHs(current, records, now)
+	if len(athCoins) != 1 || athCoins[0].ID != 1 || athCoins[0].PrevMaxMarketCap != 1000 || !athCoins[0].PrevAchievedAt.Equal(before) {
+		t.Fatalf("unexpected ath coins: %+v", athCoins)
+	}
+	want := []ATHRecord{{ID: 1, MaxMarketCap: 1200, AchievedAt: now}, {ID: 3, MaxMarketCap: 50, AchievedAt: now}}
+	if len(updates) != len(want) {
+		t.Fatalf("unexpected updates: %+v", updates)
+	}
+	for i := range want {
+		if updates[i] != want[i] {
+			t.Fatalf("update %d: got %+v want %+v", i, updates[i], want[i])
+		}
+	}
+}
+
+// checkUpsertATHs inserts records, overwrites one of them and checks that
+// LoadATHs returns the latest value of each requested id only.
+func checkUpsertATHs(t *testing.T, store StateStore) {
+	t.Helper()
+	ctx := context.Background()
+	first := time.Date(2026, 1, 1, 0, 0, 0, 0, time.UTC)
+	if err := store.UpsertATHs(ctx, []ATHRecord{{ID: 1, MaxMarketCap: 1000, AchievedAt: first}, {ID: 2, MaxMarketCap: 500, AchievedAt: first}}); err != nil {
+		t.Fatalf("UpsertATHs insert error: %v", err)
+	}
+	later := first.Add(48 * time.Hour)
+	if err := store.UpsertATHs(ctx, []ATHRecord{{ID: 1, MaxMarketCap: 1200, AchievedAt: later}}); err != nil {
+		t.Fatalf("UpsertATHs update error: %v", err)
+	}
+	got, err := store.LoadATHs(ctx, []int64{1, 2, 3})
+	if err != nil {
+		t.Fatalf("LoadATHs error: %v", err)
+	}
+	if len(got) != 2 || got[1].MaxMarketCap != 1200 || !got[1].AchievedAt.Equal(later) || got[2].MaxMarketCap != 500 || !got[2].AchievedAt.Equal(first) {
+		t.Fatalf("unexpected records after update: %+v", got)
+	}
+	if got, err := store.LoadATHs(ctx, []int64{2}); err != nil || len(got) != 1 || got[2].ID != 2 {
+		t.Fatalf("expected only the requested id, got %+v (%v)", got, err)
+	}
+}
+
+func TestUpsertATHsInsertsThenUpdates(t *testing.T) {
+	checkUpsertATHs(t, &memoryStore{})
+}
@@ -1,6 +1,7 @@
 package bot
 
 import (
+	"bytes"
 	"context"
 	"encoding/json"
 	"errors"
@@ -8,56 +9,311 @@ import (
 	"html"
 	"io"
 	"log"
+	"maps"
+	"math"
 	"net/http"
 	"net/url"
 	"os"
 	"path/filepath"
 	"reflect"
 	"regexp"
+	"slices"
+	"sort"
 	"strconv"
 	"strings"
 	"time"
+	"unicode"
+	"unicode/utf8"
 
 	"github.com/joho/godotenv"
-	"go.mongodb.org/mongo-driver/bson"
-	"go.mongodb.org/mongo-driver/mongo"
-	"go.mongodb.org/mongo-driver/mongo/options"
 )
 
+// recentPostsLimit is how many published posts are given to the AI as context.
+const recentPostsLimit = 3
+
 const defaultPrompt = `%new_coins%
 `
 
 const defaultFallbackTemplate = `🚀 New entries in CoinMarketCap Top %top_n% (%convert%)
-
-%EACH new_coins%• #%rank% %name% (%symbol%)%IF market_cap% — mcap: %market_cap%%END_IF%
-%END_EACH%%IF exited_coins%
+%IF global%🌍 Market cap: %global.total_market_cap:,.0f|n/a% · 24h volume: %global.total_volume_24h:,.0f|n/a% · BTC dominance: %global.btc_dominance:.1f|n/a%%%
+%END_IF%%IF fear_greed_label%😱 Fear & Greed: %fear_greed_value%/100 (%fear_greed_label%)
+%END_IF%
+%EACH debut_coins%• #%rank% %IF parse_mode%[%name%](%cmc_url%)%ELSE%%name%%END_IF% (%symbol%)%IF market_cap% — mcap: %market_cap:,.0f%%IF market_cap_dominance% (%market_cap_dominance:.2f%%% dominance)%END_IF%%END_IF%%IF price%, price: %price:,.4f%%END_IF%%IF percent_change_24h% (24h: %percent_change_24h:arrow%)%END_IF%%IF volume_24h%, vol 24h: %volume_24h:abbrev%%END_IF%%IF date_added%, listed %age_days% days ago%END_IF%%IF pushed_out%, pushed out %pushed_out.name% (%pushed_out.symbol%)%END_IF%
+%END_EACH%%IF returning_coins%
+🔁 Back in the Top %top_n%:
+%EACH returning_coins%• #%rank% %IF parse_mode%[%name%](%cmc_url%)%ELSE%%name%%END_IF% (%symbol%)%IF market_cap% — mcap: %market_cap:,.0f%%END_IF%%IF last_seen_at%, last posted %last_seen_at%%END_IF%%IF pushed_out%, pushed out %pushed_out.name% (%pushed_out.symbol%)%END_IF%
+%END_EACH%%END_IF%%IF recently_mentioned%
+🔂 Also back, featured recently: %EACH recently_mentioned%%name% (%symbol%)%UNLESS _last%, %END_UNLESS%%END_EACH%
+%END_IF%%IF exited_coins%
 📉 Exited:
-%EACH exited_coins%• #%rank% %name% (%symbol%)
+%EACH exited_coins%• %IF parse_mode%[%name%](%cmc_url%)%ELSE%%name%%END_IF% (%symbol%), last #%last_rank%%IF in_top_n_since_tracking%, in the Top %top_n% since tracking began%ELSE%%IF days_in_top_n%, %days_in_top_n% day(s) in the Top %top_n%%ELSE%%IF tenure_runs%, %tenure_runs% run(s) in the Top %top_n%%END_IF%%END_IF%%END_IF%
+%END_EACH%%END_IF%%IF notify_movers%%IF movers_up%
+⬆️ Big climbers:
+%EACH movers_up%• %name% (%symbol%) #%prev_rank% → #%rank% (+%rank_delta%)
+%END_EACH%%END_IF%%IF movers_down%
+⬇️ Big fallers:
+%EACH movers_down%• %name% (%symbol%) #%prev_rank% → #%rank% (%rank_delta%)
+%END_EACH%%END_IF%%ELSE%%IF rank_movers%
+📊 Biggest movers:
+%EACH rank_movers%• %name% (%symbol%) #%prev_rank% → #%rank%
+%END_EACH%%END_IF%%END_IF%%IF renamed_coins%
+🏷 Renamed:
+%EACH renamed_coins%• #%rank% %old_name% (%old_symbol%) → %name% (%symbol%)
+%END_EACH%%END_IF%%IF new_coins_links%
+🔗 Links:
+%EACH new_coins_links%• %symbol%: %website%
+%END_EACH%%END_IF%%IF ath_coins%
+🏆 New market cap ATH:
+%EACH ath_coins%• #%rank% %name% (%symbol%) — %market_cap:,.0f% (prev. %prev_max_market_cap:,.0f%)
+%END_EACH%%END_IF%%IF approaching_coins%
+👀 Knocking on the door:
+%EACH approaching_coins%• #%rank% %name% (%symbol%)
 %END_EACH%%END_IF%`
 
 type RunOptions struct {
-	DryRun       bool
-	NotifyExits  bool
-	Convert      string
-	SkipMongo    bool
-	TestMessage  string
-	TestImageURL string
+	DryRun        bool
+	NotifyExits   bool
+	Convert       string
+	SkipMongo     bool
+	TestMessage   string
+	TestImageURL  string
+	NotifyDiscord bool
+	NotifySlack   bool
+	NotifyEmail   bool
+	ParseMode     string
+	// Sort is the CMC listings sort field; empty means market_cap.
+	Sort          string
+	NotifyRenames bool
+	// RankJumpThreshold, when set, makes a climb of at least this many places
+	// newsworthy on its own, so the run posts even without new entrants.
+	RankJumpThreshold *int64
+	// ExtraConverts are the currencies after the first in --convert USD,EUR.
+	// They are fetched in the same listings call and exposed as coin quotes;
+	// Convert stays the display and state currency.
+	ExtraConverts []string
+	// MinMarketCap, when set, drops new entrants with a smaller or unknown
+	// market cap before they reach the render context and history.
+	MinMarketCap *float64
+	// Mode selects the kind of run: empty for the entrants diff, ModeDigest
+	// for the weekly gainers and losers digest.
+	Mode string
+	// NotifyMovers adds coins whose rank moved by more than MOVER_THRESHOLD
+	// as movers_up and movers_down, and posts for them even without entrants.
+	NotifyMovers bool
+	// DryRunFormat selects what --dry-run prints; empty means text.
+	DryRunFormat DryRunFormat
+	// ForceRun posts even when nothing changed, with every current coin
+	// standing in for the new entrants, to test the pipeline end to end.
+	ForceRun bool
+	// NotifyMastodon cross-posts the text to MASTODON_INSTANCE_URL.
+	NotifyMastodon bool
+	// OutputFile, with DryRun, receives the dry run output instead of stdout.
+	OutputFile string
+	// NotifyResume posts a short "resuming coverage" message when the stored
+	// state is older than MAX_STATE_AGE_HOURS and only the baseline is reset.
+	NotifyResume bool
+}
+
+// DryRunFormat is what a dry run prints: the rendered text, or a JSON object
+// with the run's decisions next to it. DryRunMarkdown is the raw text too; it
+// only matters for --output-file, which otherwise writes JSON.
+type DryRunFormat string
+
+const (
+	DryRunText     DryRunFormat = "text"
+	DryRunJSON     DryRunFormat = "json"
+	DryRunMarkdown DryRunFormat = "markdown"
+)
+
+// printDryRun prints text, or with DryRunJSON the decisions in payload (with
+// the text_source of the post) plus the text as one JSON object. With
+// OutputFile set it writes the file instead.
+func printDryRun(opt RunOptions, payload, renderCtx map[string]any, text string) error {
+	if opt.OutputFile != "" {
+		return writeDryRunFile(opt, payload, renderCtx, text)
+	}
+	if opt.DryRunFormat != DryRunJSON {
+		fmt.Println(text)
+		return nil
+	}
+	payload["text"] = text
+	b, err := json.MarshalIndent(payload, "", "  ")
+	if err != nil {
+		return err
+	}
+	fmt.Println(string(b))
+	return nil
+}
+
+// printNoChangeDryRun reports a dry run that found nothing to post. Plain
+// text prints nothing, but JSON and --output-file still get the usual
+// object with empty lists, so CI snapshots need no special case.
+func printNoChangeDryRun(opt RunOptions) error {
+	if opt.OutputFile == "" && opt.DryRunFormat != DryRunJSON {
+		return nil
+	}
+	payload := map[string]any{"new_coins": []Coin{}, "exited_coins": []Coin{}, "rank_movers": []Coin{}, "displacements": []Displacement{}}
+	return printDryRun(opt, payload, map[string]any{}, "")
+}
+
+// writeDryRunFile writes the raw text to OutputFile when it ends in .md or
+// the format is markdown, and otherwise a JSON object with the text, the
+// decisions in payload, the render context and the time. Missing parent
+// directories are created.
+func writeDryRunFile(opt RunOptions, payload, renderCtx map[string]any, text string) error {
+	out := []byte(text)
+	if opt.DryRunFormat != DryRunMarkdown && !strings.EqualFold(filepath.Ext(opt.OutputFile), ".md") {
+		doc := maps.Clone(payload)
+		doc["text"] = text
+		doc["render_context"] = renderCtx
+		doc["timestamp_utc"] = time.Now().UTC().Format(time.RFC3339)
+		b, err := json.MarshalIndent(doc, "", "  ")
+		if err != nil {
+			return fmt.Errorf("encode dry run output: %w", err)
+		}
+		out = b
+	}
+	if err := os.MkdirAll(filepath.Dir(opt.OutputFile), 0o755); err != nil {
+		return fmt.Errorf("create directory for --output-file %s: %w", opt.OutputFile, err)
+	}
+	if err := os.WriteFile(opt.OutputFile, out, 0o644); err != nil {
+		return fmt.Errorf("write --output-file %s: %w", opt.OutputFile, err)
+	}
+	log.Printf("[RunOnce] dry run output written to %s", opt.OutputFile)
+	return nil
+}
+
+// defaultCMCSort is the listings sort used before --sort existed; states
+// written back then carry no sort.
+const defaultCMCSort = "market_cap"
+
+// cmcSortFields are the listings/latest sort values CMC accepts.
+var cmcSortFields = []string{
+	"market_cap", "market_cap_strict", "name", "symbol", "date_added", "price",
+	"circulating_supply", "total_supply", "max_supply", "num_market_pairs",
+	"volume_24h", "volume_7d", "volume_30d",
+	"percent_change_1h", "percent_change_24h", "percent_change_7d",
+}
+
+// ValidCMCSort reports whether sortBy is a listings sort CMC accepts.
+func ValidCMCSort(sortBy string) bool {
+	return slices.Contains(cmcSortFields, sortBy)
+}
+
+func (o RunOptions) cmcSort() string {
+	if o.Sort == "" {
+		return defaultCMCSort
+	}
+	return o.Sort
+}
+
+// cmcConvert is the comma-separated convert value for listings requests.
+func (o RunOptions) cmcConvert() string {
+	return strings.Join(append([]string{o.Convert}, o.ExtraConverts...), ",")
+}
+
+// ParseConverts splits a --convert value such as "USD,EUR" into the primary
+// currency and the extra ones, upper-cased and without duplicates.
+func ParseConverts(raw string) (string, []string, error) {
+	var all []string
+	for _, c := range splitList(raw) {
+		c = strings.ToUpper(c)
+		if !slices.Contains(all, c) {
+			all = append(all, c)
+		}
+	}
+	if len(all) == 0 {
+		return "", nil, errors.New("--convert needs at least one currency")
+	}
+	return all[0], all[1:], nil
 }
 
 type Config struct {
-	CMCAPIKey                string
-	TelegramToken            string
-	TelegramChannelID        string
-	MongoDBConnectionString  string
-	MongoDBDatabase          string
-	MongoDBStateCollection   string
-	MongoDBCoinsCollection   string
-	MongoDBHistoryCollection string
-	TopN                     int
-	AIEnabled                bool
-	AIProvider               string
-	AIModel                  string
-	GeminiAPIKey             string
+	CMCAPIKey                 string
+	CMCBaseURL                string
+	CMCCacheDir               string
+	CMCCacheTTL               time.Duration
+	TelegramToken             string
+	TelegramChannelID         string
+	TelegramChannelIDs        []string
+	TelegramAPIBaseURL        string
+	Backend                   Backend
+	SqlitePath                string
+	MongoDBConnectionString   string
+	MongoDBDatabase           string
+	MongoDBStateCollection    string
+	MongoDBCoinsCollection    string
+	MongoDBHistoryCollection  string
+	MongoDBATHCollection      string
+	MongoDBRawCollection      string
+	MongoDBSkipIndexCreation  bool
+	MongoDBSnapshotCollection string
+	MongoDBLocksCollection    string
+	MongoDBMetricsCollection  string
+	LockTTL                   time.Duration
+	RawSnapshotEnabled        bool
+	SnapshotRetentionDays     int
+	TenureThreshold           int64
+	TenureRetention           time.Duration
+	DedupWindow               time.Duration
+	FlapWindow                time.Duration
+	MentionCooldown           time.Duration
+	MinNewCoins               int
+	MinPostInterval           time.Duration
+	MaxStateAge               time.Duration
+	SaveRaw                   bool
+	TopN                      int
+	NotifyTopN                int
+	RankChangeThreshold       int
+	WatchlistMargin           int
+	MoverThreshold            int
+	CMCMaxRetries             int
+	CMCRetryBase              time.Duration
+	CMCMinCoinFraction        float64
+	HTTPConnectTimeout        time.Duration
+	HTTPRequestTimeout        time.Duration
+	ExtraCACertPEM            []byte
+	AcceptInvalidCerts        bool
+	AITimeout                 time.Duration
+	AITemperature             *float64
+	AIMaxTokens               int
+	GeminiBreakerThreshold    int
+	GeminiBreakerCooldown     time.Duration
+	AIEnabled                 bool
+	AIProvider                string
+	AIModel                   string
+	AIModelChain              []string
+	GeminiAPIKey              string
+	OpenAIAPIKey              string
+	AnthropicAPIKey           string
+	OllamaBaseURL             string
+	FallbackProvider          string
+	CoinGeckoAPIKey           string
+	CMCCreditWarnThreshold    int64
+	DiscordWebhookURL         string
+	SlackWebhookURL           string
+	MastodonInstanceURL       string
+	MastodonAccessToken       string
+	ExcludeTags               []string
+	CoinBlocklist             CoinList
+	CoinAllowlist             CoinList
+	SMTPHost                  string
+	SMTPPort                  int
+	SMTPUsername              string
+	SMTPPassword              string
+	SMTPFrom                  string
+	SMTPTo                    []string
+	RSSTitle                  string
+	RSSLink                   string
+	RSSDescription            string
+	RSSMaxItems               int
+	GlobalMetricsEnabled      bool
+	FearGreedEnabled          bool
+	TelegramParseMode         string
+	TemplatesDir              string
+	NewCoinDetailsEnabled     bool
+	ATHAlertsEnabled          bool
+	DigestSize                int
 }
 
 func ConfigFromEnv(dryRun bool, skipMongo bool) (Config, error) {
@@ -84,12 +340,35 @@ func ConfigFromEnv(dryRun bool, skipMongo bool) (Config, error) {
 	if err != nil {
 		return Config{}, err
 	}
-	tgChat, err := optionalReq("TELEGRAM_COINMARKETCAP_TOP_100_CHANNEL_ID")
+	tgChats := splitList(os.Getenv("TELEGRAM_CHANNEL_IDS"))
+	if len(tgChats) == 0 {
+		tgChat, err := optionalReq("TELEGRAM_COINMARKETCAP_TOP_100_CHANNEL_ID")
+		if err != nil {
+			return Config{}, err
+		}
+		tgChats = splitList(tgChat)
+	}
+	tgChat := ""
+	if len(tgChats) > 0 {
+		tgChat = tgChats[0]
+	}
+	cmcBaseURL, err := validateBaseURL("CMC_BASE_URL", envOr("CMC_BASE_URL", defaultCMCBaseURL), defaultCMCBaseURL)
+	if err != nil {
+		return Config{}, err
+	}
+	telegramAPIBaseURL, err := validateBaseURL("TELEGRAM_API_BASE_URL", envOr("TELEGRAM_API_BASE_URL", defaultTelegramAPIBaseURL), defaultTelegramAPIBaseURL)
 	if err != nil {
 		return Config{}, err
 	}
+	backend := Backend(strings.ToLower(envOr("BACKEND", string(BackendMongo))))
+	if backend != BackendMongo && backend != BackendSqlite {
+		return Config{}, fmt.Errorf("BACKEND must be %q or %q", BackendMongo, BackendSqlite)
+	}
+	if backend == BackendSqlite && !sqliteCompiledIn() {
+		return Config{}, errSqliteNotCompiledIn
+	}
 	mongoURI := strings.TrimSpace(os.Getenv("MONGODB_CONNECTION_STRING"))
-	if mongoURI == "" && !skipMongo {
+	if mongoURI == "" && backend == BackendMongo && !skipMongo {
 		return Config{}, fmt.Errorf("missing required env var %s", "MONGODB_CONNECTION_STRING")
 	}
 
@@ -101,26 +380,273 @@ func ConfigFromEnv(dryRun bool, skipMongo bool) (Config, error) {
 		}
 		topN = n
 	}
+	notifyTopN, err := envInt("NOTIFY_TOP_N", topN)
+	if err != nil {
+		return Config{}, err
+	}
+	if notifyTopN == 0 || notifyTopN > topN {
+		return Config{}, fmt.Errorf("NOTIFY_TOP_N must be between 1 and TOP_N (%d)", topN)
+	}
+	watchlistMargin, err := envInt("WATCHLIST_MARGIN", 10)
+	if err != nil {
+		return Config{}, err
+	}
+	rankChangeThreshold, err := envInt("RANK_CHANGE_THRESHOLD", 10)
+	if err != nil {
+		return Config{}, err
+	}
+	moverThreshold, err := envInt("MOVER_THRESHOLD", 10)
+	if err != nil {
+		return Config{}, err
+	}
+	cmcMaxRetries, err := envInt("CMC_MAX_RETRIES", 3)
+	if err != nil {
+		return Config{}, err
+	}
+	// CMC_RETRY_BASE_MS is the old name of CMC_RETRY_DELAY_MS.
+	cmcRetryBaseMS, err := envInt("CMC_RETRY_BASE_MS", 1000)
+	if err != nil {
+		return Config{}, err
+	}
+	cmcRetryBaseMS, err = envInt("CMC_RETRY_DELAY_MS", cmcRetryBaseMS)
+	if err != nil {
+		return Config{}, err
+	}
+	connectTimeoutSecs, err := envInt("HTTP_CONNECT_TIMEOUT_SECS", 10)
+	if err != nil {
+		return Config{}, err
+	}
+	// HTTP_TIMEOUT_SECS is a shorter alias; HTTP_REQUEST_TIMEOUT_SECS wins.
+	requestTimeoutSecs, err := envInt("HTTP_TIMEOUT_SECS", 30)
+	if err != nil {
+		return Config{}, err
+	}
+	requestTimeoutSecs, err = envInt("HTTP_REQUEST_TIMEOUT_SECS", requestTimeoutSecs)
+	if err != nil {
+		return Config{}, err
+	}
+	tgParseMode := strings.TrimSpace(os.Getenv("TELEGRAM_PARSE_MODE"))
+	if tgParseMode != "" && tgParseMode != "HTML" && tgParseMode != parseModeMarkdownV2 {
+		return Config{}, fmt.Errorf("TELEGRAM_PARSE_MODE must be %q or %q", "HTML", parseModeMarkdownV2)
+	}
+	extraCACert, err := loadExtraCACert(strings.TrimSpace(os.Getenv("EXTRA_CA_CERT_PATH")))
+	if err != nil {
+		return Config{}, err
+	}
+	smtpPort, err := envInt("SMTP_PORT", defaultSMTPPort)
+	if err != nil {
+		return Config{}, err
+	}
+	rssMaxItems, err := envInt("RSS_MAX_ITEMS", defaultRSSMaxItems)
+	if err != nil {
+		return Config{}, err
+	}
+	creditWarnThreshold, err := envInt("CMC_CREDIT_WARN_THRESHOLD", 0)
+	if err != nil || creditWarnThreshold < 0 {
+		return Config{}, errors.New("CMC_CREDIT_WARN_THRESHOLD must be an integer >= 0")
+	}
+	fallbackProvider := strings.ToLower(strings.TrimSpace(os.Getenv("FALLBACK_PROVIDER")))
+	if fallbackProvider != "" && fallbackProvider != providerCoinGecko {
+		return Config{}, fmt.Errorf("FALLBACK_PROVIDER must be empty or %q", providerCoinGecko)
+	}
+	snapshotRetentionDays, err := envInt("SNAPSHOT_RETENTION_DAYS", 0)
+	if err != nil {
+		return Config{}, err
+	}
+	tenureThreshold, err := envInt("TENURE_THRESHOLD", 0)
+	if err != nil {
+		return Config{}, err
+	}
+	tenureRetentionDays, err := envInt("TENURE_RETENTION_DAYS", defaultTenureRetentionDays)
+	if err != nil {
+		return Config{}, err
+	}
+	dedupWindowHours, err := envInt("DEDUP_WINDOW_HOURS", 0)
+	if err != nil {
+		return Config{}, err
+	}
+	flapWindowHours, err := envInt("FLAP_WINDOW_HOURS", 24)
+	if err != nil {
+		return Config{}, err
+	}
+	maxStateAgeHours, err := envInt("MAX_STATE_AGE_HOURS", defaultMaxStateAgeHours)
+	if err != nil {
+		return Config{}, err
+	}
+	mentionCooldownDays, err := envInt("COIN_MENTION_COOLDOWN_DAYS", 0)
+	if err != nil {
+		return Config{}, err
+	}
+	cmcCacheTTLSecs, err := envInt("CMC_CACHE_TTL_SECS", 3600)
+	if err != nil {
+		return Config{}, err
+	}
+	minNewCoins, err := envInt("MIN_NEW_COINS", 1)
+	if err != nil {
+		return Config{}, err
+	}
+	if minNewCoins == 0 {
+		return Config{}, errors.New("MIN_NEW_COINS must be at least 1")
+	}
+	minPostIntervalMinutes, err := envInt("MIN_POST_INTERVAL_MINUTES", 0)
+	if err != nil {
+		return Config{}, err
+	}
+	lockTTLSecs, err := envInt("LOCK_TTL_SECS", 120)
+	if err != nil {
+		return Config{}, err
+	}
+	digestSize, err := envInt("DIGEST_SIZE", defaultDigestSize)
+	if err != nil {
+		return Config{}, err
+	}
+	if digestSize == 0 {
+		return Config{}, fmt.Errorf("DIGEST_SIZE must be > 0")
+	}
+	minCoinFraction := defaultCMCMinCoinFraction
+	if raw := strings.TrimSpace(os.Getenv("CMC_MIN_COIN_FRACTION")); raw != "" {
+		minCoinFraction, err = strconv.ParseFloat(raw, 64)
+		if err != nil || minCoinFraction <= 0 || minCoinFraction > 1 {
+			return Config{}, errors.New("CMC_MIN_COIN_FRACTION must be a number in (0, 1]")
+		}
+	}
+	aiTimeoutSecs, err := envInt("AI_TIMEOUT_SECS", 20)
+	if err != nil {
+		return Config{}, err
+	}
+	var aiTemperature *float64
+	if raw := strings.TrimSpace(os.Getenv("AI_TEMPERATURE")); raw != "" {
+		temp, err := strconv.ParseFloat(raw, 64)
+		if err != nil || temp < 0 || temp > 2 {
+			return Config{}, errors.New("AI_TEMPERATURE must be a number in [0, 2]")
+		}
+		aiTemperature = &temp
+	}
+	aiMaxTokens, err := envInt("AI_MAX_TOKENS", 0)
+	if err != nil {
+		return Config{}, err
+	}
+	geminiBreakerThreshold, err := envInt("GEMINI_CIRCUIT_BREAKER_THRESHOLD", 3)
+	if err != nil {
+		return Config{}, err
+	}
+	geminiBreakerCooldownSecs, err := envInt("GEMINI_CIRCUIT_BREAKER_COOLDOWN_SECS", 300)
+	if err != nil {
+		return Config{}, err
+	}
 	geminiKey := strings.TrimSpace(os.Getenv("GEMINI_API_KEY"))
+	openAIKey := strings.TrimSpace(os.Getenv("OPENAI_API_KEY"))
+	anthropicKey := strings.TrimSpace(os.Getenv("ANTHROPIC_API_KEY"))
+	aiProvider := envOr("AI_PROVIDER", "gemini")
+	aiModel := "gemini-3-flash-preview"
 	aiEnabled := geminiKey != ""
+	switch aiProvider {
+	case "openai":
+		aiModel = "gpt-4o"
+		aiEnabled = openAIKey != ""
+	case "anthropic":
+		aiModel = "claude-3-5-haiku-20241022"
+		aiEnabled = anthropicKey != ""
+	case "ollama":
+		// A local server needs no key, so the provider choice enables AI.
+		aiModel = "llama3.2"
+		aiEnabled = true
+	}
 	if raw := strings.TrimSpace(os.Getenv("AI_ENABLED")); raw != "" {
 		aiEnabled = strings.EqualFold(raw, "true")
 	}
+	aiModelChain := splitList(os.Getenv("AI_MODEL_CHAIN"))
+	if len(aiModelChain) == 0 {
+		aiModelChain = []string{envOr("AI_MODEL", aiModel)}
+	}
 
 	return Config{
-		CMCAPIKey:                cmc,
-		TelegramToken:            tgToken,
-		TelegramChannelID:        tgChat,
-		MongoDBConnectionString:  mongoURI,
-		MongoDBDatabase:          envOr("MONGODB_DB", "cmc_top"),
-		MongoDBStateCollection:   envOr("MONGODB_STATE_COLLECTION", "state"),
-		MongoDBCoinsCollection:   envOr("MONGODB_COINS_COLLECTION", "coins"),
-		MongoDBHistoryCollection: envOr("MONGODB_HISTORY_COLLECTION", "history"),
-		TopN:                     topN,
-		AIEnabled:                aiEnabled,
-		AIProvider:               envOr("AI_PROVIDER", "gemini"),
-		AIModel:                  envOr("AI_MODEL", "gemini-3-flash-preview"),
-		GeminiAPIKey:             geminiKey,
+		CMCAPIKey:                 cmc,
+		CMCBaseURL:                cmcBaseURL,
+		CMCCacheDir:               strings.TrimSpace(os.Getenv("CMC_CACHE_DIR")),
+		CMCCacheTTL:               time.Duration(cmcCacheTTLSecs) * time.Second,
+		TelegramToken:             tgToken,
+		TelegramChannelID:         tgChat,
+		TelegramAPIBaseURL:        telegramAPIBaseURL,
+		TelegramChannelIDs:        tgChats,
+		Backend:                   backend,
+		SqlitePath:                envOr("SQLITE_PATH", "cmc_top.db"),
+		MongoDBConnectionString:   mongoURI,
+		MongoDBDatabase:           envOr("MONGODB_DB", "cmc_top"),
+		MongoDBStateCollection:    envOr("MONGODB_STATE_COLLECTION", "state"),
+		MongoDBCoinsCollection:    envOr("MONGODB_COINS_COLLECTION", "coins"),
+		MongoDBHistoryCollection:  envOr("MONGODB_HISTORY_COLLECTION", "history"),
+		MongoDBATHCollection:      envOr("MONGODB_ATH_COLLECTION", "ath"),
+		MongoDBRawCollection:      envOr("MONGODB_RAW_COLLECTION", "raw_listings"),
+		MongoDBSkipIndexCreation:  strings.EqualFold(strings.TrimSpace(os.Getenv("MONGODB_SKIP_INDEX_CREATION")), "true"),
+		MongoDBSnapshotCollection: envOr("MONGODB_SNAPSHOT_COLLECTION", "snapshots"),
+		MongoDBLocksCollection:    envOr("MONGODB_LOCKS_COLLECTION", "locks"),
+		MongoDBMetricsCollection:  envOr("MONGODB_METRICS_COLLECTION", "metrics"),
+		LockTTL:                   time.Duration(lockTTLSecs) * time.Second,
+		RawSnapshotEnabled:        strings.EqualFold(strings.TrimSpace(os.Getenv("RAW_SNAPSHOT_ENABLED")), "true"),
+		SnapshotRetentionDays:     snapshotRetentionDays,
+		TenureThreshold:           int64(tenureThreshold),
+		TenureRetention:           time.Duration(tenureRetentionDays) * 24 * time.Hour,
+		DedupWindow:               time.Duration(dedupWindowHours) * time.Hour,
+		FlapWindow:                time.Duration(flapWindowHours) * time.Hour,
+		MentionCooldown:           time.Duration(mentionCooldownDays) * 24 * time.Hour,
+		MinNewCoins:               minNewCoins,
+		MinPostInterval:           time.Duration(minPostIntervalMinutes) * time.Minute,
+		MaxStateAge:               time.Duration(maxStateAgeHours) * time.Hour,
+		SaveRaw:                   strings.EqualFold(strings.TrimSpace(os.Getenv("SAVE_RAW")), "true"),
+		TopN:                      topN,
+		NotifyTopN:                notifyTopN,
+		RankChangeThreshold:       rankChangeThreshold,
+		WatchlistMargin:           watchlistMargin,
+		MoverThreshold:            moverThreshold,
+		CMCMaxRetries:             cmcMaxRetries,
+		CMCRetryBase:              time.Duration(cmcRetryBaseMS) * time.Millisecond,
+		CMCMinCoinFraction:        minCoinFraction,
+		HTTPConnectTimeout:        time.Duration(connectTimeoutSecs) * time.Second,
+		HTTPRequestTimeout:        time.Duration(requestTimeoutSecs) * time.Second,
+		ExtraCACertPEM:            extraCACert,
+		AcceptInvalidCerts:        strings.EqualFold(strings.TrimSpace(os.Getenv("ACCEPT_INVALID_CERTS")), "true"),
+		AITimeout:                 time.Duration(aiTimeoutSecs) * time.Second,
+		AITemperature:             aiTemperature,
+		AIMaxTokens:               aiMaxTokens,
+		GeminiBreakerThreshold:    geminiBreakerThreshold,
+		GeminiBreakerCooldown:     time.Duration(geminiBreakerCooldownSecs) * time.Second,
+		AIEnabled:                 aiEnabled,
+		AIProvider:                aiProvider,
+		AIModel:                   aiModelChain[0],
+		AIModelChain:              aiModelChain,
+		GeminiAPIKey:              geminiKey,
+		OpenAIAPIKey:              openAIKey,
+		AnthropicAPIKey:           anthropicKey,
+		OllamaBaseURL:             strings.TrimRight(envOr("OLLAMA_BASE_URL", "http://localhost:11434"), "/"),
+		FallbackProvider:          fallbackProvider,
+		CoinGeckoAPIKey:           strings.TrimSpace(os.Getenv("COINGECKO_API_KEY")),
+		CMCCreditWarnThreshold:    int64(creditWarnThreshold),
+		DiscordWebhookURL:         strings.TrimSpace(os.Getenv("DISCORD_WEBHOOK_URL")),
+		SlackWebhookURL:           strings.TrimSpace(os.Getenv("SLACK_WEBHOOK_URL")),
+		MastodonInstanceURL:       strings.TrimRight(strings.TrimSpace(os.Getenv("MASTODON_INSTANCE_URL")), "/"),
+		MastodonAccessToken:       strings.TrimSpace(os.Getenv("MASTODON_ACCESS_TOKEN")),
+		ExcludeTags:               splitList(strings.ToLower(os.Getenv("EXCLUDE_TAGS"))),
+		// EXCLUDE_SYMBOLS predates COIN_BLOCKLIST and feeds the same list.
+		CoinBlocklist:             ParseCoinList(os.Getenv("COIN_BLOCKLIST") + "," + os.Getenv("EXCLUDE_SYMBOLS")),
+		CoinAllowlist:             ParseCoinList(os.Getenv("COIN_ALLOWLIST")),
+		SMTPHost:                  strings.TrimSpace(os.Getenv("SMTP_HOST")),
+		SMTPPort:                  smtpPort,
+		SMTPUsername:              strings.TrimSpace(os.Getenv("SMTP_USERNAME")),
+		SMTPPassword:              os.Getenv("SMTP_PASSWORD"),
+		SMTPFrom:                  strings.TrimSpace(os.Getenv("SMTP_FROM")),
+		SMTPTo:                    splitList(os.Getenv("SMTP_TO")),
+		RSSTitle:                  envOr("RSS_TITLE", defaultRSSTitle),
+		RSSLink:                   envOr("RSS_LINK", defaultRSSLink),
+		RSSDescription:            envOr("RSS_DESCRIPTION", defaultRSSDescription),
+		RSSMaxItems:               rssMaxItems,
+		GlobalMetricsEnabled:      globalMetricsEnabledFromEnv(),
+		FearGreedEnabled:          strings.EqualFold(strings.TrimSpace(os.Getenv("FEAR_GREED_ENABLED")), "true"),
+		TelegramParseMode:         tgParseMode,
+		TemplatesDir:              envOr("TEMPLATES_DIR", defaultTemplatesDir),
+		ATHAlertsEnabled:          strings.EqualFold(strings.TrimSpace(os.Getenv("ATH_ALERTS_ENABLED")), "true"),
+		NewCoinDetailsEnabled:     !strings.EqualFold(strings.TrimSpace(os.Getenv("NEW_COIN_DETAILS_ENABLED")), "false"),
+		DigestSize:                digestSize,
 	}, nil
 }
 
@@ -131,107 +657,272 @@ func envOr(name, def string) string {
 	return def
 }
 
+// validateBaseURL checks that raw is an absolute http(s) URL and returns it
+// without a trailing slash so API paths can be appended directly. example is
+// quoted in the error.
+func validateBaseURL(name, raw, example string) (string, error) {
+	u, err := url.Parse(raw)
+	if err != nil || (u.Scheme != "http" && u.Scheme != "https") || u.Host == "" {
+		return "", fmt.Errorf("%s must be an absolute http(s) URL such as %s, got %q", name, example, raw)
+	}
+	return strings.TrimRight(raw, "/"), nil
+}
+
+// splitList splits a comma-separated env value, dropping empty items.
+func splitList(raw string) []string {
+	var out []string
+	for _, item := range strings.Split(raw, ",") {
+		if item = strings.TrimSpace(item); item != "" {
+			out = append(out, item)
+		}
+	}
+	return out
+}
+
+func envInt(name string, def int) (int, error) {
+	raw := strings.TrimSpace(os.Getenv(name))
+	if raw == "" {
+		return def, nil
+	}
+	n, err := strconv.Atoi(raw)
+	if err != nil || n < 0 {
+		return 0, fmt.Errorf("%s must be a non-negative integer", name)
+	}
+	return n, nil
+}
+
 type Coin struct {
-	ID                int64      `bson:"id" json:"id"`
-	Name              string     `bson:"name" json:"name"`
-	Symbol            string     `bson:"symbol" json:"symbol"`
-	Rank              int64      `bson:"rank" json:"rank"`
-	TickTimestamp     *time.Time `bson:"tick_timestamp,omitempty" json:"tick_timestamp,omitempty"`
-	MarketCap         *float64   `bson:"market_cap,omitempty" json:"market_cap,omitempty"`
-	MarketCapCurrency string     `bson:"market_cap_currency" json:"market_cap_currency"`
-	ImageURL          string     `bson:"image_url,omitempty" json:"image_url,omitempty"`
+	ID                 int64      `bson:"id" json:"id"`
+	Name               string     `bson:"name" json:"name"`
+	Symbol             string     `bson:"symbol" json:"symbol"`
+	Rank               int64      `bson:"rank" json:"rank"`
+	PrevRank           *int64     `bson:"prev_rank,omitempty" json:"prev_rank,omitempty"`
+	RankDelta          *int64     `bson:"-" json:"rank_delta,omitempty"`
+	TickTimestamp      *time.Time `bson:"tick_timestamp,omitempty" json:"tick_timestamp,omitempty"`
+	MarketCap          *float64   `bson:"market_cap,omitempty" json:"market_cap,omitempty"`
+	MarketCapCurrency  string     `bson:"market_cap_currency" json:"market_cap_currency"`
+	MarketCapDominance *float64   `bson:"market_cap_dominance,omitempty" json:"market_cap_dominance,omitempty"`
+	Price              *float64   `bson:"price,omitempty" json:"price,omitempty"`
+	PercentChange24h   *float64   `bson:"percent_change_24h,omitempty" json:"percent_change_24h,omitempty"`
+	PercentChange7d    *float64   `bson:"percent_change_7d,omitempty" json:"percent_change_7d,omitempty"`
+	Volume24h          *float64   `bson:"volume_24h,omitempty" json:"volume_24h,omitempty"`
+	TenureRuns         int64      `bson:"tenure_runs,omitempty" json:"tenure_runs,omitempty"`
+	ImageURL           string     `bson:"image_url,omitempty" json:"image_url,omitempty"`
+	Tags               []string   `bson:"tags,omitempty" json:"tags,omitempty"`
+	DateAdded          *time.Time `bson:"date_added,omitempty" json:"date_added,omitempty"`
+	Slug               string     `bson:"slug,omitempty" json:"slug,omitempty"`
+	// Quotes holds every requested currency, keyed by code, when --convert
+	// lists more than one; single-currency runs leave it empty.
+	Quotes map[string]CoinQuote `bson:"quotes,omitempty" json:"quotes,omitempty"`
+	// VolumeToMarketCapRatio, AgeDays and CMCURL are derived while building
+	// the render context and never stored. LastSeenAt is set on returning
+	// entrants from their latest mention in history; LastRank, DaysInTopN and
+	// InTopNSinceTracking on exited coins; PushedOut on entrants paired with
+	// the coin they displaced.
+	VolumeToMarketCapRatio *float64   `bson:"-" json:"volume_to_market_cap_ratio,omitempty"`
+	AgeDays                *int64     `bson:"-" json:"age_days,omitempty"`
+	LastSeenAt             *time.Time `bson:"-" json:"last_seen_at,omitempty"`
+	LastRank               *int64     `bson:"-" json:"last_rank,omitempty"`
+	DaysInTopN             *int64     `bson:"-" json:"days_in_top_n,omitempty"`
+	InTopNSinceTracking    bool       `bson:"-" json:"in_top_n_since_tracking,omitempty"`
+	CMCURL                 string     `bson:"-" json:"cmc_url,omitempty"`
+	PushedOut              *Coin      `bson:"-" json:"pushed_out,omitempty"`
 }
 
-type RecentPost struct {
-	CreatedAtUTC   string `json:"created_at_utc"`
-	Text           string `json:"text"`
-	MentionedCoins []Coin `json:"mentioned_coins"`
+// CMCPageURL is the coin's CoinMarketCap page, built from the slug CMC
+// returned or, for coins without one, from its name: "Shiba Inu" links to
+// https://coinmarketcap.com/currencies/shiba-inu/.
+func (c Coin) CMCPageURL() string {
+	slug := c.Slug
+	if slug == "" {
+		slug = cmcSlug(c.Name)
+	}
+	return "https://coinmarketcap.com/currencies/" + slug + "/"
 }
 
-type stateDoc struct {
-	ID        string    `bson:"_id"`
-	UpdatedAt time.Time `bson:"updated_at"`
-	TopN      int64     `bson:"top_n"`
-	Convert   string    `bson:"convert"`
-	IDs       []int64   `bson:"ids"`
+// cmcSlug lowercases name and joins its runs of letters and digits with
+// hyphens.
+func cmcSlug(name string) string {
+	words := strings.FieldsFunc(strings.ToLower(name), func(r rune) bool {
+		return !unicode.IsLetter(r) && !unicode.IsDigit(r)
+	})
+	return strings.Join(words, "-")
 }
 
-type stateCoinDoc struct {
-	StateID       string    `bson:"state_id"`
-	ID            int64     `bson:"id"`
-	Name          string    `bson:"name"`
-	Symbol        string    `bson:"symbol"`
-	Rank          int64     `bson:"rank"`
-	TickTimestamp time.Time `bson:"tick_timestamp"`
-	Updated       time.Time `bson:"updated_at"`
-	Created       time.Time `bson:"created_at,omitempty"`
-	IsActive      bool      `bson:"is_active"`
+// CoinQuote is one currency's quote of a coin.
+type CoinQuote struct {
+	MarketCap          *float64 `bson:"market_cap,omitempty" json:"market_cap,omitempty"`
+	MarketCapDominance *float64 `bson:"market_cap_dominance,omitempty" json:"market_cap_dominance,omitempty"`
+	Price              *float64 `bson:"price,omitempty" json:"price,omitempty"`
+	PercentChange24h   *float64 `bson:"percent_change_24h,omitempty" json:"percent_change_24h,omitempty"`
+	PercentChange7d    *float64 `bson:"percent_change_7d,omitempty" json:"percent_change_7d,omitempty"`
+	Volume24h          *float64 `bson:"volume_24h,omitempty" json:"volume_24h,omitempty"`
+}
 
-	MarketCap         *float64 `bson:"market_cap,omitempty"`
-	MarketCapCurrency string   `bson:"market_cap_currency"`
-	ImageURL          string   `bson:"image_url,omitempty"`
+// RenamedCoin is a coin present in both snapshots whose name or symbol changed.
+type RenamedCoin struct {
+	ID        int64  `json:"id"`
+	Rank      int64  `json:"rank"`
+	OldName   string `json:"old_name"`
+	OldSymbol string `json:"old_symbol"`
+	Name      string `json:"name"`
+	Symbol    string `json:"symbol"`
 }
 
-type historyDoc struct {
-	CreatedAt         time.Time `bson:"created_at"`
-	TopN              int64     `bson:"top_n"`
-	Convert           string    `bson:"convert"`
-	NewCoinIDs        []int64   `bson:"new_coin_ids"`
-	Text              string    `bson:"text"`
-	MentionedCoins    []Coin    `bson:"mentioned_coins"`
-	TelegramMessageID *int64    `bson:"telegram_message_id,omitempty"`
+type RecentPost struct {
+	CreatedAtUTC   string `json:"created_at_utc"`
+	Text           string `json:"text"`
+	MentionedCoins []Coin `json:"mentioned_coins"`
 }
 
-func RunOnce(ctx context.Context, cfg Config, opt RunOptions) error {
-	log.Printf("[RunOnce] start: top_n=%d convert=%s dry_run=%t notify_exits=%t skip_mongo=%t ai_enabled=%t ai_provider=%s", cfg.TopN, opt.Convert, opt.DryRun, opt.NotifyExits, opt.SkipMongo, cfg.AIEnabled, cfg.AIProvider)
+// HistoryDoc is one published post. It is appended only after Telegram
+// accepted the message. TelegramMessageIDs has one entry per configured
+// channel, nil where that channel failed; TelegramMessageID is the first
+// accepted one.
+// ID is the store's key (Mongo _id hex or sqlite row id), set on reads only.
+// Kind is empty for entrant posts and HistoryKindDigest for --mode digest.
+// Forced marks --force-run posts whose entrants were replaced by the whole
+// current list; their MentionedCoins is empty. MastodonStatusID is set when
+// --notify-mastodon posted the text. ExitedCoinIDs are the exits the post
+// announced. AIModelUsed is the model that wrote the text, empty for the
+// fallback template.
+type HistoryDoc struct {
+	ID                 string    `bson:"-" json:"id,omitempty"`
+	CreatedAt          time.Time `bson:"created_at" json:"created_at"`
+	TopN               int64     `bson:"top_n" json:"top_n"`
+	Convert            string    `bson:"convert" json:"convert"`
+	NewCoinIDs         []int64   `bson:"new_coin_ids" json:"new_coin_ids"`
+	Text               string    `bson:"text" json:"text"`
+	MentionedCoins     []Coin    `bson:"mentioned_coins" json:"mentioned_coins"`
+	TelegramMessageID  *int64    `bson:"telegram_message_id,omitempty" json:"telegram_message_id,omitempty"`
+	TelegramMessageIDs []*int64  `bson:"telegram_message_ids,omitempty" json:"telegram_message_ids,omitempty"`
+	Provider           string    `bson:"provider,omitempty" json:"provider,omitempty"`
+	CreditsUsed        int64     `bson:"credits_used" json:"credits_used"`
+	Kind               string    `bson:"kind,omitempty" json:"kind,omitempty"`
+	Forced             bool      `bson:"forced,omitempty" json:"forced,omitempty"`
+	MastodonStatusID   string    `bson:"mastodon_status_id,omitempty" json:"mastodon_status_id,omitempty"`
+	ExitedCoinIDs      []int64   `bson:"exited_coin_ids,omitempty" json:"exited_coin_ids,omitempty"`
+	AIModelUsed        string    `bson:"ai_model_used,omitempty" json:"ai_model_used,omitempty"`
+}
 
-	log.Printf("[RunOnce] step 1/11: creating HTTP client")
-	httpClient := &http.Client{Timeout: 30 * time.Second}
+func RunOnce(ctx context.Context, cfg Config, opt RunOptions) (err error) {
+	defer beginRun()()
+	sp := startSpan("run_once", "top_n", cfg.TopN, "convert", opt.Convert, "ai_provider", cfg.AIProvider)
+	defer func() { sp.end(err) }()
+	defer func(start time.Time) { Metrics.observeRunDuration(time.Since(start)) }(time.Now())
+	log.Printf("[RunOnce] start: top_n=%d convert=%s sort=%s dry_run=%t notify_exits=%t skip_mongo=%t ai_enabled=%t ai_provider=%s", cfg.TopN, opt.Convert, opt.cmcSort(), opt.DryRun, opt.NotifyExits, opt.SkipMongo, cfg.AIEnabled, cfg.AIProvider)
 
 	if opt.SkipMongo {
-		return runWithoutMongo(ctx, httpClient, cfg, opt)
+		return runWithoutMongo(ctx, withCMCCache(NewHTTPClient(cfg), cfg, opt.DryRun), cfg, opt)
 	}
 
-	log.Printf("[RunOnce] step 2/11: connecting to MongoDB")
-	db, client, err := connectDB(ctx, cfg)
+	log.Printf("[RunOnce] step 1/11: opening %s state store", cfg.Backend)
+	store, err := OpenStateStore(ctx, cfg)
 	if err != nil {
-		log.Printf("[RunOnce] failed to connect to MongoDB: %v", err)
+		log.Printf("[RunOnce] failed to open state store: %v", err)
 		return err
 	}
-	defer client.Disconnect(context.Background())
-	log.Printf("[RunOnce] connected to MongoDB database=%s", cfg.MongoDBDatabase)
+	defer store.Close(context.Background())
+
+	if !opt.DryRun {
+		release, acquired, err := acquireRunLock(ctx, store, cfg.LockTTL)
+		if err != nil {
+			log.Printf("[RunOnce] failed to acquire run lock: %v", err)
+			return err
+		}
+		if !acquired {
+			log.Printf("[RunOnce] warning: another run holds the run lock; skipping this run")
+			return nil
+		}
+		defer release()
+	}
+
+	return RunOnceWithStore(ctx, cfg, opt, store)
+}
+
+// RunOnceWithStore runs a single tick against an already opened store. The
+// caller owns the store and closes it.
+func RunOnceWithStore(ctx context.Context, cfg Config, opt RunOptions, store StateStore) (err error) {
+	if opt.Mode == ModeDigest {
+		return runDigest(ctx, cfg, opt, store)
+	}
+	start := time.Now()
+	report := RunMetricsDoc{}
+	defer func() {
+		if opt.DryRun {
+			return
+		}
+		report.CreatedAt, report.DurationMs = start.UTC(), time.Since(start).Milliseconds()
+		if err != nil {
+			report.Error = err.Error()
+		}
+		saveRunMetrics(ctx, store, report)
+	}()
+	log.Printf("[RunOnce] step 2/11: creating HTTP client")
+	httpClient := withCMCCache(NewHTTPClient(cfg), cfg, opt.DryRun)
+
+	if !opt.DryRun {
+		pruneSnapshots(ctx, store, cfg, time.Now().UTC())
+	}
 
 	log.Printf("[RunOnce] step 3/11: fetching current top-%d from CoinMarketCap", cfg.TopN)
-	current, err := fetchCMCTopN(ctx, httpClient, cfg, opt)
+	metrics := &RunMetrics{}
+	current, band, provider, err := fetchWithWatchlist(ctx, httpClient, cfg, opt, metrics)
+	if !opt.DryRun {
+		// Saved even when the fetch failed, so a rejected page can be audited.
+		saveRawListings(ctx, store, metrics.RawListings)
+	}
 	if err != nil {
 		log.Printf("[RunOnce] failed to fetch CoinMarketCap listings: %v", err)
 		return err
 	}
-	log.Printf("[RunOnce] fetched %d current coins", len(current))
+	log.Printf("[RunOnce] fetched %d current coins from %s", len(current), provider)
+	report.CoinsFetched = len(current)
+	metrics.warnCreditThreshold(cfg.CMCCreditWarnThreshold)
+	if !opt.DryRun {
+		saveSnapshot(ctx, store, cfg, Snapshot{CreatedAt: time.Now().UTC(), TopN: int64(cfg.TopN), Convert: opt.Convert, Provider: provider, Coins: current})
+	}
+	tenure, _ := updateTenure(nil, nil, current, 0)
+	nextState := State{TopN: int64(cfg.TopN), Convert: opt.Convert, Sort: opt.cmcSort(), Provider: provider, Coins: current, CreditsUsed: metrics.CMCCredits, Tenure: tenure, WatchlistIDs: coinIDs(band)}
 	log.Printf("Incoming top %d %v", cfg.TopN, coinSymbols(current))
 
-	stateCollection := db.Collection(cfg.MongoDBStateCollection)
-	coinsCollection := db.Collection(cfg.MongoDBCoinsCollection)
-	historyCollection := db.Collection(cfg.MongoDBHistoryCollection)
-	log.Printf("[RunOnce] using collections: state=%s coins=%s history=%s", cfg.MongoDBStateCollection, cfg.MongoDBCoinsCollection, cfg.MongoDBHistoryCollection)
-
 	log.Printf("[RunOnce] step 4/11: loading previous state snapshot")
-	var prev stateDoc
-	err = stateCollection.FindOne(ctx, bson.M{"_id": "top"}).Decode(&prev)
-	if errors.Is(err, mongo.ErrNoDocuments) {
+	prev, err := store.LoadState(ctx)
+	if errors.Is(err, ErrNoState) {
 		log.Printf("[RunOnce] previous state not found; writing baseline and exiting without Telegram post")
-		return writeState(ctx, stateCollection, coinsCollection, cfg.TopN, opt.Convert, current)
+		return store.WriteState(ctx, nextState)
 	}
 	if err != nil {
 		log.Printf("[RunOnce] failed to load previous state: %v", err)
 		return err
 	}
-	log.Printf("[RunOnce] loaded previous state with %d ids", len(prev.IDs))
-	prevCoins, err := loadStateCoins(ctx, coinsCollection, "top")
-	if err != nil {
-		log.Printf("[RunOnce] failed to load state coins: %v", err)
+	if err := checkStateSort(prev, opt.cmcSort()); err != nil {
+		log.Printf("[RunOnce] %v", err)
 		return err
 	}
+	if prevProvider := stateProvider(prev); prevProvider != provider {
+		// Ids differ between providers, so a diff would flag every coin as new.
+		log.Printf("[RunOnce] previous state came from %s but this run used %s; re-baselining without Telegram post", prevProvider, provider)
+		if opt.DryRun {
+			return nil
+		}
+		return store.WriteState(ctx, nextState)
+	}
+	if age, stale := stateAge(prev, cfg.MaxStateAge, time.Now().UTC()); stale {
+		log.Printf("[RunOnce] warning: previous state is %s old, beyond MAX_STATE_AGE_HOURS; re-baselining without the entrant post", age.Round(time.Minute))
+		return resumeCoverage(ctx, httpClient, cfg, opt, store, nextState, age)
+	}
+	if !opt.DryRun {
+		// Runs that post nothing or are held leave the state untouched, so
+		// the heartbeat is what tells a quiet market from an outage.
+		defer func() {
+			if err == nil {
+				touchState(ctx, store)
+			}
+		}()
+	}
+	log.Printf("[RunOnce] loaded previous state with %d ids", len(prev.IDs))
+	prevCoins := prev.Coins
 	log.Printf("From DB top %d %v", cfg.TopN, coinSymbols(prevCoins))
 
 	log.Printf("[RunOnce] step 5/11: calculating diff between previous and current top lists")
@@ -239,37 +930,190 @@ func RunOnce(ctx context.Context, cfg Config, opt RunOptions) error {
 	for _, id := range prev.IDs {
 		prevSet[id] = struct{}{}
 	}
-	currentSet := map[int64]struct{}{}
-	for _, c := range current {
-		currentSet[c.ID] = struct{}{}
+	var veterans map[int64]struct{}
+	prevTenure, tenureLeftAt := ageOutTenure(prev.Tenure, prev.TenureLeftAt, current, time.Now().UTC(), cfg.TenureRetention)
+	nextState.Tenure, veterans = updateTenure(prevTenure, prevSet, current, cfg.TenureThreshold)
+	nextState.TenureLeftAt = tenureLeftAt
+
+	headline := cfg.headlineRank()
+	diff := annotateDiff(prevCoins, prevSet, current, headline, cfg.RankChangeThreshold)
+	// Blocked coins stay in state but never show up in a post; entrants and
+	// exits are also held to the allowlist further down.
+	visible, blocked := filterCoinLists(current, cfg.CoinBlocklist, CoinList{})
+	if blocked > 0 {
+		log.Printf("[RunOnce] %d current coin(s) on COIN_BLOCKLIST/EXCLUDE_SYMBOLS are left out of the post", blocked)
+	}
+	rankMovers, _ := filterCoinLists(diff.Moved, cfg.CoinBlocklist, CoinList{})
+	log.Printf("[RunOnce] %d coin(s) moved more than %d rank(s)", len(rankMovers), cfg.RankChangeThreshold)
+	moversUp, moversDown := []Coin{}, []Coin{}
+	if opt.NotifyMovers {
+		moversUp, moversDown = splitMovers(visible, cfg.MoverThreshold)
+		log.Printf("[RunOnce] notify movers enabled; %d coin(s) up and %d down by more than %d rank(s)", len(moversUp), len(moversDown), cfg.MoverThreshold)
+	}
+	rankJumps := 0
+	if opt.RankJumpThreshold != nil {
+		rankMovers, rankJumps = addRankJumps(rankMovers, visible, prevSet, *opt.RankJumpThreshold)
+		log.Printf("[RunOnce] %d coin(s) climbed at least %d rank(s)", rankJumps, *opt.RankJumpThreshold)
+	}
+
+	renamedCoins := []RenamedCoin{}
+	if opt.NotifyRenames {
+		renamedCoins = detectRenames(visible, prevCoins)
+		log.Printf("[RunOnce] notify renames enabled; detected %d renamed coin(s)", len(renamedCoins))
+	}
+
+	athCoins, athUpdates := []ATHCoin{}, []ATHRecord{}
+	if cfg.ATHAlertsEnabled {
+		records, err := store.LoadATHs(ctx, coinIDs(current))
+		if err != nil {
+			log.Printf("[RunOnce] failed to load market cap ATHs: %v", err)
+			return err
+		}
+		athCoins, athUpdates = detectATHs(visible, records, time.Now().UTC())
+		log.Printf("[RunOnce] %d coin(s) set a new market cap ATH", len(athCoins))
 	}
 
 	newCoins := make([]Coin, 0)
-	for _, c := range current {
-		if _, ok := prevSet[c.ID]; !ok {
-			newCoins = append(newCoins, c)
+	returning := 0
+	for _, c := range diff.Entered {
+		if _, ok := veterans[c.ID]; ok {
+			returning++
+			continue
 		}
+		newCoins = append(newCoins, c)
+	}
+	if returning > 0 {
+		log.Printf("[RunOnce] %d returning coin(s) with tenure above TENURE_THRESHOLD are not announced", returning)
+	}
+	newCoins, tagged := excludeByTags(newCoins, cfg.ExcludeTags)
+	if tagged > 0 {
+		log.Printf("[RunOnce] %d new entrant(s) suppressed by EXCLUDE_TAGS", tagged)
+	}
+	suppressed := returning + tagged
+	newCoins, listed := filterCoinLists(newCoins, cfg.CoinBlocklist, cfg.CoinAllowlist)
+	if listed > 0 {
+		log.Printf("[RunOnce] %d new entrant(s) suppressed by COIN_BLOCKLIST/COIN_ALLOWLIST", listed)
+		suppressed += listed
+	}
+	if cfg.DedupWindow > 0 {
+		var deduped int
+		newCoins, deduped = dropRecentlyMentioned(ctx, store, newCoins, time.Now().UTC().Add(-cfg.DedupWindow))
+		if deduped > 0 {
+			log.Printf("[RunOnce] %d new entrant(s) already mentioned within DEDUP_WINDOW_HOURS", deduped)
+			suppressed += deduped
+		}
+	}
+	flapIDs := recentlyAnnounced(ctx, store, cfg, time.Now().UTC())
+	newCoins, flappers := splitFlappers(newCoins, flapIDs)
+	if len(flappers) > 0 {
+		log.Printf("[RunOnce] %d new entrant(s) suppressed as flapping within FLAP_WINDOW_HOURS: %v", len(flappers), coinSymbols(flappers))
+		suppressed += len(flappers)
+	}
+	var recentlyMentioned []Coin
+	if cfg.MentionCooldown > 0 {
+		newCoins, recentlyMentioned = splitMentionCooldown(ctx, store, newCoins, time.Now().UTC().Add(-cfg.MentionCooldown))
+		if len(recentlyMentioned) > 0 {
+			// Counted as suppressed so the state still moves on when every
+			// entrant is in cooldown and nothing is posted.
+			log.Printf("[RunOnce] %d new entrant(s) in COIN_MENTION_COOLDOWN_DAYS demoted to recently_mentioned: %v", len(recentlyMentioned), coinSymbols(recentlyMentioned))
+			suppressed += len(recentlyMentioned)
+		}
+	}
+	if opt.MinMarketCap != nil {
+		// Unlike the filters above, these coins are not recorded: a small cap
+		// entrant is announced once it grows past the threshold.
+		var small int
+		newCoins, small = filterMinMarketCap(newCoins, *opt.MinMarketCap)
+		if small > 0 {
+			log.Printf("[RunOnce] %d new entrant(s) below --min-market-cap %.0f", small, *opt.MinMarketCap)
+		}
+	}
+	substituted := false
+	if opt.ForceRun && len(newCoins) == 0 {
+		log.Printf("[RunOnce] --force-run: no new coins; posting with all %d current coin(s) instead", len(visible))
+		newCoins, substituted = visible, true
 	}
-	if len(newCoins) == 0 {
+	if len(newCoins) == 0 && len(renamedCoins) == 0 && rankJumps == 0 && len(athCoins) == 0 && len(moversUp)+len(moversDown) == 0 {
 		log.Printf("[RunOnce] no new coins found; exiting without Telegram post")
-		return nil
+		Metrics.observeDiff(0, 0)
+		if opt.DryRun {
+			return printNoChangeDryRun(opt)
+		}
+		if suppressed > 0 {
+			// Record the filtered entrants so they are not re-evaluated as
+			// new on every following run, nor announced once a filter is lifted.
+			if err := store.WriteState(ctx, nextState); err != nil {
+				log.Printf("[RunOnce] failed to write state: %v", err)
+				return err
+			}
+		}
+		return saveATHs(ctx, store, athUpdates)
 	}
 	log.Printf("[RunOnce] detected %d new coin(s)", len(newCoins))
+	report.NewCoins = len(newCoins)
+	if !opt.ForceRun {
+		if reason := postHoldReason(ctx, store, cfg, newCoins, time.Now().UTC()); reason != "" {
+			// The state is left alone, so the held entrants are detected
+			// again and included in the next eligible post.
+			log.Printf("[RunOnce] %s; holding the post without updating state", reason)
+			return nil
+		}
+	}
+	returningCoins := []Coin{}
+	if !substituted {
+		returningCoins = returningEntrants(ctx, store, newCoins, prevTenure, headline)
+	}
+	if len(returningCoins) > 0 {
+		log.Printf("[RunOnce] %d of the new coin(s) were in the top-%d before", len(returningCoins), cfg.notifyTopN())
+	}
 
+	// The watchlist only rides along on a post about real entrants.
+	approaching := []Coin{}
+	if !substituted && len(newCoins) > 0 {
+		prevHeadline := prevSet
+		if headline > 0 {
+			prevHeadline = headlineIDs(prevCoins, headline)
+		}
+		approaching = approachingCoins(band, prev.WatchlistIDs, prevHeadline)
+		approaching, _ = filterCoinLists(approaching, cfg.CoinBlocklist, CoinList{})
+	}
+	if len(approaching) > 0 {
+		log.Printf("[RunOnce] %d coin(s) newly within WATCHLIST_MARGIN of the top-%d", len(approaching), cfg.notifyTopN())
+	}
+
+	// Exits are always worked out so entrants can be paired with the coins
+	// they pushed out; they are only listed on their own with --notify-exits.
+	departed := diff.Exited
+	var listedExits int
+	departed, listedExits = filterCoinLists(departed, cfg.CoinBlocklist, cfg.CoinAllowlist)
+	var flappingExits []Coin
+	departed, flappingExits = splitFlappers(departed, flapIDs)
 	exitedCoins := []Coin{}
 	if opt.NotifyExits {
-		for _, c := range prevCoins {
-			if _, ok := currentSet[c.ID]; !ok {
-				exitedCoins = append(exitedCoins, c)
-			}
+		if len(flappingExits) > 0 {
+			log.Printf("[RunOnce] %d exited coin(s) suppressed as flapping within FLAP_WINDOW_HOURS: %v", len(flappingExits), coinSymbols(flappingExits))
+			flappers = append(flappers, flappingExits...)
 		}
-		log.Printf("[RunOnce] notify exits enabled; detected %d exited coin(s)", len(exitedCoins))
+		exitedCoins = annotateExits(ctx, store, departed, prevTenure, time.Now().UTC())
+		departed = exitedCoins
+		log.Printf("[RunOnce] notify exits enabled; detected %d exited coin(s), %d more suppressed by COIN_BLOCKLIST/COIN_ALLOWLIST", len(exitedCoins), listedExits)
 	} else {
-		log.Printf("[RunOnce] notify exits disabled; exited coins are not included")
+		log.Printf("[RunOnce] notify exits disabled; %d exited coin(s) are only used to pair displacements", len(departed))
+	}
+	displacements := pairDisplacements(newCoins, departed)
+	if len(displacements) > 0 {
+		log.Printf("[RunOnce] paired %d entrant(s) with the coin(s) they pushed out", len(displacements))
 	}
+	shownExits := exitedCoins
+	if !opt.NotifyExits {
+		shownExits = displacedExits(displacements)
+	}
+
+	Metrics.observeDiff(len(newCoins), len(exitedCoins))
+	report.ExitedCoins = len(exitedCoins)
 
 	log.Printf("[RunOnce] step 6/11: loading recent posts from history")
-	recentPosts, err := loadRecentPosts(ctx, historyCollection)
+	recentPosts, err := store.LoadRecentPosts(ctx, recentPostsLimit)
 	if err != nil {
 		log.Printf("[RunOnce] failed to load recent posts: %v", err)
 		return err
@@ -277,46 +1121,79 @@ func RunOnce(ctx context.Context, cfg Config, opt RunOptions) error {
 	log.Printf("[RunOnce] loaded %d recent post(s)", len(recentPosts))
 
 	log.Printf("[RunOnce] step 7/11: building render context")
-	renderCtx := buildRenderContext(cfg, opt, newCoins, exitedCoins, recentPosts)
+	renderCtx := buildRenderContext(cfg, opt, renderInputs{
+		NewCoins:          newCoins,
+		ReturningCoins:    returningCoins,
+		ExitedCoins:       exitedCoins,
+		RankMovers:        rankMovers,
+		NotifyMovers:      opt.NotifyMovers,
+		MoversUp:          moversUp,
+		MoversDown:        moversDown,
+		RenamedCoins:      renamedCoins,
+		ATHCoins:          athCoins,
+		NewCoinsDetails:   loadNewCoinDetails(ctx, httpClient, cfg, newCoins),
+		RecentPosts:       recentPosts,
+		Global:            loadGlobalMetrics(ctx, httpClient, cfg, opt.Convert),
+		FearGreed:         loadFearGreed(ctx, httpClient, cfg),
+		Approaching:       approaching,
+		Flappers:          flappers,
+		RecentlyMentioned: recentlyMentioned,
+		Displacements:     displacements,
+	})
 
 	log.Printf("[RunOnce] step 8/11: producing Telegram text")
-	text, err := produceTelegramText(ctx, httpClient, cfg, renderCtx)
+	gen, err := produceTelegramText(ctx, httpClient, cfg, renderCtx)
 	if err != nil {
 		log.Printf("[RunOnce] failed to produce Telegram text: %v", err)
 		return err
 	}
-	log.Printf("[RunOnce] produced Telegram text with %d characters", len(text))
+	text := gen.Text
+	report.TextSource = gen.Source
+	log.Printf("[RunOnce] produced Telegram text with %d characters from %s", len(text), gen.Source)
 
 	if opt.DryRun {
 		log.Printf("[RunOnce] step 9/11: dry-run enabled; printing message and exiting")
-		fmt.Println(text)
-		return nil
+		return printDryRun(opt, map[string]any{"new_coins": newCoins, "exited_coins": exitedCoins, "rank_movers": orEmpty(rankMovers), "displacements": displacements, "text_source": gen.Source}, renderCtx, text)
 	}
 
 	log.Printf("[RunOnce] step 10/11: sending Telegram message")
-	msgID, err := sendTelegramMessage(ctx, httpClient, cfg, text, firstCoinImageURL(newCoins))
+	msgIDs, err := sendTelegramMessage(ctx, httpClient, cfg, text, firstCoinImageURL(newCoins), telegramParseMode(cfg, opt))
 	if err != nil {
 		log.Printf("[RunOnce] failed to send Telegram message: %v", err)
 		return err
 	}
+	msgID := firstMessageID(msgIDs)
 	if msgID != nil {
 		log.Printf("[RunOnce] Telegram message sent successfully: message_id=%d", *msgID)
 	} else {
 		log.Printf("[RunOnce] Telegram message sent successfully: message_id is unavailable")
 	}
+	notifyDiscord(ctx, httpClient, cfg, opt, text)
+	notifySlack(ctx, httpClient, cfg, opt, text)
+	notifyEmail(cfg, opt, emailSubject(cfg.TopN, len(newCoins), time.Now().UTC()), text)
+	mastodonID := notifyMastodon(ctx, httpClient, cfg, opt, text)
 
 	log.Printf("[RunOnce] step 11/11: persisting state and writing history")
-	if err := writeState(ctx, stateCollection, coinsCollection, cfg.TopN, opt.Convert, current); err != nil {
+	if err := store.WriteState(ctx, nextState); err != nil {
 		log.Printf("[RunOnce] failed to write state: %v", err)
 		return err
 	}
-	newIDs := make([]int64, 0, len(newCoins))
-	for _, c := range newCoins {
-		newIDs = append(newIDs, c.ID)
+	if err := saveATHs(ctx, store, athUpdates); err != nil {
+		log.Printf("[RunOnce] failed to update market cap ATHs: %v", err)
+		return err
+	}
+	mentioned := newCoins
+	if substituted {
+		// The stand-in list is every current coin; recording it as mentioned
+		// would make dedup, cooldown and returning-coin lookups treat the
+		// whole top-N as recently announced.
+		mentioned = nil
 	}
-	_, err = historyCollection.InsertOne(ctx, historyDoc{
+	err = store.InsertHistory(ctx, HistoryDoc{
 		CreatedAt: time.Now().UTC(), TopN: int64(cfg.TopN), Convert: opt.Convert,
-		NewCoinIDs: newIDs, Text: text, MentionedCoins: newCoins, TelegramMessageID: msgID,
+		NewCoinIDs: coinIDs(newCoins), Text: text, MentionedCoins: mentioned, TelegramMessageID: msgID,
+		TelegramMessageIDs: msgIDs, Provider: provider, CreditsUsed: metrics.CMCCredits, Forced: substituted,
+		MastodonStatusID: mastodonID, ExitedCoinIDs: coinIDs(shownExits), AIModelUsed: gen.Model,
 	})
 	if err != nil {
 		log.Printf("[RunOnce] failed to append history: %v", err)
@@ -333,17 +1210,17 @@ func runWithoutMongo(ctx context.Context, httpClient *http.Client, cfg Config, o
 			fmt.Println(opt.TestMessage)
 			return nil
 		}
-		msgID, err := sendTelegramMessage(ctx, httpClient, cfg, opt.TestMessage, strings.TrimSpace(opt.TestImageURL))
+		msgIDs, err := sendTelegramMessage(ctx, httpClient, cfg, opt.TestMessage, strings.TrimSpace(opt.TestImageURL), telegramParseMode(cfg, opt))
 		if err != nil {
 			return err
 		}
-		if msgID != nil {
+		if msgID := firstMessageID(msgIDs); msgID != nil {
 			log.Printf("[RunOnce] skip-mongo custom test post sent: message_id=%d", *msgID)
 		}
 		return nil
 	}
 
-	current, err := fetchCMCTopN(ctx, httpClient, cfg, opt)
+	current, _, err := fetchTopN(ctx, httpClient, cfg, opt, nil)
 	if err != nil {
 		return err
 	}
@@ -356,218 +1233,1051 @@ func runWithoutMongo(ctx context.Context, httpClient *http.Client, cfg Config, o
 		newCount = 3
 	}
 	newCoins := current[:newCount]
-	renderCtx := buildRenderContext(cfg, opt, newCoins, []Coin{}, []RecentPost{})
-	text, err := produceTelegramText(ctx, httpClient, cfg, renderCtx)
+	renderCtx := buildRenderContext(cfg, opt, renderInputs{
+		NewCoins:        newCoins,
+		NewCoinsDetails: loadNewCoinDetails(ctx, httpClient, cfg, newCoins),
+		Global:          loadGlobalMetrics(ctx, httpClient, cfg, opt.Convert),
+		FearGreed:       loadFearGreed(ctx, httpClient, cfg),
+	})
+	gen, err := produceTelegramText(ctx, httpClient, cfg, renderCtx)
 	if err != nil {
 		return err
 	}
+	text := gen.Text
 	if opt.DryRun {
-		fmt.Println(text)
-		return nil
+		return printDryRun(opt, map[string]any{"new_coins": newCoins, "exited_coins": []Coin{}, "rank_movers": []Coin{}, "text_source": gen.Source}, renderCtx, text)
 	}
-	msgID, err := sendTelegramMessage(ctx, httpClient, cfg, text, firstCoinImageURL(newCoins))
+	msgIDs, err := sendTelegramMessage(ctx, httpClient, cfg, text, firstCoinImageURL(newCoins), telegramParseMode(cfg, opt))
 	if err != nil {
 		return err
 	}
-	if msgID != nil {
+	if msgID := firstMessageID(msgIDs); msgID != nil {
 		log.Printf("[RunOnce] skip-mongo post sent: message_id=%d", *msgID)
 	}
+	notifyDiscord(ctx, httpClient, cfg, opt, text)
+	notifySlack(ctx, httpClient, cfg, opt, text)
+	notifyEmail(cfg, opt, emailSubject(cfg.TopN, len(newCoins), time.Now().UTC()), text)
+	notifyMastodon(ctx, httpClient, cfg, opt, text)
 	return nil
 }
 
-func connectDB(ctx context.Context, cfg Config) (*mongo.Database, *mongo.Client, error) {
-	client, err := mongo.Connect(ctx, options.Client().ApplyURI(cfg.MongoDBConnectionString).SetAppName("coinmarketcap_top100_bot"))
-	if err != nil {
-		return nil, nil, err
-	}
-	return client.Database(cfg.MongoDBDatabase), client, nil
-}
+// cmcListingsPageLimit is the largest limit CMC accepts per listings request.
+const cmcListingsPageLimit = 5000
 
-func fetchCMCTopN(ctx context.Context, client *http.Client, cfg Config, opt RunOptions) ([]Coin, error) {
+// fetchCMCTopN pages through listings/latest until TopN coins are collected,
+// dropping ids repeated across pages. A listing shorter than TopN is an error
+// so a truncated response can never be diffed into a bogus mass-exit post.
+// Credit usage of each page is added to metrics.
+func fetchCMCTopN(ctx context.Context, client *http.Client, cfg Config, opt RunOptions, metrics *RunMetrics) (coins []Coin, err error) {
+	sp := startSpan("fetch_cmc_top_n", "top_n", cfg.TopN, "sort", opt.cmcSort())
+	defer func() { sp.end(err, "coin_count", len(coins)) }()
 	now := time.Now().UTC()
-	u := fmt.Sprintf("https://pro-api.coinmarketcap.com/v1/cryptocurrency/listings/latest?start=1&limit=%d&convert=%s&sort=market_cap&sort_dir=desc", cfg.TopN, url.QueryEscape(opt.Convert))
-	req, _ := http.NewRequestWithContext(ctx, http.MethodGet, u, nil)
-	req.Header.Set("X-CMC_PRO_API_KEY", cfg.CMCAPIKey)
-	resp, err := client.Do(req)
+	coins = make([]Coin, 0, cfg.TopN)
+	for start := 1; start <= cfg.TopN; start += cmcListingsPageLimit {
+		limit := cfg.TopN - start + 1
+		if limit > cmcListingsPageLimit {
+			limit = cmcListingsPageLimit
+		}
+		page, status, err := fetchCMCListingsPage(ctx, client, cfg, opt, start, limit, now, metrics)
+		if err != nil {
+			return nil, err
+		}
+		metrics.addCMCStatus("fetchCMCTopN", status)
+		if cfg.TopN > cmcListingsPageLimit {
+			log.Printf("[fetchCMCTopN] fetched page start=%d limit=%d: %d coin(s)", start, limit, len(page))
+		}
+		coins = append(coins, page...)
+		if len(page) < limit {
+			break
+		}
+	}
+	coins, err = checkListing(coins, cfg, opt.cmcSort())
 	if err != nil {
 		return nil, err
 	}
-	defer resp.Body.Close()
-	if resp.StatusCode >= 300 {
-		b, _ := io.ReadAll(resp.Body)
-		return nil, fmt.Errorf("cmc error: %s %s", resp.Status, string(b))
-	}
-	var payload map[string]any
-	if err := json.NewDecoder(resp.Body).Decode(&payload); err != nil {
+
+	logos, err := fetchCMCLogos(ctx, client, cfg, coins)
+	if err != nil {
+		log.Printf("[fetchCMCTopN] unable to fetch coin logos: %v", err)
+		return coins, nil
+	}
+	for i := range coins {
+		if logo := logos[coins[i].ID]; logo != "" {
+			coins[i].ImageURL = logo
+		}
+	}
+	return coins, nil
+}
+
+// defaultCMCBaseURL is the production API host; CMC_BASE_URL overrides it,
+// e.g. with https://sandbox-api.coinmarketcap.com.
+const defaultCMCBaseURL = "https://pro-api.coinmarketcap.com"
+
+// cmcURL joins an API path such as /v1/global-metrics/quotes/latest onto the
+// configured base URL.
+func (c Config) cmcURL(path string) string {
+	base := c.CMCBaseURL
+	if base == "" {
+		base = defaultCMCBaseURL
+	}
+	return base + path
+}
+
+func cmcListingsURL(cfg Config, opt RunOptions, start, limit int) string {
+	return cfg.cmcURL(fmt.Sprintf("/v1/cryptocurrency/listings/latest?start=%d&limit=%d&convert=%s&sort=%s&sort_dir=desc", start, limit, url.QueryEscape(opt.cmcConvert()), url.QueryEscape(opt.cmcSort())))
+}
+
+func fetchCMCListingsPage(ctx context.Context, client *http.Client, cfg Config, opt RunOptions, start, limit int, now time.Time, metrics *RunMetrics) ([]Coin, cmcStatus, error) {
+	u := cmcListingsURL(cfg, opt, start, limit)
+	resp, err := doWithRetry(ctx, client, cfg.httpRetryPolicy(), "fetchCMCTopN", func() (*http.Request, error) {
+		req, err := http.NewRequestWithContext(ctx, http.MethodGet, u, nil)
+		if err != nil {
+			return nil, err
+		}
+		req.Header.Set("X-CMC_PRO_API_KEY", cfg.CMCAPIKey)
+		return req, nil
+	})
+	if err != nil {
+		return nil, cmcStatus{}, upstreamError("cmc listings", err)
+	}
+	defer resp.Body.Close()
+	if resp.StatusCode >= 300 {
+		return nil, cmcStatus{}, readCMCError(resp)
+	}
+	if !cfg.SaveRaw {
+		return decodeCMCListings(resp.Body, opt.Convert, now)
+	}
+	body, err := io.ReadAll(resp.Body)
+	if err != nil {
+		return nil, cmcStatus{}, upstreamError("cmc listings", err)
+	}
+	// Kept before decoding so a page that fails validation can be audited.
+	metrics.addRawListing(now, start, limit, body)
+	return decodeCMCListings(bytes.NewReader(body), opt.Convert, now)
+}
+
+type cmcStatus struct {
+	ErrorCode    int64  `json:"error_code"`
+	ErrorMessage string `json:"error_message"`
+	CreditCount  int64  `json:"credit_count"`
+	Elapsed      int64  `json:"elapsed"`
+}
+
+type cmcQuote struct {
+	MarketCap          *float64 `json:"market_cap"`
+	MarketCapDominance *float64 `json:"market_cap_dominance"`
+	Price              *float64 `json:"price"`
+	PercentChange24h   *float64 `json:"percent_change_24h"`
+	PercentChange7d    *float64 `json:"percent_change_7d"`
+	Volume24h          *float64 `json:"volume_24h"`
+}
+
+// cmcListing uses pointers for the required fields so a missing value can be
+// told apart from a zero one.
+type cmcListing struct {
+	ID        *int64              `json:"id"`
+	Name      *string             `json:"name"`
+	Symbol    *string             `json:"symbol"`
+	Slug      string              `json:"slug"`
+	CMCRank   *int64              `json:"cmc_rank"`
+	Tags      []string            `json:"tags"`
+	DateAdded *time.Time          `json:"date_added"`
+	Quote     map[string]cmcQuote `json:"quote"`
+}
+
+type cmcListingsResponse struct {
+	Status cmcStatus    `json:"status"`
+	Data   []cmcListing `json:"data"`
+}
+
+// parseCMCListings decodes a listings/latest body strictly: id, name, symbol
+// and cmc_rank must be present on every entry, otherwise the run aborts before
+// any state is touched. market_cap stays optional.
+func parseCMCListings(r io.Reader, convert string, now time.Time) ([]Coin, error) {
+	coins, _, err := decodeCMCListings(r, convert, now)
+	return coins, err
+}
+
+// decodeCMCListings is parseCMCListings that also returns the status block,
+// which carries the credit usage of the request.
+func decodeCMCListings(r io.Reader, convert string, now time.Time) ([]Coin, cmcStatus, error) {
+	var payload cmcListingsResponse
+	if err := json.NewDecoder(r).Decode(&payload); err != nil {
+		return nil, cmcStatus{}, fmt.Errorf("decode cmc listings: %w", err)
+	}
+	if payload.Data == nil {
+		return nil, payload.Status, errors.New("cmc listings: response has no data array")
+	}
+	coins := make([]Coin, 0, len(payload.Data))
+	for i, l := range payload.Data {
+		if err := l.validate(); err != nil {
+			return nil, payload.Status, fmt.Errorf("cmc listings: entry %d: %w", i, err)
+		}
+		coin := Coin{ID: *l.ID, Name: *l.Name, Symbol: *l.Symbol, Rank: *l.CMCRank, TickTimestamp: &now, MarketCapCurrency: convert, Tags: l.Tags, DateAdded: l.DateAdded, Slug: l.Slug}
+		if q, ok := l.Quote[convert]; ok {
+			coin.MarketCap = q.MarketCap
+			coin.MarketCapDominance = q.MarketCapDominance
+			coin.Price = q.Price
+			coin.PercentChange24h = q.PercentChange24h
+			coin.PercentChange7d = q.PercentChange7d
+			coin.Volume24h = q.Volume24h
+		}
+		if len(l.Quote) > 1 {
+			coin.Quotes = make(map[string]CoinQuote, len(l.Quote))
+			for cur, q := range l.Quote {
+				coin.Quotes[cur] = CoinQuote{MarketCap: q.MarketCap, MarketCapDominance: q.MarketCapDominance, Price: q.Price, PercentChange24h: q.PercentChange24h, PercentChange7d: q.PercentChange7d, Volume24h: q.Volume24h}
+			}
+		}
+		coins = append(coins, coin)
+	}
+	return coins, payload.Status, nil
+}
+
+func (l cmcListing) validate() error {
+	switch {
+	case l.ID == nil || *l.ID <= 0:
+		return errors.New(`missing or invalid required field "id"`)
+	case l.Name == nil || *l.Name == "":
+		return fmt.Errorf(`coin id=%d: missing required field "name"`, *l.ID)
+	case l.Symbol == nil || *l.Symbol == "":
+		return fmt.Errorf(`coin id=%d: missing required field "symbol"`, *l.ID)
+	case l.CMCRank == nil || *l.CMCRank <= 0:
+		return fmt.Errorf(`coin id=%d: missing or invalid required field "cmc_rank"`, *l.ID)
+	}
+	return nil
+}
+
+// CMCError carries the status block CoinMarketCap returns on failed requests,
+// e.g. an invalid key, an exhausted plan or an unsupported convert value.
+type CMCError struct {
+	HTTPStatus int
+	Code       int64
+	Message    string
+}
+
+func (e *CMCError) Error() string {
+	return fmt.Sprintf("cmc error %d (http %d): %s", e.Code, e.HTTPStatus, e.Message)
+}
+
+// readCMCError turns a non-2xx CMC response into a *CMCError when the body has
+// the usual status block, and into a plain error with the raw body otherwise.
+func readCMCError(resp *http.Response) error {
+	b, _ := io.ReadAll(resp.Body)
+	var payload struct {
+		Status cmcStatus `json:"status"`
+	}
+	if err := json.Unmarshal(b, &payload); err == nil && payload.Status.ErrorMessage != "" {
+		return &CMCError{HTTPStatus: resp.StatusCode, Code: payload.Status.ErrorCode, Message: payload.Status.ErrorMessage}
+	}
+	return fmt.Errorf("cmc error: %s %s", resp.Status, string(b))
+}
+
+func fetchCMCLogos(ctx context.Context, client *http.Client, cfg Config, coins []Coin) (map[int64]string, error) {
+	info, err := fetchCMCInfo(ctx, client, cfg, coinIDs(coins))
+	if err != nil {
 		return nil, err
 	}
-	data, _ := payload["data"].([]any)
-	coins := make([]Coin, 0, len(data))
-	for _, item := range data {
-		m, _ := item.(map[string]any)
-		coin := Coin{ID: asInt64(m["id"]), Name: asStringDef(m["name"], "Unknown"), Symbol: asStringDef(m["symbol"], "???"), Rank: asInt64(m["cmc_rank"]), TickTimestamp: &now, MarketCapCurrency: opt.Convert}
-		if quote, ok := m["quote"].(map[string]any); ok {
-			if curr, ok := quote[opt.Convert].(map[string]any); ok {
-				if mc, ok := asFloat(curr["market_cap"]); ok {
-					coin.MarketCap = &mc
+	out := make(map[int64]string, len(info))
+	for id, d := range info {
+		if d.LogoURL != "" {
+			out[id] = d.LogoURL
+		}
+	}
+	return out, nil
+}
+
+// annotateRankChanges sets PrevRank on current coins that were also in the
+// previous snapshot and returns those whose rank moved by more than threshold,
+// biggest move first.
+func annotateRankChanges(current, prev []Coin, threshold int) []Coin {
+	prevRanks := make(map[int64]int64, len(prev))
+	for _, c := range prev {
+		prevRanks[c.ID] = c.Rank
+	}
+	movers := []Coin{}
+	for i := range current {
+		prevRank, ok := prevRanks[current[i].ID]
+		if !ok {
+			continue
+		}
+		current[i].PrevRank = &prevRank
+		if absInt64(current[i].Rank-prevRank) > int64(threshold) {
+			movers = append(movers, current[i])
+		}
+	}
+	sortRankMovers(movers)
+	return movers
+}
+
+// notifyTopN is the headline depth posts are about: NOTIFY_TOP_N, or TopN
+// for configs built without it.
+func (c Config) notifyTopN() int {
+	if c.NotifyTopN > 0 {
+		return c.NotifyTopN
+	}
+	return c.TopN
+}
+
+// headlineRank returns the rank cutoff for entrants and exits, or 0 when
+// NOTIFY_TOP_N covers the whole fetched list and membership alone decides.
+// The list order need not follow cmc_rank (eg --sort volume_24h), so only a
+// real NOTIFY_TOP_N < TOP_N compares ranks.
+func (c Config) headlineRank() int64 {
+	if n := c.notifyTopN(); n < c.TopN {
+		return int64(n)
+	}
+	return 0
+}
+
+func withinHeadline(rank, headline int64) bool {
+	return headline == 0 || rank <= headline
+}
+
+// enteredHeadline reports whether c is within the headline now but was not
+// before: either it was missing from the previous snapshot or it was tracked
+// there at a rank below the headline. It relies on PrevRank set by
+// annotateRankChanges; a tracked coin without one is not new.
+func enteredHeadline(c Coin, prevSet map[int64]struct{}, headline int64) bool {
+	if !withinHeadline(c.Rank, headline) {
+		return false
+	}
+	if _, ok := prevSet[c.ID]; !ok {
+		return true
+	}
+	return c.PrevRank != nil && !withinHeadline(*c.PrevRank, headline)
+}
+
+// leftHeadline reports whether the previous coin c was within the headline
+// and now is either gone or ranked below it.
+func leftHeadline(c Coin, currentRanks map[int64]int64, headline int64) bool {
+	if !withinHeadline(c.Rank, headline) {
+		return false
+	}
+	rank, ok := currentRanks[c.ID]
+	return !ok || !withinHeadline(rank, headline)
+}
+
+// splitMovers returns copies of the coins with a PrevRank whose rank moved by
+// more than threshold places, with RankDelta set to the places gained: climbers
+// biggest climb first, fallers biggest fall first.
+func splitMovers(current []Coin, threshold int) ([]Coin, []Coin) {
+	up, down := []Coin{}, []Coin{}
+	for _, c := range current {
+		if c.PrevRank == nil {
+			continue
+		}
+		delta := *c.PrevRank - c.Rank
+		if absInt64(delta) <= int64(threshold) {
+			continue
+		}
+		c.RankDelta = &delta
+		if delta > 0 {
+			up = append(up, c)
+		} else {
+			down = append(down, c)
+		}
+	}
+	sortRankMovers(up)
+	sortRankMovers(down)
+	return up, down
+}
+
+// addRankJumps adds coins that climbed at least threshold places to movers and
+// reports how many such jumps there were. Only ids from the previous snapshot
+// qualify, so a new entrant is reported once, as new.
+func addRankJumps(movers, current []Coin, prevSet map[int64]struct{}, threshold int64) ([]Coin, int) {
+	seen := make(map[int64]bool, len(movers))
+	for _, c := range movers {
+		seen[c.ID] = true
+	}
+	jumps := 0
+	for _, c := range current {
+		if _, ok := prevSet[c.ID]; !ok || c.PrevRank == nil || *c.PrevRank-c.Rank < threshold {
+			continue
+		}
+		jumps++
+		if !seen[c.ID] {
+			movers = append(movers, c)
+		}
+	}
+	sortRankMovers(movers)
+	return movers, jumps
+}
+
+// sortRankMovers orders coins with a PrevRank by absolute move, biggest first.
+func sortRankMovers(movers []Coin) {
+	sort.SliceStable(movers, func(a, b int) bool {
+		return absInt64(movers[a].Rank-*movers[a].PrevRank) > absInt64(movers[b].Rank-*movers[b].PrevRank)
+	})
+}
+
+// detectRenames returns coins present in both snapshots whose name or symbol
+// differs from the previous one, in current rank order.
+func detectRenames(current, prev []Coin) []RenamedCoin {
+	prevByID := make(map[int64]Coin, len(prev))
+	for _, c := range prev {
+		prevByID[c.ID] = c
+	}
+	out := []RenamedCoin{}
+	for _, c := range current {
+		p, ok := prevByID[c.ID]
+		if !ok || (p.Name == c.Name && p.Symbol == c.Symbol) {
+			continue
+		}
+		out = append(out, RenamedCoin{ID: c.ID, Rank: c.Rank, OldName: p.Name, OldSymbol: p.Symbol, Name: c.Name, Symbol: c.Symbol})
+	}
+	return out
+}
+
+func absInt64(n int64) int64 {
+	if n < 0 {
+		return -n
+	}
+	return n
+}
+
+// renderInputs is what a tick contributes to the render context. Nil slices
+// render as empty arrays.
+type renderInputs struct {
+	NewCoins        []Coin
+	ReturningCoins  []Coin
+	ExitedCoins     []Coin
+	RankMovers      []Coin
+	NotifyMovers    bool
+	MoversUp        []Coin
+	MoversDown      []Coin
+	RenamedCoins    []RenamedCoin
+	ATHCoins        []ATHCoin
+	NewCoinsDetails []CoinDetails
+	RecentPosts     []RecentPost
+	Global          *GlobalMetrics
+	FearGreed       *FearGreed
+	// Approaching are band coins newly just below the cutoff; they never
+	// make a post on their own.
+	Approaching []Coin
+	// Flappers are entrants and exits held back by FLAP_WINDOW_HOURS.
+	Flappers []Coin
+	// RecentlyMentioned are entrants in COIN_MENTION_COOLDOWN_DAYS, shown as
+	// one-liners.
+	RecentlyMentioned []Coin
+	// Displacements pair entrants with the coins they pushed out; paired
+	// entrants stay in new_coins, debut_coins and returning_coins with
+	// pushed_out set, paired exits are left out of exited_coins.
+	Displacements []Displacement
+}
+
+func buildRenderContext(cfg Config, opt RunOptions, in renderInputs) map[string]any {
+	now := time.Now().UTC()
+	in.NewCoins = withPushedOut(in.NewCoins, in.Displacements)
+	in.ReturningCoins = withPushedOut(in.ReturningCoins, in.Displacements)
+	in.ExitedCoins = withoutIDs(in.ExitedCoins, displacedExitIDs(in.Displacements))
+	renderCtx := map[string]any{
+		"project_name":        "coinmarketcap_top100_bot",
+		"timestamp_utc":       now.Format(time.RFC3339),
+		"top_n":               cfg.notifyTopN(),
+		"convert":             opt.Convert,
+		"new_coins":           withQuoteTokens(withDerivedFields(in.NewCoins, now)),
+		"debut_coins":         withQuoteTokens(withDerivedFields(debutCoins(in.NewCoins, in.ReturningCoins), now)),
+		"returning_coins":     withQuoteTokens(withDerivedFields(orEmpty(in.ReturningCoins), now)),
+		"exited_coins":        withQuoteTokens(withDerivedFields(in.ExitedCoins, now)),
+		"rank_movers":         withQuoteTokens(withDerivedFields(in.RankMovers, now)),
+		"notify_movers":       in.NotifyMovers,
+		"movers_up":           withQuoteTokens(withDerivedFields(orEmpty(in.MoversUp), now)),
+		"movers_down":         withQuoteTokens(withDerivedFields(orEmpty(in.MoversDown), now)),
+		"renamed_coins":       orEmpty(in.RenamedCoins),
+		"ath_coins":           orEmpty(in.ATHCoins),
+		"new_coins_details":   orEmpty(in.NewCoinsDetails),
+		"new_coins_links":     withWebsite(in.NewCoinsDetails),
+		"recent_posts":        orEmpty(in.RecentPosts),
+		"approaching_coins":   withQuoteTokens(withDerivedFields(orEmpty(in.Approaching), now)),
+		"suppressed_flappers": withQuoteTokens(withDerivedFields(orEmpty(in.Flappers), now)),
+		"recently_mentioned":  withQuoteTokens(withDerivedFields(orEmpty(in.RecentlyMentioned), now)),
+		"displacements":       orEmpty(in.Displacements),
+		"parse_mode":          telegramParseMode(cfg, opt),
+	}
+	if in.Global != nil {
+		renderCtx["global"] = in.Global
+	}
+	if in.FearGreed != nil {
+		renderCtx["fear_greed_value"] = in.FearGreed.Value
+		renderCtx["fear_greed_label"] = in.FearGreed.Label
+	}
+	return renderCtx
+}
+
+// withDerivedFields returns a copy of coins with volume_to_market_cap_ratio
+// set wherever both volume_24h and a non-zero market_cap are known,
+// age_days wherever date_added is, and cmc_url wherever the name is.
+func withDerivedFields(coins []Coin, now time.Time) []Coin {
+	out := make([]Coin, len(coins))
+	for i, c := range coins {
+		if c.Volume24h != nil && c.MarketCap != nil && *c.MarketCap != 0 {
+			ratio := *c.Volume24h / *c.MarketCap
+			c.VolumeToMarketCapRatio = &ratio
+		}
+		if c.DateAdded != nil {
+			days := int64(now.Sub(*c.DateAdded).Hours() / 24)
+			c.AgeDays = &days
+		}
+		if c.Name != "" || c.Slug != "" {
+			c.CMCURL = c.CMCPageURL()
+		}
+		out[i] = c
+	}
+	return out
+}
+
+// withQuoteTokens flattens each coin's quotes into keys such as market_cap_usd
+// and price_eur so a template can show several currencies side by side. The
+// coins are returned as they are when none carries quotes.
+func withQuoteTokens(coins []Coin) any {
+	if !slices.ContainsFunc(coins, func(c Coin) bool { return len(c.Quotes) > 0 }) {
+		return coins
+	}
+	out := make([]map[string]any, 0, len(coins))
+	for _, c := range coins {
+		m, ok := toMap(c)
+		if !ok {
+			continue
+		}
+		for code, q := range c.Quotes {
+			fields, ok := toMap(q)
+			if !ok {
+				continue
+			}
+			suffix := "_" + strings.ToLower(code)
+			for k, v := range fields {
+				m[k+suffix] = v
+			}
+		}
+		out = append(out, m)
+	}
+	return out
+}
+
+func orEmpty[T any](s []T) []T {
+	if s == nil {
+		return []T{}
+	}
+	return s
+}
+
+// textSources names the AI prompt and fallback template of one kind of post.
+// Files that exist win over the built-in defaults.
+type textSources struct {
+	PromptPath   string
+	Prompt       string
+	FallbackPath string
+	Fallback     string
+}
+
+var entrantTextSources = textSources{
+	PromptPath:   "prompts/newcoins.prompts.md",
+	Prompt:       defaultPrompt,
+	FallbackPath: "templates/telegram_post_fallback.template.md",
+	Fallback:     defaultFallbackTemplate,
+}
+
+// TextSource says what wrote a post: the AI provider or the fallback
+// template.
+type TextSource string
+
+const (
+	TextSourceAI       TextSource = "ai"
+	TextSourceFallback TextSource = "fallback"
+)
+
+// GeneratedText is a post with the source that produced it. Model is the AI
+// model that wrote it, empty for the fallback template.
+type GeneratedText struct {
+	Text   string
+	Source TextSource
+	Model  string
+}
+
+func produceTelegramText(ctx context.Context, client *http.Client, cfg Config, renderCtx map[string]any) (GeneratedText, error) {
+	return produceText(ctx, client, cfg, renderCtx, entrantTextSources)
+}
+
+// produceText asks the configured AI provider for the post, trying each model
+// of AI_MODEL_CHAIN in turn until one gives a usable answer, and falls back
+// to rendering the template whenever AI is off or every model fails.
+func produceText(ctx context.Context, client *http.Client, cfg Config, renderCtx map[string]any, src textSources) (GeneratedText, error) {
+	fallback := loadTemplateOrDefault(src.FallbackPath, src.Fallback)
+	call := aiCaller(cfg)
+	if call != nil && cfg.AIProvider == "gemini" && !geminiBreaker.allow(time.Now()) {
+		log.Printf("[AI] warning: Gemini circuit breaker is open; using fallback template without calling it")
+		call = nil
+	}
+	if call != nil {
+		models := cfg.aiModels()
+		prompt := renderTemplateIn(cfg.TemplatesDir, loadTemplateOrDefault(src.PromptPath, src.Prompt), renderCtx)
+		log.Printf("[AI] provider=%s models=%s prompt:\n%s", cfg.AIProvider, strings.Join(models, ","), prompt)
+		for i, model := range models {
+			attempt := cfg
+			attempt.AIModel = model
+			aiCtx, cancel := ctx, func() {}
+			if cfg.AITimeout > 0 {
+				// Keep the AI call shorter than the overall budget so the fallback
+				// template can still be rendered and sent in time.
+				aiCtx, cancel = context.WithTimeout(ctx, cfg.AITimeout)
+			}
+			text, err := call(aiCtx, client, attempt, prompt)
+			cancel()
+			if cfg.AIProvider == "gemini" {
+				geminiBreaker.record(err, time.Now(), cfg.GeminiBreakerThreshold, cfg.GeminiBreakerCooldown)
+			}
+			if err != nil {
+				Metrics.observeAICall(true)
+				log.Printf("[AI] attempt %d/%d: %s model %s failed: %v", i+1, len(models), cfg.AIProvider, model, err)
+			} else {
+				log.Printf("[AI] response:\n%s", text)
+				clean := sanitizeAIText(text)
+				Metrics.observeAICall(clean == "")
+				if clean != "" {
+					log.Printf("[AI] attempt %d/%d: %s model %s succeeded", i+1, len(models), cfg.AIProvider, model)
+					return GeneratedText{Text: clean, Source: TextSourceAI, Model: model}, nil
 				}
+				log.Printf("[AI] attempt %d/%d: %s model %s returned an empty answer", i+1, len(models), cfg.AIProvider, model)
+			}
+			if cfg.AIProvider == "gemini" && !geminiBreaker.allow(time.Now()) {
+				log.Printf("[AI] warning: Gemini circuit breaker opened; not trying the remaining models")
+				break
 			}
 		}
-		coins = append(coins, coin)
+		log.Printf("[AI] no model gave a usable answer; using fallback template")
 	}
-	logos, err := fetchCMCLogos(ctx, client, cfg, coins)
+	return GeneratedText{Text: renderTemplateIn(cfg.TemplatesDir, fallback, renderCtx), Source: TextSourceFallback}, nil
+}
+
+// aiModels is AI_MODEL_CHAIN, or just AIModel when no chain is set.
+func (c Config) aiModels() []string {
+	if len(c.AIModelChain) > 0 {
+		return c.AIModelChain
+	}
+	return []string{c.AIModel}
+}
+
+type aiCallFunc func(ctx context.Context, client *http.Client, cfg Config, prompt string) (string, error)
+
+// aiCaller picks the provider call for cfg, or nil when AI is disabled, the
+// provider is unknown, or its API key is missing.
+func aiCaller(cfg Config) aiCallFunc {
+	if !cfg.AIEnabled {
+		return nil
+	}
+	switch cfg.AIProvider {
+	case "gemini":
+		if cfg.GeminiAPIKey != "" {
+			return callGemini
+		}
+	case "openai":
+		if cfg.OpenAIAPIKey != "" {
+			return callOpenAI
+		}
+	case "anthropic":
+		if cfg.AnthropicAPIKey != "" {
+			return callAnthropic
+		}
+	case "ollama":
+		return callOllama
+	default:
+		log.Printf("[AI] unsupported provider %q; using fallback template", cfg.AIProvider)
+		return nil
+	}
+	log.Printf("[AI] provider %s has no API key configured; using fallback template", cfg.AIProvider)
+	return nil
+}
+
+func loadTemplateOrDefault(path string, fallback string) string {
+	b, err := os.ReadFile(filepath.Clean(path))
 	if err != nil {
-		log.Printf("[fetchCMCTopN] unable to fetch coin logos: %v", err)
-		return coins, nil
+		return fallback
+	}
+	return string(b)
+}
+
+// defaultAITemperature is sent when only AI_MAX_TOKENS is set.
+const defaultAITemperature = 0.7
+
+// geminiGenerationConfig is nil unless AI_TEMPERATURE or AI_MAX_TOKENS is set,
+// so Gemini keeps its own defaults otherwise.
+func (c Config) geminiGenerationConfig() map[string]any {
+	if c.AITemperature == nil && c.AIMaxTokens == 0 {
+		return nil
+	}
+	gen := map[string]any{"temperature": defaultAITemperature}
+	if c.AITemperature != nil {
+		gen["temperature"] = *c.AITemperature
+	}
+	if c.AIMaxTokens > 0 {
+		gen["maxOutputTokens"] = c.AIMaxTokens
+	}
+	return gen
+}
+
+// geminiBlockReasons are finishReason values that mean the answer was
+// withheld; asking again would be withheld the same way.
+var geminiBlockReasons = []string{"SAFETY", "RECITATION", "BLOCKLIST", "PROHIBITED_CONTENT", "SPII", "IMAGE_SAFETY"}
+
+// geminiRetryMaxTokens is the token limit of the retry after a MAX_TOKENS
+// truncation when AI_MAX_TOKENS is not set.
+const geminiRetryMaxTokens = 8192
+
+// callGemini returns the first candidate's text. A safety block fails right
+// away. An answer cut off by MAX_TOKENS is requested once more with a higher
+// token limit, and an empty answer for any other reason once more as is; if
+// that retry does no better the error makes the caller use the fallback.
+func callGemini(ctx context.Context, client *http.Client, cfg Config, prompt string) (text string, err error) {
+	sp := startSpan("call_gemini", "ai_provider", "gemini", "model", cfg.AIModel)
+	defer func() { sp.end(err, "chars", len(text)) }()
+	gen := cfg.geminiGenerationConfig()
+	for attempt := 0; ; attempt++ {
+		res, err := geminiGenerate(ctx, client, cfg, prompt, gen)
+		if err != nil {
+			return "", err
+		}
+		switch {
+		case res.BlockReason != "" || slices.Contains(geminiBlockReasons, res.FinishReason):
+			log.Printf("[Gemini] warning: answer blocked (finishReason=%q blockReason=%q); not retrying", res.FinishReason, res.BlockReason)
+			return "", fmt.Errorf("gemini blocked the answer: finishReason=%q blockReason=%q", res.FinishReason, res.BlockReason)
+		case res.FinishReason == "MAX_TOKENS":
+			log.Printf("[Gemini] warning: answer truncated by MAX_TOKENS after %d characters", len(res.Text))
+			if attempt > 0 {
+				return "", errors.New("gemini answer truncated by MAX_TOKENS")
+			}
+			limit := geminiRetryMaxTokens
+			if cfg.AIMaxTokens > 0 {
+				limit = 2 * cfg.AIMaxTokens
+			}
+			gen = maps.Clone(gen)
+			if gen == nil {
+				gen = map[string]any{}
+			}
+			gen["maxOutputTokens"] = limit
+			log.Printf("[Gemini] retrying once with maxOutputTokens=%d", limit)
+		case res.Text == "":
+			log.Printf("[Gemini] warning: empty answer (finishReason=%q)", res.FinishReason)
+			if attempt > 0 {
+				return "", errors.New("gemini returned an empty answer")
+			}
+			log.Printf("[Gemini] retrying once")
+		default:
+			return res.Text, nil
+		}
+	}
+}
+
+// geminiResult is the part of a generateContent reply callGemini acts on.
+type geminiResult struct {
+	Text         string
+	FinishReason string
+	BlockReason  string
+}
+
+func geminiGenerate(ctx context.Context, client *http.Client, cfg Config, prompt string, gen map[string]any) (geminiResult, error) {
+	u := fmt.Sprintf("https://generativelanguage.googleapis.com/v1beta/models/%s:generateContent", cfg.AIModel)
+	payload := map[string]any{"contents": []any{map[string]any{"parts": []any{map[string]any{"text": prompt}}}}}
+	if gen != nil {
+		payload["generationConfig"] = gen
+	}
+	body, _ := json.Marshal(payload)
+	resp, err := doWithRetry(ctx, client, cfg.httpRetryPolicy(), "Gemini", func() (*http.Request, error) {
+		req, err := http.NewRequestWithContext(ctx, http.MethodPost, u, bytes.NewReader(body))
+		if err != nil {
+			return nil, err
+		}
+		req.Header.Set("x-goog-api-key", cfg.GeminiAPIKey)
+		req.Header.Set("Content-Type", "application/json")
+		return req, nil
+	})
+	if err != nil {
+		return geminiResult{}, upstreamError("gemini", err)
+	}
+	defer resp.Body.Close()
+	if resp.StatusCode >= 300 {
+		b, _ := io.ReadAll(resp.Body)
+		return geminiResult{}, fmt.Errorf("gemini error: %s %s", resp.Status, string(b))
+	}
+	var parsed struct {
+		Candidates []struct {
+			Content struct {
+				Parts []struct {
+					Text string `json:"text"`
+				} `json:"parts"`
+			} `json:"content"`
+			FinishReason string `json:"finishReason"`
+		} `json:"candidates"`
+		PromptFeedback struct {
+			BlockReason string `json:"blockReason"`
+		} `json:"promptFeedback"`
+	}
+	if err := json.NewDecoder(resp.Body).Decode(&parsed); err != nil {
+		return geminiResult{}, err
+	}
+	res := geminiResult{BlockReason: parsed.PromptFeedback.BlockReason}
+	if len(parsed.Candidates) == 0 {
+		return res, nil
+	}
+	cand := parsed.Candidates[0]
+	res.FinishReason = cand.FinishReason
+	if len(cand.Content.Parts) > 0 {
+		res.Text = strings.TrimSpace(cand.Content.Parts[0].Text)
+	}
+	return res, nil
+}
+
+func callOpenAI(ctx context.Context, client *http.Client, cfg Config, prompt string) (string, error) {
+	payload := map[string]any{"model": cfg.AIModel, "messages": []any{map[string]any{"role": "user", "content": prompt}}}
+	body, _ := json.Marshal(payload)
+	resp, err := doWithRetry(ctx, client, cfg.httpRetryPolicy(), "OpenAI", func() (*http.Request, error) {
+		req, err := http.NewRequestWithContext(ctx, http.MethodPost, "https://api.openai.com/v1/chat/completions", bytes.NewReader(body))
+		if err != nil {
+			return nil, err
+		}
+		req.Header.Set("Authorization", "Bearer "+cfg.OpenAIAPIKey)
+		req.Header.Set("Content-Type", "application/json")
+		return req, nil
+	})
+	if err != nil {
+		return "", upstreamError("openai", err)
+	}
+	defer resp.Body.Close()
+	if resp.StatusCode >= 300 {
+		b, _ := io.ReadAll(resp.Body)
+		return "", fmt.Errorf("openai error: %s %s", resp.Status, string(b))
+	}
+	var parsed map[string]any
+	if err := json.NewDecoder(resp.Body).Decode(&parsed); err != nil {
+		return "", err
+	}
+	if usage, ok := parsed["usage"].(map[string]any); ok {
+		log.Printf("[OpenAI] token usage: prompt=%d completion=%d total=%d", asInt64(usage["prompt_tokens"]), asInt64(usage["completion_tokens"]), asInt64(usage["total_tokens"]))
+	}
+	choices, _ := parsed["choices"].([]any)
+	if len(choices) == 0 {
+		return "", nil
+	}
+	choice, _ := choices[0].(map[string]any)
+	message, _ := choice["message"].(map[string]any)
+	return strings.TrimSpace(asString(message["content"])), nil
+}
+
+// callOllama asks a local Ollama server for a non-streamed completion.
+func callOllama(ctx context.Context, client *http.Client, cfg Config, prompt string) (string, error) {
+	payload := map[string]any{"model": cfg.AIModel, "prompt": prompt, "stream": false}
+	body, _ := json.Marshal(payload)
+	resp, err := doWithRetry(ctx, client, cfg.httpRetryPolicy(), "Ollama", func() (*http.Request, error) {
+		req, err := http.NewRequestWithContext(ctx, http.MethodPost, cfg.OllamaBaseURL+"/api/generate", bytes.NewReader(body))
+		if err != nil {
+			return nil, err
+		}
+		req.Header.Set("Content-Type", "application/json")
+		return req, nil
+	})
+	if err != nil {
+		return "", upstreamError("ollama", err)
+	}
+	defer resp.Body.Close()
+	if resp.StatusCode >= 300 {
+		b, _ := io.ReadAll(resp.Body)
+		return "", fmt.Errorf("ollama error: %s %s", resp.Status, string(b))
 	}
-	for i := range coins {
-		if logo := logos[coins[i].ID]; logo != "" {
-			coins[i].ImageURL = logo
-		}
+	var parsed struct {
+		Response string `json:"response"`
+		Error    string `json:"error"`
 	}
-	return coins, nil
-}
-
-func fetchCMCLogos(ctx context.Context, client *http.Client, cfg Config, coins []Coin) (map[int64]string, error) {
-	if len(coins) == 0 {
-		return map[int64]string{}, nil
+	if err := json.NewDecoder(resp.Body).Decode(&parsed); err != nil {
+		return "", err
 	}
-	ids := make([]string, 0, len(coins))
-	for _, c := range coins {
-		ids = append(ids, strconv.FormatInt(c.ID, 10))
+	if parsed.Error != "" {
+		return "", fmt.Errorf("ollama error: %s", parsed.Error)
 	}
-	u := fmt.Sprintf("https://pro-api.coinmarketcap.com/v2/cryptocurrency/info?id=%s", strings.Join(ids, ","))
-	req, _ := http.NewRequestWithContext(ctx, http.MethodGet, u, nil)
-	req.Header.Set("X-CMC_PRO_API_KEY", cfg.CMCAPIKey)
-	resp, err := client.Do(req)
+	return strings.TrimSpace(parsed.Response), nil
+}
+
+// anthropicVersion is the Messages API version sent with every request.
+const anthropicVersion = "2023-06-01"
+
+func callAnthropic(ctx context.Context, client *http.Client, cfg Config, prompt string) (string, error) {
+	payload := map[string]any{"model": cfg.AIModel, "max_tokens": 1024, "messages": []any{map[string]any{"role": "user", "content": prompt}}}
+	body, _ := json.Marshal(payload)
+	resp, err := doWithRetry(ctx, client, cfg.httpRetryPolicy(), "Anthropic", func() (*http.Request, error) {
+		req, err := http.NewRequestWithContext(ctx, http.MethodPost, "https://api.anthropic.com/v1/messages", bytes.NewReader(body))
+		if err != nil {
+			return nil, err
+		}
+		req.Header.Set("x-api-key", cfg.AnthropicAPIKey)
+		req.Header.Set("anthropic-version", anthropicVersion)
+		req.Header.Set("Content-Type", "application/json")
+		return req, nil
+	})
 	if err != nil {
-		return nil, err
+		return "", upstreamError("anthropic", err)
 	}
 	defer resp.Body.Close()
+	var parsed struct {
+		Content []struct {
+			Type string `json:"type"`
+			Text string `json:"text"`
+		} `json:"content"`
+		Error *struct {
+			Type    string `json:"type"`
+			Message string `json:"message"`
+		} `json:"error"`
+	}
 	if resp.StatusCode >= 300 {
 		b, _ := io.ReadAll(resp.Body)
-		return nil, fmt.Errorf("cmc info error: %s %s", resp.Status, string(b))
+		if json.Unmarshal(b, &parsed) == nil && parsed.Error != nil {
+			return "", fmt.Errorf("anthropic error: %s %s: %s", resp.Status, parsed.Error.Type, parsed.Error.Message)
+		}
+		return "", fmt.Errorf("anthropic error: %s %s", resp.Status, string(b))
 	}
-	var payload map[string]any
-	if err := json.NewDecoder(resp.Body).Decode(&payload); err != nil {
-		return nil, err
+	if err := json.NewDecoder(resp.Body).Decode(&parsed); err != nil {
+		return "", err
+	}
+	if len(parsed.Content) == 0 {
+		return "", nil
 	}
-	out := map[int64]string{}
-	data, _ := payload["data"].(map[string]any)
-	for k, raw := range data {
-		id, err := strconv.ParseInt(k, 10, 64)
+	return strings.TrimSpace(parsed.Content[0].Text), nil
+}
+
+// sendTelegramMessage posts text to every configured channel and returns one
+// message id per channel. A failing channel is logged and leaves a nil id;
+// only when every channel failed is the error returned, so the run does not
+// persist state.
+func sendTelegramMessage(ctx context.Context, client *http.Client, cfg Config, text string, imageURL string, parseMode string) (ids []*int64, err error) {
+	channels := cfg.telegramChannels()
+	if len(channels) == 0 {
+		return nil, errors.New("no Telegram channel configured")
+	}
+	sp := startSpan("send_telegram_message", "channels", len(channels), "chars", len(text))
+	defer func() { sp.end(err) }()
+	ids = make([]*int64, len(channels))
+	failures := 0
+	for i, chatID := range channels {
+		msgID, err := sendTelegramMessageTo(ctx, client, cfg, chatID, text, imageURL, parseMode)
 		if err != nil {
+			if failures++; failures == len(channels) {
+				return nil, err
+			}
+			log.Printf("[sendTelegramMessage] warning: send to channel %s failed: %v", chatID, err)
 			continue
 		}
-		entry, _ := raw.(map[string]any)
-		if logo := asString(entry["logo"]); logo != "" {
-			out[id] = logo
+		ids[i] = msgID
+		if msgID != nil {
+			log.Printf("[sendTelegramMessage] sent to channel %s: message_id=%d", chatID, *msgID)
+		} else {
+			log.Printf("[sendTelegramMessage] sent to channel %s", chatID)
 		}
 	}
-	return out, nil
+	return ids, nil
 }
 
-func loadRecentPosts(ctx context.Context, historyCollection *mongo.Collection) ([]RecentPost, error) {
-	cur, err := historyCollection.Find(ctx, bson.M{}, options.Find().SetSort(bson.M{"created_at": -1}).SetLimit(3))
-	if err != nil {
-		return nil, err
-	}
-	defer cur.Close(ctx)
-	out := []RecentPost{}
-	for cur.Next(ctx) {
-		var d historyDoc
-		if err := cur.Decode(&d); err != nil {
-			return nil, err
+// firstMessageID is the id of the first channel that accepted a message, or
+// nil when none reported one.
+func firstMessageID(ids []*int64) *int64 {
+	for _, id := range ids {
+		if id != nil {
+			return id
 		}
-		out = append(out, RecentPost{CreatedAtUTC: d.CreatedAt.UTC().Format(time.RFC3339), Text: d.Text, MentionedCoins: d.MentionedCoins})
 	}
-	return out, cur.Err()
+	return nil
 }
 
-func buildRenderContext(cfg Config, opt RunOptions, newCoins, exited []Coin, recent []RecentPost) map[string]any {
-	return map[string]any{"project_name": "coinmarketcap_top100_bot", "timestamp_utc": time.Now().UTC().Format(time.RFC3339), "top_n": cfg.TopN, "convert": opt.Convert, "new_coins": newCoins, "exited_coins": exited, "recent_posts": recent}
+// telegramChannels returns TELEGRAM_CHANNEL_IDS, or the single channel for
+// configs built without it.
+func (c Config) telegramChannels() []string {
+	if len(c.TelegramChannelIDs) > 0 {
+		return c.TelegramChannelIDs
+	}
+	if c.TelegramChannelID != "" {
+		return []string{c.TelegramChannelID}
+	}
+	return nil
 }
 
-func produceTelegramText(ctx context.Context, client *http.Client, cfg Config, renderCtx map[string]any) (string, error) {
-	fallback := loadTemplateOrDefault("templates/telegram_post_fallback.template.md", defaultFallbackTemplate)
-	if cfg.AIEnabled && cfg.AIProvider == "gemini" && cfg.GeminiAPIKey != "" {
-		prompt := RenderTemplate(loadTemplateOrDefault("prompts/newcoins.prompts.md", defaultPrompt), renderCtx)
-		log.Printf("[Gemini] prompt:\n%s", prompt)
-		text, err := callGemini(ctx, client, cfg, prompt)
-		if err == nil {
-			log.Printf("[Gemini] response:\n%s", text)
-			clean := sanitizeAIText(text)
-			if clean != "" {
-				return clean, nil
-			}
+// sendTelegramMessageTo posts text (with the image when one is available) to
+// one chat using parseMode, which is either "MarkdownV2" or empty for the
+// default HTML mode.
+func sendTelegramMessageTo(ctx context.Context, client *http.Client, cfg Config, chatID, text string, imageURL string, parseMode string) (*int64, error) {
+	if imageURL != "" {
+		if msgID, err := sendTelegramPhoto(ctx, client, cfg, chatID, imageURL, text, parseMode); err == nil {
+			return msgID, nil
 		}
 	}
-	return RenderTemplate(fallback, renderCtx), nil
-}
 
-func loadTemplateOrDefault(path string, fallback string) string {
-	b, err := os.ReadFile(filepath.Clean(path))
-	if err != nil {
-		return fallback
-	}
-	return string(b)
+	return sendTelegramChunks(ctx, client, cfg, chatID, text, parseMode)
 }
 
-func callGemini(ctx context.Context, client *http.Client, cfg Config, prompt string) (string, error) {
-	u := fmt.Sprintf("https://generativelanguage.googleapis.com/v1beta/models/%s:generateContent", cfg.AIModel)
-	payload := map[string]any{"contents": []any{map[string]any{"parts": []any{map[string]any{"text": prompt}}}}}
-	body, _ := json.Marshal(payload)
-	req, _ := http.NewRequestWithContext(ctx, http.MethodPost, u, strings.NewReader(string(body)))
-	req.Header.Set("x-goog-api-key", cfg.GeminiAPIKey)
-	req.Header.Set("Content-Type", "application/json")
-	resp, err := client.Do(req)
-	if err != nil {
-		return "", err
-	}
-	defer resp.Body.Close()
-	if resp.StatusCode >= 300 {
-		b, _ := io.ReadAll(resp.Body)
-		return "", fmt.Errorf("gemini error: %s %s", resp.Status, string(b))
-	}
-	var parsed map[string]any
-	if err := json.NewDecoder(resp.Body).Decode(&parsed); err != nil {
-		return "", err
-	}
-	cands, _ := parsed["candidates"].([]any)
-	if len(cands) == 0 {
-		return "", nil
-	}
-	cand, _ := cands[0].(map[string]any)
-	content, _ := cand["content"].(map[string]any)
-	parts, _ := content["parts"].([]any)
-	if len(parts) == 0 {
-		return "", nil
+// telegramMessageLimit is the maximum length of a sendMessage text.
+const telegramMessageLimit = 4096
+
+// sendTelegramChunks sends text as one message, or as several consecutive
+// ones when it exceeds telegramMessageLimit, and returns the first message id.
+func sendTelegramChunks(ctx context.Context, client *http.Client, cfg Config, chatID, text string, parseMode string) (*int64, error) {
+	chunks := splitTelegramText(text, telegramMessageLimit)
+	var first *int64
+	for i, chunk := range chunks {
+		formatted, mode := formatTelegramText(chunk, parseMode)
+		msgID, err := sendTelegramMessageFormatted(ctx, client, cfg, chatID, formatted, mode)
+		if err != nil {
+			if len(chunks) > 1 {
+				return nil, fmt.Errorf("telegram chunk %d/%d: %w", i+1, len(chunks), err)
+			}
+			return nil, err
+		}
+		if i == 0 {
+			first = msgID
+		}
 	}
-	part, _ := parts[0].(map[string]any)
-	return strings.TrimSpace(asString(part["text"])), nil
+	return first, nil
 }
 
-func sendTelegramMessage(ctx context.Context, client *http.Client, cfg Config, text string, imageURL string) (*int64, error) {
-	formattedText := formatTelegramHTML(text)
+// splitTelegramText splits text into pieces of at most limit characters,
+// breaking at a blank line when possible, then at a newline, and only
+// hard-splitting a line that is longer than limit on its own.
+func splitTelegramText(text string, limit int) []string {
+	text = strings.TrimSpace(text)
+	chunks := []string{}
+	for utf8.RuneCountInString(text) > limit {
+		cut := telegramSplitPoint(text, limit)
+		chunks = append(chunks, strings.TrimRight(text[:cut], "\n"))
+		text = strings.TrimLeft(text[cut:], "\n")
+	}
+	if text != "" {
+		chunks = append(chunks, text)
+	}
+	return chunks
+}
 
-	if imageURL != "" {
-		if msgID, err := sendTelegramPhoto(ctx, client, cfg, imageURL, text); err == nil {
-			return msgID, nil
+func telegramSplitPoint(text string, limit int) int {
+	end, n := len(text), 0
+	for i := range text {
+		if n == limit {
+			end = i
+			break
 		}
+		n++
 	}
-
-	return sendTelegramMessageFormatted(ctx, client, cfg, formattedText)
+	window := text[:end]
+	if i := strings.LastIndex(window, "\n\n"); i > 0 {
+		return i
+	}
+	if i := strings.LastIndex(window, "\n"); i > 0 {
+		return i
+	}
+	return end
 }
 
-func sendTelegramMessageFormatted(ctx context.Context, client *http.Client, cfg Config, formattedText string) (*int64, error) {
-	u := fmt.Sprintf("https://api.telegram.org/bot%s/sendMessage", cfg.TelegramToken)
-	payload := telegramSendMessagePayload(cfg.TelegramChannelID, formattedText)
+func sendTelegramMessageFormatted(ctx context.Context, client *http.Client, cfg Config, chatID, formattedText string, parseMode string) (*int64, error) {
+	u := cfg.telegramURL("sendMessage")
+	payload := telegramSendMessagePayload(chatID, formattedText)
+	payload["parse_mode"] = parseMode
 	body, _ := json.Marshal(payload)
-	req, _ := http.NewRequestWithContext(ctx, http.MethodPost, u, strings.NewReader(string(body)))
-	req.Header.Set("Content-Type", "application/json")
-	resp, err := client.Do(req)
+	resp, err := postTelegramJSON(ctx, client, cfg, u, body)
 	if err != nil {
-		return nil, err
+		return nil, upstreamError("telegram sendMessage", err)
 	}
 	defer resp.Body.Close()
 	if resp.StatusCode >= 300 {
@@ -590,19 +2300,18 @@ func sendTelegramMessageFormatted(ctx context.Context, client *http.Client, cfg
 	return &v, nil
 }
 
-func sendTelegramPhoto(ctx context.Context, client *http.Client, cfg Config, imageURL, caption string) (*int64, error) {
-	formattedCaption := formatTelegramHTML(caption)
-	u := fmt.Sprintf("https://api.telegram.org/bot%s/sendPhoto", cfg.TelegramToken)
-	payload := telegramSendPhotoPayload(cfg.TelegramChannelID, imageURL)
+func sendTelegramPhoto(ctx context.Context, client *http.Client, cfg Config, chatID, imageURL, caption string, parseMode string) (*int64, error) {
+	formattedCaption, mode := formatTelegramText(caption, parseMode)
+	u := cfg.telegramURL("sendPhoto")
+	payload := telegramSendPhotoPayload(chatID, imageURL)
+	payload["parse_mode"] = mode
 	if len([]rune(formattedCaption)) <= 1024 {
 		payload["caption"] = formattedCaption
 	}
 	body, _ := json.Marshal(payload)
-	req, _ := http.NewRequestWithContext(ctx, http.MethodPost, u, strings.NewReader(string(body)))
-	req.Header.Set("Content-Type", "application/json")
-	resp, err := client.Do(req)
+	resp, err := postTelegramJSON(ctx, client, cfg, u, body)
 	if err != nil {
-		return nil, err
+		return nil, upstreamError("telegram sendPhoto", err)
 	}
 	defer resp.Body.Close()
 	if resp.StatusCode >= 300 {
@@ -623,11 +2332,83 @@ func sendTelegramPhoto(ctx context.Context, client *http.Client, cfg Config, ima
 		return nil, nil
 	}
 	if _, hasCaption := payload["caption"]; !hasCaption {
-		return sendTelegramMessageFormatted(ctx, client, cfg, formattedCaption)
+		return sendTelegramChunks(ctx, client, cfg, chatID, caption, parseMode)
 	}
 	return &v, nil
 }
 
+// defaultTelegramAPIBaseURL is the public Bot API host; TELEGRAM_API_BASE_URL
+// overrides it, e.g. with a local Bot API server.
+const defaultTelegramAPIBaseURL = "https://api.telegram.org"
+
+// telegramURL is the Bot API endpoint of method for the configured token.
+func (c Config) telegramURL(method string) string {
+	base := c.TelegramAPIBaseURL
+	if base == "" {
+		base = defaultTelegramAPIBaseURL
+	}
+	return base + "/bot" + c.TelegramToken + "/" + method
+}
+
+// postTelegramJSON posts a Bot API payload, retrying transient failures.
+func postTelegramJSON(ctx context.Context, client *http.Client, cfg Config, u string, body []byte) (*http.Response, error) {
+	return doWithRetry(ctx, client, cfg.httpRetryPolicy(), "Telegram", func() (*http.Request, error) {
+		req, err := http.NewRequestWithContext(ctx, http.MethodPost, u, bytes.NewReader(body))
+		if err != nil {
+			return nil, err
+		}
+		req.Header.Set("Content-Type", "application/json")
+		return req, nil
+	})
+}
+
+// discordContentLimit is the maximum length of a Discord message content field.
+const discordContentLimit = 2000
+
+// notifyDiscord cross-posts text to the Discord webhook when requested. It
+// never fails the run: a Telegram post that already went out stays valid.
+func notifyDiscord(ctx context.Context, client *http.Client, cfg Config, opt RunOptions, text string) {
+	if !opt.NotifyDiscord {
+		return
+	}
+	if cfg.DiscordWebhookURL == "" {
+		log.Printf("[Discord] warning: --notify-discord set but DISCORD_WEBHOOK_URL is empty; skipping")
+		return
+	}
+	if err := sendDiscordMessage(ctx, client, cfg, text); err != nil {
+		log.Printf("[Discord] warning: failed to send message: %v", err)
+		return
+	}
+	log.Printf("[Discord] message sent successfully")
+}
+
+// sendDiscordMessage posts text as is: Discord renders the template's
+// [name](url) links itself.
+func sendDiscordMessage(ctx context.Context, client *http.Client, cfg Config, text string) error {
+	content := strings.TrimSpace(text)
+	if r := []rune(content); len(r) > discordContentLimit {
+		content = string(r[:discordContentLimit-1]) + "…"
+	}
+	body, _ := json.Marshal(map[string]any{"content": content})
+	resp, err := doWithRetry(ctx, client, cfg.httpRetryPolicy(), "Discord", func() (*http.Request, error) {
+		req, err := http.NewRequestWithContext(ctx, http.MethodPost, cfg.DiscordWebhookURL, bytes.NewReader(body))
+		if err != nil {
+			return nil, err
+		}
+		req.Header.Set("Content-Type", "application/json")
+		return req, nil
+	})
+	if err != nil {
+		return upstreamError("discord", err)
+	}
+	defer resp.Body.Close()
+	if resp.StatusCode >= 300 {
+		b, _ := io.ReadAll(resp.Body)
+		return fmt.Errorf("discord error: %s %s", resp.Status, string(b))
+	}
+	return nil
+}
+
 func sanitizeAIText(text string) string {
 	trimmed := strings.TrimSpace(text)
 	trimmed = strings.TrimPrefix(trimmed, "```")
@@ -647,144 +2428,181 @@ func telegramSendPhotoPayload(chatID, imageURL string) map[string]any {
 	return map[string]any{"chat_id": chatID, "photo": imageURL, "parse_mode": "HTML"}
 }
 
-func formatTelegramHTML(text string) string {
-	escaped := html.EscapeString(strings.TrimSpace(text))
-	escaped = markdownBoldRE.ReplaceAllString(escaped, "<b>$1</b>")
-	return escaped
-}
+const parseModeMarkdownV2 = "MarkdownV2"
 
-func firstCoinImageURL(coins []Coin) string {
-	for _, c := range coins {
-		if strings.TrimSpace(c.ImageURL) != "" {
-			return c.ImageURL
-		}
+// telegramParseMode prefers the --parse-mode flag over TELEGRAM_PARSE_MODE.
+func telegramParseMode(cfg Config, opt RunOptions) string {
+	if opt.ParseMode != "" {
+		return opt.ParseMode
 	}
-	return ""
+	return cfg.TelegramParseMode
 }
 
-func writeState(ctx context.Context, stateCollection *mongo.Collection, coinsCollection *mongo.Collection, topN int, convert string, coins []Coin) error {
-	if err := replaceStateCoins(ctx, coinsCollection, "top", coins); err != nil {
-		return err
+// formatTelegramText prepares text for the requested parse mode and returns it
+// with the parse_mode value to send. Anything but MarkdownV2 means HTML.
+func formatTelegramText(text, parseMode string) (string, string) {
+	if parseMode == parseModeMarkdownV2 {
+		return escapeMarkdownV2(strings.TrimSpace(text)), parseModeMarkdownV2
 	}
+	return formatTelegramHTML(text), "HTML"
+}
 
-	ids := make([]int64, 0, len(coins))
-	for _, c := range coins {
-		ids = append(ids, c.ID)
-	}
-	_, err := stateCollection.ReplaceOne(ctx, bson.M{"_id": "top"}, stateDoc{ID: "top", UpdatedAt: time.Now().UTC(), TopN: int64(topN), Convert: convert, IDs: ids}, options.Replace().SetUpsert(true))
-	return err
+// markdownV2Reserved are the characters Telegram requires to be escaped in
+// MarkdownV2 text, including the backslash itself.
+const markdownV2Reserved = "_*[]()~`>#+-=|{}.!\\"
+
+var markdownV2LinkRE = regexp.MustCompile(`\[([^\]]*)\]\(([^)\s]*)\)`)
+
+// escapeMarkdownV2 keeps the markup the template or AI wrote: [text](url)
+// links, *bold* (or **bold**), _italic_ and `monospace` spans, and backslash
+// escapes such as the ones %MARKDOWN_ESCAPE% puts in. Every other reserved
+// character, an unpaired marker included, is escaped so the post is always
+// valid MarkdownV2. The URL of a link only needs ")" and "\" escaped.
+func escapeMarkdownV2(text string) string {
+	text = markdownBoldRE.ReplaceAllString(text, "*$1*")
+	var b strings.Builder
+	last := 0
+	for _, m := range markdownV2LinkRE.FindAllStringSubmatchIndex(text, -1) {
+		b.WriteString(escapeMarkdownV2Markup(text[last:m[0]]))
+		b.WriteString("[" + escapeMarkdownV2Markup(text[m[2]:m[3]]) + "](" + escapeMarkdownV2URL(text[m[4]:m[5]]) + ")")
+		last = m[1]
+	}
+	b.WriteString(escapeMarkdownV2Markup(text[last:]))
+	return b.String()
 }
 
-func replaceStateCoins(ctx context.Context, coinsCollection *mongo.Collection, stateID string, coins []Coin) error {
-	now := time.Now().UTC()
-	if _, err := coinsCollection.UpdateMany(ctx, bson.M{"state_id": stateID}, bson.M{"$set": bson.M{"is_active": false, "updated_at": now}}); err != nil {
-		return err
-	}
-	docs := buildStateCoinDocs(stateID, coins, now)
-	if len(docs) == 0 {
-		return nil
-	}
-	for _, d := range docs {
-		_, err := coinsCollection.UpdateOne(
-			ctx,
-			bson.M{"state_id": stateID, "id": d.ID},
-			bson.M{
-				"$set": bson.M{
-					"name":                d.Name,
-					"symbol":              d.Symbol,
-					"rank":                d.Rank,
-					"tick_timestamp":      d.TickTimestamp,
-					"market_cap":          d.MarketCap,
-					"market_cap_currency": d.MarketCapCurrency,
-					"image_url":           d.ImageURL,
-					"is_active":           true,
-					"updated_at":          now,
-				},
-				"$setOnInsert": bson.M{"created_at": now},
-			},
-			options.Update().SetUpsert(true),
-		)
-		if err != nil {
-			return err
+// escapeMarkdownV2Markup escapes s like escapeMarkdownV2Text but leaves
+// backslash escapes and paired *, _ and ` markers in place. A * or _ only
+// pairs at a word boundary, so snake_case names and URLs stay literal. The
+// inside of a bold or italic span is handled the same way, the inside of a
+// monospace span only needs its backslashes escaped.
+func escapeMarkdownV2Markup(s string) string {
+	var b strings.Builder
+	for i := 0; i < len(s); {
+		c := s[i]
+		switch {
+		case c == '\\' && i+1 < len(s) && strings.IndexByte(markdownV2Reserved, s[i+1]) >= 0:
+			b.WriteString(s[i : i+2])
+			i += 2
+			continue
+		case c == '*' || c == '_' || c == '`':
+			end := markdownV2Closer(s[i+1:], c)
+			if c != '`' && end > 0 {
+				after := i + end + 2
+				if (i > 0 && isWordByte(s[i-1])) || (after < len(s) && isWordByte(s[after])) {
+					end = -1
+				}
+			}
+			if end > 0 {
+				inner := s[i+1 : i+1+end]
+				if c == '`' {
+					inner = strings.ReplaceAll(inner, `\`, `\\`)
+				} else {
+					inner = escapeMarkdownV2Markup(inner)
+				}
+				b.WriteString(string(c) + inner + string(c))
+				i += end + 2
+				continue
+			}
 		}
+		r, size := utf8.DecodeRuneInString(s[i:])
+		if strings.ContainsRune(markdownV2Reserved, r) {
+			b.WriteByte('\\')
+		}
+		b.WriteRune(r)
+		i += size
 	}
-	return nil
+	return b.String()
 }
 
-func buildStateCoinDocs(stateID string, coins []Coin, now time.Time) []stateCoinDoc {
-	out := make([]stateCoinDoc, 0, len(coins))
-	for _, coin := range coins {
-		out = append(out, stateCoinDoc{
-			StateID:           stateID,
-			ID:                coin.ID,
-			Name:              coin.Name,
-			Symbol:            coin.Symbol,
-			Rank:              coin.Rank,
-			TickTimestamp:     now,
-			MarketCap:         coin.MarketCap,
-			MarketCapCurrency: coin.MarketCapCurrency,
-			ImageURL:          coin.ImageURL,
-			IsActive:          true,
-			Updated:           now,
-		})
+// markdownV2Closer returns the offset of the first unescaped c in s, or -1.
+func markdownV2Closer(s string, c byte) int {
+	for i := 0; i < len(s); i++ {
+		switch s[i] {
+		case '\\':
+			i++
+		case c:
+			return i
+		}
 	}
-	return out
+	return -1
 }
 
-func loadStateCoins(ctx context.Context, coinsCollection *mongo.Collection, stateID string) ([]Coin, error) {
-	cur, err := coinsCollection.Find(ctx, bson.M{"state_id": stateID, "is_active": true}, options.Find().SetSort(bson.M{"rank": 1}))
-	if err != nil {
-		return nil, err
+func isWordByte(c byte) bool {
+	return c >= 'a' && c <= 'z' || c >= 'A' && c <= 'Z' || c >= '0' && c <= '9'
+}
+
+// escapeMarkdownV2Text escapes every reserved character, the backslash
+// included, so text shows exactly as written.
+func escapeMarkdownV2Text(s string) string {
+	var b strings.Builder
+	for _, r := range s {
+		if strings.ContainsRune(markdownV2Reserved, r) {
+			b.WriteByte('\\')
+		}
+		b.WriteRune(r)
 	}
-	defer cur.Close(ctx)
+	return b.String()
+}
 
-	out := []Coin{}
-	for cur.Next(ctx) {
-		var doc stateCoinDoc
-		if err := cur.Decode(&doc); err != nil {
-			return nil, err
+func escapeMarkdownV2URL(s string) string {
+	return strings.NewReplacer(`\`, `\\`, `)`, `\)`).Replace(s)
+}
+
+func formatTelegramHTML(text string) string {
+	escaped := html.EscapeString(strings.TrimSpace(text))
+	escaped = markdownBoldRE.ReplaceAllString(escaped, "<b>$1</b>")
+	escaped = markdownV2LinkRE.ReplaceAllString(escaped, `<a href="$2">$1</a>`)
+	return escaped
+}
+
+// plainTextLinks turns the [name](url) links a template writes for Telegram
+// into "name (url)" for channels that show the text as is.
+func plainTextLinks(text string) string {
+	return markdownV2LinkRE.ReplaceAllString(text, "$1 ($2)")
+}
+
+func firstCoinImageURL(coins []Coin) string {
+	for _, c := range coins {
+		if strings.TrimSpace(c.ImageURL) != "" {
+			return c.ImageURL
 		}
-		tickTS := doc.TickTimestamp.UTC()
-		out = append(out, Coin{ID: doc.ID, Name: doc.Name, Symbol: doc.Symbol, Rank: doc.Rank, TickTimestamp: &tickTS, MarketCap: doc.MarketCap, MarketCapCurrency: doc.MarketCapCurrency, ImageURL: doc.ImageURL})
 	}
-	return out, cur.Err()
+	return ""
 }
 
 func ReplayLastTick(ctx context.Context, cfg Config, convert string) (string, *int64, error) {
-	httpClient := &http.Client{Timeout: 30 * time.Second}
-	db, client, err := connectDB(ctx, cfg)
+	httpClient := NewHTTPClient(cfg)
+	store, err := OpenStateStore(ctx, cfg)
 	if err != nil {
 		return "", nil, err
 	}
-	defer client.Disconnect(context.Background())
+	defer store.Close(context.Background())
 
-	historyCollection := db.Collection(cfg.MongoDBHistoryCollection)
-	var last historyDoc
-	err = historyCollection.FindOne(ctx, bson.M{}, options.FindOne().SetSort(bson.M{"created_at": -1})).Decode(&last)
+	last, err := store.LatestHistory(ctx)
 	if err != nil {
-		if errors.Is(err, mongo.ErrNoDocuments) {
+		if errors.Is(err, ErrNoHistory) {
 			return "", nil, fmt.Errorf("no previous tick found in history")
 		}
 		return "", nil, err
 	}
 
-	msgID, err := sendTelegramMessage(ctx, httpClient, cfg, last.Text, firstCoinImageURL(last.MentionedCoins))
+	msgIDs, err := sendTelegramMessage(ctx, httpClient, cfg, last.Text, firstCoinImageURL(last.MentionedCoins), cfg.TelegramParseMode)
 	if err != nil {
 		return "", nil, err
 	}
-
-	newIDs := make([]int64, 0, len(last.MentionedCoins))
-	for _, c := range last.MentionedCoins {
-		newIDs = append(newIDs, c.ID)
-	}
-	_, err = historyCollection.InsertOne(ctx, historyDoc{
-		CreatedAt:         time.Now().UTC(),
-		TopN:              last.TopN,
-		Convert:           convert,
-		NewCoinIDs:        newIDs,
-		Text:              last.Text,
-		MentionedCoins:    last.MentionedCoins,
-		TelegramMessageID: msgID,
+	msgID := firstMessageID(msgIDs)
+
+	err = store.InsertHistory(ctx, HistoryDoc{
+		CreatedAt:          time.Now().UTC(),
+		TopN:               last.TopN,
+		Convert:            convert,
+		NewCoinIDs:         coinIDs(last.MentionedCoins),
+		Text:               last.Text,
+		MentionedCoins:     last.MentionedCoins,
+		TelegramMessageID:  msgID,
+		TelegramMessageIDs: msgIDs,
+		Provider:           last.Provider,
 	})
 	if err != nil {
 		return "", nil, err
@@ -793,6 +2611,14 @@ func ReplayLastTick(ctx context.Context, cfg Config, convert string) (string, *i
 	return last.Text, msgID, nil
 }
 
+func coinIDs(coins []Coin) []int64 {
+	ids := make([]int64, 0, len(coins))
+	for _, c := range coins {
+		ids = append(ids, c.ID)
+	}
+	return ids
+}
+
 func coinSymbols(coins []Coin) []string {
 	symbols := make([]string, 0, len(coins))
 	for _, coin := range coins {
@@ -801,9 +2627,53 @@ func coinSymbols(coins []Coin) []string {
 	return symbols
 }
 
-func RenderTemplate(t string, ctx map[string]any) string { return renderBlock(t, ctx, nil) }
+func RenderTemplate(t string, ctx map[string]any) string {
+	return renderTemplateIn(defaultTemplatesDir, t, ctx)
+}
+
+// renderTemplateIn renders t with %INCLUDE path% partials read from dir, or
+// from defaultTemplatesDir when dir is empty.
+func renderTemplateIn(dir, t string, ctx map[string]any) string {
+	if dir == "" {
+		dir = defaultTemplatesDir
+	}
+	return renderBlock(t, ctx, nil, includeScope{dir: dir})
+}
+
+// defaultTemplatesDir is where %INCLUDE% partials are read from unless
+// TEMPLATES_DIR says otherwise.
+const defaultTemplatesDir = "templates"
+
+// maxIncludeDepth bounds nested %INCLUDE%s, so a partial that includes itself
+// renders as empty instead of recursing forever.
+const maxIncludeDepth = 8
+
+// includeScope is the directory partials are read from and how many
+// %INCLUDE%s deep the block being rendered is.
+type includeScope struct {
+	dir   string
+	depth int
+}
+
+// renderInclude reads the partial at path, relative to inc.dir, and renders
+// it with the current context. A missing file or a too deep include renders
+// as empty with a warning.
+func renderInclude(path string, root, local map[string]any, inc includeScope) string {
+	if inc.depth >= maxIncludeDepth {
+		log.Printf("[Template] warning: %%INCLUDE %s%% is nested more than %d levels deep; rendering it empty", path, maxIncludeDepth)
+		return ""
+	}
+	// Cleaning against "/" keeps the partial inside dir.
+	full := filepath.Join(inc.dir, filepath.Clean("/"+path))
+	b, err := os.ReadFile(full)
+	if err != nil {
+		log.Printf("[Template] warning: unable to read %%INCLUDE%% partial %s: %v; rendering it empty", full, err)
+		return ""
+	}
+	return renderBlock(string(b), root, local, includeScope{dir: inc.dir, depth: inc.depth + 1})
+}
 
-func renderBlock(t string, root map[string]any, local map[string]any) string {
+func renderBlock(t string, root map[string]any, local map[string]any, inc includeScope) string {
 	var out strings.Builder
 	for i := 0; i < len(t); {
 		s := t[i:]
@@ -812,44 +2682,73 @@ func renderBlock(t string, root map[string]any, local map[string]any) string {
 			i += 2
 			continue
 		}
+		if strings.HasPrefix(s, "%INCLUDE ") {
+			end := strings.Index(t[i+9:], "%")
+			if end < 0 {
+				break
+			}
+			out.WriteString(renderInclude(strings.TrimSpace(t[i+9:i+9+end]), root, local, inc))
+			i += 9 + end + 1
+			continue
+		}
 		if strings.HasPrefix(s, "%EACH ") {
 			end := strings.Index(t[i+6:], "%")
 			if end < 0 {
 				break
 			}
-			tag := strings.TrimSpace(t[i+6 : i+6+end])
+			tag, limit := parseEachTag(t[i+6 : i+6+end])
 			blockStart := i + 6 + end + 1
-			endEach := strings.Index(t[blockStart:], "%END_EACH%")
+			endEach := findClosingTag(t[blockStart:], "%EACH ", "%END_EACH%")
 			if endEach < 0 {
 				break
 			}
 			block := t[blockStart : blockStart+endEach]
 			if arr := toSlice(resolve(local, root, tag)); len(arr) > 0 {
-				for _, it := range arr {
-					if m, ok := toMap(it); ok {
-						out.WriteString(renderBlock(block, root, m))
+				remaining := 0
+				if limit >= 0 && limit < len(arr) {
+					arr, remaining = arr[:limit], len(arr)-limit
+				}
+				for n, it := range arr {
+					m, ok := toMap(it)
+					if !ok {
+						continue
 					}
+					// Copy so a map item from the context is not modified.
+					m = maps.Clone(m)
+					m["_index"], m["_number"] = n, n+1
+					m["_first"], m["_last"] = n == 0, n == len(arr)-1
+					if remaining > 0 {
+						m["_remaining"] = remaining
+					}
+					out.WriteString(renderBlock(block, root, m, inc))
 				}
 			}
 			i = blockStart + endEach + len("%END_EACH%")
 			continue
 		}
-		if strings.HasPrefix(s, "%IF ") {
-			end := strings.Index(t[i+4:], "%")
+		if strings.HasPrefix(s, "%IF ") || strings.HasPrefix(s, "%UNLESS ") {
+			// UNLESS is IF with the condition negated.
+			open, closeTag, negate := "%IF ", "%END_IF%", false
+			if strings.HasPrefix(s, "%UNLESS ") {
+				open, closeTag, negate = "%UNLESS ", "%END_UNLESS%", true
+			}
+			end := strings.Index(t[i+len(open):], "%")
 			if end < 0 {
 				break
 			}
-			varName := strings.TrimSpace(t[i+4 : i+4+end])
-			blockStart := i + 4 + end + 1
-			endIf := strings.Index(t[blockStart:], "%END_IF%")
+			varName := strings.TrimSpace(t[i+len(open) : i+len(open)+end])
+			blockStart := i + len(open) + end + 1
+			endIf := findClosingTag(t[blockStart:], open, closeTag)
 			if endIf < 0 {
 				break
 			}
-			block := t[blockStart : blockStart+endIf]
-			if truthy(resolve(local, root, varName)) {
-				out.WriteString(renderBlock(block, root, local))
+			thenBlock, elseBlock := splitElse(t[blockStart : blockStart+endIf])
+			if truthy(resolve(local, root, varName)) != negate {
+				out.WriteString(renderBlock(thenBlock, root, local, inc))
+			} else {
+				out.WriteString(renderBlock(elseBlock, root, local, inc))
 			}
-			i = blockStart + endIf + len("%END_IF%")
+			i = blockStart + endIf + len(closeTag)
 			continue
 		}
 		if t[i] == '%' {
@@ -862,11 +2761,43 @@ func renderBlock(t string, root map[string]any, local map[string]any) string {
 			token := t[i+1 : i+1+end]
 			parts := strings.SplitN(token, "|", 2)
 			key := strings.TrimSpace(parts[0])
+			spec := ""
+			if k, f, ok := strings.Cut(key, ":"); ok {
+				key, spec = strings.TrimSpace(k), strings.TrimSpace(f)
+			}
 			def := ""
 			if len(parts) > 1 {
 				def = parts[1]
 			}
-			val := stringify(resolve(local, root, key))
+			raw := resolve(local, root, key)
+			if key == "MARKDOWN_ESCAPE" {
+				// %MARKDOWN_ESCAPE:key% escapes the value for MarkdownV2 so
+				// its * and _ show as written; the sender keeps backslash
+				// escapes. Other parse modes get the value as is.
+				v := stringify(resolve(local, root, spec))
+				if resolve(local, root, "parse_mode") == parseModeMarkdownV2 {
+					v = escapeMarkdownV2Text(v)
+				}
+				raw, spec = v, ""
+			}
+			switch key {
+			case "FORMAT_NUMBER":
+				// %FORMAT_NUMBER:key% groups thousands with no decimals.
+				raw, spec = resolve(local, root, spec), ",.0f"
+			case "FORMAT_CURRENCY":
+				// %FORMAT_CURRENCY:key% abbreviates with the convert currency
+				// symbol, eg $12.3B.
+				raw, spec = resolve(local, root, spec), ""
+				if f, ok := asFloat(raw); ok {
+					raw = formatCurrency(f, stringify(root["convert"]))
+				}
+			}
+			val := stringify(raw)
+			if spec != "" {
+				if formatted, ok := formatNumber(raw, spec); ok {
+					val = formatted
+				}
+			}
 			if strings.TrimSpace(val) == "" {
 				val = def
 			}
@@ -880,21 +2811,86 @@ func renderBlock(t string, root map[string]any, local map[string]any) string {
 	return out.String()
 }
 
+// parseEachTag splits "new_coins LIMIT 5" into the list name and the limit;
+// the limit is -1 (unlimited) when there is no valid LIMIT suffix.
+func parseEachTag(tag string) (string, int) {
+	fields := strings.Fields(tag)
+	if len(fields) == 3 && fields[1] == "LIMIT" {
+		if n, err := strconv.Atoi(fields[2]); err == nil && n >= 0 {
+			return fields[0], n
+		}
+	}
+	return strings.TrimSpace(tag), -1
+}
+
+// findClosingTag returns the index of the close tag matching an already opened
+// block, skipping over nested blocks that use the same open tag.
+func findClosingTag(t, open, close string) int {
+	depth := 0
+	for i := 0; i < len(t); i++ {
+		switch {
+		case strings.HasPrefix(t[i:], open):
+			depth++
+		case strings.HasPrefix(t[i:], close):
+			if depth == 0 {
+				return i
+			}
+			depth--
+		}
+	}
+	return -1
+}
+
+// splitElse splits an IF/UNLESS body on its first top-level %ELSE%, ignoring
+// any %ELSE% that belongs to a nested IF or UNLESS.
+func splitElse(block string) (string, string) {
+	depth := 0
+	for i := 0; i < len(block); i++ {
+		switch {
+		case strings.HasPrefix(block[i:], "%IF "), strings.HasPrefix(block[i:], "%UNLESS "):
+			depth++
+		case strings.HasPrefix(block[i:], "%END_IF%"), strings.HasPrefix(block[i:], "%END_UNLESS%"):
+			depth--
+		case depth == 0 && strings.HasPrefix(block[i:], "%ELSE%"):
+			return block[:i], block[i+len("%ELSE%"):]
+		}
+	}
+	return block, ""
+}
+
+// resolve looks key up in the loop item first and then in the root context.
+// Dotted keys such as quote.USD.price walk nested objects one segment at a
+// time and yield nil as soon as a segment is missing.
 func resolve(local, root map[string]any, key string) any {
 	if key == "" {
 		return nil
 	}
+	parts := strings.Split(key, ".")
+	var v any
+	found := false
 	if local != nil {
-		if v, ok := local[key]; ok {
-			return v
+		v, found = local[parts[0]]
+	}
+	if !found {
+		v = root[parts[0]]
+	}
+	for _, part := range parts[1:] {
+		m, ok := toMap(v)
+		if !ok {
+			return nil
+		}
+		if v, ok = m[part]; !ok {
+			return nil
 		}
 	}
-	return root[key]
+	return v
 }
 func truthy(v any) bool {
 	switch vv := v.(type) {
 	case nil:
 		return false
+	case bool:
+		return vv
 	case string:
 		return vv != ""
 	default:
@@ -945,13 +2941,119 @@ func stringify(v any) string {
 		return string(b)
 	}
 }
-func asString(v any) string { s, _ := v.(string); return s }
-func asStringDef(v any, def string) string {
-	if s := asString(v); s != "" {
-		return s
+// formatNumber renders a numeric value using a small printf-like spec: an
+// optional leading "," for thousands grouping followed by an optional ".N" or
+// ".Nf" for fixed decimals, e.g. ",.0f", ".2f" or ",". Non-numeric values and
+// unknown specs report false so the caller keeps the plain rendering.
+func formatNumber(v any, spec string) (string, bool) {
+	f, ok := asFloat(v)
+	if !ok {
+		return "", false
+	}
+	switch spec {
+	case "arrow":
+		return formatPercentArrow(f), true
+	case "abbrev":
+		return formatAbbrev(f), true
+	}
+	grouping := strings.HasPrefix(spec, ",")
+	spec = strings.TrimSuffix(strings.TrimPrefix(spec, ","), "f")
+	decimals := -1
+	if strings.HasPrefix(spec, ".") {
+		n, err := strconv.Atoi(spec[1:])
+		if err != nil || n < 0 {
+			return "", false
+		}
+		decimals = n
+	} else if spec != "" {
+		return "", false
 	}
-	return def
+	out := strconv.FormatFloat(f, 'f', decimals, 64)
+	if grouping {
+		out = groupThousands(out)
+	}
+	return out, true
+}
+
+// formatPercentArrow renders a percent change as ▲2.35% / ▼1.20%.
+func formatPercentArrow(f float64) string {
+	arrow := ""
+	switch {
+	case f > 0:
+		arrow = "▲"
+	case f < 0:
+		arrow = "▼"
+	}
+	return arrow + strconv.FormatFloat(math.Abs(f), 'f', 2, 64) + "%"
+}
+
+// formatAbbrev renders large amounts with a K/M/B/T suffix, eg 1.23B.
+func formatAbbrev(f float64) string {
+	return abbreviate(f, 2)
+}
+
+var abbrevUnits = []struct {
+	size   float64
+	suffix string
+}{{1, ""}, {1e3, "K"}, {1e6, "M"}, {1e9, "B"}, {1e12, "T"}}
+
+// abbreviate writes f with the given decimals in the smallest unit whose
+// rounded value stays below 1000, so 999,950 at one decimal is 1.0M rather
+// than 1000.0K.
+func abbreviate(f float64, decimals int) string {
+	for _, u := range abbrevUnits[:len(abbrevUnits)-1] {
+		s := strconv.FormatFloat(f/u.size, 'f', decimals, 64)
+		if v, _ := strconv.ParseFloat(s, 64); math.Abs(v) < 1000 {
+			return s + u.suffix
+		}
+	}
+	last := abbrevUnits[len(abbrevUnits)-1]
+	return strconv.FormatFloat(f/last.size, 'f', decimals, 64) + last.suffix
+}
+
+// currencySymbols maps convert codes to the symbol FORMAT_CURRENCY prints.
+var currencySymbols = map[string]string{"USD": "$", "EUR": "€", "GBP": "£", "JPY": "¥", "CNY": "¥", "INR": "₹", "KRW": "₩", "RUB": "₽", "TRY": "₺", "BTC": "₿"}
+
+// formatCurrency renders an amount as $12.3B: one decimal and a K/M/B/T
+// suffix from a thousand up, two decimals below. Codes without a known symbol
+// are written out, eg CHF 4.5M.
+func formatCurrency(f float64, convert string) string {
+	code := strings.ToUpper(strings.TrimSpace(convert))
+	prefix, ok := currencySymbols[code]
+	if !ok && code != "" {
+		prefix = code + " "
+	}
+	sign := ""
+	if f < 0 {
+		sign, f = "-", -f
+	}
+	amount := strconv.FormatFloat(f, 'f', 2, 64)
+	if v, _ := strconv.ParseFloat(amount, 64); v >= 1000 {
+		amount = abbreviate(f, 1)
+	}
+	return sign + prefix + amount
 }
+
+func groupThousands(s string) string {
+	sign := ""
+	if strings.HasPrefix(s, "-") {
+		sign, s = "-", s[1:]
+	}
+	intPart, frac := s, ""
+	if i := strings.IndexByte(s, '.'); i >= 0 {
+		intPart, frac = s[:i], s[i:]
+	}
+	var b strings.Builder
+	for i := 0; i < len(intPart); i++ {
+		if i > 0 && (len(intPart)-i)%3 == 0 {
+			b.WriteByte(',')
+		}
+		b.WriteByte(intPart[i])
+	}
+	return sign + b.String() + frac
+}
+
+func asString(v any) string { s, _ := v.(string); return s }
 func asInt64(v any) int64 {
 	switch n := v.(type) {
 	case float64:
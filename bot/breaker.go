@@ -0,0 +1,44 @@
+package bot
+
+import (
+	"log"
+	"sync"
+	"time"
+)
+
+// geminiBreaker is shared by every run in the process, so a daemon (--interval)
+// or a warm Lambda stops calling Gemini while it keeps failing.
+var geminiBreaker = &circuitBreaker{name: "Gemini"}
+
+// circuitBreaker opens after threshold consecutive failures and rejects calls
+// until the cooldown has passed. The first call after that is a probe: a
+// failure reopens it straight away, a success closes it.
+type circuitBreaker struct {
+	name      string
+	mu        sync.Mutex
+	failures  int
+	openUntil time.Time
+}
+
+// allow reports whether a call may be made at now.
+func (b *circuitBreaker) allow(now time.Time) bool {
+	b.mu.Lock()
+	defer b.mu.Unlock()
+	return !now.Before(b.openUntil)
+}
+
+// record counts the outcome of a call. threshold <= 0 disables the breaker.
+func (b *circuitBreaker) record(err error, now time.Time, threshold int, cooldown time.Duration) {
+	b.mu.Lock()
+	defer b.mu.Unlock()
+	if err == nil {
+		b.failures = 0
+		b.openUntil = time.Time{}
+		return
+	}
+	b.failures++
+	if threshold > 0 && b.failures >= threshold {
+		b.openUntil = now.Add(cooldown)
+		log.Printf("[%s] warning: circuit breaker open after %d consecutive failure(s); skipping calls until %s", b.name, b.failures, b.openUntil.Format(time.RFC3339))
+	}
+}
@@ -0,0 +1,53 @@
+package bot
+
+import (
+	"context"
+	"errors"
+	"net/http"
+	"testing"
+	"time"
+)
+
+func TestCircuitBreakerOpensAfterThresholdAndProbesAfterCooldown(t *testing.T) {
+	b := &circuitBreaker{name: "test"}
+	now := time.Date(2026, 1, 15, 10, 0, 0, 0, time.UTC)
+	fail := errors.New("boom")
+
+	b.record(fail, now, 2, time.Minute)
+	if !b.allow(now) {
+		t.Fatal("breaker should stay closed below the threshold")
+	}
+	b.record(fail, now, 2, time.Minute)
+	if b.allow(now.Add(30 * time.Second)) {
+		t.Fatal("breaker should be open during the cooldown")
+	}
+	if !b.allow(now.Add(time.Minute)) {
+		t.Fatal("breaker should let a probe through after the cooldown")
+	}
+	b.record(fail, now.Add(time.Minute), 2, time.Minute)
+	if b.allow(now.Add(90 * time.Second)) {
+		t.Fatal("a failed probe should reopen the breaker")
+	}
+	b.record(nil, now.Add(2*time.Minute), 2, time.Minute)
+	b.record(fail, now.Add(2*time.Minute), 2, time.Minute)
+	if !b.allow(now.Add(2 * time.Minute)) {
+		t.Fatal("a success should reset the failure count")
+	}
+}
+
+func TestProduceTelegramTextSkipsGeminiWhileBreakerIsOpen(t *testing.T) {
+	saved := geminiBreaker
+	geminiBreaker = &circuitBreaker{name: "Gemini", openUntil: time.Now().Add(time.Hour)}
+	defer func() { geminiBreaker = saved }()
+
+	client := &http.Client{Transport: roundTripFunc(func(req *http.Request) (*http.Response, error) {
+		t.Fatalf("unexpected request while the breaker is open: %s", req.URL)
+		return nil, nil
+	})}
+	cfg := Config{AIEnabled: true, AIProvider: "gemini", GeminiAPIKey: "key", TopN: 100}
+
+	gen, err := produceTelegramText(context.Background(), client, cfg, map[string]any{"top_n": 100, "new_coins": []Coin{}})
+	if err != nil || gen.Text == "" || gen.Source != TextSourceFallback {
+		t.Fatalf("expected fallback text, got %+v, %v", gen, err)
+	}
+}
@@ -0,0 +1,90 @@
+package bot
+
+import (
+	"bytes"
+	"crypto/sha256"
+	"encoding/hex"
+	"io"
+	"log"
+	"net/http"
+	"os"
+	"path/filepath"
+	"strings"
+	"time"
+)
+
+// withCMCCache returns client with its CoinMarketCap GET responses cached in
+// CMC_CACHE_DIR for CMC_CACHE_TTL_SECS, so repeated dry runs during
+// development do not spend credits. Real runs always get the client as is,
+// so production state is never built from a stale listing.
+func withCMCCache(client *http.Client, cfg Config, dryRun bool) *http.Client {
+	if !dryRun || cfg.CMCCacheDir == "" {
+		return client
+	}
+	next := client.Transport
+	if next == nil {
+		next = http.DefaultTransport
+	}
+	cached := *client
+	cached.Transport = &cmcCacheTransport{next: next, dir: cfg.CMCCacheDir, ttl: cfg.CMCCacheTTL, prefix: cfg.cmcURL("")}
+	return &cached
+}
+
+// cmcCacheTransport serves GET requests under prefix from files named by the
+// SHA-256 of the URL while they are younger than ttl. Only 200 responses are
+// stored; cache read or write failures fall back to the network with a
+// warning.
+type cmcCacheTransport struct {
+	next   http.RoundTripper
+	dir    string
+	ttl    time.Duration
+	prefix string
+}
+
+func (t *cmcCacheTransport) RoundTrip(req *http.Request) (*http.Response, error) {
+	u := req.URL.String()
+	if req.Method != http.MethodGet || !strings.HasPrefix(u, t.prefix) {
+		return t.next.RoundTrip(req)
+	}
+	sum := sha256.Sum256([]byte(u))
+	path := filepath.Join(t.dir, hex.EncodeToString(sum[:])+".json")
+	if info, err := os.Stat(path); err == nil && time.Since(info.ModTime()) < t.ttl {
+		if body, err := os.ReadFile(path); err == nil {
+			log.Printf("[CMCCache] serving %s from %s", req.URL.Path, path)
+			return cachedResponse(req, body), nil
+		}
+	}
+
+	resp, err := t.next.RoundTrip(req)
+	if err != nil || resp.StatusCode != http.StatusOK {
+		return resp, err
+	}
+	body, err := io.ReadAll(resp.Body)
+	resp.Body.Close()
+	if err != nil {
+		return nil, err
+	}
+	resp.Body = io.NopCloser(bytes.NewReader(body))
+	if err := os.MkdirAll(t.dir, 0o755); err != nil {
+		log.Printf("[CMCCache] warning: failed to create %s: %v", t.dir, err)
+		return resp, nil
+	}
+	if err := os.WriteFile(path, body, 0o644); err != nil {
+		log.Printf("[CMCCache] warning: failed to write %s: %v", path, err)
+	}
+	return resp, nil
+}
+
+func cachedResponse(req *http.Request, body []byte) *http.Response {
+	return &http.Response{
+		Status:        "200 OK",
+		StatusCode:    http.StatusOK,
+		Proto:         "HTTP/1.1",
+		ProtoMajor:    1,
+		ProtoMinor:    1,
+		Header:        http.Header{"Content-Type": []string{"application/json"}},
+		Body:          io.NopCloser(bytes.NewReader(body)),
+		ContentLength: int64(len(body)),
+		Request:       req,
+	}
+}
@@ -0,0 +1,51 @@
+package bot
+
+import (
+	"net/http"
+	"os"
+	"path/filepath"
+	"testing"
+	"time"
+)
+
+func TestCMCCacheServesRepeatedGetsWithinTTL(t *testing.T) {
+	calls := 0
+	client := &http.Client{Transport: roundTripFunc(func(req *http.Request) (*http.Response, error) {
+		calls++
+		return jsonResponse(http.StatusOK, `{"data":[]}`), nil
+	})}
+	dir := t.TempDir()
+	cfg := Config{CMCBaseURL: "https://cmc.test", CMCCacheDir: dir, CMCCacheTTL: time.Hour}
+
+	if got := withCMCCache(client, cfg, false); got != client {
+		t.Fatal("the cache must not be used outside dry runs")
+	}
+	cached := withCMCCache(client, cfg, true)
+	for i := 0; i < 2; i++ {
+		resp, err := cached.Get("https://cmc.test/v1/cryptocurrency/listings/latest?limit=100")
+		if err != nil {
+			t.Fatalf("get: %v", err)
+		}
+		resp.Body.Close()
+	}
+	if calls != 1 {
+		t.Fatalf("expected 1 network call, got %d", calls)
+	}
+
+	files, _ := filepath.Glob(filepath.Join(dir, "*.json"))
+	if len(files) != 1 {
+		t.Fatalf("expected one cache file, got %v", files)
+	}
+	stale := time.Now().Add(-2 * time.Hour)
+	if err := os.Chtimes(files[0], stale, stale); err != nil {
+		t.Fatalf("chtimes: %v", err)
+	}
+	resp, err := cached.Get("https://cmc.test/v1/cryptocurrency/listings/latest?limit=100")
+	if err != nil {
+		t.Fatalf("get: %v", err)
+	}
+	resp.Body.Close()
+	if calls != 2 {
+		t.Fatalf("expected an expired entry to be refetched, got %d call(s)", calls)
+	}
+}
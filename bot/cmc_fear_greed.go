@@ -0,0 +1,71 @@
+package bot
+
+import (
+	"context"
+	"encoding/json"
+	"fmt"
+	"io"
+	"log"
+	"net/http"
+)
+
+// FearGreed is CMC's market sentiment index, 0 (extreme fear) to 100
+// (extreme greed).
+type FearGreed struct {
+	Value int64
+	Label string
+}
+
+// cmcFearGreedResponse skips the status block: the v3 endpoint reports
+// error_code as a string, unlike the v1/v2 ones cmcStatus is modelled on.
+type cmcFearGreedResponse struct {
+	Data *struct {
+		Value               *int64 `json:"value"`
+		ValueClassification string `json:"value_classification"`
+	} `json:"data"`
+}
+
+// loadFearGreed fetches the index when FEAR_GREED_ENABLED is set. Like the
+// global metrics it is decoration: any failure is logged and yields nil.
+func loadFearGreed(ctx context.Context, client *http.Client, cfg Config) *FearGreed {
+	if !cfg.FearGreedEnabled {
+		return nil
+	}
+	fg, err := fetchCMCFearGreed(ctx, client, cfg)
+	if err != nil {
+		log.Printf("[FearGreed] warning: failed to fetch fear & greed index; posting without it: %v", err)
+		return nil
+	}
+	return fg
+}
+
+func fetchCMCFearGreed(ctx context.Context, client *http.Client, cfg Config) (*FearGreed, error) {
+	u := cfg.cmcURL("/v3/fear-and-greed/latest")
+	resp, err := doWithRetry(ctx, client, cfg.httpRetryPolicy(), "FearGreed", func() (*http.Request, error) {
+		req, err := http.NewRequestWithContext(ctx, http.MethodGet, u, nil)
+		if err != nil {
+			return nil, err
+		}
+		req.Header.Set("X-CMC_PRO_API_KEY", cfg.CMCAPIKey)
+		return req, nil
+	})
+	if err != nil {
+		return nil, upstreamError("cmc fear and greed", err)
+	}
+	defer resp.Body.Close()
+	if resp.StatusCode >= 300 {
+		return nil, readCMCError(resp)
+	}
+	return parseCMCFearGreed(resp.Body)
+}
+
+func parseCMCFearGreed(r io.Reader) (*FearGreed, error) {
+	var payload cmcFearGreedResponse
+	if err := json.NewDecoder(r).Decode(&payload); err != nil {
+		return nil, fmt.Errorf("decode cmc fear and greed: %w", err)
+	}
+	if payload.Data == nil || payload.Data.Value == nil || payload.Data.ValueClassification == "" {
+		return nil, fmt.Errorf("cmc fear and greed: response has no value or classification")
+	}
+	return &FearGreed{Value: *payload.Data.Value, Label: payload.Data.ValueClassification}, nil
+}
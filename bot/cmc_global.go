@@ -0,0 +1,94 @@
+package bot
+
+import (
+	"context"
+	"encoding/json"
+	"fmt"
+	"log"
+	"net/http"
+	"net/url"
+	"os"
+	"strings"
+)
+
+// GlobalMetrics is the market-wide snapshot exposed to templates as `global`.
+type GlobalMetrics struct {
+	TotalMarketCap         *float64 `json:"total_market_cap,omitempty"`
+	TotalVolume24h         *float64 `json:"total_volume_24h,omitempty"`
+	BTCDominance           *float64 `json:"btc_dominance,omitempty"`
+	ETHDominance           *float64 `json:"eth_dominance,omitempty"`
+	ActiveCryptocurrencies *int64   `json:"active_cryptocurrencies,omitempty"`
+	Currency               string   `json:"currency"`
+}
+
+type cmcGlobalMetricsResponse struct {
+	Status cmcStatus `json:"status"`
+	Data   *struct {
+		BTCDominance           *float64 `json:"btc_dominance"`
+		ETHDominance           *float64 `json:"eth_dominance"`
+		ActiveCryptocurrencies *int64   `json:"active_cryptocurrencies"`
+		Quote                  map[string]struct {
+			TotalMarketCap *float64 `json:"total_market_cap"`
+			TotalVolume24h *float64 `json:"total_volume_24h"`
+		} `json:"quote"`
+	} `json:"data"`
+}
+
+// loadGlobalMetrics fetches global metrics unless FETCH_GLOBAL_METRICS=false.
+// The call is secondary: any failure is logged and yields nil.
+func loadGlobalMetrics(ctx context.Context, client *http.Client, cfg Config, convert string) *GlobalMetrics {
+	if !cfg.GlobalMetricsEnabled {
+		return nil
+	}
+	global, err := fetchCMCGlobalMetrics(ctx, client, cfg, convert)
+	if err != nil {
+		log.Printf("[GlobalMetrics] warning: failed to fetch global metrics; posting without them: %v", err)
+		return nil
+	}
+	return global
+}
+
+func fetchCMCGlobalMetrics(ctx context.Context, client *http.Client, cfg Config, convert string) (*GlobalMetrics, error) {
+	u := cfg.cmcURL("/v1/global-metrics/quotes/latest?convert=" + url.QueryEscape(convert))
+	resp, err := doWithRetry(ctx, client, cfg.httpRetryPolicy(), "GlobalMetrics", func() (*http.Request, error) {
+		req, err := http.NewRequestWithContext(ctx, http.MethodGet, u, nil)
+		if err != nil {
+			return nil, err
+		}
+		req.Header.Set("X-CMC_PRO_API_KEY", cfg.CMCAPIKey)
+		return req, nil
+	})
+	if err != nil {
+		return nil, upstreamError("cmc global metrics", err)
+	}
+	defer resp.Body.Close()
+	if resp.StatusCode >= 300 {
+		return nil, readCMCError(resp)
+	}
+	var payload cmcGlobalMetricsResponse
+	if err := json.NewDecoder(resp.Body).Decode(&payload); err != nil {
+		return nil, fmt.Errorf("decode cmc global metrics: %w", err)
+	}
+	if payload.Data == nil {
+		return nil, fmt.Errorf("cmc global metrics: response has no data object")
+	}
+	global := &GlobalMetrics{BTCDominance: payload.Data.BTCDominance, ETHDominance: payload.Data.ETHDominance, ActiveCryptocurrencies: payload.Data.ActiveCryptocurrencies, Currency: convert}
+	if q, ok := payload.Data.Quote[convert]; ok {
+		global.TotalMarketCap = q.TotalMarketCap
+		global.TotalVolume24h = q.TotalVolume24h
+	}
+	return global, nil
+}
+
+// globalMetricsEnabledFromEnv reads FETCH_GLOBAL_METRICS (default true). The
+// older opt-in GLOBAL_METRICS_ENABLED is still honoured when the new variable
+// is unset.
+func globalMetricsEnabledFromEnv() bool {
+	if raw := strings.TrimSpace(os.Getenv("FETCH_GLOBAL_METRICS")); raw != "" {
+		return !strings.EqualFold(raw, "false")
+	}
+	if raw := strings.TrimSpace(os.Getenv("GLOBAL_METRICS_ENABLED")); raw != "" {
+		return strings.EqualFold(raw, "true")
+	}
+	return true
+}
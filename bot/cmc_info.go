@@ -0,0 +1,111 @@
+package bot
+
+import (
+	"context"
+	"encoding/json"
+	"fmt"
+	"log"
+	"net/http"
+	"strconv"
+	"strings"
+)
+
+// CoinDetails is the /v2/cryptocurrency/info metadata shown for new entrants.
+type CoinDetails struct {
+	ID          int64  `json:"id"`
+	Symbol      string `json:"symbol"`
+	Description string `json:"description,omitempty"`
+	Website     string `json:"website,omitempty"`
+	Twitter     string `json:"twitter,omitempty"`
+	LogoURL     string `json:"logo_url,omitempty"`
+}
+
+type cmcInfoEntry struct {
+	Symbol      string `json:"symbol"`
+	Description string `json:"description"`
+	Logo        string `json:"logo"`
+	URLs        struct {
+		Website []string `json:"website"`
+		Twitter []string `json:"twitter"`
+	} `json:"urls"`
+}
+
+// loadNewCoinDetails fetches metadata for the new entrants only, in the same
+// order. It is skipped when NEW_COIN_DETAILS_ENABLED=false and degrades to an
+// empty list on failure so the post still goes out.
+func loadNewCoinDetails(ctx context.Context, client *http.Client, cfg Config, newCoins []Coin) []CoinDetails {
+	if !cfg.NewCoinDetailsEnabled || len(newCoins) == 0 {
+		return []CoinDetails{}
+	}
+	info, err := fetchCMCInfo(ctx, client, cfg, coinIDs(newCoins))
+	if err != nil {
+		log.Printf("[CoinDetails] warning: failed to fetch new coin metadata; posting without it: %v", err)
+		return []CoinDetails{}
+	}
+	out := make([]CoinDetails, 0, len(newCoins))
+	for _, c := range newCoins {
+		if d, ok := info[c.ID]; ok {
+			out = append(out, d)
+		}
+	}
+	return out
+}
+
+// withWebsite returns the details that have a website, for the links section.
+func withWebsite(details []CoinDetails) []CoinDetails {
+	out := []CoinDetails{}
+	for _, d := range details {
+		if d.Website != "" {
+			out = append(out, d)
+		}
+	}
+	return out
+}
+
+func fetchCMCInfo(ctx context.Context, client *http.Client, cfg Config, ids []int64) (map[int64]CoinDetails, error) {
+	if len(ids) == 0 {
+		return map[int64]CoinDetails{}, nil
+	}
+	parts := make([]string, 0, len(ids))
+	for _, id := range ids {
+		parts = append(parts, strconv.FormatInt(id, 10))
+	}
+	u := cfg.cmcURL("/v2/cryptocurrency/info?id=" + strings.Join(parts, ","))
+	resp, err := doWithRetry(ctx, client, cfg.httpRetryPolicy(), "fetchCMCInfo", func() (*http.Request, error) {
+		req, err := http.NewRequestWithContext(ctx, http.MethodGet, u, nil)
+		if err != nil {
+			return nil, err
+		}
+		req.Header.Set("X-CMC_PRO_API_KEY", cfg.CMCAPIKey)
+		return req, nil
+	})
+	if err != nil {
+		return nil, upstreamError("cmc info", err)
+	}
+	defer resp.Body.Close()
+	if resp.StatusCode >= 300 {
+		return nil, readCMCError(resp)
+	}
+	var payload struct {
+		Data map[string]cmcInfoEntry `json:"data"`
+	}
+	if err := json.NewDecoder(resp.Body).Decode(&payload); err != nil {
+		return nil, fmt.Errorf("decode cmc info: %w", err)
+	}
+	out := make(map[int64]CoinDetails, len(payload.Data))
+	for k, entry := range payload.Data {
+		id, err := strconv.ParseInt(k, 10, 64)
+		if err != nil {
+			continue
+		}
+		d := CoinDetails{ID: id, Symbol: entry.Symbol, Description: strings.TrimSpace(entry.Description), LogoURL: entry.Logo}
+		if len(entry.URLs.Website) > 0 {
+			d.Website = entry.URLs.Website[0]
+		}
+		if len(entry.URLs.Twitter) > 0 {
+			d.Twitter = entry.URLs.Twitter[0]
+		}
+		out[id] = d
+	}
+	return out, nil
+}
@@ -0,0 +1,582 @@
+package bot
+
+import (
+	"bytes"
+	"context"
+	"errors"
+	"fmt"
+	"io"
+	"net/http"
+	"net/http/httptest"
+	"os"
+	"strconv"
+	"strings"
+	"testing"
+	"time"
+)
+
+type roundTripFunc func(req *http.Request) (*http.Response, error)
+
+func (f roundTripFunc) RoundTrip(req *http.Request) (*http.Response, error) { return f(req) }
+
+func jsonResponse(status int, body string) *http.Response {
+	return &http.Response{
+		StatusCode: status,
+		Status:     http.StatusText(status),
+		Body:       io.NopCloser(bytes.NewBufferString(body)),
+		Header:     make(http.Header),
+	}
+}
+
+func TestFetchCMCTopNSurfacesCMCErrorMessage(t *testing.T) {
+	client := &http.Client{Transport: roundTripFunc(func(req *http.Request) (*http.Response, error) {
+		return jsonResponse(http.StatusPaymentRequired, `{"status":{"error_code":1003,"error_message":"Your API Key must be activated."}}`), nil
+	})}
+
+	_, err := fetchCMCTopN(context.Background(), client, Config{TopN: 1}, RunOptions{Convert: "USD"}, nil)
+	var cmcErr *CMCError
+	if !errors.As(err, &cmcErr) {
+		t.Fatalf("expected *CMCError, got %v", err)
+	}
+	if cmcErr.Code != 1003 || cmcErr.HTTPStatus != http.StatusPaymentRequired || cmcErr.Message != "Your API Key must be activated." {
+		t.Fatalf("unexpected CMC error: %+v", cmcErr)
+	}
+}
+
+func listingsBody(ids ...int) string {
+	items := make([]string, 0, len(ids))
+	for _, id := range ids {
+		items = append(items, fmt.Sprintf(`{"id":%d,"name":"Coin %d","symbol":"C%d","cmc_rank":%d}`, id, id, id, id))
+	}
+	return `{"data":[` + strings.Join(items, ",") + `]}`
+}
+
+func TestFetchCMCTopNPaginatesAndDeduplicates(t *testing.T) {
+	var starts []int
+	client := &http.Client{Transport: roundTripFunc(func(req *http.Request) (*http.Response, error) {
+		if strings.Contains(req.URL.Path, "/info") {
+			return jsonResponse(http.StatusOK, `{"data":{}}`), nil
+		}
+		start, _ := strconv.Atoi(req.URL.Query().Get("start"))
+		limit, _ := strconv.Atoi(req.URL.Query().Get("limit"))
+		starts = append(starts, start)
+		ids := []int{}
+		if start > 1 {
+			ids = append(ids, start-1) // overlap with the previous page
+		}
+		for id := start; id < start+limit; id++ {
+			ids = append(ids, id)
+		}
+		return jsonResponse(http.StatusOK, listingsBody(ids...)), nil
+	})}
+
+	coins, err := fetchCMCTopN(context.Background(), client, Config{TopN: 5002}, RunOptions{Convert: "USD"}, nil)
+	if err != nil {
+		t.Fatalf("fetchCMCTopN error: %v", err)
+	}
+	if len(starts) != 2 || starts[0] != 1 || starts[1] != 5001 {
+		t.Fatalf("unexpected page starts: %v", starts)
+	}
+	if len(coins) != 5002 || coins[5001].ID != 5002 {
+		t.Fatalf("unexpected coins: len=%d", len(coins))
+	}
+}
+
+func TestFetchCMCTopNRejectsTruncatedListing(t *testing.T) {
+	client := &http.Client{Transport: roundTripFunc(func(req *http.Request) (*http.Response, error) {
+		return jsonResponse(http.StatusOK, listingsBody(1, 2)), nil
+	})}
+
+	_, err := fetchCMCTopN(context.Background(), client, Config{TopN: 3}, RunOptions{Convert: "USD"}, nil)
+	if err == nil || !strings.Contains(err.Error(), "returned 2 coins, expected 3") {
+		t.Fatalf("expected truncated listing error, got %v", err)
+	}
+}
+
+func TestParseCMCListingsFixture(t *testing.T) {
+	f, err := os.Open("testdata/cmc_listings_ok.json")
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer f.Close()
+
+	coins, err := parseCMCListings(f, "USD", time.Now())
+	if err != nil {
+		t.Fatalf("parseCMCListings error: %v", err)
+	}
+	if len(coins) != 2 || coins[0].Symbol != "BTC" || coins[1].Rank != 2 {
+		t.Fatalf("unexpected coins: %+v", coins)
+	}
+	if coins[0].MarketCap == nil || *coins[0].MarketCap != 1280000000000.25 {
+		t.Fatalf("unexpected BTC market cap: %v", coins[0].MarketCap)
+	}
+	if coins[1].MarketCap != nil {
+		t.Fatalf("null market cap should stay nil, got %v", *coins[1].MarketCap)
+	}
+	if coins[0].Price == nil || *coins[0].Price != 65000.5 || coins[0].PercentChange24h == nil || *coins[0].PercentChange24h != 2.35 {
+		t.Fatalf("unexpected BTC price fields: %+v", coins[0])
+	}
+	if coins[1].PercentChange24h != nil || coins[1].PercentChange7d != nil {
+		t.Fatalf("missing percent changes should stay nil: %+v", coins[1])
+	}
+}
+
+func TestParseCMCListingsRejectsPartialEntry(t *testing.T) {
+	f, err := os.Open("testdata/cmc_listings_partial.json")
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer f.Close()
+
+	_, err = parseCMCListings(f, "USD", time.Now())
+	if err == nil || !strings.Contains(err.Error(), `"symbol"`) || !strings.Contains(err.Error(), "id=1027") {
+		t.Fatalf("expected error naming the missing symbol field, got %v", err)
+	}
+}
+
+func TestFallbackTemplateRendersFixturePriceAndChange(t *testing.T) {
+	f, err := os.Open("testdata/cmc_listings_ok.json")
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer f.Close()
+
+	coins, err := parseCMCListings(f, "USD", time.Now())
+	if err != nil {
+		t.Fatalf("parseCMCListings error: %v", err)
+	}
+	renderCtx := buildRenderContext(Config{TopN: 100}, RunOptions{Convert: "USD"}, renderInputs{NewCoins: coins})
+	got := RenderTemplate(defaultFallbackTemplate, renderCtx)
+	if !strings.Contains(got, "price: 65,000.5000 (24h: ▲2.35%)") {
+		t.Fatalf("missing BTC price/change in %q", got)
+	}
+	if !strings.Contains(got, "(ETH), price: 3,200.1000\n") {
+		t.Fatalf("ETH without 24h change should only show price in %q", got)
+	}
+}
+
+func TestRenderContextAddsVolumeToMarketCapRatio(t *testing.T) {
+	mcap, vol := 2e9, 5e8
+	coins := []Coin{{ID: 1, Symbol: "AAA", MarketCap: &mcap, Volume24h: &vol}, {ID: 2, Symbol: "BBB", Volume24h: &vol}}
+	renderCtx := buildRenderContext(Config{TopN: 100}, RunOptions{Convert: "USD"}, renderInputs{NewCoins: coins})
+
+	got := RenderTemplate("%EACH new_coins%%symbol%=%volume_to_market_cap_ratio:.2f|n/a% %volume_24h:abbrev%;%END_EACH%", renderCtx)
+	if got != "AAA=0.25 500.00M;BBB=n/a 500.00M;" {
+		t.Fatalf("unexpected output: %q", got)
+	}
+	if coins[0].VolumeToMarketCapRatio != nil {
+		t.Fatal("render context must not modify the input coins")
+	}
+}
+
+func TestFallbackTemplateShowsMarketCapDominance(t *testing.T) {
+	body := `{"data":[{"id":1,"name":"Bitcoin","symbol":"BTC","cmc_rank":1,"quote":{"USD":{"market_cap":1280000000000,"market_cap_dominance":54.3219}}}]}`
+	coins, err := parseCMCListings(strings.NewReader(body), "USD", time.Now())
+	if err != nil {
+		t.Fatalf("parseCMCListings error: %v", err)
+	}
+	if coins[0].MarketCapDominance == nil || *coins[0].MarketCapDominance != 54.3219 {
+		t.Fatalf("unexpected dominance: %v", coins[0].MarketCapDominance)
+	}
+	renderCtx := buildRenderContext(Config{TopN: 100}, RunOptions{Convert: "USD"}, renderInputs{NewCoins: coins})
+	if got := RenderTemplate(defaultFallbackTemplate, renderCtx); !strings.Contains(got, "mcap: 1,280,000,000,000 (54.32% dominance)") {
+		t.Fatalf("missing dominance in %q", got)
+	}
+}
+
+func TestShippedFallbackTemplateShowsMarketCapDominance(t *testing.T) {
+	tpl, err := os.ReadFile("../templates/telegram_post_fallback.template.md")
+	if err != nil {
+		t.Fatal(err)
+	}
+	marketCap, dominance := 1280000000000.0, 54.3219
+	coins := []Coin{{ID: 1, Name: "Bitcoin", Symbol: "BTC", Rank: 1, MarketCap: &marketCap, MarketCapCurrency: "USD", MarketCapDominance: &dominance}}
+	got := RenderTemplate(string(tpl), buildRenderContext(Config{TopN: 100}, RunOptions{Convert: "USD"}, renderInputs{NewCoins: coins}))
+	if !strings.Contains(got, "mcap: 1,280,000,000,000 USD (54.32% dominance)") {
+		t.Fatalf("unexpected render of the shipped template: %q", got)
+	}
+	if strings.Contains(got, "IF ") || strings.Contains(got, "END_IF") || strings.Contains(got, "EACH") {
+		t.Fatalf("template markup leaked into %q", got)
+	}
+}
+
+func TestShippedPromptPassesCoinURLsToTheModel(t *testing.T) {
+	tpl, err := os.ReadFile("../prompts/newcoins.prompts.md")
+	if err != nil {
+		t.Fatal(err)
+	}
+	marketCap := 5.0
+	sui := Coin{ID: 20947, Name: "Sui", Symbol: "SUI", Slug: "sui", Rank: 42, MarketCap: &marketCap, MarketCapCurrency: "USD", ImageURL: "https://img/sui.png"}
+	renderCtx := buildRenderContext(Config{TopN: 100}, RunOptions{Convert: "USD"}, renderInputs{
+		NewCoins:    []Coin{sui},
+		RecentPosts: []RecentPost{{CreatedAtUTC: "2026-01-15T10:00:00Z", Text: "earlier post", MentionedCoins: []Coin{sui}}},
+	})
+	got := RenderTemplate(string(tpl), renderCtx)
+	if !strings.Contains(got, "market_cap=5 USD image_url=https://img/sui.png cmc_url=https://") {
+		t.Fatalf("expected image_url and cmc_url on the entrant line, got %q", got)
+	}
+	if !strings.Contains(got, "symbol=SUI market_cap=5 USD\n") {
+		t.Fatalf("expected a clean mentioned_coins line, got %q", got)
+	}
+}
+
+func TestParseCMCListingsDateAddedAndAgeDays(t *testing.T) {
+	body := `{"data":[{"id":1,"name":"Bitcoin","symbol":"BTC","cmc_rank":1,"date_added":"2013-04-28T00:00:00.000Z"},{"id":2,"name":"Fresh","symbol":"FRSH","cmc_rank":2}]}`
+	coins, err := parseCMCListings(strings.NewReader(body), "USD", time.Now())
+	if err != nil {
+		t.Fatalf("parseCMCListings error: %v", err)
+	}
+	if coins[0].DateAdded == nil || !coins[0].DateAdded.Equal(time.Date(2013, 4, 28, 0, 0, 0, 0, time.UTC)) || coins[1].DateAdded != nil {
+		t.Fatalf("unexpected date_added: %v / %v", coins[0].DateAdded, coins[1].DateAdded)
+	}
+
+	listed := time.Now().UTC().Add(-34*24*time.Hour - time.Hour)
+	coins[1].DateAdded = &listed
+	renderCtx := buildRenderContext(Config{TopN: 2}, RunOptions{Convert: "USD"}, renderInputs{NewCoins: coins[1:]})
+	if got := RenderTemplate("%EACH new_coins%listed %age_days% days ago%END_EACH%", renderCtx); got != "listed 34 days ago" {
+		t.Fatalf("unexpected output: %q", got)
+	}
+}
+
+func TestParseCMCFearGreedFixture(t *testing.T) {
+	f, err := os.Open("testdata/cmc_fear_greed.json")
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer f.Close()
+
+	fg, err := parseCMCFearGreed(f)
+	if err != nil {
+		t.Fatalf("parseCMCFearGreed error: %v", err)
+	}
+	if fg.Value != 23 || fg.Label != "Extreme Fear" {
+		t.Fatalf("unexpected fear & greed: %+v", fg)
+	}
+	renderCtx := buildRenderContext(Config{TopN: 100}, RunOptions{Convert: "USD"}, renderInputs{FearGreed: fg})
+	if got := RenderTemplate(defaultFallbackTemplate, renderCtx); !strings.Contains(got, "Fear & Greed: 23/100 (Extreme Fear)\n") {
+		t.Fatalf("missing fear & greed line in %q", got)
+	}
+}
+
+func TestLoadFearGreedDegradesOnFailure(t *testing.T) {
+	client := &http.Client{Transport: roundTripFunc(func(req *http.Request) (*http.Response, error) {
+		if req.URL.Path != "/v3/fear-and-greed/latest" {
+			t.Fatalf("unexpected request: %s", req.URL)
+		}
+		return jsonResponse(http.StatusOK, `{"data":null}`), nil
+	})}
+
+	if fg := loadFearGreed(context.Background(), client, Config{FearGreedEnabled: true}); fg != nil {
+		t.Fatalf("expected nil on failure, got %+v", fg)
+	}
+}
+
+func TestFetchCMCGlobalMetricsParsesConvertQuote(t *testing.T) {
+	client := &http.Client{Transport: roundTripFunc(func(req *http.Request) (*http.Response, error) {
+		if !strings.Contains(req.URL.Path, "/v1/global-metrics/quotes/latest") || req.URL.Query().Get("convert") != "EUR" {
+			t.Fatalf("unexpected request: %s", req.URL)
+		}
+		return jsonResponse(http.StatusOK, `{"status":{"error_code":0},"data":{"btc_dominance":52.1,"eth_dominance":17.3,"active_cryptocurrencies":9876,"quote":{"EUR":{"total_market_cap":2300000000000,"total_volume_24h":91000000000}}}}`), nil
+	})}
+
+	global, err := fetchCMCGlobalMetrics(context.Background(), client, Config{}, "EUR")
+	if err != nil {
+		t.Fatalf("fetchCMCGlobalMetrics error: %v", err)
+	}
+	if global.TotalMarketCap == nil || *global.TotalMarketCap != 2300000000000 || global.BTCDominance == nil || *global.BTCDominance != 52.1 || global.ActiveCryptocurrencies == nil || *global.ActiveCryptocurrencies != 9876 {
+		t.Fatalf("unexpected global metrics: %+v", global)
+	}
+}
+
+func TestLoadGlobalMetricsDegradesOnFailure(t *testing.T) {
+	client := &http.Client{Transport: roundTripFunc(func(req *http.Request) (*http.Response, error) {
+		return jsonResponse(http.StatusForbidden, `{"status":{"error_code":1006,"error_message":"plan does not support this endpoint"}}`), nil
+	})}
+
+	if global := loadGlobalMetrics(context.Background(), client, Config{GlobalMetricsEnabled: true}, "USD"); global != nil {
+		t.Fatalf("expected nil global metrics on failure, got %+v", global)
+	}
+}
+
+func TestGlobalMetricsEnabledFromEnv(t *testing.T) {
+	cases := []struct {
+		fetch, legacy string
+		want          bool
+	}{
+		{"", "", true},
+		{"false", "", false},
+		{"", "false", false},
+		{"", "true", true},
+		{"true", "false", true},
+		{"FALSE", "true", false},
+	}
+	for _, tc := range cases {
+		t.Setenv("FETCH_GLOBAL_METRICS", tc.fetch)
+		t.Setenv("GLOBAL_METRICS_ENABLED", tc.legacy)
+		if got := globalMetricsEnabledFromEnv(); got != tc.want {
+			t.Fatalf("FETCH_GLOBAL_METRICS=%q GLOBAL_METRICS_ENABLED=%q: got %v, want %v", tc.fetch, tc.legacy, got, tc.want)
+		}
+	}
+}
+
+func TestLoadNewCoinDetailsKeepsNewCoinOrder(t *testing.T) {
+	client := &http.Client{Transport: roundTripFunc(func(req *http.Request) (*http.Response, error) {
+		if got := req.URL.Query().Get("id"); got != "2,1" {
+			t.Fatalf("expected info for new coin ids only, got id=%s", got)
+		}
+		return jsonResponse(http.StatusOK, `{"data":{
+			"1":{"symbol":"AAA","description":"First coin.","logo":"https://img/1.png","urls":{"website":["https://aaa.example"],"twitter":[]}},
+			"2":{"symbol":"BBB","description":"","logo":"https://img/2.png","urls":{"website":[],"twitter":["https://x.com/bbb"]}}}}`), nil
+	})}
+	cfg := Config{NewCoinDetailsEnabled: true}
+
+	details := loadNewCoinDetails(context.Background(), client, cfg, []Coin{{ID: 2, Symbol: "BBB"}, {ID: 1, Symbol: "AAA"}})
+	if len(details) != 2 || details[0].ID != 2 || details[1].ID != 1 {
+		t.Fatalf("unexpected details order: %+v", details)
+	}
+	if details[1].Website != "https://aaa.example" || details[1].Description != "First coin." || details[0].Twitter != "https://x.com/bbb" || details[0].LogoURL != "https://img/2.png" {
+		t.Fatalf("unexpected details: %+v", details)
+	}
+}
+
+func TestLoadNewCoinDetailsDegradesOnFailure(t *testing.T) {
+	client := &http.Client{Transport: roundTripFunc(func(req *http.Request) (*http.Response, error) {
+		return jsonResponse(http.StatusUnauthorized, `{"status":{"error_code":1001,"error_message":"invalid key"}}`), nil
+	})}
+
+	if details := loadNewCoinDetails(context.Background(), client, Config{NewCoinDetailsEnabled: true}, []Coin{{ID: 1}}); len(details) != 0 {
+		t.Fatalf("expected no details on failure, got %+v", details)
+	}
+}
+
+func TestFallbackTemplateShowsLinksOnlyWithWebsites(t *testing.T) {
+	coins := []Coin{{ID: 1, Name: "Alpha", Symbol: "AAA", Rank: 1}, {ID: 2, Name: "Beta", Symbol: "BBB", Rank: 2}}
+	render := func(details []CoinDetails) string {
+		return RenderTemplate(defaultFallbackTemplate, buildRenderContext(Config{TopN: 100}, RunOptions{Convert: "USD"}, renderInputs{NewCoins: coins, NewCoinsDetails: details}))
+	}
+
+	if got := render([]CoinDetails{{ID: 1, Symbol: "AAA", Twitter: "https://x.com/aaa"}}); strings.Contains(got, "Links") {
+		t.Fatalf("details without a website should not add a Links header: %q", got)
+	}
+	got := render([]CoinDetails{{ID: 1, Symbol: "AAA"}, {ID: 2, Symbol: "BBB", Website: "https://beta.example"}})
+	if !strings.Contains(got, "🔗 Links:\n• BBB: https://beta.example\n") || strings.Contains(got, "• AAA:") {
+		t.Fatalf("expected only BBB under Links, got %q", got)
+	}
+}
+
+func TestFetchCMCTopNAccumulatesCreditUsage(t *testing.T) {
+	client := &http.Client{Transport: roundTripFunc(func(req *http.Request) (*http.Response, error) {
+		if strings.Contains(req.URL.Path, "/info") {
+			return jsonResponse(http.StatusOK, `{"status":{"credit_count":1},"data":{}}`), nil
+		}
+		body := strings.Replace(listingsBody(1, 2), `{"data"`, `{"status":{"error_code":0,"credit_count":2,"elapsed":15},"data"`, 1)
+		return jsonResponse(http.StatusOK, body), nil
+	})}
+
+	metrics := &RunMetrics{}
+	if _, err := fetchCMCTopN(context.Background(), client, Config{TopN: 2}, RunOptions{Convert: "USD"}, metrics); err != nil {
+		t.Fatalf("fetchCMCTopN error: %v", err)
+	}
+	if metrics.CMCCredits != 2 || metrics.CMCElapsedMs != 15 || metrics.CMCRequests != 1 {
+		t.Fatalf("unexpected metrics: %+v", metrics)
+	}
+}
+
+func TestParseCMCListingsMultiConvert(t *testing.T) {
+	f, err := os.Open("testdata/cmc_listings_multi_convert.json")
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer f.Close()
+
+	coins, err := parseCMCListings(f, "USD", time.Now())
+	if err != nil {
+		t.Fatalf("parseCMCListings error: %v", err)
+	}
+	btc := coins[0]
+	if btc.MarketCapCurrency != "USD" || btc.Price == nil || *btc.Price != 65000.5 {
+		t.Fatalf("primary currency should stay USD: %+v", btc)
+	}
+	eur, ok := btc.Quotes["EUR"]
+	if !ok || eur.MarketCap == nil || *eur.MarketCap != 1180000000000 || eur.Price == nil || *eur.Price != 60000.25 {
+		t.Fatalf("unexpected EUR quote: %+v", btc.Quotes)
+	}
+
+	renderCtx := buildRenderContext(Config{TopN: 2}, RunOptions{Convert: "USD", ExtraConverts: []string{"EUR"}}, renderInputs{NewCoins: coins})
+	got := RenderTemplate(`%EACH new_coins%%symbol% €%quotes.EUR.market_cap:,.0f%;%END_EACH%`, renderCtx)
+	if got != "BTC €1,180,000,000,000;ETH €354,000,000,000;" {
+		t.Fatalf("unexpected quotes rendering: %q", got)
+	}
+	got = RenderTemplate(`%EACH new_coins%%symbol% $%market_cap_usd:,.0f% €%market_cap_eur:,.0f%;%END_EACH%`, renderCtx)
+	if got != "BTC $1,280,000,000,000 €1,180,000,000,000;ETH $384,000,000,000 €354,000,000,000;" {
+		t.Fatalf("unexpected per-currency tokens: %q", got)
+	}
+}
+
+func TestRunOnceDoesNotRebaselineWhenConvertListGrows(t *testing.T) {
+	body, err := os.ReadFile("testdata/cmc_listings_multi_convert.json")
+	if err != nil {
+		t.Fatal(err)
+	}
+	srv := newCMCMockServer(t, string(body))
+	store := &memoryStore{state: &State{TopN: 2, Convert: "USD", IDs: []int64{1, 1027}, Coins: []Coin{{ID: 1, Rank: 1}, {ID: 1027, Rank: 2}}}}
+
+	opt := RunOptions{Convert: "USD", ExtraConverts: []string{"EUR"}}
+	if err := RunOnceWithStore(context.Background(), Config{CMCBaseURL: srv.URL, TopN: 2}, opt, store); err != nil {
+		t.Fatalf("RunOnceWithStore error: %v", err)
+	}
+	if len(store.history) != 0 || store.stateWrites != 0 {
+		t.Fatalf("adding EUR should not change the diff, got %d history entries and %d state writes", len(store.history), store.stateWrites)
+	}
+}
+
+func TestParseConverts(t *testing.T) {
+	primary, extra, err := ParseConverts(" usd, EUR,USD ,")
+	if err != nil || primary != "USD" || len(extra) != 1 || extra[0] != "EUR" {
+		t.Fatalf("unexpected converts: %q %v %v", primary, extra, err)
+	}
+	if _, _, err := ParseConverts(" , "); err == nil {
+		t.Fatalf("expected error for an empty --convert")
+	}
+}
+
+func TestCMCListingsURLUsesConfiguredBase(t *testing.T) {
+	cfg := Config{CMCBaseURL: "https://sandbox-api.coinmarketcap.com"}
+	got := cmcListingsURL(cfg, RunOptions{Convert: "USD", ExtraConverts: []string{"EUR"}, Sort: "volume_24h"}, 1, 100)
+	want := "https://sandbox-api.coinmarketcap.com/v1/cryptocurrency/listings/latest?start=1&limit=100&convert=USD%2CEUR&sort=volume_24h&sort_dir=desc"
+	if got != want {
+		t.Fatalf("cmcListingsURL = %q, want %q", got, want)
+	}
+	if got := cmcListingsURL(Config{}, RunOptions{Convert: "USD"}, 1, 5); !strings.HasPrefix(got, defaultCMCBaseURL+"/v1/") {
+		t.Fatalf("empty base should default to the production host, got %q", got)
+	}
+}
+
+func TestConfigFromEnvValidatesCMCBaseURL(t *testing.T) {
+	t.Setenv("CMC_API_KEY", "key")
+	for _, raw := range []string{"sandbox-api.coinmarketcap.com", "ftp://example.com", "https://"} {
+		t.Setenv("CMC_BASE_URL", raw)
+		if _, err := ConfigFromEnv(true, true); err == nil || !strings.Contains(err.Error(), "CMC_BASE_URL must be an absolute http(s) URL") {
+			t.Fatalf("expected validation error for %q, got %v", raw, err)
+		}
+	}
+	t.Setenv("CMC_BASE_URL", "https://sandbox-api.coinmarketcap.com/")
+	cfg, err := ConfigFromEnv(true, true)
+	if err != nil {
+		t.Fatalf("ConfigFromEnv error: %v", err)
+	}
+	if cfg.CMCBaseURL != "https://sandbox-api.coinmarketcap.com" {
+		t.Fatalf("unexpected base URL %q", cfg.CMCBaseURL)
+	}
+}
+
+func TestConfigFromEnvRejectsSqliteWithoutDriver(t *testing.T) {
+	if sqliteCompiledIn() {
+		t.Skip("built with -tags sqlite")
+	}
+	t.Setenv("CMC_API_KEY", "key")
+	t.Setenv("BACKEND", "sqlite")
+	if _, err := ConfigFromEnv(true, true); err == nil || !strings.Contains(err.Error(), "rebuild with -tags sqlite") {
+		t.Fatalf("expected BACKEND=sqlite to be rejected, got %v", err)
+	}
+}
+
+func TestFetchCMCTopNAgainstLocalMockServer(t *testing.T) {
+	srv := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		if r.Header.Get("X-CMC_PRO_API_KEY") != "sandbox-key" {
+			w.WriteHeader(http.StatusUnauthorized)
+			return
+		}
+		switch r.URL.Path {
+		case "/v1/cryptocurrency/listings/latest":
+			fmt.Fprint(w, listingsBody(1, 2))
+		case "/v2/cryptocurrency/info":
+			fmt.Fprint(w, `{"data":{"1":{"logo":"https://img/1.png"}}}`)
+		default:
+			http.NotFound(w, r)
+		}
+	}))
+	defer srv.Close()
+
+	cfg := Config{CMCAPIKey: "sandbox-key", CMCBaseURL: srv.URL, TopN: 2}
+	coins, err := fetchCMCTopN(context.Background(), srv.Client(), cfg, RunOptions{Convert: "USD"}, nil)
+	if err != nil {
+		t.Fatalf("fetchCMCTopN error: %v", err)
+	}
+	if len(coins) != 2 || coins[0].ImageURL != "https://img/1.png" {
+		t.Fatalf("unexpected coins: %+v", coins)
+	}
+}
+
+// newCMCMockServer serves listings for listings/latest and an empty info
+// response; point Config.CMCBaseURL at its URL.
+func newCMCMockServer(t *testing.T, listings string) *httptest.Server {
+	t.Helper()
+	srv := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		switch r.URL.Path {
+		case "/v1/cryptocurrency/listings/latest":
+			fmt.Fprint(w, listings)
+		case "/v2/cryptocurrency/info":
+			fmt.Fprint(w, `{"data":{}}`)
+		default:
+			t.Errorf("unexpected request to %s", r.URL)
+			http.NotFound(w, r)
+		}
+	}))
+	t.Cleanup(srv.Close)
+	return srv
+}
+
+func TestRunOnceSavesRawListingWhenEnabled(t *testing.T) {
+	body := `{"status":{"error_code":0,"credit_count":1},"data":[{"id":1,"name":"Bitcoin","symbol":"BTC","cmc_rank":1}],"ignored":true}`
+	srv := newCMCMockServer(t, body)
+
+	store := &memoryStore{}
+	if err := RunOnceWithStore(context.Background(), Config{CMCBaseURL: srv.URL, TopN: 1}, RunOptions{Convert: "USD"}, store); err != nil {
+		t.Fatalf("RunOnceWithStore error: %v", err)
+	}
+	if len(store.raws) != 0 {
+		t.Fatalf("SAVE_RAW unset should not store anything, got %d", len(store.raws))
+	}
+
+	store = &memoryStore{}
+	if err := RunOnceWithStore(context.Background(), Config{CMCBaseURL: srv.URL, TopN: 1, SaveRaw: true}, RunOptions{Convert: "USD"}, store); err != nil {
+		t.Fatalf("RunOnceWithStore error: %v", err)
+	}
+	if len(store.raws) != 1 {
+		t.Fatalf("expected one raw listing, got %d", len(store.raws))
+	}
+	raw := store.raws[0]
+	if raw.Start != 1 || raw.Limit != 1 || raw.FetchedAt.IsZero() || string(raw.Status) != `{"error_code":0,"credit_count":1}` || !strings.HasPrefix(string(raw.Data), `[{"id":1,`) {
+		t.Fatalf("unexpected raw listing: %+v", raw)
+	}
+
+	doc, err := rawListingDoc(raw)
+	if err != nil {
+		t.Fatalf("rawListingDoc error: %v", err)
+	}
+	keys := []string{}
+	for _, e := range doc {
+		keys = append(keys, e.Key)
+	}
+	if strings.Join(keys, ",") != "fetched_at,start,limit,data,status" {
+		t.Fatalf("unexpected raw document keys: %v", keys)
+	}
+}
+
+func TestRunOnceSavesRawListingThatFailsValidation(t *testing.T) {
+	srv := newCMCMockServer(t, `{"status":{"error_code":0},"data":[{"id":1,"name":"Bitcoin","symbol":"BTC"}]}`)
+	store := &memoryStore{}
+	if err := RunOnceWithStore(context.Background(), Config{CMCBaseURL: srv.URL, TopN: 1, SaveRaw: true}, RunOptions{Convert: "USD"}, store); err == nil {
+		t.Fatalf("expected the listing without cmc_rank to fail the run")
+	}
+	if len(store.raws) != 1 || !strings.Contains(string(store.raws[0].Data), `"symbol":"BTC"`) {
+		t.Fatalf("expected the rejected page to be saved, got %+v", store.raws)
+	}
+
+	var m RunMetrics
+	m.addRawListing(time.Now().UTC(), 1, 1, []byte("upstream timed out"))
+	if len(m.RawListings) != 1 || string(m.RawListings[0].Data) != `"upstream timed out"` {
+		t.Fatalf("expected a non-JSON body to be kept as a string, got %+v", m.RawListings)
+	}
+}
@@ -0,0 +1,54 @@
+package bot
+
+import (
+	"encoding/json"
+	"log"
+	"time"
+)
+
+// RunMetrics accumulates per-run CoinMarketCap usage from the status block of
+// each listings response, and the raw listings pages when SAVE_RAW is set. A
+// nil *RunMetrics ignores updates.
+type RunMetrics struct {
+	CMCCredits   int64
+	CMCElapsedMs int64
+	CMCRequests  int
+	RawListings  []RawListing
+}
+
+func (m *RunMetrics) addCMCStatus(label string, st cmcStatus) {
+	if m == nil {
+		return
+	}
+	m.CMCCredits += st.CreditCount
+	m.CMCElapsedMs += st.Elapsed
+	m.CMCRequests++
+	log.Printf("[%s] cmc credits used=%d elapsed=%dms (run total: %d credit(s) over %d request(s))", label, st.CreditCount, st.Elapsed, m.CMCCredits, m.CMCRequests)
+}
+
+// addRawListing keeps the status and data of a listings body verbatim. A body
+// that is not a JSON object is kept whole as a JSON string in Data.
+func (m *RunMetrics) addRawListing(fetchedAt time.Time, start, limit int, body []byte) {
+	if m == nil {
+		return
+	}
+	var envelope struct {
+		Status json.RawMessage `json:"status"`
+		Data   json.RawMessage `json:"data"`
+	}
+	if err := json.Unmarshal(body, &envelope); err != nil {
+		log.Printf("[fetchCMCTopN] warning: raw listings page start=%d is not a JSON object, keeping it as text: %v", start, err)
+		envelope.Status = nil
+		envelope.Data, _ = json.Marshal(string(body))
+	}
+	m.RawListings = append(m.RawListings, RawListing{FetchedAt: fetchedAt, Start: start, Limit: limit, Status: envelope.Status, Data: envelope.Data})
+}
+
+// warnCreditThreshold logs a warning when a run spent more than threshold
+// credits; a threshold of 0 disables the check.
+func (m *RunMetrics) warnCreditThreshold(threshold int64) {
+	if m == nil || threshold <= 0 || m.CMCCredits <= threshold {
+		return
+	}
+	log.Printf("[RunOnce] warning: this run used %d CMC credits, above CMC_CREDIT_WARN_THRESHOLD=%d; check TOP_N and --convert", m.CMCCredits, threshold)
+}
@@ -0,0 +1,139 @@
+package bot
+
+import (
+	"context"
+	"encoding/json"
+	"fmt"
+	"hash/fnv"
+	"log"
+	"net/http"
+	"net/url"
+	"strings"
+	"time"
+)
+
+// Data providers recorded on state and history. An empty provider on an old
+// document means CMC.
+const (
+	providerCMC       = "cmc"
+	providerCoinGecko = "coingecko"
+)
+
+// coinGeckoPageLimit is the largest per_page /coins/markets accepts.
+const coinGeckoPageLimit = 250
+
+// coinGeckoOrders maps the --sort values CoinGecko can serve onto its order
+// parameter.
+var coinGeckoOrders = map[string]string{
+	"market_cap": "market_cap_desc",
+	"volume_24h": "volume_desc",
+}
+
+// fetchTopN fetches the listing from CMC and, when FALLBACK_PROVIDER=coingecko
+// is set and CMC failed after its retries, from CoinGecko. It returns the
+// provider that produced the coins.
+func fetchTopN(ctx context.Context, client *http.Client, cfg Config, opt RunOptions, metrics *RunMetrics) ([]Coin, string, error) {
+	coins, err := fetchCMCTopN(ctx, client, cfg, opt, metrics)
+	if err == nil {
+		return coins, providerCMC, nil
+	}
+	if cfg.FallbackProvider != providerCoinGecko {
+		return nil, "", err
+	}
+	log.Printf("[fetchTopN] warning: CoinMarketCap failed (%v); falling back to CoinGecko", err)
+	coins, fallbackErr := fetchCoinGeckoTopN(ctx, client, cfg, opt)
+	if fallbackErr != nil {
+		return nil, "", fmt.Errorf("%w; coingecko fallback: %v", err, fallbackErr)
+	}
+	return coins, providerCoinGecko, nil
+}
+
+type coinGeckoMarket struct {
+	ID                       string   `json:"id"`
+	Symbol                   string   `json:"symbol"`
+	Name                     string   `json:"name"`
+	Image                    string   `json:"image"`
+	CurrentPrice             *float64 `json:"current_price"`
+	MarketCap                *float64 `json:"market_cap"`
+	MarketCapRank            *int64   `json:"market_cap_rank"`
+	TotalVolume              *float64 `json:"total_volume"`
+	PriceChangePercentage24h *float64 `json:"price_change_percentage_24h"`
+}
+
+func fetchCoinGeckoTopN(ctx context.Context, client *http.Client, cfg Config, opt RunOptions) ([]Coin, error) {
+	order, ok := coinGeckoOrders[opt.cmcSort()]
+	if !ok {
+		return nil, fmt.Errorf("coingecko cannot sort by %s", opt.cmcSort())
+	}
+	now := time.Now().UTC()
+	coins := make([]Coin, 0, cfg.TopN)
+	for page := 1; len(coins) < cfg.TopN; page++ {
+		perPage := min(cfg.TopN-len(coins), coinGeckoPageLimit)
+		u := fmt.Sprintf("https://api.coingecko.com/api/v3/coins/markets?vs_currency=%s&order=%s&per_page=%d&page=%d", url.QueryEscape(strings.ToLower(opt.Convert)), order, perPage, page)
+		resp, err := doWithRetry(ctx, client, cfg.httpRetryPolicy(), "fetchCoinGeckoTopN", func() (*http.Request, error) {
+			req, err := http.NewRequestWithContext(ctx, http.MethodGet, u, nil)
+			if err != nil {
+				return nil, err
+			}
+			if cfg.CoinGeckoAPIKey != "" {
+				req.Header.Set("x-cg-demo-api-key", cfg.CoinGeckoAPIKey)
+			}
+			return req, nil
+		})
+		if err != nil {
+			return nil, upstreamError("coingecko markets", err)
+		}
+		var markets []coinGeckoMarket
+		if resp.StatusCode >= 300 {
+			resp.Body.Close()
+			return nil, fmt.Errorf("coingecko markets: %s", resp.Status)
+		}
+		err = json.NewDecoder(resp.Body).Decode(&markets)
+		resp.Body.Close()
+		if err != nil {
+			return nil, fmt.Errorf("decode coingecko markets: %w", err)
+		}
+		for _, m := range markets {
+			if m.ID == "" || m.Symbol == "" || m.Name == "" {
+				return nil, fmt.Errorf("coingecko markets: entry %q is missing id, symbol or name", m.ID)
+			}
+			rank := int64(len(coins) + 1)
+			if m.MarketCapRank != nil && *m.MarketCapRank > 0 {
+				rank = *m.MarketCapRank
+			}
+			coins = append(coins, Coin{
+				ID:                coinGeckoNumericID(m.ID),
+				Name:              m.Name,
+				Symbol:            strings.ToUpper(m.Symbol),
+				Rank:              rank,
+				TickTimestamp:     &now,
+				MarketCap:         m.MarketCap,
+				MarketCapCurrency: opt.Convert,
+				Price:             m.CurrentPrice,
+				PercentChange24h:  m.PriceChangePercentage24h,
+				Volume24h:         m.TotalVolume,
+				ImageURL:          m.Image,
+			})
+		}
+		if len(markets) < perPage {
+			break
+		}
+	}
+	if len(coins) < cfg.TopN {
+		return nil, fmt.Errorf("coingecko returned %d coins, expected %d; refusing to use a truncated listing", len(coins), cfg.TopN)
+	}
+	return coins, nil
+}
+
+// coinGeckoNumericID maps a CoinGecko string id onto the int64 ids state uses.
+//
+// Strategy: a stable hash, not a lookup of CMC ids by symbol. Symbols are not
+// unique across coins, and a diff between CMC and CoinGecko data is never
+// made anyway (a provider switch re-baselines), so ids only have to stay the
+// same across consecutive CoinGecko runs. The FNV-1a hash is cut to 62 bits
+// and negated so it can never collide with a CMC id, which is positive.
+func coinGeckoNumericID(id string) int64 {
+	h := fnv.New64a()
+	_, _ = h.Write([]byte(id))
+	return -int64(h.Sum64()>>2) - 1
+}
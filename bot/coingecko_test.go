@@ -0,0 +1,55 @@
+package bot
+
+import (
+	"context"
+	"net/http"
+	"strings"
+	"testing"
+)
+
+func TestFetchTopNFallsBackToCoinGecko(t *testing.T) {
+	client := &http.Client{Transport: roundTripFunc(func(req *http.Request) (*http.Response, error) {
+		if strings.Contains(req.URL.Host, "coinmarketcap") {
+			return jsonResponse(http.StatusForbidden, `{"status":{"error_code":1006,"error_message":"plan does not allow this"}}`), nil
+		}
+		if req.URL.Query().Get("vs_currency") != "eur" || req.URL.Query().Get("order") != "market_cap_desc" {
+			t.Fatalf("unexpected coingecko request: %s", req.URL)
+		}
+		return jsonResponse(http.StatusOK, `[
+			{"id":"bitcoin","symbol":"btc","name":"Bitcoin","image":"https://img/btc.png","current_price":60000,"market_cap":1200000000000,"market_cap_rank":1,"total_volume":30000000000,"price_change_percentage_24h":-1.5},
+			{"id":"ethereum","symbol":"eth","name":"Ethereum","current_price":3000,"market_cap":null,"market_cap_rank":null}]`), nil
+	})}
+
+	coins, provider, err := fetchTopN(context.Background(), client, Config{TopN: 2, FallbackProvider: providerCoinGecko}, RunOptions{Convert: "EUR"}, nil)
+	if err != nil {
+		t.Fatalf("fetchTopN error: %v", err)
+	}
+	if provider != providerCoinGecko || len(coins) != 2 {
+		t.Fatalf("unexpected provider=%s coins=%+v", provider, coins)
+	}
+	btc := coins[0]
+	if btc.Symbol != "BTC" || btc.Rank != 1 || btc.MarketCapCurrency != "EUR" || btc.ImageURL != "https://img/btc.png" || btc.PercentChange24h == nil || *btc.PercentChange24h != -1.5 {
+		t.Fatalf("unexpected BTC mapping: %+v", btc)
+	}
+	if btc.ID >= 0 || btc.ID != coinGeckoNumericID("bitcoin") {
+		t.Fatalf("coingecko ids should map to stable negative ids, got %d", btc.ID)
+	}
+	if coins[1].Rank != 2 || coins[1].MarketCap != nil {
+		t.Fatalf("missing market_cap_rank should fall back to position: %+v", coins[1])
+	}
+}
+
+func TestFetchTopNWithoutFallbackReturnsCMCError(t *testing.T) {
+	calls := 0
+	client := &http.Client{Transport: roundTripFunc(func(req *http.Request) (*http.Response, error) {
+		calls++
+		return jsonResponse(http.StatusForbidden, `{"status":{"error_code":1006,"error_message":"plan does not allow this"}}`), nil
+	})}
+
+	if _, _, err := fetchTopN(context.Background(), client, Config{TopN: 1}, RunOptions{Convert: "USD"}, nil); err == nil {
+		t.Fatalf("expected CMC error without a fallback provider")
+	}
+	if calls != 1 {
+		t.Fatalf("expected only the CMC request, got %d requests", calls)
+	}
+}
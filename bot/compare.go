@@ -0,0 +1,192 @@
+package bot
+
+import (
+	"context"
+	"encoding/json"
+	"errors"
+	"fmt"
+	"io"
+	"strings"
+	"time"
+)
+
+// Output formats of the compare command.
+const (
+	CompareOutputText = "text"
+	CompareOutputJSON = "json"
+)
+
+// TopDiff is what changed between two top lists. Entered and Moved are
+// copies of current coins with PrevRank set where the coin was tracked
+// before; Exited holds the previous coins with their last rank.
+type TopDiff struct {
+	Entered []Coin `json:"entered"`
+	Exited  []Coin `json:"exited"`
+	Moved   []Coin `json:"moved"`
+}
+
+// diffTops compares two top lists with the rules RunOnce applies: a coin
+// entered when it is within the headline now but was missing from prev or
+// ranked below the headline there, and exited in the opposite case. Moved are
+// the coins in both lists whose rank changed by more than threshold, biggest
+// move first. Neither input is modified.
+func diffTops(prev, curr []Coin, headline int64, threshold int) TopDiff {
+	prevSet := make(map[int64]struct{}, len(prev))
+	for _, c := range prev {
+		prevSet[c.ID] = struct{}{}
+	}
+	return annotateDiff(prev, prevSet, append([]Coin{}, curr...), headline, threshold)
+}
+
+// annotateDiff is diffTops working on current itself: PrevRank and RankDelta
+// are set on its coins in place, which RunOnce keeps in the next state.
+// prevSet is the previous membership; RunOnce takes it from the stored ids,
+// which may list coins prev has no entry for.
+func annotateDiff(prev []Coin, prevSet map[int64]struct{}, current []Coin, headline int64, threshold int) TopDiff {
+	moved := annotateRankChanges(current, prev, threshold)
+	currentRanks := make(map[int64]int64, len(current))
+	for _, c := range current {
+		currentRanks[c.ID] = c.Rank
+	}
+	diff := TopDiff{Entered: []Coin{}, Exited: []Coin{}, Moved: moved}
+	for _, c := range current {
+		if enteredHeadline(c, prevSet, headline) {
+			diff.Entered = append(diff.Entered, c)
+		}
+	}
+	for _, c := range prev {
+		if leftHeadline(c, currentRanks, headline) {
+			diff.Exited = append(diff.Exited, c)
+		}
+	}
+	return diff
+}
+
+// CompareReport is the diff between the snapshots nearest to two points in
+// time.
+type CompareReport struct {
+	From    time.Time `json:"from"`
+	To      time.Time `json:"to"`
+	TopN    int       `json:"top_n"`
+	Convert string    `json:"convert"`
+	TopDiff
+}
+
+// Compare diffs the latest snapshots taken at or before from and to and
+// writes the report to w. It only reads the store: nothing is fetched, posted
+// or written. Snapshots are only stored with RAW_SNAPSHOT_ENABLED; history
+// documents hold just the coins a post mentioned, so they cannot stand in
+// for a full top list.
+func Compare(ctx context.Context, cfg Config, from, to time.Time, output string, w io.Writer) error {
+	if output != CompareOutputText && output != CompareOutputJSON {
+		return fmt.Errorf("unsupported --output value %q (want text or json)", output)
+	}
+	if !from.Before(to) {
+		return fmt.Errorf("--from %s must be before --to %s", from.Format(time.RFC3339), to.Format(time.RFC3339))
+	}
+	store, err := OpenStateStore(ctx, cfg)
+	if err != nil {
+		return err
+	}
+	defer store.Close(context.Background())
+	return compareWithStore(ctx, store, cfg, from, to, output, w)
+}
+
+func compareWithStore(ctx context.Context, store StateStore, cfg Config, from, to time.Time, output string, w io.Writer) error {
+	prev, err := compareSnapshot(ctx, store, from)
+	if err != nil {
+		return err
+	}
+	curr, err := compareSnapshot(ctx, store, to)
+	if err != nil {
+		return err
+	}
+	if snapshotProvider(prev) != snapshotProvider(curr) {
+		return fmt.Errorf("snapshot at %s came from %s but the one at %s from %s; coin ids are not comparable", prev.CreatedAt.Format(time.RFC3339), snapshotProvider(prev), curr.CreatedAt.Format(time.RFC3339), snapshotProvider(curr))
+	}
+	// Filtered after the diff, the way a run filters what it posts.
+	diff := diffTops(prev.Coins, curr.Coins, cfg.headlineRank(), cfg.RankChangeThreshold)
+	diff.Entered, _ = filterCoinLists(diff.Entered, cfg.CoinBlocklist, cfg.CoinAllowlist)
+	diff.Exited, _ = filterCoinLists(diff.Exited, cfg.CoinBlocklist, cfg.CoinAllowlist)
+	diff.Moved, _ = filterCoinLists(diff.Moved, cfg.CoinBlocklist, CoinList{})
+	report := CompareReport{
+		From:    prev.CreatedAt,
+		To:      curr.CreatedAt,
+		TopN:    cfg.notifyTopN(),
+		Convert: curr.Convert,
+		TopDiff: diff,
+	}
+	if output == CompareOutputJSON {
+		b, err := json.MarshalIndent(report, "", "  ")
+		if err != nil {
+			return err
+		}
+		_, err = fmt.Fprintln(w, string(b))
+		return err
+	}
+	_, err = io.WriteString(w, formatCompareReport(report))
+	return err
+}
+
+// ParseCompareTime reads a --from or --to value: an RFC 3339 timestamp, or a
+// date (2006-01-02) meaning the end of that day in UTC, so the snapshot taken
+// last on that day is used.
+func ParseCompareTime(raw string) (time.Time, error) {
+	raw = strings.TrimSpace(raw)
+	if t, err := time.Parse(time.RFC3339, raw); err == nil {
+		return t.UTC(), nil
+	}
+	day, err := time.Parse(time.DateOnly, raw)
+	if err != nil {
+		return time.Time{}, fmt.Errorf("invalid time %q (want 2006-01-02 or an RFC 3339 timestamp)", raw)
+	}
+	return day.Add(24*time.Hour - time.Nanosecond), nil
+}
+
+func compareSnapshot(ctx context.Context, store StateStore, ts time.Time) (Snapshot, error) {
+	snap, err := store.LoadSnapshotAt(ctx, ts)
+	if errors.Is(err, ErrNoSnapshot) {
+		return Snapshot{}, fmt.Errorf("no snapshot stored at or before %s; snapshots are only kept with RAW_SNAPSHOT_ENABLED=true", ts.Format(time.RFC3339))
+	}
+	return snap, err
+}
+
+func snapshotProvider(snap Snapshot) string {
+	if snap.Provider == "" {
+		return providerCMC
+	}
+	return snap.Provider
+}
+
+// formatCompareReport renders the report as plain text, one section per kind
+// of change.
+func formatCompareReport(r CompareReport) string {
+	const layout = "2006-01-02 15:04 UTC"
+	var b strings.Builder
+	fmt.Fprintf(&b, "Top %d (%s) from %s to %s\n", r.TopN, r.Convert, r.From.UTC().Format(layout), r.To.UTC().Format(layout))
+	fmt.Fprintf(&b, "\nEntered (%d):\n", len(r.Entered))
+	for _, c := range r.Entered {
+		fmt.Fprintf(&b, "  #%d %s (%s)%s\n", c.Rank, c.Name, c.Symbol, compareWasRank(c))
+	}
+	fmt.Fprintf(&b, "\nExited (%d):\n", len(r.Exited))
+	for _, c := range r.Exited {
+		fmt.Fprintf(&b, "  %s (%s), was #%d\n", c.Name, c.Symbol, c.Rank)
+	}
+	fmt.Fprintf(&b, "\nMoved (%d):\n", len(r.Moved))
+	for _, c := range r.Moved {
+		delta := *c.PrevRank - c.Rank
+		arrow := "▲"
+		if delta < 0 {
+			arrow = "▼"
+		}
+		fmt.Fprintf(&b, "  #%d %s (%s) %s%d%s\n", c.Rank, c.Name, c.Symbol, arrow, absInt64(delta), compareWasRank(c))
+	}
+	return b.String()
+}
+
+func compareWasRank(c Coin) string {
+	if c.PrevRank == nil {
+		return ""
+	}
+	return fmt.Sprintf(", was #%d", *c.PrevRank)
+}
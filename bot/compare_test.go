@@ -0,0 +1,81 @@
+package bot
+
+import (
+	"bytes"
+	"context"
+	"encoding/json"
+	"strings"
+	"testing"
+	"time"
+)
+
+func TestDiffTopsFindsEntrantsExitsAndMoves(t *testing.T) {
+	prev := []Coin{{ID: 1, Symbol: "BTC", Rank: 1}, {ID: 2, Symbol: "ETH", Rank: 2}, {ID: 3, Symbol: "DOGE", Rank: 3}, {ID: 4, Symbol: "ADA", Rank: 4}}
+	curr := []Coin{{ID: 1, Symbol: "BTC", Rank: 1}, {ID: 4, Symbol: "ADA", Rank: 2}, {ID: 5, Symbol: "SUI", Rank: 3}, {ID: 2, Symbol: "ETH", Rank: 4}}
+
+	diff := diffTops(prev, curr, 3, 1)
+
+	if got := coinSymbols(diff.Entered); strings.Join(got, ",") != "ADA,SUI" {
+		t.Fatalf("unexpected entered: %v", got)
+	}
+	if got := coinSymbols(diff.Exited); strings.Join(got, ",") != "ETH,DOGE" {
+		t.Fatalf("unexpected exited: %v", got)
+	}
+	if got := coinSymbols(diff.Moved); strings.Join(got, ",") != "ADA,ETH" || *diff.Moved[0].PrevRank != 4 {
+		t.Fatalf("unexpected moved: %v", got)
+	}
+	if curr[1].PrevRank != nil {
+		t.Fatalf("diffTops must not modify its input")
+	}
+}
+
+func TestCompareWithStoreUsesNearestSnapshots(t *testing.T) {
+	march := time.Date(2026, 3, 1, 9, 0, 0, 0, time.UTC)
+	april := time.Date(2026, 4, 1, 9, 0, 0, 0, time.UTC)
+	store := &memoryStore{snapshots: []Snapshot{
+		{CreatedAt: march.Add(-24 * time.Hour), Convert: "USD", Coins: []Coin{{ID: 1, Name: "Bitcoin", Symbol: "BTC", Rank: 1}}},
+		{CreatedAt: march, Convert: "USD", Coins: []Coin{{ID: 1, Name: "Bitcoin", Symbol: "BTC", Rank: 1}, {ID: 74, Name: "Dogecoin", Symbol: "DOGE", Rank: 2}}},
+		{CreatedAt: april, Convert: "USD", Coins: []Coin{{ID: 1, Name: "Bitcoin", Symbol: "BTC", Rank: 1}, {ID: 20947, Name: "Sui", Symbol: "SUI", Rank: 2}}},
+	}}
+	cfg := Config{TopN: 2}
+
+	var buf bytes.Buffer
+	if err := compareWithStore(context.Background(), store, cfg, march.Add(time.Hour), april.Add(time.Hour), CompareOutputJSON, &buf); err != nil {
+		t.Fatalf("compareWithStore error: %v", err)
+	}
+	var report CompareReport
+	if err := json.Unmarshal(buf.Bytes(), &report); err != nil {
+		t.Fatalf("invalid json: %v\n%s", err, buf.String())
+	}
+	if !report.From.Equal(march) || !report.To.Equal(april) || len(report.Entered) != 1 || report.Entered[0].Symbol != "SUI" || len(report.Exited) != 1 || report.Exited[0].Symbol != "DOGE" {
+		t.Fatalf("unexpected report: %+v", report)
+	}
+
+	buf.Reset()
+	if err := compareWithStore(context.Background(), store, cfg, march, april, CompareOutputText, &buf); err != nil {
+		t.Fatalf("compareWithStore error: %v", err)
+	}
+	for _, want := range []string{"Top 2 (USD) from 2026-03-01 09:00 UTC to 2026-04-01 09:00 UTC", "Entered (1):\n  #2 Sui (SUI)\n", "Exited (1):\n  Dogecoin (DOGE), was #2\n"} {
+		if !strings.Contains(buf.String(), want) {
+			t.Fatalf("text report missing %q:\n%s", want, buf.String())
+		}
+	}
+
+	if err := compareWithStore(context.Background(), store, cfg, march.Add(-72*time.Hour), april, CompareOutputText, &buf); err == nil || !strings.Contains(err.Error(), "RAW_SNAPSHOT_ENABLED") {
+		t.Fatalf("expected missing snapshot error, got %v", err)
+	}
+}
+
+func TestParseCompareTime(t *testing.T) {
+	got, err := ParseCompareTime("2026-03-01")
+	if err != nil || !got.Equal(time.Date(2026, 3, 1, 23, 59, 59, 999999999, time.UTC)) {
+		t.Fatalf("unexpected date result: %v %v", got, err)
+	}
+	got, err = ParseCompareTime("2026-03-01T12:00:00+02:00")
+	if err != nil || !got.Equal(time.Date(2026, 3, 1, 10, 0, 0, 0, time.UTC)) {
+		t.Fatalf("unexpected timestamp result: %v %v", got, err)
+	}
+	if _, err := ParseCompareTime("March 1"); err == nil {
+		t.Fatalf("expected error for unparsable time")
+	}
+}
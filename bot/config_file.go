@@ -0,0 +1,201 @@
+package bot
+
+import (
+	"errors"
+	"fmt"
+	"os"
+	"slices"
+	"strconv"
+	"strings"
+)
+
+// configFileSections maps a TOML section to the prefix of the env vars its
+// keys stand for, e.g. [cmc] max_retries -> CMC_MAX_RETRIES and
+// [run] top_n -> TOP_N.
+var configFileSections = map[string]string{
+	"cmc":      "CMC_",
+	"telegram": "TELEGRAM_",
+	"mongodb":  "MONGODB_",
+	"ai":       "AI_",
+	"run":      "",
+}
+
+// configFileAliases covers keys whose env var doesn't follow the prefix rule.
+var configFileAliases = map[string]string{
+	"telegram.bot_token":   "TELEGRAM_COINMARKETCAP_TOP_100_BOT_TOKEN",
+	"telegram.channel_id":  "TELEGRAM_COINMARKETCAP_TOP_100_CHANNEL_ID",
+	"ai.gemini_api_key":    "GEMINI_API_KEY",
+	"ai.openai_api_key":    "OPENAI_API_KEY",
+	"ai.anthropic_api_key": "ANTHROPIC_API_KEY",
+	"ai.ollama_base_url":   "OLLAMA_BASE_URL",
+}
+
+// configFileVars are the env vars a config file may set: the ones
+// ConfigFromEnv reads. Anything else, such as PATH, HOME or HTTPS_PROXY, is
+// rejected so a config file can only configure the bot.
+var configFileVars = strings.Fields(`
+	ACCEPT_INVALID_CERTS AI_ENABLED AI_MAX_TOKENS AI_MODEL AI_MODEL_CHAIN AI_PROVIDER
+	AI_TEMPERATURE AI_TIMEOUT_SECS ANTHROPIC_API_KEY ATH_ALERTS_ENABLED BACKEND CMC_API_KEY
+	CMC_BASE_URL CMC_CACHE_DIR CMC_CACHE_TTL_SECS CMC_CREDIT_WARN_THRESHOLD CMC_MAX_RETRIES
+	CMC_MIN_COIN_FRACTION CMC_RETRY_BASE_MS CMC_RETRY_DELAY_MS COINGECKO_API_KEY
+	COIN_ALLOWLIST COIN_BLOCKLIST COIN_MENTION_COOLDOWN_DAYS DEDUP_WINDOW_HOURS DIGEST_SIZE
+	DISCORD_WEBHOOK_URL EXCLUDE_SYMBOLS EXCLUDE_TAGS EXTRA_CA_CERT_PATH FALLBACK_PROVIDER
+	FEAR_GREED_ENABLED FETCH_GLOBAL_METRICS FLAP_WINDOW_HOURS GEMINI_API_KEY
+	GEMINI_CIRCUIT_BREAKER_COOLDOWN_SECS GEMINI_CIRCUIT_BREAKER_THRESHOLD
+	GLOBAL_METRICS_ENABLED HTTP_CONNECT_TIMEOUT_SECS HTTP_REQUEST_TIMEOUT_SECS
+	HTTP_TIMEOUT_SECS LOCK_TTL_SECS MASTODON_ACCESS_TOKEN MASTODON_INSTANCE_URL
+	MAX_STATE_AGE_HOURS MIN_NEW_COINS MIN_POST_INTERVAL_MINUTES MONGODB_ATH_COLLECTION
+	MONGODB_COINS_COLLECTION MONGODB_CONNECTION_STRING MONGODB_DB MONGODB_HISTORY_COLLECTION
+	MONGODB_LOCKS_COLLECTION MONGODB_METRICS_COLLECTION MONGODB_RAW_COLLECTION
+	MONGODB_SKIP_INDEX_CREATION MONGODB_SNAPSHOT_COLLECTION MONGODB_STATE_COLLECTION
+	MOVER_THRESHOLD NEW_COIN_DETAILS_ENABLED NOTIFY_TOP_N OLLAMA_BASE_URL OPENAI_API_KEY
+	RANK_CHANGE_THRESHOLD RAW_SNAPSHOT_ENABLED RSS_DESCRIPTION RSS_LINK RSS_MAX_ITEMS
+	RSS_TITLE SAVE_RAW SLACK_WEBHOOK_URL SMTP_FROM SMTP_HOST SMTP_PASSWORD SMTP_PORT SMTP_TO
+	SMTP_USERNAME SNAPSHOT_RETENTION_DAYS SQLITE_PATH TELEGRAM_API_BASE_URL
+	TELEGRAM_CHANNEL_IDS TELEGRAM_COINMARKETCAP_TOP_100_BOT_TOKEN
+	TELEGRAM_COINMARKETCAP_TOP_100_CHANNEL_ID TELEGRAM_PARSE_MODE TEMPLATES_DIR
+	TENURE_RETENTION_DAYS TENURE_THRESHOLD TOP_N WATCHLIST_MARGIN
+`)
+
+// ConfigFromFile loads a TOML config file and then builds the config exactly
+// like ConfigFromEnv. File values only fill in env vars that are not already
+// set, so real env vars always win (the same rule godotenv uses for .env).
+// A string value of "$NAME" is read from the env var NAME, which keeps
+// secrets out of committed files.
+func ConfigFromFile(path string, dryRun bool, skipMongo bool) (Config, error) {
+	if err := loadConfigFile(path); err != nil {
+		return Config{}, err
+	}
+	return ConfigFromEnv(dryRun, skipMongo)
+}
+
+func loadConfigFile(path string) error {
+	data, err := os.ReadFile(path)
+	if err != nil {
+		return fmt.Errorf("read config file: %w", err)
+	}
+	sections, err := parseConfigTOML(string(data))
+	if err != nil {
+		return fmt.Errorf("%s: %w", path, err)
+	}
+	for section, values := range sections {
+		for key, value := range values {
+			name, err := configEnvName(section, key)
+			if err != nil {
+				return fmt.Errorf("%s: %w", path, err)
+			}
+			if strings.HasPrefix(value, "$") {
+				value = os.Getenv(value[1:])
+			}
+			if value == "" || strings.TrimSpace(os.Getenv(name)) != "" {
+				continue
+			}
+			if err := os.Setenv(name, value); err != nil {
+				return err
+			}
+		}
+	}
+	return nil
+}
+
+func configEnvName(section, key string) (string, error) {
+	if alias, ok := configFileAliases[section+"."+key]; ok {
+		return alias, nil
+	}
+	prefix, ok := configFileSections[section]
+	if !ok {
+		if section == "" {
+			return "", fmt.Errorf("key %q must be inside a section", key)
+		}
+		return "", fmt.Errorf("unknown section [%s]", section)
+	}
+	name := prefix + strings.ToUpper(key)
+	if !slices.Contains(configFileVars, name) {
+		return "", fmt.Errorf("unknown key %q in [%s]", key, section)
+	}
+	return name, nil
+}
+
+// parseConfigTOML reads the flat TOML subset used by config files: [section]
+// headers and key = value lines where the value is a basic or literal string,
+// a number or a boolean. Values are returned as their string form.
+func parseConfigTOML(data string) (map[string]map[string]string, error) {
+	out := map[string]map[string]string{}
+	section := ""
+	for n, line := range strings.Split(data, "\n") {
+		line = strings.TrimSpace(line)
+		if line == "" || strings.HasPrefix(line, "#") {
+			continue
+		}
+		if strings.HasPrefix(line, "[") {
+			end := strings.Index(line, "]")
+			if end < 0 || !isTOMLLineEnd(line[end+1:]) {
+				return nil, fmt.Errorf("line %d: invalid section header", n+1)
+			}
+			section = strings.TrimSpace(line[1:end])
+			continue
+		}
+		key, raw, ok := strings.Cut(line, "=")
+		if !ok {
+			return nil, fmt.Errorf("line %d: expected key = value", n+1)
+		}
+		key = strings.TrimSpace(key)
+		value, err := parseTOMLValue(strings.TrimSpace(raw))
+		if err != nil {
+			return nil, fmt.Errorf("line %d: %s: %w", n+1, key, err)
+		}
+		if out[section] == nil {
+			out[section] = map[string]string{}
+		}
+		out[section][key] = value
+	}
+	return out, nil
+}
+
+func parseTOMLValue(raw string) (string, error) {
+	switch {
+	case strings.HasPrefix(raw, `"`):
+		end := -1
+		for i := 1; i < len(raw) && end < 0; i++ {
+			switch raw[i] {
+			case '\\':
+				i++
+			case '"':
+				end = i
+			}
+		}
+		if end < 0 {
+			return "", errors.New("unterminated string")
+		}
+		if !isTOMLLineEnd(raw[end+1:]) {
+			return "", errors.New("unexpected text after string")
+		}
+		return strconv.Unquote(raw[:end+1])
+	case strings.HasPrefix(raw, "'"):
+		end := strings.Index(raw[1:], "'")
+		if end < 0 {
+			return "", errors.New("unterminated string")
+		}
+		if !isTOMLLineEnd(raw[end+2:]) {
+			return "", errors.New("unexpected text after string")
+		}
+		return raw[1 : end+1], nil
+	}
+	if i := strings.Index(raw, "#"); i >= 0 {
+		raw = strings.TrimSpace(raw[:i])
+	}
+	if raw == "true" || raw == "false" {
+		return raw, nil
+	}
+	num := strings.ReplaceAll(raw, "_", "")
+	if _, err := strconv.ParseFloat(num, 64); err == nil {
+		return num, nil
+	}
+	return "", fmt.Errorf("unsupported value %q", raw)
+}
+
+// isTOMLLineEnd reports whether rest is empty or only a trailing comment.
+func isTOMLLineEnd(rest string) bool {
+	rest = strings.TrimSpace(rest)
+	return rest == "" || strings.HasPrefix(rest, "#")
+}
@@ -0,0 +1,97 @@
+package bot
+
+import (
+	"os"
+	"path/filepath"
+	"testing"
+)
+
+func TestConfigFromFileFillsUnsetEnvAndResolvesSecrets(t *testing.T) {
+	for _, name := range []string{"CMC_API_KEY", "CMC_MAX_RETRIES", "TELEGRAM_COINMARKETCAP_TOP_100_BOT_TOKEN", "TELEGRAM_COINMARKETCAP_TOP_100_CHANNEL_ID", "MONGODB_CONNECTION_STRING", "TOP_N"} {
+		t.Setenv(name, "")
+	}
+	t.Setenv("MONGODB_DB", "from_env")
+	t.Setenv("TEST_CMC_SECRET", "secret")
+
+	path := filepath.Join(t.TempDir(), "config.toml")
+	content := `# sample config
+[cmc]
+api_key = "$TEST_CMC_SECRET"
+max_retries = 5
+
+[telegram]
+bot_token = 'tok'
+channel_id = "@chan" # main channel
+
+[mongodb]
+connection_string = "mongodb://localhost:27017"
+db = "from_file"
+
+[run]
+top_n = 50
+`
+	if err := os.WriteFile(path, []byte(content), 0o600); err != nil {
+		t.Fatal(err)
+	}
+
+	cfg, err := ConfigFromFile(path, false, false)
+	if err != nil {
+		t.Fatalf("ConfigFromFile error: %v", err)
+	}
+	if cfg.CMCAPIKey != "secret" || cfg.CMCMaxRetries != 5 || cfg.TopN != 50 {
+		t.Fatalf("unexpected cmc/run config: %+v", cfg)
+	}
+	if cfg.TelegramToken != "tok" || cfg.TelegramChannelID != "@chan" {
+		t.Fatalf("unexpected telegram config: %q %q", cfg.TelegramToken, cfg.TelegramChannelID)
+	}
+	if cfg.MongoDBConnectionString != "mongodb://localhost:27017" || cfg.MongoDBDatabase != "from_env" {
+		t.Fatalf("env should win over file: %q %q", cfg.MongoDBConnectionString, cfg.MongoDBDatabase)
+	}
+}
+
+func TestParseConfigTOMLRejectsUnknownSection(t *testing.T) {
+	path := filepath.Join(t.TempDir(), "config.toml")
+	if err := os.WriteFile(path, []byte("[nope]\nkey = 1\n"), 0o600); err != nil {
+		t.Fatal(err)
+	}
+	if err := loadConfigFile(path); err == nil {
+		t.Fatalf("expected unknown section error")
+	}
+}
+
+func TestConfigExampleFileBuildsConfig(t *testing.T) {
+	for _, name := range []string{"CMC_API_KEY", "CMC_MAX_RETRIES", "TELEGRAM_COINMARKETCAP_TOP_100_BOT_TOKEN", "TELEGRAM_COINMARKETCAP_TOP_100_CHANNEL_ID", "MONGODB_CONNECTION_STRING", "MONGODB_DB", "AI_ENABLED", "AI_PROVIDER", "AI_MODEL", "AI_MODEL_CHAIN", "GEMINI_API_KEY", "TOP_N"} {
+		t.Setenv(name, "")
+	}
+	t.Setenv("COINMARKETCAP_API_KEY", "cmc-key")
+	t.Setenv("TELEGRAM_BOT_TOKEN", "tok")
+
+	cfg, err := ConfigFromFile(filepath.Join("..", "config.example.toml"), false, false)
+	if err != nil {
+		t.Fatalf("ConfigFromFile error: %v", err)
+	}
+	if cfg.CMCAPIKey != "cmc-key" || cfg.TelegramToken != "tok" || cfg.TelegramChannelID != "@your_channel" {
+		t.Fatalf("unexpected secrets or channel: %q %q %q", cfg.CMCAPIKey, cfg.TelegramToken, cfg.TelegramChannelID)
+	}
+	if cfg.TopN != 100 || cfg.AIProvider != "gemini" || cfg.AIModel != "gemini-3-flash-preview" || cfg.AIEnabled {
+		t.Fatalf("unexpected run/ai config: %+v", cfg)
+	}
+}
+
+func TestLoadConfigFileRejectsKeysOutsideTheBotConfig(t *testing.T) {
+	for _, content := range []string{"[run]\npath = \"/tmp/evil\"\n", "[run]\nhttps_proxy = \"http://proxy:8080\"\n", "[cmc]\nnot_a_setting = 1\n"} {
+		t.Setenv("PATH", os.Getenv("PATH"))
+		t.Setenv("HTTPS_PROXY", "")
+		path := filepath.Join(t.TempDir(), "config.toml")
+		if err := os.WriteFile(path, []byte(content), 0o600); err != nil {
+			t.Fatal(err)
+		}
+		before := os.Getenv("PATH")
+		if err := loadConfigFile(path); err == nil {
+			t.Fatalf("expected %q to be rejected", content)
+		}
+		if os.Getenv("PATH") != before || os.Getenv("HTTPS_PROXY") != "" {
+			t.Fatalf("a rejected file must not change the environment")
+		}
+	}
+}
@@ -0,0 +1,33 @@
+package bot
+
+import (
+	"context"
+	"log"
+	"time"
+)
+
+// RunLoop calls run every interval until ctx is cancelled. A failed run is
+// logged and the loop keeps going. Cancelling ctx does not abort the run in
+// flight: run gets a context detached from ctx's cancellation, and the loop
+// returns once that run has finished.
+func RunLoop(ctx context.Context, interval time.Duration, run func(context.Context) error) {
+	runCtx := context.WithoutCancel(ctx)
+	for cycle := 1; ; cycle++ {
+		log.Printf("[RunLoop] cycle %d starting", cycle)
+		start := time.Now()
+		if err := run(runCtx); err != nil {
+			log.Printf("[RunLoop] cycle %d failed after %s: %v", cycle, time.Since(start).Round(time.Millisecond), err)
+		} else {
+			log.Printf("[RunLoop] cycle %d done in %s", cycle, time.Since(start).Round(time.Millisecond))
+		}
+
+		timer := time.NewTimer(interval)
+		select {
+		case <-ctx.Done():
+			timer.Stop()
+			log.Printf("[RunLoop] shutting down after cycle %d", cycle)
+			return
+		case <-timer.C:
+		}
+	}
+}
@@ -0,0 +1,38 @@
+package bot
+
+import (
+	"context"
+	"errors"
+	"testing"
+	"time"
+)
+
+func TestRunLoopRepeatsAndSurvivesErrors(t *testing.T) {
+	ctx, cancel := context.WithCancel(context.Background())
+	defer cancel()
+
+	calls := 0
+	done := make(chan struct{})
+	go func() {
+		RunLoop(ctx, time.Millisecond, func(runCtx context.Context) error {
+			calls++
+			if calls == 3 {
+				cancel()
+				if runCtx.Err() != nil {
+					t.Errorf("in-flight run should not see the shutdown cancellation")
+				}
+			}
+			return errors.New("boom")
+		})
+		close(done)
+	}()
+
+	select {
+	case <-done:
+	case <-time.After(5 * time.Second):
+		t.Fatal("RunLoop did not stop after cancellation")
+	}
+	if calls != 3 {
+		t.Fatalf("expected 3 runs, got %d", calls)
+	}
+}
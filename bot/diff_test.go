@@ -0,0 +1,330 @@
+package bot
+
+import (
+	"context"
+	"encoding/json"
+	"fmt"
+	"io"
+	"os"
+	"path/filepath"
+	"strings"
+	"testing"
+)
+
+func TestAnnotateRankChangesReturnsBiggestMoversFirst(t *testing.T) {
+	prev := []Coin{{ID: 1, Rank: 1}, {ID: 2, Rank: 87}, {ID: 3, Rank: 50}, {ID: 4, Rank: 30}}
+	current := []Coin{{ID: 1, Rank: 1}, {ID: 2, Rank: 12}, {ID: 3, Rank: 65}, {ID: 4, Rank: 25}, {ID: 5, Rank: 99}}
+
+	movers := annotateRankChanges(current, prev, 10)
+	if len(movers) != 2 || movers[0].ID != 2 || movers[1].ID != 3 {
+		t.Fatalf("unexpected movers: %+v", movers)
+	}
+	if current[1].PrevRank == nil || *current[1].PrevRank != 87 {
+		t.Fatalf("expected prev_rank 87 on coin 2, got %v", current[1].PrevRank)
+	}
+	if current[4].PrevRank != nil {
+		t.Fatalf("new coin should not have prev_rank")
+	}
+}
+
+func TestCheckStateSortRejectsMismatch(t *testing.T) {
+	if err := checkStateSort(State{}, "market_cap"); err != nil {
+		t.Fatalf("state without sort should count as market_cap: %v", err)
+	}
+	if err := checkStateSort(State{Sort: "volume_24h"}, "volume_24h"); err != nil {
+		t.Fatalf("unexpected error for matching sort: %v", err)
+	}
+	if err := checkStateSort(State{Sort: "market_cap"}, "volume_24h"); err == nil {
+		t.Fatalf("expected error for sort mismatch")
+	}
+}
+
+func TestDetectRenamesComparesNameAndSymbolByID(t *testing.T) {
+	prev := []Coin{{ID: 1, Name: "Bitcoin", Symbol: "BTC", Rank: 1}, {ID: 3890, Name: "Polygon", Symbol: "MATIC", Rank: 20}, {ID: 7, Name: "Gone", Symbol: "GONE", Rank: 99}}
+	current := []Coin{{ID: 1, Name: "Bitcoin", Symbol: "BTC", Rank: 1}, {ID: 3890, Name: "POL (ex-MATIC)", Symbol: "POL", Rank: 22}, {ID: 8, Name: "New", Symbol: "NEW", Rank: 100}}
+
+	renamed := detectRenames(current, prev)
+	if len(renamed) != 1 {
+		t.Fatalf("expected one rename, got %+v", renamed)
+	}
+	want := RenamedCoin{ID: 3890, Rank: 22, OldName: "Polygon", OldSymbol: "MATIC", Name: "POL (ex-MATIC)", Symbol: "POL"}
+	if renamed[0] != want {
+		t.Fatalf("unexpected rename: got %+v want %+v", renamed[0], want)
+	}
+}
+
+func TestAddRankJumpsReportsClimbersOnceAndSkipsNewCoins(t *testing.T) {
+	prev := []Coin{{ID: 1, Rank: 40}, {ID: 2, Rank: 90}, {ID: 3, Rank: 10}}
+	current := []Coin{{ID: 1, Rank: 28}, {ID: 2, Rank: 60}, {ID: 3, Rank: 20}, {ID: 4, Rank: 5}}
+	prevSet := map[int64]struct{}{1: {}, 2: {}, 3: {}}
+
+	movers := annotateRankChanges(current, prev, 20)
+	movers, jumps := addRankJumps(movers, current, prevSet, 10)
+	if jumps != 2 {
+		t.Fatalf("expected 2 jumps, got %d", jumps)
+	}
+	if len(movers) != 2 || movers[0].ID != 2 || movers[1].ID != 1 {
+		t.Fatalf("unexpected movers: %+v", movers)
+	}
+	for _, m := range movers {
+		if m.ID == 4 {
+			t.Fatalf("new coin must not be reported as a mover")
+		}
+	}
+}
+
+func TestRunOnceForceRunPostsCurrentCoinsWithoutEntrants(t *testing.T) {
+	srv := newCMCMockServer(t, `{"data":[
+		{"id":1,"name":"Bitcoin","symbol":"BTC","cmc_rank":1},
+		{"id":1027,"name":"Ethereum","symbol":"ETH","cmc_rank":2}]}`)
+	store := &memoryStore{state: &State{TopN: 2, Convert: "USD", IDs: []int64{1, 1027}, Coins: []Coin{{ID: 1, Rank: 1}, {ID: 1027, Rank: 2}}}}
+	cfg := Config{CMCBaseURL: srv.URL, TopN: 2}
+
+	out := captureStdout(t, func() {
+		if err := RunOnceWithStore(context.Background(), cfg, RunOptions{Convert: "USD", DryRun: true, ForceRun: true}, store); err != nil {
+			t.Fatalf("RunOnceWithStore error: %v", err)
+		}
+	})
+	if !strings.Contains(out, "• #1 Bitcoin (BTC)") || !strings.Contains(out, "• #2 Ethereum (ETH)") {
+		t.Fatalf("forced run should render every current coin, got %q", out)
+	}
+
+	out = captureStdout(t, func() {
+		if err := RunOnceWithStore(context.Background(), cfg, RunOptions{Convert: "USD", DryRun: true}, store); err != nil {
+			t.Fatalf("RunOnceWithStore error: %v", err)
+		}
+	})
+	if out != "" {
+		t.Fatalf("an unforced run without changes should print nothing, got %q", out)
+	}
+}
+
+func TestRunOnceForceRunRecordsOnlySubstitutedPostsAsForced(t *testing.T) {
+	tg, _ := newTelegramMockServer(t)
+	store := &memoryStore{state: &State{TopN: 2, Convert: "USD", IDs: []int64{1, 1027}, Coins: []Coin{{ID: 1, Rank: 1}, {ID: 1027, Rank: 2}}}}
+	cfg := Config{TopN: 2, TelegramToken: "token", TelegramChannelID: "@channel", TelegramAPIBaseURL: tg.URL}
+	run := func(listing string) HistoryDoc {
+		cfg.CMCBaseURL = newCMCMockServer(t, listing).URL
+		if err := RunOnceWithStore(context.Background(), cfg, RunOptions{Convert: "USD", ForceRun: true}, store); err != nil {
+			t.Fatalf("RunOnceWithStore error: %v", err)
+		}
+		return store.history[len(store.history)-1]
+	}
+
+	doc := run(`{"data":[
+		{"id":1,"name":"Bitcoin","symbol":"BTC","cmc_rank":1},
+		{"id":1027,"name":"Ethereum","symbol":"ETH","cmc_rank":2}]}`)
+	if !doc.Forced || len(doc.MentionedCoins) != 0 {
+		t.Fatalf("a substituted post should be forced without mentions: %+v", doc)
+	}
+
+	doc = run(`{"data":[
+		{"id":1,"name":"Bitcoin","symbol":"BTC","cmc_rank":1},
+		{"id":5426,"name":"Solana","symbol":"SOL","cmc_rank":2}]}`)
+	if doc.Forced || len(doc.MentionedCoins) != 1 || doc.MentionedCoins[0].ID != 5426 {
+		t.Fatalf("a --force-run post with real entrants is a normal post: %+v", doc)
+	}
+}
+
+func TestSplitMoversSeparatesClimbersAndFallers(t *testing.T) {
+	prev := []Coin{{ID: 1, Rank: 1}, {ID: 2, Rank: 40}, {ID: 3, Rank: 12}, {ID: 4, Rank: 20}, {ID: 5, Rank: 80}}
+	current := []Coin{{ID: 1, Rank: 1}, {ID: 2, Rank: 25}, {ID: 3, Rank: 30}, {ID: 4, Rank: 22}, {ID: 5, Rank: 50}, {ID: 6, Rank: 60}}
+	annotateRankChanges(current, prev, 100)
+
+	up, down := splitMovers(current, 10)
+	if len(up) != 2 || up[0].ID != 5 || *up[0].RankDelta != 30 || up[1].ID != 2 || *up[1].RankDelta != 15 {
+		t.Fatalf("unexpected climbers: %+v", up)
+	}
+	if len(down) != 1 || down[0].ID != 3 || *down[0].RankDelta != -18 {
+		t.Fatalf("unexpected fallers: %+v", down)
+	}
+}
+
+func TestRunOnceNotifyMoversPostsWithoutEntrants(t *testing.T) {
+	srv := newCMCMockServer(t, `{"data":[
+		{"id":1,"name":"Bitcoin","symbol":"BTC","cmc_rank":1},
+		{"id":74,"name":"Dogecoin","symbol":"DOGE","cmc_rank":2}]}`)
+	store := &memoryStore{state: &State{TopN: 2, Convert: "USD", IDs: []int64{1, 74}, Coins: []Coin{{ID: 1, Rank: 1}, {ID: 74, Rank: 15}}}}
+	cfg := Config{CMCBaseURL: srv.URL, TopN: 2, MoverThreshold: 10, RankChangeThreshold: 10}
+
+	out := captureStdout(t, func() {
+		if err := RunOnceWithStore(context.Background(), cfg, RunOptions{Convert: "USD", DryRun: true, NotifyMovers: true}, store); err != nil {
+			t.Fatalf("RunOnceWithStore error: %v", err)
+		}
+	})
+	if !strings.Contains(out, "⬆️ Big climbers:\n• Dogecoin (DOGE) #15 → #2 (+13)") || strings.Contains(out, "Biggest movers") {
+		t.Fatalf("expected a climbers section instead of rank_movers, got %q", out)
+	}
+}
+
+// runDry serves listings from a CMC mock, runs RunOnceWithStore as a dry run
+// with cfg and opt and returns what it printed.
+func runDry(t *testing.T, cfg Config, opt RunOptions, listings string, store StateStore) string {
+	t.Helper()
+	cfg.CMCBaseURL = newCMCMockServer(t, listings).URL
+	opt.DryRun = true
+	return captureStdout(t, func() {
+		if err := RunOnceWithStore(context.Background(), cfg, opt, store); err != nil {
+			t.Fatalf("RunOnceWithStore error: %v", err)
+		}
+	})
+}
+
+// captureStdout returns what fn printed to os.Stdout.
+func captureStdout(t *testing.T, fn func()) string {
+	t.Helper()
+	r, w, err := os.Pipe()
+	if err != nil {
+		t.Fatal(err)
+	}
+	orig := os.Stdout
+	os.Stdout = w
+	defer func() { os.Stdout = orig }()
+	fn()
+	w.Close()
+	b, err := io.ReadAll(r)
+	if err != nil {
+		t.Fatal(err)
+	}
+	return string(b)
+}
+
+func TestRunOnceDryRunJSONListsDecisions(t *testing.T) {
+	srv := newCMCMockServer(t, `{"data":[
+		{"id":1,"name":"Bitcoin","symbol":"BTC","cmc_rank":1},
+		{"id":5426,"name":"Solana","symbol":"SOL","cmc_rank":2}]}`)
+	store := &memoryStore{state: &State{TopN: 2, Convert: "USD", IDs: []int64{1, 2}, Coins: []Coin{{ID: 1, Rank: 1}, {ID: 2, Rank: 2}}}}
+	cfg := Config{CMCBaseURL: srv.URL, TopN: 2, RankChangeThreshold: 10}
+
+	out := captureStdout(t, func() {
+		opt := RunOptions{Convert: "USD", DryRun: true, DryRunFormat: DryRunJSON, NotifyExits: true}
+		if err := RunOnceWithStore(context.Background(), cfg, opt, store); err != nil {
+			t.Fatalf("RunOnceWithStore error: %v", err)
+		}
+	})
+	var got map[string]json.RawMessage
+	if err := json.Unmarshal([]byte(out), &got); err != nil {
+		t.Fatalf("dry run output is not JSON: %v\n%s", err, out)
+	}
+	for _, key := range []string{"new_coins", "exited_coins", "rank_movers", "text"} {
+		if _, ok := got[key]; !ok {
+			t.Fatalf("missing %q in %s", key, out)
+		}
+	}
+	var newCoins []Coin
+	if err := json.Unmarshal(got["new_coins"], &newCoins); err != nil || len(newCoins) != 1 || newCoins[0].Symbol != "SOL" {
+		t.Fatalf("unexpected new_coins: %s (%v)", got["new_coins"], err)
+	}
+}
+
+func TestRunOnceNotifyTopNAnnouncesOnlyHeadlineEntrants(t *testing.T) {
+	for _, tc := range []struct {
+		rank int
+		post bool
+	}{{90, true}, {120, false}} {
+		srv := newCMCMockServer(t, fmt.Sprintf(`{"data":[
+			{"id":1,"name":"Bitcoin","symbol":"BTC","cmc_rank":1},
+			{"id":5426,"name":"Solana","symbol":"SOL","cmc_rank":%d}]}`, tc.rank))
+		store := &memoryStore{state: &State{TopN: 200, Convert: "USD", IDs: []int64{1, 5426}, Coins: []Coin{{ID: 1, Rank: 1}, {ID: 5426, Rank: 150}}}}
+		cfg := Config{CMCBaseURL: srv.URL, TopN: 200, NotifyTopN: 100, CMCMinCoinFraction: 0.01, RankChangeThreshold: 100}
+
+		out := captureStdout(t, func() {
+			if err := RunOnceWithStore(context.Background(), cfg, RunOptions{Convert: "USD", DryRun: true}, store); err != nil {
+				t.Fatalf("RunOnceWithStore error: %v", err)
+			}
+		})
+		if posted := strings.Contains(out, "Solana (SOL)"); posted != tc.post {
+			t.Fatalf("rank 150 -> %d: posted=%t, want %t; output %q", tc.rank, posted, tc.post, out)
+		}
+		if tc.post && !strings.Contains(out, "Top 100") {
+			t.Fatalf("post should be about the headline top 100, got %q", out)
+		}
+	}
+}
+
+func TestEnteredHeadlineUsesMembershipWithoutNotifyTopN(t *testing.T) {
+	prevSet := map[int64]struct{}{1: {}}
+	prevRank := int64(150)
+	tracked := Coin{ID: 1, Rank: 90, PrevRank: &prevRank}
+	if headline := (Config{TopN: 200}).headlineRank(); enteredHeadline(tracked, prevSet, headline) {
+		t.Fatal("without NOTIFY_TOP_N a coin already in the snapshot is not new")
+	}
+	if !enteredHeadline(tracked, prevSet, (Config{TopN: 200, NotifyTopN: 100}).headlineRank()) {
+		t.Fatal("a tracked coin climbing into the headline should be new")
+	}
+	if !enteredHeadline(Coin{ID: 2, Rank: 500}, prevSet, (Config{TopN: 100}).headlineRank()) {
+		t.Fatal("with membership alone a coin missing from the snapshot is new whatever its cmc_rank")
+	}
+}
+
+func TestRunOnceDryRunWritesOutputFile(t *testing.T) {
+	srv := newCMCMockServer(t, `{"data":[
+		{"id":1,"name":"Bitcoin","symbol":"BTC","cmc_rank":1},
+		{"id":5426,"name":"Solana","symbol":"SOL","cmc_rank":2}]}`)
+	cfg := Config{CMCBaseURL: srv.URL, TopN: 2, RankChangeThreshold: 10}
+	dir := t.TempDir()
+
+	for _, path := range []string{filepath.Join(dir, "ci", "post.json"), filepath.Join(dir, "post.md")} {
+		store := &memoryStore{state: &State{TopN: 2, Convert: "USD", IDs: []int64{1, 2}, Coins: []Coin{{ID: 1, Rank: 1}, {ID: 2, Rank: 2}}}}
+		opt := RunOptions{Convert: "USD", DryRun: true, OutputFile: path}
+		if out := captureStdout(t, func() {
+			if err := RunOnceWithStore(context.Background(), cfg, opt, store); err != nil {
+				t.Fatalf("RunOnceWithStore error: %v", err)
+			}
+		}); out != "" {
+			t.Fatalf("--output-file should keep stdout empty, got %q", out)
+		}
+		b, err := os.ReadFile(path)
+		if err != nil {
+			t.Fatalf("read output file: %v", err)
+		}
+		if filepath.Ext(path) == ".md" {
+			if !strings.Contains(string(b), "Solana") || strings.HasPrefix(string(b), "{") {
+				t.Fatalf("expected the raw post text in %s, got %q", path, b)
+			}
+			continue
+		}
+		var doc map[string]any
+		if err := json.Unmarshal(b, &doc); err != nil {
+			t.Fatalf("output file is not JSON: %v\n%s", err, b)
+		}
+		for _, key := range []string{"text", "new_coins", "render_context", "timestamp_utc"} {
+			if _, ok := doc[key]; !ok {
+				t.Fatalf("missing %q in %s", key, b)
+			}
+		}
+	}
+}
+
+func TestRunOnceDryRunWithoutChangesStillWritesJSON(t *testing.T) {
+	srv := newCMCMockServer(t, `{"data":[
+		{"id":1,"name":"Bitcoin","symbol":"BTC","cmc_rank":1},
+		{"id":1027,"name":"Ethereum","symbol":"ETH","cmc_rank":2}]}`)
+	store := &memoryStore{state: &State{TopN: 2, Convert: "USD", IDs: []int64{1, 1027}, Coins: []Coin{{ID: 1, Rank: 1}, {ID: 1027, Rank: 2}}}}
+	cfg := Config{CMCBaseURL: srv.URL, TopN: 2, RankChangeThreshold: 10}
+	path := filepath.Join(t.TempDir(), "post.json")
+
+	out := captureStdout(t, func() {
+		if err := RunOnceWithStore(context.Background(), cfg, RunOptions{Convert: "USD", DryRun: true, DryRunFormat: DryRunJSON}, store); err != nil {
+			t.Fatalf("RunOnceWithStore error: %v", err)
+		}
+		if err := RunOnceWithStore(context.Background(), cfg, RunOptions{Convert: "USD", DryRun: true, OutputFile: path}, store); err != nil {
+			t.Fatalf("RunOnceWithStore error: %v", err)
+		}
+	})
+	b, err := os.ReadFile(path)
+	if err != nil {
+		t.Fatalf("read output file: %v", err)
+	}
+	for name, raw := range map[string][]byte{"stdout": []byte(out), "output file": b} {
+		var doc map[string]json.RawMessage
+		if err := json.Unmarshal(raw, &doc); err != nil {
+			t.Fatalf("%s is not JSON: %v\n%s", name, err, raw)
+		}
+		for _, key := range []string{"new_coins", "exited_coins", "rank_movers", "displacements"} {
+			if string(doc[key]) != "[]" {
+				t.Fatalf("%s: expected an empty %q list, got %s", name, key, doc[key])
+			}
+		}
+	}
+}
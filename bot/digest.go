@@ -0,0 +1,137 @@
+package bot
+
+import (
+	"context"
+	"log"
+	"sort"
+	"time"
+)
+
+// ModeDigest is the RunOptions.Mode of the weekly gainers and losers digest.
+const ModeDigest = "digest"
+
+// HistoryKindDigest marks digest posts in history.
+const HistoryKindDigest = "digest"
+
+// defaultDigestSize is how many gainers and losers a digest lists by default.
+const defaultDigestSize = 5
+
+const defaultDigestPrompt = `%gainers%
+%losers%
+`
+
+const defaultDigestTemplate = `📅 Weekly digest: CoinMarketCap Top %top_n% (%convert%)
+%IF global%🌍 Market cap: %global.total_market_cap:,.0f|n/a% · BTC dominance: %global.btc_dominance:.1f|n/a%%%
+%END_IF%%IF fear_greed_label%😱 Fear & Greed: %fear_greed_value%/100 (%fear_greed_label%)
+%END_IF%%IF gainers%
+🟢 Top gainers (7d):
+%EACH gainers%• #%rank% %name% (%symbol%) %percent_change_7d:arrow%%IF market_cap% — mcap: %market_cap:abbrev%%END_IF%
+%END_EACH%%END_IF%%IF losers%
+🔴 Top losers (7d):
+%EACH losers%• #%rank% %name% (%symbol%) %percent_change_7d:arrow%%IF market_cap% — mcap: %market_cap:abbrev%%END_IF%
+%END_EACH%%END_IF%`
+
+var digestTextSources = textSources{
+	PromptPath:   "prompts/digest.prompts.md",
+	Prompt:       defaultDigestPrompt,
+	FallbackPath: "templates/telegram_digest_fallback.template.md",
+	Fallback:     defaultDigestTemplate,
+}
+
+// digestMovers sorts coins by percent_change_7d and returns the best k as
+// gainers and the worst k as losers, worst first. Coins without a 7d change
+// are skipped, and a coin never shows up in both lists.
+func digestMovers(coins []Coin, k int) ([]Coin, []Coin) {
+	ranked := make([]Coin, 0, len(coins))
+	for _, c := range coins {
+		if c.PercentChange7d != nil {
+			ranked = append(ranked, c)
+		}
+	}
+	sort.SliceStable(ranked, func(i, j int) bool { return *ranked[i].PercentChange7d > *ranked[j].PercentChange7d })
+	gainers := ranked[:min(k, len(ranked))]
+	rest := ranked[len(gainers):]
+	losers := make([]Coin, 0, k)
+	for i := len(rest) - 1; i >= 0 && len(losers) < k; i-- {
+		losers = append(losers, rest[i])
+	}
+	return gainers, losers
+}
+
+func buildDigestContext(cfg Config, opt RunOptions, gainers, losers []Coin, global *GlobalMetrics, fearGreed *FearGreed) map[string]any {
+	now := time.Now().UTC()
+	renderCtx := map[string]any{
+		"project_name":  "coinmarketcap_top100_bot",
+		"timestamp_utc": now.Format(time.RFC3339),
+		"top_n":         cfg.TopN,
+		"convert":       opt.Convert,
+		"gainers":       withQuoteTokens(withDerivedFields(gainers, now)),
+		"losers":        withQuoteTokens(withDerivedFields(losers, now)),
+	}
+	if global != nil {
+		renderCtx["global"] = global
+	}
+	if fearGreed != nil {
+		renderCtx["fear_greed_value"] = fearGreed.Value
+		renderCtx["fear_greed_label"] = fearGreed.Label
+	}
+	return renderCtx
+}
+
+// runDigest posts the weekly gainers and losers of the current top-N. It
+// never reads or writes the entrants state; the post is recorded in history
+// with kind "digest".
+func runDigest(ctx context.Context, cfg Config, opt RunOptions, store StateStore) error {
+	log.Printf("[Digest] start: top_n=%d convert=%s size=%d dry_run=%t", cfg.TopN, opt.Convert, cfg.DigestSize, opt.DryRun)
+	httpClient := withCMCCache(NewHTTPClient(cfg), cfg, opt.DryRun)
+	metrics := &RunMetrics{}
+	current, provider, err := fetchTopN(ctx, httpClient, cfg, opt, metrics)
+	if err != nil {
+		log.Printf("[Digest] failed to fetch listings: %v", err)
+		return err
+	}
+	metrics.warnCreditThreshold(cfg.CMCCreditWarnThreshold)
+	current, _ = filterCoinLists(current, cfg.CoinBlocklist, CoinList{})
+
+	gainers, losers := digestMovers(current, cfg.DigestSize)
+	if len(gainers) == 0 && len(losers) == 0 {
+		log.Printf("[Digest] no coin has a 7d change from %s; exiting without Telegram post", provider)
+		return nil
+	}
+	log.Printf("[Digest] %d gainer(s) and %d loser(s)", len(gainers), len(losers))
+
+	renderCtx := buildDigestContext(cfg, opt, gainers, losers, loadGlobalMetrics(ctx, httpClient, cfg, opt.Convert), loadFearGreed(ctx, httpClient, cfg))
+	gen, err := produceText(ctx, httpClient, cfg, renderCtx, digestTextSources)
+	if err != nil {
+		log.Printf("[Digest] failed to produce Telegram text: %v", err)
+		return err
+	}
+	text := gen.Text
+	if opt.DryRun {
+		return printDryRun(opt, map[string]any{"gainers": gainers, "losers": losers, "text_source": gen.Source}, renderCtx, text)
+	}
+
+	mentioned := append(append([]Coin{}, gainers...), losers...)
+	msgIDs, err := sendTelegramMessage(ctx, httpClient, cfg, text, firstCoinImageURL(mentioned), telegramParseMode(cfg, opt))
+	if err != nil {
+		log.Printf("[Digest] failed to send Telegram message: %v", err)
+		return err
+	}
+	notifyDiscord(ctx, httpClient, cfg, opt, text)
+	notifySlack(ctx, httpClient, cfg, opt, text)
+	notifyEmail(cfg, opt, digestEmailSubject(cfg.TopN, time.Now().UTC()), text)
+	mastodonID := notifyMastodon(ctx, httpClient, cfg, opt, text)
+
+	err = store.InsertHistory(ctx, HistoryDoc{
+		CreatedAt: time.Now().UTC(), TopN: int64(cfg.TopN), Convert: opt.Convert,
+		NewCoinIDs: []int64{}, Text: text, MentionedCoins: mentioned, TelegramMessageID: firstMessageID(msgIDs),
+		TelegramMessageIDs: msgIDs, Provider: provider, CreditsUsed: metrics.CMCCredits, Kind: HistoryKindDigest,
+		MastodonStatusID: mastodonID, AIModelUsed: gen.Model,
+	})
+	if err != nil {
+		log.Printf("[Digest] failed to append history: %v", err)
+		return err
+	}
+	log.Printf("[Digest] completed successfully")
+	return nil
+}
@@ -0,0 +1,86 @@
+package bot
+
+import (
+	"context"
+	"strings"
+	"testing"
+	"time"
+)
+
+func TestDigestMoversSplitsBestAndWorstWeek(t *testing.T) {
+	pct := func(f float64) *float64 { return &f }
+	coins := []Coin{
+		{ID: 1, Symbol: "BTC", PercentChange7d: pct(2)},
+		{ID: 2, Symbol: "ETH", PercentChange7d: pct(-8)},
+		{ID: 3, Symbol: "SOL", PercentChange7d: pct(15)},
+		{ID: 4, Symbol: "USDT"},
+		{ID: 5, Symbol: "DOGE", PercentChange7d: pct(-1)},
+		{ID: 6, Symbol: "XRP", PercentChange7d: pct(40)},
+	}
+
+	gainers, losers := digestMovers(coins, 2)
+	if len(gainers) != 2 || gainers[0].Symbol != "XRP" || gainers[1].Symbol != "SOL" {
+		t.Fatalf("unexpected gainers: %+v", gainers)
+	}
+	if len(losers) != 2 || losers[0].Symbol != "ETH" || losers[1].Symbol != "DOGE" {
+		t.Fatalf("unexpected losers: %+v", losers)
+	}
+
+	gainers, losers = digestMovers(coins, 3)
+	if len(gainers) != 3 || len(losers) != 2 || losers[1].Symbol != "DOGE" {
+		t.Fatalf("a coin should not be both a gainer and a loser: gainers=%+v losers=%+v", gainers, losers)
+	}
+}
+
+func TestDigestFallbackTemplateListsGainersAndLosers(t *testing.T) {
+	pct := func(f float64) *float64 { return &f }
+	gainers := []Coin{{Rank: 5, Name: "Solana", Symbol: "SOL", PercentChange7d: pct(15.5), MarketCap: pct(7.2e10)}}
+	losers := []Coin{{Rank: 2, Name: "Ethereum", Symbol: "ETH", PercentChange7d: pct(-8)}}
+
+	got := RenderTemplate(defaultDigestTemplate, buildDigestContext(Config{TopN: 100}, RunOptions{Convert: "USD"}, gainers, losers, nil, nil))
+	for _, want := range []string{"Top 100 (USD)", "• #5 Solana (SOL) ▲15.50% — mcap: 72.00B", "• #2 Ethereum (ETH) ▼8.00%"} {
+		if !strings.Contains(got, want) {
+			t.Fatalf("missing %q in %q", want, got)
+		}
+	}
+}
+
+func TestRunDigestLeavesStateUntouched(t *testing.T) {
+	srv := newCMCMockServer(t, `{"data":[
+		{"id":1,"name":"Bitcoin","symbol":"BTC","cmc_rank":1,"quote":{"USD":{"market_cap":1.2e12,"percent_change_7d":3.1}}},
+		{"id":1027,"name":"Ethereum","symbol":"ETH","cmc_rank":2,"quote":{"USD":{"market_cap":3.8e11,"percent_change_7d":-4.2}}}]}`)
+	store := &memoryStore{state: &State{TopN: 2, Convert: "USD", IDs: []int64{1, 2}, Coins: []Coin{{ID: 1, Rank: 1}, {ID: 2, Rank: 2}}}}
+	cfg := Config{CMCBaseURL: srv.URL, TopN: 2, DigestSize: 5}
+
+	if err := RunOnceWithStore(context.Background(), cfg, RunOptions{Convert: "USD", Mode: ModeDigest, DryRun: true}, store); err != nil {
+		t.Fatalf("RunOnceWithStore error: %v", err)
+	}
+	if store.stateWrites != 0 || store.state.IDs[1] != 2 || len(store.history) != 0 {
+		t.Fatalf("digest dry run should not touch state or history: state=%+v writes=%d history=%d", store.state.IDs, store.stateWrites, len(store.history))
+	}
+}
+
+func TestRunDigestEmailsThePost(t *testing.T) {
+	srv := newCMCMockServer(t, `{"data":[
+		{"id":1,"name":"Bitcoin","symbol":"BTC","cmc_rank":1,"quote":{"USD":{"market_cap":1.2e12,"percent_change_7d":3.1}}},
+		{"id":1027,"name":"Ethereum","symbol":"ETH","cmc_rank":2,"quote":{"USD":{"market_cap":3.8e11,"percent_change_7d":-4.2}}}]}`)
+	tg, _ := newTelegramMockServer(t)
+	host, port, received := fakeSMTPServer(t, false)
+	store := &memoryStore{state: &State{}}
+	cfg := Config{
+		CMCBaseURL: srv.URL, TopN: 2, DigestSize: 5, TelegramToken: "token", TelegramChannelID: "@channel", TelegramAPIBaseURL: tg.URL,
+		SMTPHost: host, SMTPPort: port, SMTPFrom: "bot@example.com", SMTPTo: []string{"a@example.com"},
+	}
+
+	if err := RunOnceWithStore(context.Background(), cfg, RunOptions{Convert: "USD", Mode: ModeDigest, NotifyEmail: true}, store); err != nil {
+		t.Fatalf("RunOnceWithStore error: %v", err)
+	}
+	select {
+	case msg := <-received:
+		if !strings.Contains(msg, "Subject: CMC Top 2: weekly gainers and losers [") || !strings.Contains(msg, "Bitcoin (BTC)") {
+			t.Fatalf("unexpected digest email:\n%s", msg)
+		}
+	case <-time.After(5 * time.Second):
+		t.Fatal("fake SMTP server received no message")
+	}
+}
@@ -0,0 +1,79 @@
+package bot
+
+import (
+	"context"
+	"encoding/json"
+	"net/http"
+	"net/http/httptest"
+	"strings"
+	"testing"
+	"unicode/utf8"
+)
+
+func TestSendDiscordMessagePostsTruncatedContent(t *testing.T) {
+	var content string
+	srv := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		if r.Method != http.MethodPost || r.Header.Get("Content-Type") != "application/json" {
+			t.Errorf("unexpected %s request with content type %q", r.Method, r.Header.Get("Content-Type"))
+		}
+		var body struct {
+			Content string `json:"content"`
+		}
+		if err := json.NewDecoder(r.Body).Decode(&body); err != nil {
+			t.Errorf("decode body: %v", err)
+		}
+		content = body.Content
+		w.WriteHeader(http.StatusNoContent)
+	}))
+	defer srv.Close()
+
+	if err := sendDiscordMessage(context.Background(), srv.Client(), Config{DiscordWebhookURL: srv.URL}, "  🚀 [Pepe](https://coinmarketcap.com/currencies/pepe/)\n"); err != nil {
+		t.Fatalf("sendDiscordMessage error: %v", err)
+	}
+	if content != "🚀 [Pepe](https://coinmarketcap.com/currencies/pepe/)" {
+		t.Fatalf("expected the trimmed text with its link untouched, got %q", content)
+	}
+
+	if err := sendDiscordMessage(context.Background(), srv.Client(), Config{DiscordWebhookURL: srv.URL}, strings.Repeat("🚀", discordContentLimit+20)); err != nil {
+		t.Fatalf("sendDiscordMessage error: %v", err)
+	}
+	if utf8.RuneCountInString(content) != discordContentLimit || !strings.HasSuffix(content, "…") {
+		t.Fatalf("expected %d characters ending in an ellipsis, got %d", discordContentLimit, utf8.RuneCountInString(content))
+	}
+}
+
+func TestSendDiscordMessageReportsRejectedWebhook(t *testing.T) {
+	srv := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		w.WriteHeader(http.StatusNotFound)
+		_, _ = w.Write([]byte(`{"message":"Unknown Webhook","code":10015}`))
+	}))
+	defer srv.Close()
+
+	err := sendDiscordMessage(context.Background(), srv.Client(), Config{DiscordWebhookURL: srv.URL}, "hello")
+	if err == nil || !strings.Contains(err.Error(), "404") || !strings.Contains(err.Error(), "Unknown Webhook") {
+		t.Fatalf("expected the status and body in the error, got %v", err)
+	}
+	// notifyDiscord only logs the failure; the run carries on.
+	notifyDiscord(context.Background(), srv.Client(), Config{DiscordWebhookURL: srv.URL}, RunOptions{NotifyDiscord: true}, "hello")
+}
+
+func TestSendDiscordMessageRetriesTransientFailure(t *testing.T) {
+	calls := 0
+	srv := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		calls++
+		if calls == 1 {
+			w.WriteHeader(http.StatusServiceUnavailable)
+			return
+		}
+		w.WriteHeader(http.StatusNoContent)
+	}))
+	defer srv.Close()
+
+	cfg := Config{DiscordWebhookURL: srv.URL, CMCMaxRetries: 2}
+	if err := sendDiscordMessage(context.Background(), srv.Client(), cfg, "hello"); err != nil {
+		t.Fatalf("sendDiscordMessage error: %v", err)
+	}
+	if calls != 2 {
+		t.Fatalf("expected the 503 to be retried once, got %d calls", calls)
+	}
+}
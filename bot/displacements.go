@@ -0,0 +1,100 @@
+package bot
+
+import "sort"
+
+// Displacement pairs an entrant with the coin it pushed out of the top-N.
+type Displacement struct {
+	Entered Coin `json:"entered"`
+	Exited  Coin `json:"exited"`
+}
+
+// pairDisplacements matches entrants with exits by nearest rank, comparing
+// an entrant's rank with the exited coin's previous rank. The closest pairs
+// are taken first; ties go to the better ranked entrant, then the better
+// ranked exit. Coins left without a partner are not returned. The result is
+// ordered by entrant rank.
+func pairDisplacements(entrants, exits []Coin) []Displacement {
+	type candidate struct {
+		entrant, exit int
+		dist          int64
+	}
+	candidates := make([]candidate, 0, len(entrants)*len(exits))
+	for i, e := range entrants {
+		for j, x := range exits {
+			candidates = append(candidates, candidate{i, j, absInt64(e.Rank - x.Rank)})
+		}
+	}
+	sort.SliceStable(candidates, func(a, b int) bool {
+		ca, cb := candidates[a], candidates[b]
+		if ca.dist != cb.dist {
+			return ca.dist < cb.dist
+		}
+		if ra, rb := entrants[ca.entrant].Rank, entrants[cb.entrant].Rank; ra != rb {
+			return ra < rb
+		}
+		return exits[ca.exit].Rank < exits[cb.exit].Rank
+	})
+	usedEntrants, usedExits := map[int]bool{}, map[int]bool{}
+	out := []Displacement{}
+	for _, c := range candidates {
+		if usedEntrants[c.entrant] || usedExits[c.exit] {
+			continue
+		}
+		usedEntrants[c.entrant], usedExits[c.exit] = true, true
+		out = append(out, Displacement{Entered: entrants[c.entrant], Exited: exits[c.exit]})
+	}
+	sort.SliceStable(out, func(a, b int) bool { return out[a].Entered.Rank < out[b].Entered.Rank })
+	return out
+}
+
+// displacedExitIDs returns the ids of the exited coin of each pair.
+func displacedExitIDs(ds []Displacement) map[int64]struct{} {
+	ids := make(map[int64]struct{}, len(ds))
+	for _, d := range ds {
+		ids[d.Exited.ID] = struct{}{}
+	}
+	return ids
+}
+
+// withPushedOut returns a copy of entrants with pushed_out set to the coin
+// each paired entrant displaced.
+func withPushedOut(entrants []Coin, ds []Displacement) []Coin {
+	if len(ds) == 0 {
+		return entrants
+	}
+	out := make([]Coin, len(entrants))
+	for i, c := range entrants {
+		for _, d := range ds {
+			if d.Entered.ID == c.ID {
+				exited := d.Exited
+				c.PushedOut = &exited
+				break
+			}
+		}
+		out[i] = c
+	}
+	return out
+}
+
+// displacedExits returns the exited coin of each pair.
+func displacedExits(ds []Displacement) []Coin {
+	out := make([]Coin, 0, len(ds))
+	for _, d := range ds {
+		out = append(out, d.Exited)
+	}
+	return out
+}
+
+// withoutIDs returns the coins whose id is not in ids.
+func withoutIDs(coins []Coin, ids map[int64]struct{}) []Coin {
+	if len(ids) == 0 {
+		return coins
+	}
+	out := make([]Coin, 0, len(coins))
+	for _, c := range coins {
+		if _, ok := ids[c.ID]; !ok {
+			out = append(out, c)
+		}
+	}
+	return out
+}
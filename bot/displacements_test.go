@@ -0,0 +1,59 @@
+package bot
+
+import (
+	"strings"
+	"testing"
+)
+
+func TestPairDisplacementsWithMoreEntrantsThanExits(t *testing.T) {
+	entrants := []Coin{{ID: 1, Symbol: "A", Rank: 91}, {ID: 2, Symbol: "B", Rank: 97}, {ID: 3, Symbol: "C", Rank: 100}}
+	exits := []Coin{{ID: 10, Symbol: "X", Rank: 98}}
+
+	got := pairDisplacements(entrants, exits)
+	if len(got) != 1 || got[0].Entered.Symbol != "B" || got[0].Exited.Symbol != "X" {
+		t.Fatalf("expected B to displace X, got %+v", got)
+	}
+	marked := withPushedOut(entrants, got)
+	if marked[0].PushedOut != nil || marked[1].PushedOut == nil || marked[1].PushedOut.Symbol != "X" || marked[2].PushedOut != nil {
+		t.Fatalf("expected only B marked as pushing out X, got %+v", marked)
+	}
+}
+
+func TestPairDisplacementsBreaksTiesByRank(t *testing.T) {
+	entrants := []Coin{{ID: 1, Symbol: "A", Rank: 98}, {ID: 2, Symbol: "B", Rank: 100}}
+	exits := []Coin{{ID: 10, Symbol: "X", Rank: 99}, {ID: 11, Symbol: "Y", Rank: 97}}
+
+	// A is one rank from both X and Y and, as the better ranked entrant,
+	// takes the better ranked exit Y; B gets X.
+	got := pairDisplacements(entrants, exits)
+	if len(got) != 2 || got[0].Entered.Symbol != "A" || got[0].Exited.Symbol != "Y" || got[1].Entered.Symbol != "B" || got[1].Exited.Symbol != "X" {
+		t.Fatalf("unexpected pairs: %+v", got)
+	}
+}
+
+func TestPairDisplacementsWithoutExits(t *testing.T) {
+	if got := pairDisplacements([]Coin{{ID: 1, Rank: 99}}, nil); len(got) != 0 {
+		t.Fatalf("expected no pairs, got %+v", got)
+	}
+}
+
+func TestRunOncePairsDisplacementsWithoutNotifyExits(t *testing.T) {
+	const listings = `{"data":[
+		{"id":1,"name":"Bitcoin","symbol":"BTC","cmc_rank":1},
+		{"id":2,"name":"Ethereum","symbol":"ETH","cmc_rank":2},
+		{"id":21159,"name":"Ondo","symbol":"ONDO","cmc_rank":3}]}`
+	store := &memoryStore{state: &State{TopN: 3, Convert: "USD", IDs: []int64{1, 2, 8916}, Coins: []Coin{
+		{ID: 1, Name: "Bitcoin", Symbol: "BTC", Rank: 1},
+		{ID: 2, Name: "Ethereum", Symbol: "ETH", Rank: 2},
+		{ID: 8916, Name: "Floki", Symbol: "FLOKI", Rank: 3},
+	}}}
+	cfg := Config{TopN: 3, RankChangeThreshold: 10}
+
+	out := runDry(t, cfg, RunOptions{Convert: "USD"}, listings, store)
+	if !strings.Contains(out, "• #3 Ondo (ONDO), pushed out Floki (FLOKI)") {
+		t.Fatalf("expected the entrant listed with the coin it pushed out: %q", out)
+	}
+	if strings.Contains(out, "Exited") {
+		t.Fatalf("exits should not be listed without --notify-exits: %q", out)
+	}
+}
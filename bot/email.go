@@ -0,0 +1,124 @@
+package bot
+
+import (
+	"crypto/tls"
+	"fmt"
+	"log"
+	"net"
+	"net/smtp"
+	"strconv"
+	"strings"
+	"time"
+)
+
+const defaultSMTPPort = 587
+
+// notifyEmail mails the post text to SMTP_TO under subject when requested.
+// Like the other cross-posts it never fails the run: SMTP errors, including
+// rejected credentials, are only logged.
+func notifyEmail(cfg Config, opt RunOptions, subject, text string) {
+	if !opt.NotifyEmail {
+		return
+	}
+	if cfg.SMTPHost == "" || cfg.SMTPFrom == "" || len(cfg.SMTPTo) == 0 {
+		log.Printf("[Email] warning: --notify-email set but SMTP_HOST, SMTP_FROM or SMTP_TO is empty; skipping")
+		return
+	}
+	if err := sendEmail(cfg, subject, plainTextLinks(text)); err != nil {
+		log.Printf("[Email] warning: failed to send email: %v", err)
+		return
+	}
+	log.Printf("[Email] sent %q to %d recipient(s)", subject, len(cfg.SMTPTo))
+}
+
+func emailSubject(topN, newCoins int, now time.Time) string {
+	noun := "entrants"
+	if newCoins == 1 {
+		noun = "entrant"
+	}
+	return fmt.Sprintf("CMC Top %d: %d new %s [%s]", topN, newCoins, noun, now.Format("2006-01-02"))
+}
+
+func digestEmailSubject(topN int, now time.Time) string {
+	return fmt.Sprintf("CMC Top %d: weekly gainers and losers [%s]", topN, now.Format("2006-01-02"))
+}
+
+// sendEmail sends text as a plain-text message. It follows smtp.SendMail
+// (STARTTLS when offered, PLAIN auth only when SMTP_USERNAME is set) but
+// bounds the whole session by the HTTP timeouts so a stuck server cannot
+// stall the run.
+func sendEmail(cfg Config, subject, text string) error {
+	port := cfg.SMTPPort
+	if port == 0 {
+		port = defaultSMTPPort
+	}
+	addr := net.JoinHostPort(cfg.SMTPHost, strconv.Itoa(port))
+	if err := sendEmailTo(cfg, addr, buildEmailMessage(cfg.SMTPFrom, cfg.SMTPTo, subject, text)); err != nil {
+		return fmt.Errorf("smtp %s: %w", addr, err)
+	}
+	return nil
+}
+
+func sendEmailTo(cfg Config, addr string, msg []byte) error {
+	connectTimeout, requestTimeout := cfg.HTTPConnectTimeout, cfg.HTTPRequestTimeout
+	if connectTimeout <= 0 {
+		connectTimeout = defaultHTTPConnectTimeout
+	}
+	if requestTimeout <= 0 {
+		requestTimeout = defaultHTTPRequestTimeout
+	}
+	conn, err := net.DialTimeout("tcp", addr, connectTimeout)
+	if err != nil {
+		return err
+	}
+	_ = conn.SetDeadline(time.Now().Add(requestTimeout))
+	c, err := smtp.NewClient(conn, cfg.SMTPHost)
+	if err != nil {
+		conn.Close()
+		return err
+	}
+	defer c.Close()
+	if ok, _ := c.Extension("STARTTLS"); ok {
+		if err := c.StartTLS(&tls.Config{ServerName: cfg.SMTPHost, MinVersion: tls.VersionTLS12}); err != nil {
+			return err
+		}
+	}
+	if cfg.SMTPUsername != "" {
+		if err := c.Auth(smtp.PlainAuth("", cfg.SMTPUsername, cfg.SMTPPassword, cfg.SMTPHost)); err != nil {
+			return err
+		}
+	}
+	if err := c.Mail(cfg.SMTPFrom); err != nil {
+		return err
+	}
+	for _, to := range cfg.SMTPTo {
+		if err := c.Rcpt(to); err != nil {
+			return err
+		}
+	}
+	w, err := c.Data()
+	if err != nil {
+		return err
+	}
+	if _, err := w.Write(msg); err != nil {
+		return err
+	}
+	if err := w.Close(); err != nil {
+		return err
+	}
+	return c.Quit()
+}
+
+func buildEmailMessage(from string, to []string, subject, text string) []byte {
+	var b strings.Builder
+	b.WriteString("From: " + from + "\r\n")
+	b.WriteString("To: " + strings.Join(to, ", ") + "\r\n")
+	b.WriteString("Subject: " + subject + "\r\n")
+	b.WriteString("Date: " + time.Now().UTC().Format(time.RFC1123Z) + "\r\n")
+	b.WriteString("MIME-Version: 1.0\r\n")
+	b.WriteString("Content-Type: text/plain; charset=UTF-8\r\n")
+	b.WriteString("Content-Transfer-Encoding: 8bit\r\n\r\n")
+	b.WriteString(strings.ReplaceAll(strings.TrimSpace(text), "\n", "\r\n"))
+	b.WriteString("\r\n")
+	return []byte(b.String())
+}
@@ -0,0 +1,104 @@
+package bot
+
+import (
+	"bufio"
+	"net"
+	"strconv"
+	"strings"
+	"testing"
+	"time"
+)
+
+// fakeSMTPServer accepts one session on localhost, answering the commands
+// net/smtp sends. With rejectAuth it answers AUTH with 535.
+func fakeSMTPServer(t *testing.T, rejectAuth bool) (host string, port int, received chan string) {
+	t.Helper()
+	ln, err := net.Listen("tcp", "127.0.0.1:0")
+	if err != nil {
+		t.Fatal(err)
+	}
+	t.Cleanup(func() { ln.Close() })
+	received = make(chan string, 1)
+	go func() {
+		conn, err := ln.Accept()
+		if err != nil {
+			return
+		}
+		defer conn.Close()
+		r := bufio.NewReader(conn)
+		reply := func(s string) { conn.Write([]byte(s + "\r\n")) }
+		reply("220 localhost ESMTP")
+		var data strings.Builder
+		for {
+			line, err := r.ReadString('\n')
+			if err != nil {
+				return
+			}
+			cmd := strings.ToUpper(strings.TrimSpace(line))
+			switch {
+			case strings.HasPrefix(cmd, "EHLO"):
+				reply("250-localhost")
+				reply("250 AUTH PLAIN")
+			case strings.HasPrefix(cmd, "AUTH"):
+				if rejectAuth {
+					reply("535 5.7.8 authentication failed")
+					continue
+				}
+				reply("235 2.7.0 accepted")
+			case strings.HasPrefix(cmd, "MAIL"), strings.HasPrefix(cmd, "RCPT"):
+				reply("250 ok")
+			case cmd == "DATA":
+				reply("354 go ahead")
+				for {
+					l, err := r.ReadString('\n')
+					if err != nil {
+						return
+					}
+					if l == ".\r\n" {
+						break
+					}
+					data.WriteString(l)
+				}
+				received <- data.String()
+				reply("250 queued")
+			case cmd == "QUIT":
+				reply("221 bye")
+				return
+			default:
+				reply("250 ok")
+			}
+		}
+	}()
+	addr := ln.Addr().(*net.TCPAddr)
+	return "127.0.0.1", addr.Port, received
+}
+
+func TestSendEmailDeliversPlainText(t *testing.T) {
+	host, port, received := fakeSMTPServer(t, false)
+	cfg := Config{SMTPHost: host, SMTPPort: port, SMTPUsername: "bot", SMTPPassword: "secret", SMTPFrom: "bot@example.com", SMTPTo: []string{"a@example.com", "b@example.com"}}
+
+	if err := sendEmail(cfg, emailSubject(100, 3, time.Date(2024, 1, 15, 0, 0, 0, 0, time.UTC)), "🚀 New coins\n• PEPE"); err != nil {
+		t.Fatalf("sendEmail error: %v", err)
+	}
+	select {
+	case msg := <-received:
+		if !strings.Contains(msg, "Subject: CMC Top 100: 3 new entrants [2024-01-15]\r\n") || !strings.Contains(msg, "To: a@example.com, b@example.com\r\n") {
+			t.Fatalf("unexpected headers:\n%s", msg)
+		}
+		if !strings.Contains(msg, "\r\n\r\n🚀 New coins\r\n• PEPE\r\n") {
+			t.Fatalf("unexpected body:\n%s", msg)
+		}
+	case <-time.After(5 * time.Second):
+		t.Fatal("fake SMTP server received no message")
+	}
+}
+
+func TestSendEmailReportsAuthFailure(t *testing.T) {
+	host, port, _ := fakeSMTPServer(t, true)
+	cfg := Config{SMTPHost: host, SMTPPort: port, SMTPUsername: "bot", SMTPPassword: "wrong", SMTPFrom: "bot@example.com", SMTPTo: []string{"a@example.com"}}
+
+	err := sendEmail(cfg, "subject", "text")
+	if err == nil || !strings.Contains(err.Error(), "535") || !strings.Contains(err.Error(), "127.0.0.1:"+strconv.Itoa(port)) {
+		t.Fatalf("expected an auth error naming the server, got %v", err)
+	}
+}
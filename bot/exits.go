@@ -0,0 +1,64 @@
+package bot
+
+import (
+	"context"
+	"errors"
+	"log"
+	"time"
+)
+
+// annotateExits sets LastRank on exited coins from the previous snapshot,
+// and DaysInTopN from the latest post that announced them as an entrant.
+// A coin no post ever announced gets InTopNSinceTracking only when it is in
+// the oldest stored snapshot; otherwise it entered unannounced later, or no
+// snapshot says when, and TenureRuns is set from tenure instead. A failed
+// history or snapshot query only logs a warning and leaves the days out.
+func annotateExits(ctx context.Context, store StateStore, exited []Coin, tenure map[int64]int64, now time.Time) []Coin {
+	if len(exited) == 0 {
+		return exited
+	}
+	entered, err := store.LastEntered(ctx, coinIDs(exited))
+	if err != nil {
+		log.Printf("[RunOnce] warning: failed to look up when exited coins entered: %v", err)
+	}
+	var oldest map[int64]struct{}
+	if err == nil && len(entered) < len(exited) {
+		oldest = oldestSnapshotIDs(ctx, store)
+	}
+	out := make([]Coin, len(exited))
+	for i, c := range exited {
+		lastRank := c.Rank
+		c.LastRank = &lastRank
+		_, inOldest := oldest[c.ID]
+		switch at, ok := entered[c.ID]; {
+		case ok:
+			days := int64(now.Sub(at) / (24 * time.Hour))
+			c.DaysInTopN = &days
+		case err != nil:
+		case inOldest:
+			c.InTopNSinceTracking = true
+		case tenure[c.ID] > 0:
+			c.TenureRuns = tenure[c.ID]
+		}
+		out[i] = c
+	}
+	return out
+}
+
+// oldestSnapshotIDs returns the coin ids of the oldest stored snapshot, or nil
+// when there is none (RAW_SNAPSHOT_ENABLED off) or the lookup failed.
+func oldestSnapshotIDs(ctx context.Context, store StateStore) map[int64]struct{} {
+	snap, err := store.LoadOldestSnapshot(ctx)
+	if errors.Is(err, ErrNoSnapshot) {
+		return nil
+	}
+	if err != nil {
+		log.Printf("[RunOnce] warning: failed to load the oldest snapshot for exited coins: %v", err)
+		return nil
+	}
+	ids := make(map[int64]struct{}, len(snap.Coins))
+	for _, c := range snap.Coins {
+		ids[c.ID] = struct{}{}
+	}
+	return ids
+}
@@ -0,0 +1,65 @@
+package bot
+
+import (
+	"context"
+	"strings"
+	"testing"
+	"time"
+)
+
+func TestAnnotateExitsUsesLatestEntryPost(t *testing.T) {
+	now := time.Date(2026, 5, 20, 12, 0, 0, 0, time.UTC)
+	store := &memoryStore{history: []HistoryDoc{
+		{CreatedAt: now.Add(-40 * 24 * time.Hour), NewCoinIDs: []int64{5}},
+		{CreatedAt: now.Add(-12 * 24 * time.Hour), NewCoinIDs: []int64{5, 7}},
+		{CreatedAt: now.Add(-2 * 24 * time.Hour), NewCoinIDs: []int64{9}, Forced: true},
+	}, snapshots: []Snapshot{
+		{CreatedAt: now.Add(-30 * 24 * time.Hour), Coins: []Coin{{ID: 5}, {ID: 11}}},
+		{CreatedAt: now.Add(-60 * 24 * time.Hour), Coins: []Coin{{ID: 9}}},
+	}}
+	exited := []Coin{{ID: 5, Rank: 98, Symbol: "SHIB"}, {ID: 9, Rank: 100, Symbol: "PEPE"}, {ID: 11, Rank: 99, Symbol: "BONK"}}
+	tenure := map[int64]int64{5: 80, 9: 400, 11: 35}
+
+	got := annotateExits(context.Background(), store, exited, tenure, now)
+	if got[0].LastRank == nil || *got[0].LastRank != 98 || got[0].DaysInTopN == nil || *got[0].DaysInTopN != 12 || got[0].TenureRuns != 0 {
+		t.Fatalf("SHIB should have last rank 98 and 12 days in, got %+v", got[0])
+	}
+	if got[1].DaysInTopN != nil || !got[1].InTopNSinceTracking {
+		t.Fatalf("PEPE was only in a forced post and is in the oldest snapshot, got %+v", got[1])
+	}
+	if got[2].DaysInTopN != nil || got[2].InTopNSinceTracking || got[2].TenureRuns != 35 {
+		t.Fatalf("BONK joined after the oldest snapshot and should only carry its tenure, got %+v", got[2])
+	}
+}
+
+func TestAnnotateExitsWithoutSnapshotsNeverClaimsTracking(t *testing.T) {
+	store := &memoryStore{}
+	exited := []Coin{{ID: 9, Rank: 100, Symbol: "PEPE"}, {ID: 12, Rank: 97, Symbol: "WIF"}}
+
+	got := annotateExits(context.Background(), store, exited, map[int64]int64{9: 400}, time.Now().UTC())
+	if got[0].InTopNSinceTracking || got[0].TenureRuns != 400 {
+		t.Fatalf("PEPE should fall back to its tenure, got %+v", got[0])
+	}
+	if got[1].InTopNSinceTracking || got[1].TenureRuns != 0 || got[1].DaysInTopN != nil {
+		t.Fatalf("WIF has no data and should carry no claim, got %+v", got[1])
+	}
+}
+
+func TestFallbackTemplateRendersExitTenure(t *testing.T) {
+	days, last, lastPepe := int64(12), int64(98), int64(100)
+	exited := []Coin{
+		{Name: "Shiba Inu", Symbol: "SHIB", LastRank: &last, DaysInTopN: &days},
+		{Name: "Pepe", Symbol: "PEPE", LastRank: &lastPepe, InTopNSinceTracking: true},
+		{Name: "Bonk", Symbol: "BONK", LastRank: &last, TenureRuns: 35},
+	}
+	got := RenderTemplate(defaultFallbackTemplate, buildRenderContext(Config{TopN: 100}, RunOptions{Convert: "USD", NotifyExits: true}, renderInputs{ExitedCoins: exited}))
+	for _, want := range []string{
+		"• Shiba Inu (SHIB), last #98, 12 day(s) in the Top 100",
+		"• Pepe (PEPE), last #100, in the Top 100 since tracking began",
+		"• Bonk (BONK), last #98, 35 run(s) in the Top 100",
+	} {
+		if !strings.Contains(got, want) {
+			t.Fatalf("missing %q in %q", want, got)
+		}
+	}
+}
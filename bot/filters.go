@@ -0,0 +1,85 @@
+package bot
+
+import (
+	"slices"
+	"strconv"
+	"strings"
+)
+
+// excludeByTags drops coins carrying any of the CMC tags in exclude (compared
+// case-insensitively) and reports how many were dropped.
+func excludeByTags(coins []Coin, exclude []string) ([]Coin, int) {
+	if len(exclude) == 0 {
+		return coins, 0
+	}
+	kept := make([]Coin, 0, len(coins))
+	for _, c := range coins {
+		if slices.ContainsFunc(c.Tags, func(tag string) bool { return slices.Contains(exclude, strings.ToLower(tag)) }) {
+			continue
+		}
+		kept = append(kept, c)
+	}
+	return kept, len(coins) - len(kept)
+}
+
+// CoinList matches coins by CMC id or by symbol (case-insensitive), as given
+// in COIN_BLOCKLIST / COIN_ALLOWLIST; EXCLUDE_SYMBOLS adds to the blocklist.
+type CoinList struct {
+	ids     map[int64]struct{}
+	symbols map[string]struct{}
+}
+
+// ParseCoinList parses comma-separated ids and symbols, eg "1839,BNB,okb".
+// Entries that parse as integers are ids, everything else is a symbol.
+func ParseCoinList(raw string) CoinList {
+	l := CoinList{ids: map[int64]struct{}{}, symbols: map[string]struct{}{}}
+	for _, item := range splitList(raw) {
+		if id, err := strconv.ParseInt(item, 10, 64); err == nil {
+			l.ids[id] = struct{}{}
+			continue
+		}
+		l.symbols[strings.ToUpper(item)] = struct{}{}
+	}
+	return l
+}
+
+func (l CoinList) empty() bool { return len(l.ids) == 0 && len(l.symbols) == 0 }
+
+func (l CoinList) matches(c Coin) bool {
+	if _, ok := l.ids[c.ID]; ok {
+		return true
+	}
+	_, ok := l.symbols[strings.ToUpper(c.Symbol)]
+	return ok
+}
+
+// filterCoinLists drops blocked coins and, when the allowlist is not empty,
+// every coin that is not on it. It reports how many coins were dropped.
+func filterCoinLists(coins []Coin, block, allow CoinList) ([]Coin, int) {
+	if block.empty() && allow.empty() {
+		return coins, 0
+	}
+	kept := make([]Coin, 0, len(coins))
+	for _, c := range coins {
+		if block.matches(c) || (!allow.empty() && !allow.matches(c)) {
+			continue
+		}
+		kept = append(kept, c)
+	}
+	return kept, len(coins) - len(kept)
+}
+
+// filterMinMarketCap leaves coins whose market cap is unknown or below
+// threshold out of the post and reports how many it left out. Unlike the
+// other entrant filters the coins are kept unrecorded: they are not counted as
+// suppressed, so they stay pending until they pass the threshold.
+func filterMinMarketCap(coins []Coin, threshold float64) ([]Coin, int) {
+	kept := make([]Coin, 0, len(coins))
+	for _, c := range coins {
+		if c.MarketCap == nil || *c.MarketCap < threshold {
+			continue
+		}
+		kept = append(kept, c)
+	}
+	return kept, len(coins) - len(kept)
+}
@@ -0,0 +1,104 @@
+package bot
+
+import (
+	"context"
+	"slices"
+	"testing"
+)
+
+func TestExcludeByTags(t *testing.T) {
+	coins := []Coin{{ID: 1, Tags: []string{"pow"}}, {ID: 2, Tags: []string{"Stablecoin", "usd-stablecoin"}}, {ID: 3, Tags: []string{"wrapped-tokens"}}, {ID: 4}}
+	kept, suppressed := excludeByTags(coins, []string{"stablecoin", "wrapped-tokens"})
+	if suppressed != 2 || len(kept) != 2 || kept[0].ID != 1 || kept[1].ID != 4 {
+		t.Fatalf("unexpected result: kept=%+v suppressed=%d", kept, suppressed)
+	}
+}
+
+func TestRunOnceSkipsPostWhenAllEntrantsAreExcludedByTag(t *testing.T) {
+	srv := newCMCMockServer(t, `{"data":[
+		{"id":1,"name":"Bitcoin","symbol":"BTC","cmc_rank":1,"tags":["pow"]},
+		{"id":3,"name":"Yet Another Dollar","symbol":"YAD","cmc_rank":2,"tags":["stablecoin"]}]}`)
+	store := &memoryStore{state: &State{TopN: 2, Convert: "USD", IDs: []int64{1, 2}, Coins: []Coin{{ID: 1, Rank: 1}, {ID: 2, Rank: 2}}}}
+	cfg := Config{CMCBaseURL: srv.URL, TopN: 2, ExcludeTags: []string{"stablecoin"}}
+
+	if err := RunOnceWithStore(context.Background(), cfg, RunOptions{Convert: "USD"}, store); err != nil {
+		t.Fatalf("RunOnceWithStore error: %v", err)
+	}
+	if len(store.history) != 0 {
+		t.Fatalf("expected no post, got %d history entries", len(store.history))
+	}
+	if store.stateWrites != 1 || len(store.state.IDs) != 2 || store.state.IDs[1] != 3 {
+		t.Fatalf("excluded entrant should still be recorded in state, got %+v after %d write(s)", store.state.IDs, store.stateWrites)
+	}
+}
+
+func TestRunOnceIgnoresExcludedSymbols(t *testing.T) {
+	srv := newCMCMockServer(t, `{"data":[
+		{"id":1,"name":"Bitcoin","symbol":"BTC","cmc_rank":1},
+		{"id":825,"name":"Tether","symbol":"usdt","cmc_rank":2}]}`)
+	store := &memoryStore{state: &State{TopN: 2, Convert: "USD", IDs: []int64{1, 3408}, Coins: []Coin{{ID: 1, Symbol: "BTC", Rank: 1}, {ID: 3408, Symbol: "USDC", Rank: 2}}}}
+	t.Setenv("CMC_API_KEY", "key")
+	t.Setenv("COIN_BLOCKLIST", "")
+	t.Setenv("EXCLUDE_SYMBOLS", "usdt, USDC")
+	cfg, err := ConfigFromEnv(true, true)
+	if err != nil {
+		t.Fatalf("ConfigFromEnv error: %v", err)
+	}
+	cfg.CMCBaseURL, cfg.TopN = srv.URL, 2
+
+	if err := RunOnceWithStore(context.Background(), cfg, RunOptions{Convert: "USD", NotifyExits: true}, store); err != nil {
+		t.Fatalf("RunOnceWithStore error: %v", err)
+	}
+	if len(store.history) != 0 {
+		t.Fatalf("expected no post for an excluded entrant or exit, got %d history entries", len(store.history))
+	}
+	if store.stateWrites != 1 || !slices.Contains(store.state.IDs, 825) {
+		t.Fatalf("excluded coins should still be recorded in state like the blocklist, got %+v after %d write(s)", store.state.IDs, store.stateWrites)
+	}
+}
+
+func TestFilterCoinListsMatchesIDsAndSymbols(t *testing.T) {
+	coins := []Coin{{ID: 1, Symbol: "BTC"}, {ID: 1839, Symbol: "BNB"}, {ID: 3635, Symbol: "CRO"}, {ID: 3897, Symbol: "OKB"}, {ID: 5426, Symbol: "SOL"}}
+
+	kept, dropped := filterCoinLists(coins, ParseCoinList("1839, okb"), CoinList{})
+	if dropped != 2 || len(kept) != 3 || kept[1].Symbol != "CRO" {
+		t.Fatalf("blocklist: kept=%+v dropped=%d", kept, dropped)
+	}
+
+	kept, dropped = filterCoinLists(coins, CoinList{}, ParseCoinList("sol,1"))
+	if dropped != 3 || len(kept) != 2 || kept[0].ID != 1 || kept[1].ID != 5426 {
+		t.Fatalf("allowlist: kept=%+v dropped=%d", kept, dropped)
+	}
+
+	kept, dropped = filterCoinLists(coins, ParseCoinList("Sol"), ParseCoinList("SOL,BTC"))
+	if dropped != 4 || len(kept) != 1 || kept[0].ID != 1 {
+		t.Fatalf("blocklist should win over allowlist: kept=%+v dropped=%d", kept, dropped)
+	}
+
+	if kept, dropped = filterCoinLists(coins, ParseCoinList(" , "), ParseCoinList("")); dropped != 0 || len(kept) != len(coins) {
+		t.Fatalf("empty lists should keep everything: kept=%+v dropped=%d", kept, dropped)
+	}
+}
+
+func TestFilterMinMarketCapDropsSmallAndUnknown(t *testing.T) {
+	big, small := 2e9, 5e8
+	kept, dropped := filterMinMarketCap([]Coin{{ID: 1, MarketCap: &big}, {ID: 2, MarketCap: &small}, {ID: 3}}, 1e9)
+	if dropped != 2 || len(kept) != 1 || kept[0].ID != 1 {
+		t.Fatalf("unexpected result: kept=%+v dropped=%d", kept, dropped)
+	}
+}
+
+func TestRunOnceWithAllEntrantsBelowMinMarketCapDoesNothing(t *testing.T) {
+	srv := newCMCMockServer(t, `{"data":[
+		{"id":1,"name":"Bitcoin","symbol":"BTC","cmc_rank":1,"quote":{"USD":{"market_cap":1000000000000}}},
+		{"id":3,"name":"Tiny","symbol":"TINY","cmc_rank":2,"quote":{"USD":{"market_cap":90000000}}}]}`)
+	store := &memoryStore{state: &State{TopN: 2, Convert: "USD", IDs: []int64{1, 2}, Coins: []Coin{{ID: 1, Rank: 1}, {ID: 2, Rank: 2}}}}
+	minCap := 1e8
+
+	if err := RunOnceWithStore(context.Background(), Config{CMCBaseURL: srv.URL, TopN: 2}, RunOptions{Convert: "USD", MinMarketCap: &minCap}, store); err != nil {
+		t.Fatalf("RunOnceWithStore error: %v", err)
+	}
+	if len(store.history) != 0 || store.stateWrites != 0 {
+		t.Fatalf("expected a no-new-entrants run, got %d history entries and %d state writes", len(store.history), store.stateWrites)
+	}
+}
@@ -0,0 +1,41 @@
+package bot
+
+import (
+	"context"
+	"log"
+	"time"
+)
+
+// recentlyAnnounced returns the ids that a post announced as an entrant or
+// an exit within FLAP_WINDOW_HOURS of now, or nil when the window is off. A
+// failed history query only logs a warning and suppresses nothing.
+func recentlyAnnounced(ctx context.Context, store StateStore, cfg Config, now time.Time) map[int64]struct{} {
+	if cfg.FlapWindow <= 0 {
+		return nil
+	}
+	ids, err := store.AnnouncedSince(ctx, now.Add(-cfg.FlapWindow))
+	if err != nil {
+		log.Printf("[RunOnce] warning: failed to load recent entrants and exits for FLAP_WINDOW_HOURS: %v", err)
+		return nil
+	}
+	return ids
+}
+
+// splitFlappers separates the coins in announced, which are flipping around
+// the cutoff, from the rest. Flappers stay out of the post but, like other
+// filtered coins, are still recorded in state.
+func splitFlappers(coins []Coin, announced map[int64]struct{}) ([]Coin, []Coin) {
+	if len(announced) == 0 {
+		return coins, nil
+	}
+	kept := make([]Coin, 0, len(coins))
+	var flappers []Coin
+	for _, c := range coins {
+		if _, ok := announced[c.ID]; ok {
+			flappers = append(flappers, c)
+			continue
+		}
+		kept = append(kept, c)
+	}
+	return kept, flappers
+}
@@ -0,0 +1,46 @@
+package bot
+
+import (
+	"strings"
+	"testing"
+	"time"
+)
+
+func TestRunOnceSuppressesFlappingEntrantsAndExits(t *testing.T) {
+	const listings = `{"data":[
+		{"id":1,"name":"Bitcoin","symbol":"BTC","cmc_rank":1},
+		{"id":2010,"name":"Cardano","symbol":"ADA","cmc_rank":2},
+		{"id":5426,"name":"Solana","symbol":"SOL","cmc_rank":3}]}`
+	now := time.Now().UTC()
+	store := &memoryStore{
+		state: &State{TopN: 3, Convert: "USD", IDs: []int64{1, 1027, 74}, Coins: []Coin{
+			{ID: 1, Name: "Bitcoin", Symbol: "BTC", Rank: 1},
+			{ID: 1027, Name: "Ethereum", Symbol: "ETH", Rank: 2},
+			{ID: 74, Name: "Dogecoin", Symbol: "DOGE", Rank: 3},
+		}},
+		history: []HistoryDoc{
+			{CreatedAt: now.Add(-48 * time.Hour), NewCoinIDs: []int64{2010}, ExitedCoinIDs: []int64{1027}},
+			{CreatedAt: now.Add(-2 * time.Hour), NewCoinIDs: []int64{5426}, ExitedCoinIDs: []int64{74}},
+		},
+	}
+	cfg := Config{TopN: 3, RankChangeThreshold: 10, FlapWindow: 24 * time.Hour}
+
+	out := runDry(t, cfg, RunOptions{Convert: "USD", NotifyExits: true}, listings, store)
+	if strings.Contains(out, "Solana") || strings.Contains(out, "Dogecoin") {
+		t.Fatalf("SOL and DOGE flipped within the window and should be suppressed: %q", out)
+	}
+	if !strings.Contains(out, "Cardano") || !strings.Contains(out, "Ethereum") {
+		t.Fatalf("ADA and ETH were announced 48h ago and should be reported: %q", out)
+	}
+}
+
+func TestSplitFlappers(t *testing.T) {
+	coins := []Coin{{ID: 1}, {ID: 2}, {ID: 3}}
+	kept, flappers := splitFlappers(coins, map[int64]struct{}{2: {}})
+	if len(kept) != 2 || len(flappers) != 1 || flappers[0].ID != 2 {
+		t.Fatalf("unexpected split: kept=%v flappers=%v", kept, flappers)
+	}
+	if kept, flappers := splitFlappers(coins, nil); len(kept) != 3 || flappers != nil {
+		t.Fatalf("nil window must keep every coin: kept=%v flappers=%v", kept, flappers)
+	}
+}
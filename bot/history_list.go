@@ -0,0 +1,89 @@
+package bot
+
+import (
+	"context"
+	"encoding/json"
+	"fmt"
+	"io"
+	"strings"
+	"text/tabwriter"
+)
+
+// Output formats of the list-history command.
+const (
+	HistoryFormatTable = "table"
+	HistoryFormatJSON  = "json"
+)
+
+// historyPreviewLen is how many characters of a post the table shows.
+const historyPreviewLen = 60
+
+// ListHistory writes up to limit published posts, newest first, to w: a
+// table of created_at, new coin symbols and a text preview, or the full
+// history documents as a JSON array.
+func ListHistory(ctx context.Context, cfg Config, limit int, format string, w io.Writer) error {
+	if format != HistoryFormatTable && format != HistoryFormatJSON {
+		return fmt.Errorf("unsupported --format value %q (want table or json)", format)
+	}
+	store, err := OpenStateStore(ctx, cfg)
+	if err != nil {
+		return err
+	}
+	defer store.Close(context.Background())
+
+	docs, err := store.ListHistory(ctx, limit)
+	if err != nil {
+		return err
+	}
+	if format == HistoryFormatJSON {
+		return writeHistoryJSON(w, docs)
+	}
+	return writeHistoryTable(w, docs)
+}
+
+func writeHistoryJSON(w io.Writer, docs []HistoryDoc) error {
+	b, err := json.MarshalIndent(docs, "", "  ")
+	if err != nil {
+		return err
+	}
+	_, err = fmt.Fprintln(w, string(b))
+	return err
+}
+
+func writeHistoryTable(w io.Writer, docs []HistoryDoc) error {
+	tw := tabwriter.NewWriter(w, 0, 0, 2, ' ', 0)
+	fmt.Fprintln(tw, "CREATED_AT\tNEW_COINS\tTEXT")
+	for _, d := range docs {
+		fmt.Fprintf(tw, "%s\t%s\t%s\n", d.CreatedAt.UTC().Format("2006-01-02 15:04:05"), historyNewSymbols(d), textPreview(d.Text, historyPreviewLen))
+	}
+	return tw.Flush()
+}
+
+// historyNewSymbols lists the symbols of the post's new entrants, taken from
+// its mentioned coins, or "-" when it had none (digests, rank-only posts).
+func historyNewSymbols(d HistoryDoc) string {
+	isNew := make(map[int64]bool, len(d.NewCoinIDs))
+	for _, id := range d.NewCoinIDs {
+		isNew[id] = true
+	}
+	var symbols []string
+	for _, c := range d.MentionedCoins {
+		if isNew[c.ID] {
+			symbols = append(symbols, c.Symbol)
+			delete(isNew, c.ID)
+		}
+	}
+	if len(symbols) == 0 {
+		return "-"
+	}
+	return strings.Join(symbols, ",")
+}
+
+// textPreview folds text onto one line and cuts it to n characters with "…".
+func textPreview(text string, n int) string {
+	out := strings.Join(strings.Fields(text), " ")
+	if r := []rune(out); len(r) > n {
+		out = string(r[:n-1]) + "…"
+	}
+	return out
+}
@@ -0,0 +1,47 @@
+package bot
+
+import (
+	"bytes"
+	"encoding/json"
+	"strings"
+	"testing"
+	"time"
+)
+
+func TestWriteHistoryTableShowsNewSymbolsAndPreview(t *testing.T) {
+	docs := []HistoryDoc{
+		{CreatedAt: time.Date(2026, 3, 2, 9, 30, 0, 0, time.UTC), NewCoinIDs: []int64{20947, 5426},
+			MentionedCoins: []Coin{{ID: 20947, Symbol: "SUI"}, {ID: 1, Symbol: "BTC"}, {ID: 5426, Symbol: "SOL"}},
+			Text:           "🚀 New in the Top 100:\n• SUI\n• SOL joins with a market cap that is far too long to show in full"},
+		{CreatedAt: time.Date(2026, 3, 1, 9, 0, 0, 0, time.UTC), Kind: HistoryKindDigest, NewCoinIDs: []int64{}, Text: "📅 Weekly digest"},
+	}
+	var buf bytes.Buffer
+	if err := writeHistoryTable(&buf, docs); err != nil {
+		t.Fatalf("writeHistoryTable error: %v", err)
+	}
+	lines := strings.Split(strings.TrimRight(buf.String(), "\n"), "\n")
+	if len(lines) != 3 || !strings.HasPrefix(lines[0], "CREATED_AT") {
+		t.Fatalf("unexpected table:\n%s", buf.String())
+	}
+	if !strings.Contains(lines[1], "2026-03-02 09:30:00") || !strings.Contains(lines[1], "SUI,SOL") || !strings.HasSuffix(lines[1], "…") {
+		t.Fatalf("unexpected first row: %q", lines[1])
+	}
+	if !strings.Contains(lines[2], " - ") || !strings.HasSuffix(lines[2], "📅 Weekly digest") {
+		t.Fatalf("unexpected digest row: %q", lines[2])
+	}
+}
+
+func TestWriteHistoryJSONDumpsFullDocs(t *testing.T) {
+	var buf bytes.Buffer
+	docs := []HistoryDoc{{ID: "7", CreatedAt: time.Date(2026, 3, 2, 9, 30, 0, 0, time.UTC), NewCoinIDs: []int64{20947}, Text: "🚀 SUI", AIModelUsed: "gpt-4o"}}
+	if err := writeHistoryJSON(&buf, docs); err != nil {
+		t.Fatalf("writeHistoryJSON error: %v", err)
+	}
+	var got []map[string]any
+	if err := json.Unmarshal(buf.Bytes(), &got); err != nil {
+		t.Fatalf("invalid json: %v\n%s", err, buf.String())
+	}
+	if len(got) != 1 || got[0]["id"] != "7" || got[0]["text"] != "🚀 SUI" || got[0]["ai_model_used"] != "gpt-4o" || got[0]["created_at"] != "2026-03-02T09:30:00Z" {
+		t.Fatalf("unexpected json: %v", got)
+	}
+}
@@ -0,0 +1,89 @@
+package bot
+
+import (
+	"context"
+	"crypto/tls"
+	"crypto/x509"
+	"errors"
+	"fmt"
+	"log"
+	"net"
+	"net/http"
+	"os"
+	"time"
+)
+
+const (
+	defaultHTTPConnectTimeout = 10 * time.Second
+	defaultHTTPRequestTimeout = 30 * time.Second
+)
+
+// NewHTTPClient builds the client shared by every upstream call in a run, with
+// the connect and total request timeouts from cfg. Proxies come from
+// HTTPS_PROXY/HTTP_PROXY/NO_PROXY; EXTRA_CA_CERT_PATH and ACCEPT_INVALID_CERTS
+// adjust TLS verification.
+func NewHTTPClient(cfg Config) *http.Client {
+	connectTimeout := cfg.HTTPConnectTimeout
+	if connectTimeout <= 0 {
+		connectTimeout = defaultHTTPConnectTimeout
+	}
+	requestTimeout := cfg.HTTPRequestTimeout
+	if requestTimeout <= 0 {
+		requestTimeout = defaultHTTPRequestTimeout
+	}
+	transport := http.DefaultTransport.(*http.Transport).Clone()
+	transport.DialContext = (&net.Dialer{Timeout: connectTimeout, KeepAlive: 30 * time.Second}).DialContext
+	transport.TLSHandshakeTimeout = connectTimeout
+	transport.Proxy = http.ProxyFromEnvironment
+	if len(cfg.ExtraCACertPEM) > 0 || cfg.AcceptInvalidCerts {
+		tlsConfig := &tls.Config{MinVersion: tls.VersionTLS12}
+		if len(cfg.ExtraCACertPEM) > 0 {
+			// The PEM was already validated by loadExtraCACert.
+			tlsConfig.RootCAs, _ = certPoolWith(cfg.ExtraCACertPEM)
+		}
+		if cfg.AcceptInvalidCerts {
+			log.Printf("[HTTPClient] WARNING: ACCEPT_INVALID_CERTS=true, TLS certificates are NOT verified; use only for debugging")
+			tlsConfig.InsecureSkipVerify = true
+		}
+		transport.TLSClientConfig = tlsConfig
+	}
+	return &http.Client{Timeout: requestTimeout, Transport: transport}
+}
+
+// loadExtraCACert reads the PEM file named by EXTRA_CA_CERT_PATH so a bad path
+// or file fails config loading instead of the first TLS handshake.
+func loadExtraCACert(path string) ([]byte, error) {
+	if path == "" {
+		return nil, nil
+	}
+	pem, err := os.ReadFile(path)
+	if err != nil {
+		return nil, fmt.Errorf("EXTRA_CA_CERT_PATH: %w", err)
+	}
+	if _, err := certPoolWith(pem); err != nil {
+		return nil, fmt.Errorf("EXTRA_CA_CERT_PATH %s: %w", path, err)
+	}
+	return pem, nil
+}
+
+// certPoolWith returns the system roots plus the certificates in pem.
+func certPoolWith(pem []byte) (*x509.CertPool, error) {
+	pool, err := x509.SystemCertPool()
+	if err != nil || pool == nil {
+		pool = x509.NewCertPool()
+	}
+	if !pool.AppendCertsFromPEM(pem) {
+		return nil, errors.New("no PEM certificates found")
+	}
+	return pool, nil
+}
+
+// upstreamError names the upstream in transport errors so a timeout against
+// CMC, the AI provider or Telegram is obvious from the log line alone.
+func upstreamError(upstream string, err error) error {
+	var netErr net.Error
+	if errors.Is(err, context.DeadlineExceeded) || (errors.As(err, &netErr) && netErr.Timeout()) {
+		return fmt.Errorf("%s request timed out: %w", upstream, err)
+	}
+	return fmt.Errorf("%s request failed: %w", upstream, err)
+}
@@ -0,0 +1,80 @@
+package bot
+
+import (
+	"context"
+	"encoding/pem"
+	"net/http"
+	"net/http/httptest"
+	"os"
+	"path/filepath"
+	"strings"
+	"testing"
+	"time"
+)
+
+func TestNewHTTPClientTimesOutOnSlowServer(t *testing.T) {
+	release := make(chan struct{})
+	srv := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		select {
+		case <-release:
+		case <-r.Context().Done():
+		}
+	}))
+	defer srv.Close()
+	defer close(release)
+
+	client := NewHTTPClient(Config{HTTPRequestTimeout: 200 * time.Millisecond})
+	req, err := http.NewRequestWithContext(context.Background(), http.MethodGet, srv.URL, nil)
+	if err != nil {
+		t.Fatal(err)
+	}
+	start := time.Now()
+	_, err = client.Do(req)
+	elapsed := time.Since(start)
+	if err == nil {
+		t.Fatalf("expected a timeout error")
+	}
+	if got := upstreamError("cmc listings", err).Error(); !strings.Contains(got, "cmc listings request timed out") {
+		t.Fatalf("expected a timeout error, got %q", got)
+	}
+	if elapsed < 200*time.Millisecond || elapsed > 2*time.Second {
+		t.Fatalf("expected to fail around the 200ms timeout, took %s", elapsed)
+	}
+}
+
+func TestNewHTTPClientTrustsExtraCACert(t *testing.T) {
+	srv := httptest.NewTLSServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {}))
+	defer srv.Close()
+
+	path := filepath.Join(t.TempDir(), "ca.pem")
+	certPEM := pem.EncodeToMemory(&pem.Block{Type: "CERTIFICATE", Bytes: srv.Certificate().Raw})
+	if err := os.WriteFile(path, certPEM, 0o600); err != nil {
+		t.Fatal(err)
+	}
+
+	if _, err := NewHTTPClient(Config{}).Get(srv.URL); err == nil {
+		t.Fatalf("expected the test server certificate to be rejected without the extra CA")
+	}
+	caPEM, err := loadExtraCACert(path)
+	if err != nil {
+		t.Fatalf("loadExtraCACert error: %v", err)
+	}
+	resp, err := NewHTTPClient(Config{ExtraCACertPEM: caPEM}).Get(srv.URL)
+	if err != nil {
+		t.Fatalf("request with extra CA failed: %v", err)
+	}
+	resp.Body.Close()
+}
+
+func TestLoadExtraCACertFailsFast(t *testing.T) {
+	if _, err := loadExtraCACert(filepath.Join(t.TempDir(), "missing.pem")); err == nil {
+		t.Fatalf("expected an error for a missing file")
+	}
+	path := filepath.Join(t.TempDir(), "bad.pem")
+	if err := os.WriteFile(path, []byte("not a certificate"), 0o600); err != nil {
+		t.Fatal(err)
+	}
+	if _, err := loadExtraCACert(path); err == nil || !strings.Contains(err.Error(), "no PEM certificates") {
+		t.Fatalf("expected a PEM error, got %v", err)
+	}
+}
@@ -0,0 +1,34 @@
+package bot
+
+import (
+	"context"
+	"log"
+)
+
+// InitState fetches the current top-N and writes it as the state baseline,
+// replacing whatever was stored. It never posts and never touches history,
+// so it is the safe way to (re)seed state before pointing the bot at a new
+// channel.
+func InitState(ctx context.Context, cfg Config, opt RunOptions) error {
+	store, err := OpenStateStore(ctx, cfg)
+	if err != nil {
+		return err
+	}
+	defer store.Close(context.Background())
+	return initStateWithStore(ctx, cfg, opt, store)
+}
+
+func initStateWithStore(ctx context.Context, cfg Config, opt RunOptions, store StateStore) error {
+	metrics := &RunMetrics{}
+	current, band, provider, err := fetchWithWatchlist(ctx, NewHTTPClient(cfg), cfg, opt, metrics)
+	if err != nil {
+		return err
+	}
+	tenure, _ := updateTenure(nil, nil, current, 0)
+	st := State{TopN: int64(cfg.TopN), Convert: opt.Convert, Sort: opt.cmcSort(), Provider: provider, CreditsUsed: metrics.CMCCredits, Coins: current, Tenure: tenure, WatchlistIDs: coinIDs(band)}
+	if err := store.WriteState(ctx, st); err != nil {
+		return err
+	}
+	log.Printf("[InitState] wrote baseline of %d coins from %s (top_n=%d convert=%s sort=%s); nothing was posted", len(current), provider, cfg.TopN, opt.Convert, opt.cmcSort())
+	return nil
+}
@@ -0,0 +1,25 @@
+package bot
+
+import (
+	"context"
+	"testing"
+)
+
+func TestInitStateWritesStateOnceAndNeverPosts(t *testing.T) {
+	srv := newCMCMockServer(t, listingsBody(1, 2, 3))
+
+	store := &memoryStore{state: &State{TopN: 3, IDs: []int64{7, 8, 9}}}
+	cfg := Config{CMCBaseURL: srv.URL, TopN: 3, TelegramToken: "token", TelegramChannelID: "@chan"}
+	if err := initStateWithStore(context.Background(), cfg, RunOptions{Convert: "USD"}, store); err != nil {
+		t.Fatalf("initStateWithStore error: %v", err)
+	}
+	if store.stateWrites != 1 {
+		t.Fatalf("expected exactly one WriteState, got %d", store.stateWrites)
+	}
+	if got := store.state.IDs; len(got) != 3 || got[0] != 1 || got[2] != 3 {
+		t.Fatalf("baseline should replace the old state, got ids %v", got)
+	}
+	if len(store.history) != 0 {
+		t.Fatalf("init-state must not write history, got %d entries", len(store.history))
+	}
+}
@@ -0,0 +1,67 @@
+package bot
+
+import (
+	"fmt"
+	"log"
+	"math"
+)
+
+// defaultCMCMinCoinFraction is how much of the requested top-N a listing must
+// cover before it is trusted; CMC_MIN_COIN_FRACTION overrides it. A short
+// listing that is accepted is diffed and stored like a full one, so the coins
+// it misses show up as exits and then as re-entries.
+const defaultCMCMinCoinFraction = 0.9
+
+// minCoinCount is the smallest listing accepted for cfg.TopN. A zero fraction
+// (a Config built by hand) requires the full top-N.
+func (c Config) minCoinCount() int {
+	if c.CMCMinCoinFraction <= 0 {
+		return c.TopN
+	}
+	return int(math.Ceil(c.CMCMinCoinFraction * float64(c.TopN)))
+}
+
+// checkListing dedupes a fetched listing by id, keeping the entry with the
+// best rank, and rejects listings that are too short or, when sorted by
+// market cap, whose ranks mostly fall outside 1..top_n. A rejected listing
+// never reaches WriteState; one accepted below TopN by CMC_MIN_COIN_FRACTION
+// is stored as is.
+func checkListing(coins []Coin, cfg Config, sort string) ([]Coin, error) {
+	index := make(map[int64]int, len(coins))
+	out := make([]Coin, 0, len(coins))
+	for _, c := range coins {
+		i, dup := index[c.ID]
+		if !dup {
+			index[c.ID] = len(out)
+			out = append(out, c)
+			continue
+		}
+		log.Printf("[checkListing] warning: dropping duplicate coin id=%d (%s), ranks %d and %d", c.ID, c.Symbol, out[i].Rank, c.Rank)
+		if c.Rank < out[i].Rank {
+			out[i] = c
+		}
+	}
+
+	need := cfg.minCoinCount()
+	if len(out) < need {
+		return nil, fmt.Errorf("cmc returned %d coins, expected %d (at least %d required by CMC_MIN_COIN_FRACTION); refusing to use a truncated listing", len(out), cfg.TopN, need)
+	}
+	if sort != "market_cap" {
+		// cmc_rank is always the market cap rank, so other sorts have no
+		// expected range.
+		return out, nil
+	}
+	inRange := map[int64]struct{}{}
+	for _, c := range out {
+		if c.Rank >= 1 && c.Rank <= int64(cfg.TopN) {
+			inRange[c.Rank] = struct{}{}
+		}
+	}
+	if len(inRange) < need {
+		return nil, fmt.Errorf("cmc returned %d coins but only %d distinct ranks within 1..%d (at least %d required by CMC_MIN_COIN_FRACTION); refusing to use an implausible listing", len(out), len(inRange), cfg.TopN, need)
+	}
+	if len(out) < cfg.TopN {
+		log.Printf("[checkListing] warning: cmc returned %d coins, expected %d; accepted by CMC_MIN_COIN_FRACTION", len(out), cfg.TopN)
+	}
+	return out, nil
+}
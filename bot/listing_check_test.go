@@ -0,0 +1,57 @@
+package bot
+
+import (
+	"strings"
+	"testing"
+)
+
+func rankedCoins(ranks ...int64) []Coin {
+	coins := make([]Coin, 0, len(ranks))
+	for i, r := range ranks {
+		coins = append(coins, Coin{ID: int64(i + 1), Rank: r})
+	}
+	return coins
+}
+
+func TestCheckListingKeepsBestRankOfDuplicates(t *testing.T) {
+	coins := []Coin{{ID: 1, Rank: 1}, {ID: 2, Rank: 5}, {ID: 3, Rank: 3}, {ID: 2, Rank: 2}}
+	got, err := checkListing(coins, Config{TopN: 3}, "market_cap")
+	if err != nil {
+		t.Fatalf("checkListing error: %v", err)
+	}
+	if len(got) != 3 || got[1].ID != 2 || got[1].Rank != 2 {
+		t.Fatalf("unexpected coins: %+v", got)
+	}
+}
+
+func TestCheckListingHonoursMinCoinFraction(t *testing.T) {
+	t.Setenv("CMC_API_KEY", "key")
+	t.Setenv("CMC_MIN_COIN_FRACTION", "")
+	cfg, err := ConfigFromEnv(true, true)
+	if err != nil {
+		t.Fatalf("ConfigFromEnv error: %v", err)
+	}
+	if cfg.CMCMinCoinFraction != 0.9 {
+		t.Fatalf("expected CMC_MIN_COIN_FRACTION to default to 0.9, got %v", cfg.CMCMinCoinFraction)
+	}
+
+	coins := rankedCoins(1, 2, 3, 4, 5, 6, 7, 8, 9)
+	if _, err := checkListing(coins, Config{TopN: 10, CMCMinCoinFraction: cfg.CMCMinCoinFraction}, "market_cap"); err != nil {
+		t.Fatalf("9 of 10 should pass at the default 0.9: %v", err)
+	}
+	_, err = checkListing(coins[:8], Config{TopN: 10, CMCMinCoinFraction: cfg.CMCMinCoinFraction}, "market_cap")
+	if err == nil || !strings.Contains(err.Error(), "returned 8 coins, expected 10") {
+		t.Fatalf("expected truncated listing error, got %v", err)
+	}
+}
+
+func TestCheckListingRejectsImplausibleRanks(t *testing.T) {
+	coins := rankedCoins(1, 2, 40, 41, 42)
+	_, err := checkListing(coins, Config{TopN: 5}, "market_cap")
+	if err == nil || !strings.Contains(err.Error(), "only 2 distinct ranks within 1..5") {
+		t.Fatalf("expected implausible rank error, got %v", err)
+	}
+	if _, err := checkListing(coins, Config{TopN: 5}, "volume_24h"); err != nil {
+		t.Fatalf("ranks are not checked for other sorts: %v", err)
+	}
+}
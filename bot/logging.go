@@ -0,0 +1,141 @@
+package bot
+
+import (
+	"context"
+	"fmt"
+	"io"
+	"log/slog"
+	"os"
+	"strings"
+	"sync/atomic"
+	"time"
+)
+
+// Log formats accepted by LOG_FORMAT.
+const (
+	LogFormatText = "text"
+	LogFormatJSON = "json"
+)
+
+// currentRunID is attached as run_id to every record logged while a run is
+// in progress, so the lines of one tick can be grouped in aggregated logs.
+var currentRunID atomic.Pointer[string]
+
+// SetupLogging installs a slog handler on stderr as the default logger:
+// LOG_FORMAT "text" (or empty) uses key=value lines, "json" one JSON object
+// per line for cloud log collectors. The standard log package is routed
+// through the same handler, so existing log.Printf calls pick up the format,
+// the target and the run id.
+func SetupLogging(format string) error {
+	var h slog.Handler
+	switch strings.ToLower(strings.TrimSpace(format)) {
+	case "", LogFormatText:
+		h = slog.NewTextHandler(os.Stderr, nil)
+	case LogFormatJSON:
+		h = newJSONHandler(os.Stderr)
+	default:
+		return fmt.Errorf("unsupported LOG_FORMAT %q (want text or json)", format)
+	}
+	slog.SetDefault(slog.New(runHandler{h}))
+	return nil
+}
+
+// newJSONHandler writes one object per line with timestamp, level, message
+// and target first, the names CloudWatch and Datadog pick up without a
+// parsing rule.
+func newJSONHandler(w io.Writer) slog.Handler {
+	return slog.NewJSONHandler(w, &slog.HandlerOptions{
+		ReplaceAttr: func(groups []string, a slog.Attr) slog.Attr {
+			if len(groups) > 0 {
+				return a
+			}
+			switch a.Key {
+			case slog.TimeKey:
+				a.Key = "timestamp"
+			case slog.MessageKey:
+				a.Key = "message"
+			}
+			return a
+		},
+	})
+}
+
+// runHandler adds target and run_id to records and logs lines that read
+// "warning:" at warn level.
+type runHandler struct {
+	slog.Handler
+}
+
+func (h runHandler) Handle(ctx context.Context, r slog.Record) error {
+	if r.Level == slog.LevelInfo && strings.Contains(r.Message, "warning:") {
+		warn := slog.NewRecord(r.Time, slog.LevelWarn, r.Message, r.PC)
+		warn.AddAttrs(recordAttrs(r)...)
+		r = warn
+	}
+	r.AddAttrs(slog.String("target", logTarget(r.Message)))
+	if id := currentRunID.Load(); id != nil {
+		r.AddAttrs(slog.String("run_id", *id))
+	}
+	return h.Handler.Handle(ctx, r)
+}
+
+func (h runHandler) WithAttrs(attrs []slog.Attr) slog.Handler {
+	return runHandler{h.Handler.WithAttrs(attrs)}
+}
+
+func (h runHandler) WithGroup(name string) slog.Handler {
+	return runHandler{h.Handler.WithGroup(name)}
+}
+
+// logTarget is the component a message comes from: the "[RunOnce]" style
+// prefix of log.Printf lines, or "bot" for the rest.
+func logTarget(msg string) string {
+	if strings.HasPrefix(msg, "[") {
+		if end := strings.IndexByte(msg, ']'); end > 1 {
+			return msg[1:end]
+		}
+	}
+	return "bot"
+}
+
+func recordAttrs(r slog.Record) []slog.Attr {
+	attrs := make([]slog.Attr, 0, r.NumAttrs())
+	r.Attrs(func(a slog.Attr) bool {
+		attrs = append(attrs, a)
+		return true
+	})
+	return attrs
+}
+
+// beginRun gives the records of the run that follows a fresh run_id. The
+// returned func clears it again.
+func beginRun() func() {
+	id := newRunID()
+	currentRunID.Store(&id)
+	return func() { currentRunID.CompareAndSwap(&id, nil) }
+}
+
+// span logs the start and the end of one step with its fields, and the
+// elapsed time on completion.
+type span struct {
+	name  string
+	start time.Time
+	attrs []any
+}
+
+func startSpan(name string, attrs ...any) *span {
+	slog.Info(name+" started", attrs...)
+	return &span{name: name, start: time.Now(), attrs: attrs}
+}
+
+// end logs the completion of s with extra fields, or the failure when err is
+// not nil.
+func (s *span) end(err error, extra ...any) {
+	attrs := append(append([]any{}, s.attrs...), extra...)
+	attrs = append(attrs, "elapsed_ms", time.Since(s.start).Milliseconds())
+	if err != nil {
+		slog.Error(s.name+" failed", append(attrs, "error", err.Error())...)
+		return
+	}
+	slog.Info(s.name+" finished", attrs...)
+}
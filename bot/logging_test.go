@@ -0,0 +1,61 @@
+package bot
+
+import (
+	"bytes"
+	"encoding/json"
+	"log/slog"
+	"testing"
+)
+
+func TestRunHandlerTagsRecordsWithRunID(t *testing.T) {
+	var buf bytes.Buffer
+	logger := slog.New(runHandler{slog.NewJSONHandler(&buf, nil)})
+
+	end := beginRun()
+	logger.Info("[RunOnce] warning: something odd", "top_n", 100)
+	end()
+	logger.Info("[RunOnce] after the run")
+
+	lines := bytes.Split(bytes.TrimSpace(buf.Bytes()), []byte("\n"))
+	if len(lines) != 2 {
+		t.Fatalf("expected 2 lines, got %q", buf.String())
+	}
+	var first, second map[string]any
+	if err := json.Unmarshal(lines[0], &first); err != nil {
+		t.Fatalf("invalid JSON line %q: %v", lines[0], err)
+	}
+	if err := json.Unmarshal(lines[1], &second); err != nil {
+		t.Fatalf("invalid JSON line %q: %v", lines[1], err)
+	}
+	if first["run_id"] == nil || first["level"] != "WARN" || first["top_n"] != float64(100) {
+		t.Fatalf("unexpected first record: %v", first)
+	}
+	if _, ok := second["run_id"]; ok || second["level"] != "INFO" {
+		t.Fatalf("record after the run should have no run_id: %v", second)
+	}
+}
+
+func TestSetupLoggingRejectsUnknownFormat(t *testing.T) {
+	if err := SetupLogging("xml"); err == nil {
+		t.Fatal("expected an error for LOG_FORMAT=xml")
+	}
+}
+
+func TestJSONLogLineShape(t *testing.T) {
+	var buf bytes.Buffer
+	logger := slog.New(runHandler{newJSONHandler(&buf)})
+	logger.Info("[Digest] start: top_n=100")
+
+	var line map[string]any
+	if err := json.Unmarshal(bytes.TrimSpace(buf.Bytes()), &line); err != nil {
+		t.Fatalf("invalid JSON line %q: %v", buf.String(), err)
+	}
+	for _, key := range []string{"timestamp", "level", "target", "message"} {
+		if _, ok := line[key]; !ok {
+			t.Fatalf("missing %q in %v", key, line)
+		}
+	}
+	if line["level"] != "INFO" || line["target"] != "Digest" || line["message"] != "[Digest] start: top_n=100" {
+		t.Fatalf("unexpected record: %v", line)
+	}
+}
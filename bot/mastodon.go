@@ -0,0 +1,84 @@
+package bot
+
+import (
+	"context"
+	"encoding/json"
+	"errors"
+	"fmt"
+	"io"
+	"log"
+	"net/http"
+	"strings"
+)
+
+// mastodonStatusLimit is the default status length of a Mastodon instance.
+const mastodonStatusLimit = 500
+
+// errMastodonDuplicate is a 422 reply, which Mastodon sends for a status it
+// considers a duplicate of a recent one.
+var errMastodonDuplicate = errors.New("mastodon rejected the status as a duplicate")
+
+// notifyMastodon cross-posts text to MASTODON_INSTANCE_URL when requested and
+// returns the new status id, or "" when nothing was posted. Like the other
+// cross-posts it never fails the run.
+func notifyMastodon(ctx context.Context, client *http.Client, cfg Config, opt RunOptions, text string) string {
+	if !opt.NotifyMastodon {
+		return ""
+	}
+	if cfg.MastodonInstanceURL == "" || cfg.MastodonAccessToken == "" {
+		log.Printf("[Mastodon] warning: --notify-mastodon set but MASTODON_INSTANCE_URL or MASTODON_ACCESS_TOKEN is empty; skipping")
+		return ""
+	}
+	id, err := postToMastodon(ctx, client, cfg.MastodonInstanceURL, cfg.MastodonAccessToken, text)
+	if errors.Is(err, errMastodonDuplicate) {
+		log.Printf("[Mastodon] warning: %v; skipping", err)
+		return ""
+	}
+	if err != nil {
+		log.Printf("[Mastodon] warning: failed to post status: %v", err)
+		return ""
+	}
+	log.Printf("[Mastodon] status posted successfully: id=%s", id)
+	return id
+}
+
+// postToMastodon publishes text, cut to mastodonStatusLimit characters, as a
+// public status and returns its id.
+func postToMastodon(ctx context.Context, client *http.Client, instanceURL, token, text string) (string, error) {
+	body, _ := json.Marshal(map[string]any{"status": mastodonStatus(text), "visibility": "public"})
+	req, err := http.NewRequestWithContext(ctx, http.MethodPost, instanceURL+"/api/v1/statuses", strings.NewReader(string(body)))
+	if err != nil {
+		return "", err
+	}
+	req.Header.Set("Content-Type", "application/json")
+	req.Header.Set("Authorization", "Bearer "+token)
+	resp, err := client.Do(req)
+	if err != nil {
+		return "", upstreamError("mastodon", err)
+	}
+	defer resp.Body.Close()
+	b, _ := io.ReadAll(resp.Body)
+	if resp.StatusCode == http.StatusUnprocessableEntity {
+		return "", fmt.Errorf("%w: %s", errMastodonDuplicate, string(b))
+	}
+	if resp.StatusCode >= 300 {
+		return "", fmt.Errorf("mastodon error: %s %s", resp.Status, string(b))
+	}
+	var parsed struct {
+		ID string `json:"id"`
+	}
+	if err := json.Unmarshal(b, &parsed); err != nil {
+		return "", fmt.Errorf("decode mastodon status: %w", err)
+	}
+	return parsed.ID, nil
+}
+
+// mastodonStatus writes links as plain text, trims it and cuts it to
+// mastodonStatusLimit characters with "…".
+func mastodonStatus(text string) string {
+	out := strings.TrimSpace(plainTextLinks(text))
+	if r := []rune(out); len(r) > mastodonStatusLimit {
+		out = string(r[:mastodonStatusLimit-1]) + "…"
+	}
+	return out
+}
@@ -0,0 +1,49 @@
+package bot
+
+import (
+	"context"
+	"encoding/json"
+	"net/http"
+	"net/http/httptest"
+	"strings"
+	"testing"
+	"unicode/utf8"
+)
+
+func TestPostToMastodonSendsTruncatedPublicStatus(t *testing.T) {
+	srv := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		if r.URL.Path != "/api/v1/statuses" || r.Header.Get("Authorization") != "Bearer secret" {
+			t.Errorf("unexpected request %s with auth %q", r.URL.Path, r.Header.Get("Authorization"))
+		}
+		var body struct {
+			Status     string `json:"status"`
+			Visibility string `json:"visibility"`
+		}
+		if err := json.NewDecoder(r.Body).Decode(&body); err != nil {
+			t.Errorf("decode body: %v", err)
+		}
+		if utf8.RuneCountInString(body.Status) != mastodonStatusLimit || body.Visibility != "public" {
+			t.Errorf("unexpected status of %d characters with visibility %q", utf8.RuneCountInString(body.Status), body.Visibility)
+		}
+		_, _ = w.Write([]byte(`{"id":"1103"}`))
+	}))
+	defer srv.Close()
+
+	id, err := postToMastodon(context.Background(), srv.Client(), srv.URL, "secret", strings.Repeat("🚀", mastodonStatusLimit+20))
+	if err != nil || id != "1103" {
+		t.Fatalf("postToMastodon = %q, %v", id, err)
+	}
+}
+
+func TestNotifyMastodonOnlyWarnsOnDuplicate(t *testing.T) {
+	srv := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		w.WriteHeader(http.StatusUnprocessableEntity)
+		_, _ = w.Write([]byte(`{"error":"Validation failed: Text has already been posted"}`))
+	}))
+	defer srv.Close()
+
+	cfg := Config{MastodonInstanceURL: srv.URL, MastodonAccessToken: "secret"}
+	if id := notifyMastodon(context.Background(), srv.Client(), cfg, RunOptions{NotifyMastodon: true}, "hello"); id != "" {
+		t.Fatalf("a duplicate status should leave no id, got %q", id)
+	}
+}
@@ -0,0 +1,565 @@
+package bot
+
+import (
+	"context"
+	"encoding/json"
+	"errors"
+	"fmt"
+	"log"
+	"strconv"
+	"time"
+
+	"go.mongodb.org/mongo-driver/bson"
+	"go.mongodb.org/mongo-driver/bson/primitive"
+	"go.mongodb.org/mongo-driver/mongo"
+	"go.mongodb.org/mongo-driver/mongo/options"
+)
+
+// MongoStore keeps the state document and its coins in separate collections
+// and appends published posts to the history collection.
+type MongoStore struct {
+	client    *mongo.Client
+	state     *mongo.Collection
+	coins     *mongo.Collection
+	history   *mongo.Collection
+	ath       *mongo.Collection
+	raw       *mongo.Collection
+	snapshots *mongo.Collection
+	locks     *mongo.Collection
+	metrics   *mongo.Collection
+}
+
+var (
+	_ StateStore = (*MongoStore)(nil)
+	_ RunLocker  = (*MongoStore)(nil)
+)
+
+func OpenMongoStore(ctx context.Context, cfg Config) (*MongoStore, error) {
+	db, client, err := connectDB(ctx, cfg)
+	if err != nil {
+		return nil, err
+	}
+	log.Printf("[MongoStore] connected to database=%s collections: state=%s coins=%s history=%s ath=%s", cfg.MongoDBDatabase, cfg.MongoDBStateCollection, cfg.MongoDBCoinsCollection, cfg.MongoDBHistoryCollection, cfg.MongoDBATHCollection)
+	return &MongoStore{
+		client:    client,
+		state:     db.Collection(cfg.MongoDBStateCollection),
+		coins:     db.Collection(cfg.MongoDBCoinsCollection),
+		history:   db.Collection(cfg.MongoDBHistoryCollection),
+		ath:       db.Collection(cfg.MongoDBATHCollection),
+		raw:       db.Collection(cfg.MongoDBRawCollection),
+		snapshots: db.Collection(cfg.MongoDBSnapshotCollection),
+		locks:     db.Collection(cfg.MongoDBLocksCollection),
+		metrics:   db.Collection(cfg.MongoDBMetricsCollection),
+	}, nil
+}
+
+func (s *MongoStore) Close(ctx context.Context) error { return s.client.Disconnect(ctx) }
+
+func (s *MongoStore) LoadState(ctx context.Context) (State, error) {
+	var prev stateDoc
+	err := s.state.FindOne(ctx, bson.M{"_id": "top"}).Decode(&prev)
+	if errors.Is(err, mongo.ErrNoDocuments) {
+		return State{}, ErrNoState
+	}
+	if err != nil {
+		return State{}, err
+	}
+	coins, err := loadStateCoins(ctx, s.coins, "top")
+	if err != nil {
+		return State{}, err
+	}
+	tenure := make(map[int64]int64, len(prev.Tenure))
+	for k, n := range prev.Tenure {
+		if id, err := strconv.ParseInt(k, 10, 64); err == nil {
+			tenure[id] = n
+		}
+	}
+	leftAt := make(map[int64]time.Time, len(prev.TenureLeftAt))
+	for k, at := range prev.TenureLeftAt {
+		if id, err := strconv.ParseInt(k, 10, 64); err == nil {
+			leftAt[id] = at
+		}
+	}
+	return State{UpdatedAt: prev.UpdatedAt, TopN: prev.TopN, Convert: prev.Convert, Sort: prev.Sort, Provider: prev.Provider, CreditsUsed: prev.CreditsUsed, IDs: prev.IDs, Coins: coins, Tenure: tenure, TenureLeftAt: leftAt, WatchlistIDs: prev.WatchlistIDs, LastRunAt: prev.LastRunAt}, nil
+}
+
+func (s *MongoStore) WriteState(ctx context.Context, st State) error {
+	return writeState(ctx, s.state, s.coins, st)
+}
+
+func (s *MongoStore) TouchState(ctx context.Context, at time.Time) error {
+	_, err := s.state.UpdateOne(ctx, bson.M{"_id": "top"}, bson.M{"$set": bson.M{"last_run_at": at}})
+	return err
+}
+
+func (s *MongoStore) InsertHistory(ctx context.Context, doc HistoryDoc) error {
+	_, err := s.history.InsertOne(ctx, doc)
+	return err
+}
+
+func (s *MongoStore) LoadRecentPosts(ctx context.Context, limit int) ([]RecentPost, error) {
+	return loadRecentPosts(ctx, s.history, limit)
+}
+
+func (s *MongoStore) LatestHistory(ctx context.Context) (HistoryDoc, error) {
+	var last HistoryDoc
+	err := s.history.FindOne(ctx, notDigest(bson.M{}), options.FindOne().SetSort(bson.M{"created_at": -1})).Decode(&last)
+	if errors.Is(err, mongo.ErrNoDocuments) {
+		return HistoryDoc{}, ErrNoHistory
+	}
+	return last, err
+}
+
+func (s *MongoStore) ListHistory(ctx context.Context, limit int) ([]HistoryDoc, error) {
+	cur, err := s.history.Find(ctx, bson.M{}, options.Find().SetSort(bson.M{"created_at": -1}).SetLimit(int64(limit)))
+	if err != nil {
+		return nil, err
+	}
+	defer cur.Close(ctx)
+	out := []HistoryDoc{}
+	for cur.Next(ctx) {
+		var d historyDocWithID
+		if err := cur.Decode(&d); err != nil {
+			return nil, err
+		}
+		d.HistoryDoc.ID = d.ObjectID.Hex()
+		out = append(out, d.HistoryDoc)
+	}
+	return out, cur.Err()
+}
+
+// notDigest adds a kind condition to filter that leaves out digest posts:
+// their mentions are weekly movers, not entrants, and they don't count as
+// the latest post.
+func notDigest(filter bson.M) bson.M {
+	filter["kind"] = bson.M{"$ne": HistoryKindDigest}
+	return filter
+}
+
+// historyDocWithID decodes the generated _id next to the history fields.
+type historyDocWithID struct {
+	ObjectID   primitive.ObjectID `bson:"_id"`
+	HistoryDoc `bson:",inline"`
+}
+
+func (s *MongoStore) InsertRunMetrics(ctx context.Context, doc RunMetricsDoc) error {
+	_, err := s.metrics.InsertOne(ctx, doc)
+	return err
+}
+
+func (s *MongoStore) InsertRawListing(ctx context.Context, raw RawListing) error {
+	doc, err := rawListingDoc(raw)
+	if err != nil {
+		return err
+	}
+	_, err = s.raw.InsertOne(ctx, doc)
+	return err
+}
+
+// rawListingDoc converts the JSON status and data into BSON so the stored
+// document can be queried like the original response.
+func rawListingDoc(raw RawListing) (bson.D, error) {
+	var body bson.D
+	envelope, _ := json.Marshal(map[string]json.RawMessage{"status": orNull(raw.Status), "data": orNull(raw.Data)})
+	if err := bson.UnmarshalExtJSON(envelope, false, &body); err != nil {
+		return nil, fmt.Errorf("convert raw listing: %w", err)
+	}
+	return append(bson.D{{Key: "fetched_at", Value: raw.FetchedAt}, {Key: "start", Value: raw.Start}, {Key: "limit", Value: raw.Limit}}, body...), nil
+}
+
+func (s *MongoStore) InsertSnapshot(ctx context.Context, snap Snapshot) error {
+	_, err := s.snapshots.InsertOne(ctx, snap)
+	return err
+}
+
+func (s *MongoStore) DeleteSnapshotsBefore(ctx context.Context, cutoff time.Time) (int64, error) {
+	res, err := s.snapshots.DeleteMany(ctx, bson.M{"created_at": bson.M{"$lt": cutoff}})
+	if err != nil {
+		return 0, err
+	}
+	return res.DeletedCount, nil
+}
+
+func (s *MongoStore) LoadSnapshotAt(ctx context.Context, ts time.Time) (Snapshot, error) {
+	var snap Snapshot
+	err := s.snapshots.FindOne(ctx, bson.M{"created_at": bson.M{"$lte": ts}}, options.FindOne().SetSort(bson.M{"created_at": -1})).Decode(&snap)
+	if errors.Is(err, mongo.ErrNoDocuments) {
+		return Snapshot{}, ErrNoSnapshot
+	}
+	return snap, err
+}
+
+func (s *MongoStore) LoadOldestSnapshot(ctx context.Context) (Snapshot, error) {
+	var snap Snapshot
+	err := s.snapshots.FindOne(ctx, bson.M{}, options.FindOne().SetSort(bson.M{"created_at": 1})).Decode(&snap)
+	if errors.Is(err, mongo.ErrNoDocuments) {
+		return Snapshot{}, ErrNoSnapshot
+	}
+	return snap, err
+}
+
+func (s *MongoStore) LastMentioned(ctx context.Context, ids []int64) (map[int64]time.Time, error) {
+	out := map[int64]time.Time{}
+	if len(ids) == 0 {
+		return out, nil
+	}
+	// The first $match can use the mentioned_coins.id index; the second drops
+	// the other coins of each post after $unwind.
+	match := bson.D{{Key: "$match", Value: notDigest(bson.M{"mentioned_coins.id": bson.M{"$in": ids}})}}
+	cur, err := s.history.Aggregate(ctx, mongo.Pipeline{
+		match,
+		{{Key: "$unwind", Value: "$mentioned_coins"}},
+		match,
+		{{Key: "$group", Value: bson.M{"_id": "$mentioned_coins.id", "last_seen_at": bson.M{"$max": "$created_at"}}}},
+	})
+	if err != nil {
+		return nil, err
+	}
+	defer cur.Close(ctx)
+	for cur.Next(ctx) {
+		var row struct {
+			ID         int64     `bson:"_id"`
+			LastSeenAt time.Time `bson:"last_seen_at"`
+		}
+		if err := cur.Decode(&row); err != nil {
+			return nil, err
+		}
+		out[row.ID] = row.LastSeenAt.UTC()
+	}
+	return out, cur.Err()
+}
+
+func (s *MongoStore) MentionedSince(ctx context.Context, since time.Time) (map[int64]struct{}, error) {
+	ids, err := s.history.Distinct(ctx, "mentioned_coins.id", notDigest(bson.M{"created_at": bson.M{"$gte": since}}))
+	if err != nil {
+		return nil, err
+	}
+	out := make(map[int64]struct{}, len(ids))
+	for _, v := range ids {
+		switch id := v.(type) {
+		case int64:
+			out[id] = struct{}{}
+		case int32:
+			out[int64(id)] = struct{}{}
+		}
+	}
+	return out, nil
+}
+
+func (s *MongoStore) AnnouncedSince(ctx context.Context, since time.Time) (map[int64]struct{}, error) {
+	opts := options.Find().SetProjection(bson.M{"new_coin_ids": 1, "exited_coin_ids": 1, "forced": 1})
+	cur, err := s.history.Find(ctx, bson.M{"created_at": bson.M{"$gte": since}}, opts)
+	if err != nil {
+		return nil, err
+	}
+	defer cur.Close(ctx)
+	out := map[int64]struct{}{}
+	for cur.Next(ctx) {
+		var doc HistoryDoc
+		if err := cur.Decode(&doc); err != nil {
+			return nil, err
+		}
+		for _, id := range doc.ExitedCoinIDs {
+			out[id] = struct{}{}
+		}
+		if doc.Forced {
+			continue
+		}
+		for _, id := range doc.NewCoinIDs {
+			out[id] = struct{}{}
+		}
+	}
+	return out, cur.Err()
+}
+
+func (s *MongoStore) LastEntered(ctx context.Context, ids []int64) (map[int64]time.Time, error) {
+	out := map[int64]time.Time{}
+	if len(ids) == 0 {
+		return out, nil
+	}
+	cur, err := s.history.Aggregate(ctx, mongo.Pipeline{
+		{{Key: "$match", Value: bson.M{"new_coin_ids": bson.M{"$in": ids}, "forced": bson.M{"$ne": true}}}},
+		{{Key: "$unwind", Value: "$new_coin_ids"}},
+		{{Key: "$match", Value: bson.M{"new_coin_ids": bson.M{"$in": ids}}}},
+		{{Key: "$group", Value: bson.M{"_id": "$new_coin_ids", "entered_at": bson.M{"$max": "$created_at"}}}},
+	})
+	if err != nil {
+		return nil, err
+	}
+	defer cur.Close(ctx)
+	for cur.Next(ctx) {
+		var row struct {
+			ID        int64     `bson:"_id"`
+			EnteredAt time.Time `bson:"entered_at"`
+		}
+		if err := cur.Decode(&row); err != nil {
+			return nil, err
+		}
+		out[row.ID] = row.EnteredAt.UTC()
+	}
+	return out, cur.Err()
+}
+
+// AcquireRunLock takes the "run" lock document unless another owner holds
+// an unexpired lease. The document is created once, already expired, so the
+// lease itself is a single findOneAndUpdate without upsert: two concurrent
+// runs can never both match the expired filter.
+func (s *MongoStore) AcquireRunLock(ctx context.Context, owner string, ttl time.Duration) (bool, error) {
+	_, err := s.locks.UpdateOne(ctx, bson.M{"_id": runLockID}, bson.M{"$setOnInsert": bson.M{"expires_at": time.Unix(0, 0).UTC()}}, options.Update().SetUpsert(true))
+	if err != nil && !mongo.IsDuplicateKeyError(err) {
+		return false, err
+	}
+	now := time.Now().UTC()
+	err = s.locks.FindOneAndUpdate(ctx,
+		bson.M{"_id": runLockID, "expires_at": bson.M{"$lte": now}},
+		bson.M{"$set": bson.M{"owner": owner, "acquired_at": now, "expires_at": now.Add(ttl)}},
+	).Err()
+	if errors.Is(err, mongo.ErrNoDocuments) {
+		return false, nil
+	}
+	return err == nil, err
+}
+
+// ReleaseRunLock expires the lease if owner still holds it.
+func (s *MongoStore) ReleaseRunLock(ctx context.Context, owner string) error {
+	_, err := s.locks.UpdateOne(ctx, bson.M{"_id": runLockID, "owner": owner}, bson.M{"$set": bson.M{"expires_at": time.Now().UTC()}})
+	return err
+}
+
+func (s *MongoStore) LoadATHs(ctx context.Context, ids []int64) (map[int64]ATHRecord, error) {
+	cur, err := s.ath.Find(ctx, bson.M{"_id": bson.M{"$in": ids}})
+	if err != nil {
+		return nil, err
+	}
+	defer cur.Close(ctx)
+	out := map[int64]ATHRecord{}
+	for cur.Next(ctx) {
+		var d athDoc
+		if err := cur.Decode(&d); err != nil {
+			return nil, err
+		}
+		out[d.ID] = ATHRecord{ID: d.ID, MaxMarketCap: d.MaxMarketCap, AchievedAt: d.AchievedAt.UTC()}
+	}
+	return out, cur.Err()
+}
+
+func (s *MongoStore) UpsertATHs(ctx context.Context, records []ATHRecord) error {
+	for _, r := range records {
+		_, err := s.ath.ReplaceOne(ctx, bson.M{"_id": r.ID}, athDoc{ID: r.ID, MaxMarketCap: r.MaxMarketCap, AchievedAt: r.AchievedAt}, options.Replace().SetUpsert(true))
+		if err != nil {
+			return err
+		}
+	}
+	return nil
+}
+
+// athDoc is keyed by CMC coin id.
+type athDoc struct {
+	ID           int64     `bson:"_id"`
+	MaxMarketCap float64   `bson:"max_market_cap"`
+	AchievedAt   time.Time `bson:"achieved_at"`
+}
+
+type stateDoc struct {
+	ID          string    `bson:"_id"`
+	UpdatedAt   time.Time `bson:"updated_at"`
+	TopN        int64     `bson:"top_n"`
+	Convert     string    `bson:"convert"`
+	Sort        string    `bson:"sort,omitempty"`
+	Provider    string    `bson:"provider,omitempty"`
+	CreditsUsed int64     `bson:"credits_used"`
+	IDs         []int64   `bson:"ids"`
+	// Tenure is keyed by the decimal coin id; BSON keys must be strings.
+	Tenure       map[string]int64     `bson:"tenure,omitempty"`
+	TenureLeftAt map[string]time.Time `bson:"tenure_left_at,omitempty"`
+	WatchlistIDs []int64              `bson:"watchlist_ids,omitempty"`
+	LastRunAt    time.Time            `bson:"last_run_at,omitempty"`
+}
+
+type stateCoinDoc struct {
+	StateID       string    `bson:"state_id"`
+	ID            int64     `bson:"id"`
+	Name          string    `bson:"name"`
+	Symbol        string    `bson:"symbol"`
+	Rank          int64     `bson:"rank"`
+	TickTimestamp time.Time `bson:"tick_timestamp"`
+	Updated       time.Time `bson:"updated_at"`
+	Created       time.Time `bson:"created_at,omitempty"`
+	IsActive      bool      `bson:"is_active"`
+
+	MarketCap         *float64 `bson:"market_cap,omitempty"`
+	MarketCapCurrency string   `bson:"market_cap_currency"`
+	ImageURL          string   `bson:"image_url,omitempty"`
+
+	// Older coin documents predate these fields; they decode as nil.
+	Price              *float64   `bson:"price,omitempty"`
+	PercentChange24h   *float64   `bson:"percent_change_24h,omitempty"`
+	PercentChange7d    *float64   `bson:"percent_change_7d,omitempty"`
+	Volume24h          *float64   `bson:"volume_24h,omitempty"`
+	DateAdded          *time.Time `bson:"date_added,omitempty"`
+	MarketCapDominance *float64   `bson:"market_cap_dominance,omitempty"`
+
+	Quotes map[string]CoinQuote `bson:"quotes,omitempty"`
+}
+
+func connectDB(ctx context.Context, cfg Config) (*mongo.Database, *mongo.Client, error) {
+	client, err := mongo.Connect(ctx, options.Client().ApplyURI(cfg.MongoDBConnectionString).SetAppName("coinmarketcap_top100_bot"))
+	if err != nil {
+		return nil, nil, err
+	}
+	db := client.Database(cfg.MongoDBDatabase)
+	if !cfg.MongoDBSkipIndexCreation {
+		ensureIndexes(ctx, db, cfg)
+	}
+	return db, client, nil
+}
+
+// historyIndexes back loadRecentPosts (newest first) and lookups of the
+// coins a post mentioned.
+var historyIndexes = []mongo.IndexModel{
+	{Keys: bson.D{{Key: "created_at", Value: -1}}},
+	{Keys: bson.D{{Key: "mentioned_coins.id", Value: 1}}, Options: options.Index().SetSparse(true)},
+}
+
+// ensureIndexes creates the history indexes if they are missing. Creating an
+// existing index is a no-op, so this runs on every connect. A replica set
+// waits for a majority to build them; a standalone server takes no commit
+// quorum. Failures are only logged: the bot works without indexes, just slower.
+func ensureIndexes(ctx context.Context, db *mongo.Database, cfg Config) {
+	opts := options.CreateIndexes()
+	var hello struct {
+		SetName string `bson:"setName"`
+	}
+	if err := db.RunCommand(ctx, bson.D{{Key: "hello", Value: 1}}).Decode(&hello); err == nil && hello.SetName != "" {
+		opts.SetCommitQuorumMajority()
+	}
+	if _, err := db.Collection(cfg.MongoDBHistoryCollection).Indexes().CreateMany(ctx, historyIndexes, opts); err != nil {
+		log.Printf("[MongoStore] warning: unable to create history indexes: %v", err)
+		return
+	}
+	log.Printf("[MongoStore] indexes on %s (created_at desc, mentioned_coins.id sparse) exist", cfg.MongoDBHistoryCollection)
+}
+
+func loadRecentPosts(ctx context.Context, historyCollection *mongo.Collection, limit int) ([]RecentPost, error) {
+	cur, err := historyCollection.Find(ctx, bson.M{}, options.Find().SetSort(bson.M{"created_at": -1}).SetLimit(int64(limit)))
+	if err != nil {
+		return nil, err
+	}
+	defer cur.Close(ctx)
+	out := []RecentPost{}
+	for cur.Next(ctx) {
+		var d HistoryDoc
+		if err := cur.Decode(&d); err != nil {
+			return nil, err
+		}
+		out = append(out, recentPostFromHistory(d))
+	}
+	return out, cur.Err()
+}
+
+func writeState(ctx context.Context, stateCollection *mongo.Collection, coinsCollection *mongo.Collection, st State) error {
+	if err := replaceStateCoins(ctx, coinsCollection, "top", st.Coins); err != nil {
+		return err
+	}
+
+	tenure := make(map[string]int64, len(st.Tenure))
+	for id, n := range st.Tenure {
+		tenure[strconv.FormatInt(id, 10)] = n
+	}
+	leftAt := make(map[string]time.Time, len(st.TenureLeftAt))
+	for id, at := range st.TenureLeftAt {
+		leftAt[strconv.FormatInt(id, 10)] = at
+	}
+	now := time.Now().UTC()
+	_, err := stateCollection.ReplaceOne(ctx, bson.M{"_id": "top"}, stateDoc{ID: "top", UpdatedAt: now, LastRunAt: now, TopN: st.TopN, Convert: st.Convert, Sort: st.Sort, Provider: st.Provider, CreditsUsed: st.CreditsUsed, IDs: coinIDs(st.Coins), Tenure: tenure, TenureLeftAt: leftAt, WatchlistIDs: st.WatchlistIDs}, options.Replace().SetUpsert(true))
+	return err
+}
+
+func replaceStateCoins(ctx context.Context, coinsCollection *mongo.Collection, stateID string, coins []Coin) error {
+	now := time.Now().UTC()
+	if _, err := coinsCollection.UpdateMany(ctx, bson.M{"state_id": stateID}, bson.M{"$set": bson.M{"is_active": false, "updated_at": now}}); err != nil {
+		return err
+	}
+	docs := buildStateCoinDocs(stateID, coins, now)
+	if len(docs) == 0 {
+		return nil
+	}
+	for _, d := range docs {
+		_, err := coinsCollection.UpdateOne(
+			ctx,
+			bson.M{"state_id": stateID, "id": d.ID},
+			bson.M{
+				"$set": bson.M{
+					"name":                 d.Name,
+					"symbol":               d.Symbol,
+					"rank":                 d.Rank,
+					"tick_timestamp":       d.TickTimestamp,
+					"market_cap":           d.MarketCap,
+					"market_cap_currency":  d.MarketCapCurrency,
+					"image_url":            d.ImageURL,
+					"price":                d.Price,
+					"percent_change_24h":   d.PercentChange24h,
+					"percent_change_7d":    d.PercentChange7d,
+					"volume_24h":           d.Volume24h,
+					"quotes":               d.Quotes,
+					"date_added":           d.DateAdded,
+					"market_cap_dominance": d.MarketCapDominance,
+					"is_active":            true,
+					"updated_at":           now,
+				},
+				"$setOnInsert": bson.M{"created_at": now},
+			},
+			options.Update().SetUpsert(true),
+		)
+		if err != nil {
+			return err
+		}
+	}
+	return nil
+}
+
+func buildStateCoinDocs(stateID string, coins []Coin, now time.Time) []stateCoinDoc {
+	out := make([]stateCoinDoc, 0, len(coins))
+	for _, coin := range coins {
+		out = append(out, stateCoinDoc{
+			StateID:            stateID,
+			ID:                 coin.ID,
+			Name:               coin.Name,
+			Symbol:             coin.Symbol,
+			Rank:               coin.Rank,
+			TickTimestamp:      now,
+			MarketCap:          coin.MarketCap,
+			MarketCapCurrency:  coin.MarketCapCurrency,
+			ImageURL:           coin.ImageURL,
+			Price:              coin.Price,
+			PercentChange24h:   coin.PercentChange24h,
+			PercentChange7d:    coin.PercentChange7d,
+			Volume24h:          coin.Volume24h,
+			Quotes:             coin.Quotes,
+			DateAdded:          coin.DateAdded,
+			MarketCapDominance: coin.MarketCapDominance,
+			IsActive:           true,
+			Updated:            now,
+		})
+	}
+	return out
+}
+
+func loadStateCoins(ctx context.Context, coinsCollection *mongo.Collection, stateID string) ([]Coin, error) {
+	cur, err := coinsCollection.Find(ctx, bson.M{"state_id": stateID, "is_active": true}, options.Find().SetSort(bson.M{"rank": 1}))
+	if err != nil {
+		return nil, err
+	}
+	defer cur.Close(ctx)
+
+	out := []Coin{}
+	for cur.Next(ctx) {
+		var doc stateCoinDoc
+		if err := cur.Decode(&doc); err != nil {
+			return nil, err
+		}
+		tickTS := doc.TickTimestamp.UTC()
+		out = append(out, Coin{ID: doc.ID, Name: doc.Name, Symbol: doc.Symbol, Rank: doc.Rank, TickTimestamp: &tickTS, MarketCap: doc.MarketCap, MarketCapCurrency: doc.MarketCapCurrency, ImageURL: doc.ImageURL, Price: doc.Price, PercentChange24h: doc.PercentChange24h, PercentChange7d: doc.PercentChange7d, Volume24h: doc.Volume24h, Quotes: doc.Quotes, DateAdded: doc.DateAdded, MarketCapDominance: doc.MarketCapDominance})
+	}
+	return out, cur.Err()
+}
@@ -0,0 +1,99 @@
+package bot
+
+import (
+	"fmt"
+	"io"
+	"log"
+	"net/http"
+	"strconv"
+	"sync"
+	"time"
+)
+
+// metricsNamespace prefixes every exported metric name.
+const metricsNamespace = "cmc_top_"
+
+// MetricsCollector accumulates process-wide AI call counters and the gauges
+// of the latest run, and serves them in the Prometheus text format. The zero
+// value is ready to use. It writes the exposition format by hand rather than
+// through client_golang: five plain values do not justify pulling the client,
+// its protobuf and procfs dependencies into the Lambda binary.
+type MetricsCollector struct {
+	mu          sync.Mutex
+	newCoins    int
+	exitedCoins int
+	aiCalls     int64
+	aiFailures  int64
+	runDuration time.Duration
+}
+
+// Metrics is the collector RunOnce reports to.
+var Metrics = &MetricsCollector{}
+
+func (m *MetricsCollector) observeDiff(newCoins, exitedCoins int) {
+	m.mu.Lock()
+	defer m.mu.Unlock()
+	m.newCoins, m.exitedCoins = newCoins, exitedCoins
+}
+
+// observeAICall counts one AI request; failed is true for errors and for
+// answers that were unusable after sanitizing.
+func (m *MetricsCollector) observeAICall(failed bool) {
+	m.mu.Lock()
+	defer m.mu.Unlock()
+	m.aiCalls++
+	if failed {
+		m.aiFailures++
+	}
+}
+
+func (m *MetricsCollector) observeRunDuration(d time.Duration) {
+	m.mu.Lock()
+	defer m.mu.Unlock()
+	m.runDuration = d
+}
+
+// WritePrometheus writes every metric with its HELP and TYPE lines.
+func (m *MetricsCollector) WritePrometheus(w io.Writer) error {
+	m.mu.Lock()
+	metrics := []struct {
+		name, kind, help string
+		value            float64
+	}{
+		{"new_coins", "gauge", "New entrants detected by the latest run.", float64(m.newCoins)},
+		{"exited_coins", "gauge", "Exited coins reported by the latest run.", float64(m.exitedCoins)},
+		{"ai_calls_total", "counter", "AI provider requests made.", float64(m.aiCalls)},
+		{"ai_failures_total", "counter", "AI provider requests that failed or returned an unusable answer.", float64(m.aiFailures)},
+		{"run_duration_seconds", "gauge", "Duration of the latest run in seconds.", m.runDuration.Seconds()},
+	}
+	m.mu.Unlock()
+	for _, metric := range metrics {
+		name := metricsNamespace + metric.name
+		value := strconv.FormatFloat(metric.value, 'g', -1, 64)
+		if _, err := fmt.Fprintf(w, "# HELP %s %s\n# TYPE %s %s\n%s %s\n", name, metric.help, name, metric.kind, name, value); err != nil {
+			return err
+		}
+	}
+	return nil
+}
+
+// ServeHTTP serves the metrics for a GET /metrics route.
+func (m *MetricsCollector) ServeHTTP(w http.ResponseWriter, r *http.Request) {
+	w.Header().Set("Content-Type", "text/plain; version=0.0.4; charset=utf-8")
+	if err := m.WritePrometheus(w); err != nil {
+		log.Printf("[Metrics] warning: failed to write metrics: %v", err)
+	}
+}
+
+// ServeMetrics serves Metrics on GET /metrics at addr in the background. A
+// listener error is only logged.
+func ServeMetrics(addr string) {
+	mux := http.NewServeMux()
+	mux.Handle("/metrics", Metrics)
+	go func() {
+		log.Printf("[Metrics] serving /metrics on %s", addr)
+		if err := http.ListenAndServe(addr, mux); err != nil {
+			log.Printf("[Metrics] warning: metrics server stopped: %v", err)
+		}
+	}()
+}
@@ -0,0 +1,31 @@
+package bot
+
+import (
+	"strings"
+	"testing"
+	"time"
+)
+
+func TestMetricsCollectorWritesPrometheusText(t *testing.T) {
+	m := &MetricsCollector{}
+	m.observeDiff(3, 1)
+	m.observeAICall(false)
+	m.observeAICall(true)
+	m.observeRunDuration(1500 * time.Millisecond)
+
+	var out strings.Builder
+	if err := m.WritePrometheus(&out); err != nil {
+		t.Fatalf("WritePrometheus error: %v", err)
+	}
+	for _, want := range []string{
+		"# TYPE cmc_top_new_coins gauge\ncmc_top_new_coins 3\n",
+		"cmc_top_exited_coins 1\n",
+		"# TYPE cmc_top_ai_calls_total counter\ncmc_top_ai_calls_total 2\n",
+		"cmc_top_ai_failures_total 1\n",
+		"# TYPE cmc_top_run_duration_seconds gauge\ncmc_top_run_duration_seconds 1.5\n",
+	} {
+		if !strings.Contains(out.String(), want) {
+			t.Fatalf("missing %q in:\n%s", want, out.String())
+		}
+	}
+}
@@ -0,0 +1,101 @@
+package bot
+
+import (
+	"context"
+	"log"
+	"time"
+)
+
+// returningEntrants picks the new entrants that were inside the headline
+// before: coins a past post mentioned, or, when the headline is the whole
+// tracked list (headline 0), that an earlier state held (tenure keeps
+// departed coins). Tenure only records tracked membership, so with a
+// NOTIFY_TOP_N below TOP_N a coin merely tracked further down is not
+// returning. Those with a mention get LastSeenAt from the latest one. History
+// is read with a single LastMentioned call; a failure only logs a warning and
+// leaves the tenure check.
+func returningEntrants(ctx context.Context, store StateStore, coins []Coin, tenure map[int64]int64, headline int64) []Coin {
+	out := []Coin{}
+	if len(coins) == 0 {
+		return out
+	}
+	lastSeen, err := store.LastMentioned(ctx, coinIDs(coins))
+	if err != nil {
+		log.Printf("[RunOnce] warning: failed to look up past mentions of new entrants: %v", err)
+	}
+	for _, c := range coins {
+		ts, mentioned := lastSeen[c.ID]
+		_, held := tenure[c.ID]
+		if !mentioned && (!held || headline != 0) {
+			continue
+		}
+		if mentioned {
+			c.LastSeenAt = &ts
+		}
+		out = append(out, c)
+	}
+	return out
+}
+
+// dropRecentlyMentioned removes the coins a post mentioned at or after since
+// and reports how many were dropped. A failed history query only logs a
+// warning and keeps every coin.
+func dropRecentlyMentioned(ctx context.Context, store StateStore, coins []Coin, since time.Time) ([]Coin, int) {
+	if len(coins) == 0 {
+		return coins, 0
+	}
+	recent, err := store.MentionedSince(ctx, since)
+	if err != nil {
+		log.Printf("[RunOnce] warning: failed to load recently mentioned coins: %v", err)
+		return coins, 0
+	}
+	out := make([]Coin, 0, len(coins))
+	for _, c := range coins {
+		if _, ok := recent[c.ID]; !ok {
+			out = append(out, c)
+		}
+	}
+	return out, len(coins) - len(out)
+}
+
+// splitMentionCooldown moves the coins a post mentioned at or after since out
+// of coins, with LastSeenAt set from that mention. History is read with a
+// single LastMentioned call; a failure only logs a warning and keeps every
+// coin as a headline entrant.
+func splitMentionCooldown(ctx context.Context, store StateStore, coins []Coin, since time.Time) ([]Coin, []Coin) {
+	if len(coins) == 0 {
+		return coins, nil
+	}
+	lastSeen, err := store.LastMentioned(ctx, coinIDs(coins))
+	if err != nil {
+		log.Printf("[RunOnce] warning: failed to look up past mentions for COIN_MENTION_COOLDOWN_DAYS: %v", err)
+		return coins, nil
+	}
+	kept := make([]Coin, 0, len(coins))
+	var recent []Coin
+	for _, c := range coins {
+		ts, ok := lastSeen[c.ID]
+		if !ok || ts.Before(since) {
+			kept = append(kept, c)
+			continue
+		}
+		c.LastSeenAt = &ts
+		recent = append(recent, c)
+	}
+	return kept, recent
+}
+
+// debutCoins returns the coins that are not in returning.
+func debutCoins(coins, returning []Coin) []Coin {
+	seen := make(map[int64]struct{}, len(returning))
+	for _, c := range returning {
+		seen[c.ID] = struct{}{}
+	}
+	out := make([]Coin, 0, len(coins))
+	for _, c := range coins {
+		if _, ok := seen[c.ID]; !ok {
+			out = append(out, c)
+		}
+	}
+	return out
+}
@@ -0,0 +1,151 @@
+package bot
+
+import (
+	"context"
+	"slices"
+	"strings"
+	"testing"
+	"time"
+)
+
+func TestReturningEntrantsUsesHistoryAndTenure(t *testing.T) {
+	seenAt := time.Date(2026, 3, 1, 12, 0, 0, 0, time.UTC)
+	store := &memoryStore{history: []HistoryDoc{
+		{CreatedAt: seenAt.Add(-72 * time.Hour), MentionedCoins: []Coin{{ID: 5, Symbol: "SHIB"}}},
+		{CreatedAt: seenAt, MentionedCoins: []Coin{{ID: 3, Symbol: "LINK"}, {ID: 5, Symbol: "SHIB"}}},
+	}}
+	coins := []Coin{{ID: 5, Symbol: "SHIB"}, {ID: 7, Symbol: "TON"}, {ID: 9, Symbol: "PEPE"}}
+
+	returning := returningEntrants(context.Background(), store, coins, map[int64]int64{7: 4}, 0)
+	if len(returning) != 2 || returning[0].ID != 5 || returning[1].ID != 7 {
+		t.Fatalf("unexpected returning coins: %+v", returning)
+	}
+	if returning[0].LastSeenAt == nil || !returning[0].LastSeenAt.Equal(seenAt) {
+		t.Fatalf("SHIB should carry its latest mention, got %v", returning[0].LastSeenAt)
+	}
+	if returning[1].LastSeenAt != nil {
+		t.Fatalf("TON was only held in state, got last_seen_at %v", returning[1].LastSeenAt)
+	}
+	if store.mentionLookups != 1 {
+		t.Fatalf("expected a single history lookup, got %d", store.mentionLookups)
+	}
+
+	debut := debutCoins(coins, returning)
+	if len(debut) != 1 || debut[0].Symbol != "PEPE" {
+		t.Fatalf("unexpected debut coins: %+v", debut)
+	}
+}
+
+func TestReturningEntrantsIgnoresTenureBelowHeadline(t *testing.T) {
+	seenAt := time.Date(2026, 3, 1, 12, 0, 0, 0, time.UTC)
+	store := &memoryStore{history: []HistoryDoc{{CreatedAt: seenAt, MentionedCoins: []Coin{{ID: 5, Symbol: "SHIB"}}}}}
+	coins := []Coin{{ID: 5, Symbol: "SHIB"}, {ID: 7, Symbol: "TON"}}
+
+	// TON was only tracked below the top 20, so it enters the headline for
+	// the first time.
+	returning := returningEntrants(context.Background(), store, coins, map[int64]int64{5: 2, 7: 40}, 20)
+	if len(returning) != 1 || returning[0].ID != 5 {
+		t.Fatalf("only the previously posted SHIB should be returning, got %+v", returning)
+	}
+}
+
+func TestDigestPostsAreNotMentionsOrTheLatestPost(t *testing.T) {
+	postedAt := time.Date(2026, 3, 1, 12, 0, 0, 0, time.UTC)
+	store := &memoryStore{history: []HistoryDoc{
+		{CreatedAt: postedAt, Text: "entrants", MentionedCoins: []Coin{{ID: 5, Symbol: "SHIB"}}},
+		{CreatedAt: postedAt.Add(time.Hour), Text: "digest", Kind: HistoryKindDigest, MentionedCoins: []Coin{{ID: 7, Symbol: "TON"}}},
+	}}
+	ctx := context.Background()
+
+	seen, _ := store.LastMentioned(ctx, []int64{5, 7})
+	if _, ok := seen[7]; ok || !seen[5].Equal(postedAt) {
+		t.Fatalf("digest mention must not count: %v", seen)
+	}
+	recent, _ := store.MentionedSince(ctx, postedAt.Add(-time.Hour))
+	if _, ok := recent[7]; ok || len(recent) != 1 {
+		t.Fatalf("digest mention must not count: %v", recent)
+	}
+	last, err := store.LatestHistory(ctx)
+	if err != nil || last.Text != "entrants" {
+		t.Fatalf("latest post should skip the digest, got %q (%v)", last.Text, err)
+	}
+}
+
+func TestFallbackTemplateSeparatesReturningCoins(t *testing.T) {
+	seenAt := time.Date(2026, 3, 1, 12, 0, 0, 0, time.UTC)
+	coins := []Coin{{ID: 5, Rank: 14, Name: "Shiba Inu", Symbol: "SHIB", LastSeenAt: &seenAt}, {ID: 9, Rank: 20, Name: "Pepe", Symbol: "PEPE"}}
+	renderCtx := buildRenderContext(Config{TopN: 20}, RunOptions{Convert: "USD"}, renderInputs{NewCoins: coins, ReturningCoins: coins[:1]})
+
+	got := RenderTemplate(defaultFallbackTemplate, renderCtx)
+	want := "• #20 Pepe (PEPE)\n🔁 Back in the Top 20:\n• #14 Shiba Inu (SHIB), last posted 2026-03-01T12:00:00Z\n"
+	if !strings.Contains(got, want) {
+		t.Fatalf("missing %q in %q", want, got)
+	}
+}
+
+func TestRunOnceDropsCoinsMentionedWithinDedupWindow(t *testing.T) {
+	const listings = `{"data":[
+		{"id":1,"name":"Bitcoin","symbol":"BTC","cmc_rank":1},
+		{"id":5426,"name":"Solana","symbol":"SOL","cmc_rank":2},
+		{"id":74,"name":"Dogecoin","symbol":"DOGE","cmc_rank":3}]}`
+	now := time.Now().UTC()
+	store := &memoryStore{
+		state: &State{TopN: 3, Convert: "USD", IDs: []int64{1, 2, 3}, Coins: []Coin{{ID: 1, Rank: 1}, {ID: 2, Rank: 2}, {ID: 3, Rank: 3}}},
+		history: []HistoryDoc{
+			{CreatedAt: now.Add(-48 * time.Hour), MentionedCoins: []Coin{{ID: 74, Symbol: "DOGE"}}},
+			{CreatedAt: now.Add(-2 * time.Hour), MentionedCoins: []Coin{{ID: 5426, Symbol: "SOL"}}},
+		},
+	}
+	cfg := Config{TopN: 3, RankChangeThreshold: 10, DedupWindow: 24 * time.Hour}
+
+	out := runDry(t, cfg, RunOptions{Convert: "USD"}, listings, store)
+	if strings.Contains(out, "Solana") {
+		t.Fatalf("SOL was mentioned 2h ago and should be suppressed: %q", out)
+	}
+	if !strings.Contains(out, "Dogecoin") {
+		t.Fatalf("DOGE was mentioned 48h ago and should be announced: %q", out)
+	}
+}
+
+func TestRunOnceDemotesEntrantsInMentionCooldown(t *testing.T) {
+	const listings = `{"data":[
+		{"id":1,"name":"Bitcoin","symbol":"BTC","cmc_rank":1},
+		{"id":5426,"name":"Solana","symbol":"SOL","cmc_rank":2},
+		{"id":74,"name":"Dogecoin","symbol":"DOGE","cmc_rank":3}]}`
+	now := time.Now().UTC()
+	store := &memoryStore{
+		state:   &State{TopN: 3, Convert: "USD", IDs: []int64{1, 2, 3}, Coins: []Coin{{ID: 1, Rank: 1}, {ID: 2, Rank: 2}, {ID: 3, Rank: 3}}},
+		history: []HistoryDoc{{CreatedAt: now.Add(-48 * time.Hour), MentionedCoins: []Coin{{ID: 5426, Symbol: "SOL"}}}},
+	}
+	cfg := Config{TopN: 3, RankChangeThreshold: 10, MentionCooldown: 7 * 24 * time.Hour}
+
+	out := runDry(t, cfg, RunOptions{Convert: "USD"}, listings, store)
+	if !strings.Contains(out, "• #3 Dogecoin (DOGE)") {
+		t.Fatalf("DOGE should stay a headline entrant: %q", out)
+	}
+	if strings.Contains(out, "• #2 Solana") || !strings.Contains(out, "featured recently: Solana (SOL)") {
+		t.Fatalf("SOL should be a one-liner only: %q", out)
+	}
+}
+
+func TestRunOnceAllEntrantsInMentionCooldownSkipsPostButWritesState(t *testing.T) {
+	srv := newCMCMockServer(t, `{"data":[
+		{"id":1,"name":"Bitcoin","symbol":"BTC","cmc_rank":1},
+		{"id":2,"name":"Ethereum","symbol":"ETH","cmc_rank":2},
+		{"id":5426,"name":"Solana","symbol":"SOL","cmc_rank":3}]}`)
+	store := &memoryStore{
+		state:   &State{TopN: 3, Convert: "USD", IDs: []int64{1, 2, 3}, Coins: []Coin{{ID: 1, Rank: 1}, {ID: 2, Rank: 2}, {ID: 3, Rank: 3}}},
+		history: []HistoryDoc{{CreatedAt: time.Now().UTC().Add(-time.Hour), MentionedCoins: []Coin{{ID: 5426, Symbol: "SOL"}}}},
+	}
+	cfg := Config{CMCBaseURL: srv.URL, TopN: 3, RankChangeThreshold: 10, MentionCooldown: 24 * time.Hour}
+
+	if err := RunOnceWithStore(context.Background(), cfg, RunOptions{Convert: "USD"}, store); err != nil {
+		t.Fatalf("RunOnceWithStore error: %v", err)
+	}
+	if len(store.history) != 1 {
+		t.Fatalf("nothing should be posted, got %d history doc(s)", len(store.history))
+	}
+	if store.stateWrites != 1 || !slices.Contains(store.state.IDs, 5426) {
+		t.Fatalf("state should move on to include SOL: writes=%d ids=%v", store.stateWrites, store.state.IDs)
+	}
+}
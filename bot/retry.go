@@ -0,0 +1,157 @@
+package bot
+
+import (
+	"bytes"
+	"context"
+	"encoding/json"
+	"io"
+	"log"
+	"math/rand"
+	"net/http"
+	"strconv"
+	"strings"
+	"time"
+)
+
+const (
+	// maxRetryAttempts and maxRetryDelay bound retries no matter what the env
+	// asks for, so a scheduled invocation can never hang on a flapping upstream.
+	// maxRetryDelay only caps the computed backoff: a delay the upstream asks
+	// for is slept in full unless it runs past the context deadline.
+	maxRetryAttempts = 10
+	maxRetryDelay    = 10 * time.Second
+)
+
+type retryPolicy struct {
+	MaxRetries int
+	BaseDelay  time.Duration
+}
+
+// httpRetryPolicy is the policy shared by CMC, Gemini and Telegram calls. The
+// CMC_* env names predate the other callers.
+func (c Config) httpRetryPolicy() retryPolicy {
+	return retryPolicy{MaxRetries: c.CMCMaxRetries, BaseDelay: c.CMCRetryBase}
+}
+
+// doWithRetry sends the request built by newReq, retrying network errors,
+// 429 and 500/502/503/504 responses with exponential backoff and jitter.
+// Retry-After is honored when the upstream sends it; if that wait would
+// outlast the context deadline the last response is returned instead.
+func doWithRetry(ctx context.Context, client *http.Client, policy retryPolicy, label string, newReq func() (*http.Request, error)) (*http.Response, error) {
+	retries := policy.MaxRetries
+	if retries > maxRetryAttempts {
+		retries = maxRetryAttempts
+	}
+	for attempt := 0; ; attempt++ {
+		req, err := newReq()
+		if err != nil {
+			return nil, err
+		}
+		resp, err := client.Do(req)
+		if attempt >= retries || ctx.Err() != nil || !isRetryable(resp, err) {
+			return resp, err
+		}
+
+		delay := backoffDelay(policy.BaseDelay, attempt)
+		var reason string
+		if err != nil {
+			reason = err.Error()
+		} else {
+			reason = resp.Status
+			if d, ok := serverRetryDelay(resp); ok {
+				delay = d
+			}
+		}
+		if deadline, ok := ctx.Deadline(); ok && time.Until(deadline) < delay {
+			log.Printf("[%s] warning: attempt %d/%d failed (%s); the %s retry delay outlasts the deadline, giving up", label, attempt+1, retries+1, reason, delay)
+			return resp, err
+		}
+		if resp != nil {
+			_, _ = io.Copy(io.Discard, resp.Body)
+			resp.Body.Close()
+		}
+		log.Printf("[%s] warning: attempt %d/%d failed (%s); retrying in %s", label, attempt+1, retries+1, reason, delay)
+
+		timer := time.NewTimer(delay)
+		select {
+		case <-ctx.Done():
+			timer.Stop()
+			return nil, ctx.Err()
+		case <-timer.C:
+		}
+	}
+}
+
+func isRetryable(resp *http.Response, err error) bool {
+	if err != nil {
+		return true
+	}
+	switch resp.StatusCode {
+	case http.StatusTooManyRequests, http.StatusInternalServerError, http.StatusBadGateway, http.StatusServiceUnavailable, http.StatusGatewayTimeout:
+		return true
+	}
+	return false
+}
+
+func backoffDelay(base time.Duration, attempt int) time.Duration {
+	if base <= 0 {
+		return 0
+	}
+	d := base << uint(attempt)
+	if d <= 0 || d > maxRetryDelay {
+		d = maxRetryDelay
+	}
+	return min(d+time.Duration(rand.Int63n(int64(d)/2+1)), maxRetryDelay)
+}
+
+// serverRetryDelay returns the wait the upstream asked for, from the
+// Retry-After header or, on a 429, Telegram's body field. The body is
+// buffered back onto resp so a caller that gets the response still sees it.
+func serverRetryDelay(resp *http.Response) (time.Duration, bool) {
+	if d, ok := parseRetryAfter(resp.Header.Get("Retry-After")); ok {
+		return d, true
+	}
+	if resp.StatusCode != http.StatusTooManyRequests {
+		return 0, false
+	}
+	b, _ := io.ReadAll(io.LimitReader(resp.Body, 64<<10))
+	resp.Body.Close()
+	resp.Body = io.NopCloser(bytes.NewReader(b))
+	return retryAfterFromBody(bytes.NewReader(b))
+}
+
+// retryAfterFromBody reads the parameters.retry_after seconds Telegram puts in
+// the body of a 429 instead of (or besides) the Retry-After header.
+func retryAfterFromBody(body io.Reader) (time.Duration, bool) {
+	var payload struct {
+		Parameters struct {
+			RetryAfter *int `json:"retry_after"`
+		} `json:"parameters"`
+	}
+	if err := json.NewDecoder(io.LimitReader(body, 64<<10)).Decode(&payload); err != nil {
+		return 0, false
+	}
+	if payload.Parameters.RetryAfter == nil || *payload.Parameters.RetryAfter < 0 {
+		return 0, false
+	}
+	return time.Duration(*payload.Parameters.RetryAfter) * time.Second, true
+}
+
+// parseRetryAfter accepts both forms of the Retry-After header: delay seconds
+// and an HTTP date.
+func parseRetryAfter(v string) (time.Duration, bool) {
+	v = strings.TrimSpace(v)
+	if v == "" {
+		return 0, false
+	}
+	if secs, err := strconv.Atoi(v); err == nil && secs >= 0 {
+		return time.Duration(secs) * time.Second, true
+	}
+	if at, err := http.ParseTime(v); err == nil {
+		if d := time.Until(at); d > 0 {
+			return d, true
+		}
+		return 0, true
+	}
+	return 0, false
+}
@@ -0,0 +1,147 @@
+package bot
+
+import (
+	"bytes"
+	"context"
+	"io"
+	"net/http"
+	"strings"
+	"testing"
+	"time"
+)
+
+type sequenceRoundTripper struct {
+	listingStatuses []int
+	listingCalls    int
+}
+
+func (s *sequenceRoundTripper) RoundTrip(req *http.Request) (*http.Response, error) {
+	status := http.StatusOK
+	body := `{"data":[{"id":1,"name":"Bitcoin","symbol":"BTC","cmc_rank":1,"quote":{"USD":{"market_cap":1000}}}]}`
+	if strings.Contains(req.URL.Path, "/info") {
+		body = `{"data":{}}`
+	} else {
+		if s.listingCalls < len(s.listingStatuses) {
+			status = s.listingStatuses[s.listingCalls]
+		}
+		s.listingCalls++
+	}
+	if status != http.StatusOK {
+		body = `{"status":{"error_code":1008,"error_message":"rate limited"}}`
+	}
+	return &http.Response{
+		StatusCode: status,
+		Status:     http.StatusText(status),
+		Body:       io.NopCloser(bytes.NewBufferString(body)),
+		Header:     make(http.Header),
+	}, nil
+}
+
+func TestFetchCMCTopNRetriesOnTooManyRequests(t *testing.T) {
+	rt := &sequenceRoundTripper{listingStatuses: []int{http.StatusTooManyRequests, http.StatusOK}}
+	client := &http.Client{Transport: rt}
+	cfg := Config{TopN: 1, CMCMaxRetries: 3, CMCRetryBase: time.Millisecond}
+
+	coins, err := fetchCMCTopN(context.Background(), client, cfg, RunOptions{Convert: "USD"}, nil)
+	if err != nil {
+		t.Fatalf("fetchCMCTopN error: %v", err)
+	}
+	if rt.listingCalls != 2 {
+		t.Fatalf("expected 2 listing calls, got %d", rt.listingCalls)
+	}
+	if len(coins) != 1 || coins[0].Symbol != "BTC" {
+		t.Fatalf("unexpected coins: %+v", coins)
+	}
+}
+
+func TestFetchCMCTopNDoesNotRetryUnauthorized(t *testing.T) {
+	rt := &sequenceRoundTripper{listingStatuses: []int{http.StatusUnauthorized, http.StatusOK}}
+	client := &http.Client{Transport: rt}
+	cfg := Config{TopN: 1, CMCMaxRetries: 3, CMCRetryBase: time.Millisecond}
+
+	if _, err := fetchCMCTopN(context.Background(), client, cfg, RunOptions{Convert: "USD"}, nil); err == nil {
+		t.Fatalf("expected error for 401 response")
+	}
+	if rt.listingCalls != 1 {
+		t.Fatalf("expected a single listing call, got %d", rt.listingCalls)
+	}
+}
+
+func TestParseRetryAfterSeconds(t *testing.T) {
+	if d, ok := parseRetryAfter("2"); !ok || d != 2*time.Second {
+		t.Fatalf("unexpected retry-after: %v %v", d, ok)
+	}
+	if _, ok := parseRetryAfter("soon"); ok {
+		t.Fatalf("expected invalid retry-after to be ignored")
+	}
+}
+
+func TestCallGeminiRetriesServiceUnavailable(t *testing.T) {
+	statuses := []int{http.StatusServiceUnavailable, http.StatusServiceUnavailable, http.StatusOK}
+	calls := 0
+	client := &http.Client{Transport: roundTripFunc(func(req *http.Request) (*http.Response, error) {
+		status := statuses[calls]
+		calls++
+		if status != http.StatusOK {
+			return jsonResponse(status, `{"error":{"message":"overloaded"}}`), nil
+		}
+		return jsonResponse(status, `{"candidates":[{"content":{"parts":[{"text":"hello"}]}}]}`), nil
+	})}
+	cfg := Config{AIModel: "test-model", GeminiAPIKey: "k", CMCMaxRetries: 3, CMCRetryBase: time.Millisecond}
+
+	text, err := callGemini(context.Background(), client, cfg, "prompt")
+	if err != nil {
+		t.Fatalf("callGemini error: %v", err)
+	}
+	if calls != 3 || text != "hello" {
+		t.Fatalf("expected 3 calls and text %q, got %d calls and %q", "hello", calls, text)
+	}
+}
+
+func TestCallGeminiDoesNotRetryForbidden(t *testing.T) {
+	calls := 0
+	client := &http.Client{Transport: roundTripFunc(func(req *http.Request) (*http.Response, error) {
+		calls++
+		return jsonResponse(http.StatusForbidden, `{"error":{"message":"forbidden"}}`), nil
+	})}
+	cfg := Config{AIModel: "test-model", GeminiAPIKey: "k", CMCMaxRetries: 3, CMCRetryBase: time.Millisecond}
+
+	if _, err := callGemini(context.Background(), client, cfg, "prompt"); err == nil {
+		t.Fatalf("expected error for 403 response")
+	}
+	if calls != 1 {
+		t.Fatalf("expected a single call, got %d", calls)
+	}
+}
+
+func TestServerRetryDelayIsNotClamped(t *testing.T) {
+	resp := jsonResponse(http.StatusTooManyRequests, `{"ok":false,"error_code":429,"description":"Too Many Requests: retry after 30","parameters":{"retry_after":30}}`)
+	if d, ok := serverRetryDelay(resp); !ok || d != 30*time.Second {
+		t.Fatalf("expected the full 30s retry_after, got %v %v", d, ok)
+	}
+	b, _ := io.ReadAll(resp.Body)
+	if !strings.Contains(string(b), "retry after 30") {
+		t.Fatalf("expected the body to stay readable, got %q", b)
+	}
+}
+
+func TestDoWithRetryGivesUpWhenRetryAfterOutlastsDeadline(t *testing.T) {
+	calls := 0
+	client := &http.Client{Transport: roundTripFunc(func(req *http.Request) (*http.Response, error) {
+		calls++
+		return jsonResponse(http.StatusTooManyRequests, `{"ok":false,"error_code":429,"description":"Too Many Requests: retry after 30","parameters":{"retry_after":30}}`), nil
+	})}
+	ctx, cancel := context.WithTimeout(context.Background(), 5*time.Second)
+	defer cancel()
+
+	resp, err := doWithRetry(ctx, client, retryPolicy{MaxRetries: 3}, "test", func() (*http.Request, error) {
+		return http.NewRequestWithContext(ctx, http.MethodPost, "https://api.telegram.org/botX/sendMessage", nil)
+	})
+	if err != nil {
+		t.Fatalf("doWithRetry error: %v", err)
+	}
+	defer resp.Body.Close()
+	if calls != 1 || resp.StatusCode != http.StatusTooManyRequests {
+		t.Fatalf("expected the 429 back after a single call, got %d after %d calls", resp.StatusCode, calls)
+	}
+}
@@ -0,0 +1,129 @@
+package bot
+
+import (
+	"context"
+	"encoding/xml"
+	"fmt"
+	"log"
+	"os"
+	"path/filepath"
+	"strings"
+	"time"
+)
+
+const (
+	defaultRSSTitle       = "CoinMarketCap Top 100 entrants"
+	defaultRSSLink        = "https://coinmarketcap.com/"
+	defaultRSSDescription = "Coins that entered the CoinMarketCap top list, as posted to Telegram."
+	defaultRSSMaxItems    = 50
+)
+
+type rssFeed struct {
+	XMLName xml.Name   `xml:"rss"`
+	Version string     `xml:"version,attr"`
+	Channel rssChannel `xml:"channel"`
+}
+
+type rssChannel struct {
+	Title         string    `xml:"title"`
+	Link          string    `xml:"link"`
+	Description   string    `xml:"description"`
+	LastBuildDate string    `xml:"lastBuildDate"`
+	Items         []rssItem `xml:"item"`
+}
+
+type rssItem struct {
+	Title       string  `xml:"title"`
+	Description rssText `xml:"description"`
+	PubDate     string  `xml:"pubDate"`
+	GUID        rssGUID `xml:"guid"`
+}
+
+type rssText struct {
+	Text string `xml:",cdata"`
+}
+
+type rssGUID struct {
+	IsPermaLink bool   `xml:"isPermaLink,attr"`
+	Value       string `xml:",chardata"`
+}
+
+// WriteRSSFeed writes the latest published posts from history as an RSS 2.0
+// feed to path. The file is replaced atomically so a web server never serves
+// a half-written feed.
+func WriteRSSFeed(ctx context.Context, cfg Config, path string) error {
+	store, err := OpenStateStore(ctx, cfg)
+	if err != nil {
+		return err
+	}
+	defer store.Close(context.Background())
+
+	limit := cfg.RSSMaxItems
+	if limit <= 0 {
+		limit = defaultRSSMaxItems
+	}
+	docs, err := store.ListHistory(ctx, limit)
+	if err != nil {
+		return err
+	}
+	out, err := buildRSSFeed(cfg, docs, time.Now().UTC())
+	if err != nil {
+		return err
+	}
+	tmp, err := os.CreateTemp(filepath.Dir(path), ".rss-*")
+	if err != nil {
+		return err
+	}
+	defer os.Remove(tmp.Name())
+	if _, err := tmp.Write(out); err != nil {
+		tmp.Close()
+		return err
+	}
+	if err := tmp.Close(); err != nil {
+		return err
+	}
+	// CreateTemp makes the file 0600; the feed is meant to be served.
+	if err := os.Chmod(tmp.Name(), 0o644); err != nil {
+		return err
+	}
+	if err := os.Rename(tmp.Name(), path); err != nil {
+		return err
+	}
+	log.Printf("[RSS] wrote %d item(s) to %s", len(docs), path)
+	return nil
+}
+
+// buildRSSFeed renders docs (newest first) as an RSS 2.0 document.
+func buildRSSFeed(cfg Config, docs []HistoryDoc, now time.Time) ([]byte, error) {
+	feed := rssFeed{Version: "2.0", Channel: rssChannel{
+		Title:         cfg.RSSTitle,
+		Link:          cfg.RSSLink,
+		Description:   cfg.RSSDescription,
+		LastBuildDate: now.Format(time.RFC1123Z),
+		Items:         make([]rssItem, 0, len(docs)),
+	}}
+	for _, d := range docs {
+		feed.Channel.Items = append(feed.Channel.Items, rssItem{
+			Title:       rssItemTitle(d),
+			Description: rssText{Text: plainTextLinks(d.Text)},
+			PubDate:     d.CreatedAt.UTC().Format(time.RFC1123Z),
+			GUID:        rssGUID{Value: fmt.Sprintf("coinmarketcap_top100_bot:history:%s", d.ID)},
+		})
+	}
+	body, err := xml.MarshalIndent(feed, "", "  ")
+	if err != nil {
+		return nil, err
+	}
+	return append([]byte(xml.Header), append(body, '\n')...), nil
+}
+
+func rssItemTitle(d HistoryDoc) string {
+	names := make([]string, 0, len(d.MentionedCoins))
+	for _, c := range d.MentionedCoins {
+		names = append(names, c.Name)
+	}
+	if len(names) == 0 {
+		return fmt.Sprintf("Top %d update", d.TopN)
+	}
+	return fmt.Sprintf("New in top %d: %s", d.TopN, strings.Join(names, ", "))
+}
@@ -0,0 +1,42 @@
+package bot
+
+import (
+	"encoding/xml"
+	"strings"
+	"testing"
+	"time"
+)
+
+func TestBuildRSSFeedIsWellFormed(t *testing.T) {
+	cfg := Config{RSSTitle: "Top & entrants", RSSLink: "https://t.me/example", RSSDescription: "desc"}
+	created := time.Date(2024, 1, 15, 10, 0, 0, 0, time.UTC)
+	docs := []HistoryDoc{
+		{ID: "65a4f0c2e1", CreatedAt: created, TopN: 100, Text: "<b>New</b> ]]> entrant", MentionedCoins: []Coin{{Name: "Pepe"}, {Name: "Sui"}}},
+		{ID: "65a4f0c2e0", CreatedAt: created.Add(-time.Hour), TopN: 100, Text: "exits only"},
+	}
+
+	out, err := buildRSSFeed(cfg, docs, created)
+	if err != nil {
+		t.Fatalf("buildRSSFeed error: %v", err)
+	}
+	var feed rssFeed
+	if err := xml.Unmarshal(out, &feed); err != nil {
+		t.Fatalf("feed is not well-formed XML: %v\n%s", err, out)
+	}
+	if feed.Version != "2.0" || feed.Channel.Title != "Top & entrants" || len(feed.Channel.Items) != 2 {
+		t.Fatalf("unexpected feed: %+v", feed)
+	}
+	first := feed.Channel.Items[0]
+	if first.Title != "New in top 100: Pepe, Sui" || first.PubDate != "Mon, 15 Jan 2024 10:00:00 +0000" || first.GUID.Value != "coinmarketcap_top100_bot:history:65a4f0c2e1" {
+		t.Fatalf("unexpected first item: %+v", first)
+	}
+	if first.Description.Text != docs[0].Text {
+		t.Fatalf("CDATA round trip changed the text: %q", first.Description.Text)
+	}
+	if !strings.Contains(string(out), "<![CDATA[<b>New</b>") {
+		t.Fatalf("description should be wrapped in CDATA:\n%s", out)
+	}
+	if feed.Channel.Items[1].Title != "Top 100 update" {
+		t.Fatalf("unexpected title without mentioned coins: %q", feed.Channel.Items[1].Title)
+	}
+}
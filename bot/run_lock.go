@@ -0,0 +1,51 @@
+package bot
+
+import (
+	"context"
+	"crypto/rand"
+	"encoding/hex"
+	"log"
+	"time"
+)
+
+// runLockID is the _id of the lock document shared by all runs.
+const runLockID = "run"
+
+// RunLocker is implemented by stores that can keep overlapping runs, eg two
+// Lambda instances or a slow cron tick, from writing state at the same time.
+type RunLocker interface {
+	// AcquireRunLock reports false, without error, while another owner holds
+	// an unexpired lock.
+	AcquireRunLock(ctx context.Context, owner string, ttl time.Duration) (bool, error)
+	ReleaseRunLock(ctx context.Context, owner string) error
+}
+
+// acquireRunLock takes the run lock for ttl when store supports it. The
+// returned release must be deferred: it runs on errors and panics alike, and
+// uses a context that outlives a cancelled ctx. Stores without locking, and
+// a zero ttl, always acquire.
+func acquireRunLock(ctx context.Context, store StateStore, ttl time.Duration) (func(), bool, error) {
+	locker, ok := store.(RunLocker)
+	if !ok || ttl <= 0 {
+		return func() {}, true, nil
+	}
+	owner := newRunID()
+	acquired, err := locker.AcquireRunLock(ctx, owner, ttl)
+	if err != nil || !acquired {
+		return func() {}, false, err
+	}
+	log.Printf("[RunLock] acquired run lock %s for %s", owner, ttl)
+	release := func() {
+		if err := locker.ReleaseRunLock(context.WithoutCancel(ctx), owner); err != nil {
+			log.Printf("[RunLock] warning: failed to release run lock %s, it expires on its own: %v", owner, err)
+		}
+	}
+	return release, true, nil
+}
+
+// newRunID returns a random hex id, used for run ids and lock owners.
+func newRunID() string {
+	b := make([]byte, 8)
+	_, _ = rand.Read(b)
+	return hex.EncodeToString(b)
+}
@@ -0,0 +1,71 @@
+package bot
+
+import (
+	"context"
+	"testing"
+	"time"
+)
+
+// lockingStore adds a single in-memory lease to memoryStore.
+type lockingStore struct {
+	memoryStore
+	owner     string
+	expiresAt time.Time
+	released  int
+}
+
+func (s *lockingStore) AcquireRunLock(ctx context.Context, owner string, ttl time.Duration) (bool, error) {
+	now := time.Now()
+	if now.Before(s.expiresAt) {
+		return false, nil
+	}
+	s.owner, s.expiresAt = owner, now.Add(ttl)
+	return true, nil
+}
+
+func (s *lockingStore) ReleaseRunLock(ctx context.Context, owner string) error {
+	if s.owner == owner {
+		s.expiresAt = time.Time{}
+		s.released++
+	}
+	return nil
+}
+
+func TestAcquireRunLockSkipsWhileHeld(t *testing.T) {
+	store := &lockingStore{}
+	release, acquired, err := acquireRunLock(context.Background(), store, time.Minute)
+	if err != nil || !acquired {
+		t.Fatalf("first run should get the lock: acquired=%t err=%v", acquired, err)
+	}
+
+	if _, acquired, err := acquireRunLock(context.Background(), store, time.Minute); err != nil || acquired {
+		t.Fatalf("second run should be skipped while the lock is held: acquired=%t err=%v", acquired, err)
+	}
+
+	release()
+	if store.released != 1 {
+		t.Fatalf("expected the lock to be released once, got %d", store.released)
+	}
+	if _, acquired, _ := acquireRunLock(context.Background(), store, time.Minute); !acquired {
+		t.Fatalf("lock should be free after release")
+	}
+}
+
+func TestAcquireRunLockReleasesOnPanic(t *testing.T) {
+	store := &lockingStore{}
+	func() {
+		defer func() { _ = recover() }()
+		release, _, _ := acquireRunLock(context.Background(), store, time.Minute)
+		defer release()
+		panic("run failed")
+	}()
+	if store.released != 1 {
+		t.Fatalf("expected the deferred release to run on panic, got %d releases", store.released)
+	}
+}
+
+func TestAcquireRunLockWithoutLockerAlwaysRuns(t *testing.T) {
+	if _, acquired, err := acquireRunLock(context.Background(), &memoryStore{}, time.Minute); err != nil || !acquired {
+		t.Fatalf("stores without locking should always run: acquired=%t err=%v", acquired, err)
+	}
+}
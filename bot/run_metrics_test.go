@@ -0,0 +1,55 @@
+package bot
+
+import (
+	"context"
+	"testing"
+	"time"
+)
+
+func TestRunOnceWritesRunMetricsDoc(t *testing.T) {
+	const listings = `{"data":[
+		{"id":1,"name":"Bitcoin","symbol":"BTC","cmc_rank":1},
+		{"id":2,"name":"Ethereum","symbol":"ETH","cmc_rank":2},
+		{"id":5426,"name":"Solana","symbol":"SOL","cmc_rank":3}]}`
+	srv := newCMCMockServer(t, listings)
+	tg, sent := newTelegramMockServer(t)
+	store := &memoryStore{state: &State{TopN: 3, Convert: "USD", IDs: []int64{1, 2, 3}, Coins: []Coin{{ID: 1, Rank: 1}, {ID: 2, Rank: 2}, {ID: 3, Rank: 3}}}}
+	cfg := Config{CMCBaseURL: srv.URL, TopN: 3, RankChangeThreshold: 10, TelegramToken: "token", TelegramChannelID: "@channel", TelegramAPIBaseURL: tg.URL}
+	opt := RunOptions{Convert: "USD", NotifyExits: true}
+
+	runDry(t, cfg, opt, listings, store)
+	if len(store.runMetrics) != 0 {
+		t.Fatalf("dry runs must not write metrics: %+v", store.runMetrics)
+	}
+
+	before := time.Now().UTC()
+	if err := RunOnceWithStore(context.Background(), cfg, opt, store); err != nil {
+		t.Fatalf("RunOnceWithStore error: %v", err)
+	}
+	if *sent != 1 || len(store.history) != 1 {
+		t.Fatalf("expected the run to post once, got %d call(s) and %d history doc(s)", *sent, len(store.history))
+	}
+	if len(store.runMetrics) != 1 {
+		t.Fatalf("expected one metrics doc, got %+v", store.runMetrics)
+	}
+	doc := store.runMetrics[0]
+	if doc.CoinsFetched != 3 || doc.NewCoins != 1 || doc.ExitedCoins != 1 || doc.TextSource != TextSourceFallback || doc.Error != "" {
+		t.Fatalf("unexpected metrics doc: %+v", doc)
+	}
+	if doc.CreatedAt.Before(before.Add(-time.Second)) || doc.DurationMs < 0 {
+		t.Fatalf("unexpected timing in metrics doc: %+v", doc)
+	}
+}
+
+func TestRunOnceWritesRunMetricsForFailedRun(t *testing.T) {
+	srv := newCMCMockServer(t, `{"data":[{"id":1,"name":"Bitcoin","symbol":"BTC"}]}`)
+	store := &memoryStore{state: &State{TopN: 1, Convert: "USD", IDs: []int64{1}, Coins: []Coin{{ID: 1, Rank: 1}}}}
+	cfg := Config{CMCBaseURL: srv.URL, TopN: 1}
+
+	if err := RunOnceWithStore(context.Background(), cfg, RunOptions{Convert: "USD"}, store); err == nil {
+		t.Fatalf("expected the invalid listing to fail the run")
+	}
+	if len(store.runMetrics) != 1 || store.runMetrics[0].Error == "" || store.runMetrics[0].CoinsFetched != 0 {
+		t.Fatalf("expected one metrics doc flagged with the error, got %+v", store.runMetrics)
+	}
+}
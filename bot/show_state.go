@@ -0,0 +1,78 @@
+package bot
+
+import (
+	"context"
+	"encoding/csv"
+	"encoding/json"
+	"errors"
+	"fmt"
+	"io"
+	"strconv"
+	"text/tabwriter"
+	"time"
+)
+
+// Output formats of the show-state command.
+const (
+	StateFormatText = "text"
+	StateFormatJSON = "json"
+	StateFormatCSV  = "csv"
+)
+
+// ShowState writes the stored state to w: its metadata and one line per coin
+// in text, the whole document in json, or rank,symbol,name,market_cap rows in
+// csv. It fails when no state has been written yet.
+func ShowState(ctx context.Context, cfg Config, format string, w io.Writer) error {
+	if format != StateFormatText && format != StateFormatJSON && format != StateFormatCSV {
+		return fmt.Errorf("unsupported --format value %q (want text, json or csv)", format)
+	}
+	store, err := OpenStateStore(ctx, cfg)
+	if err != nil {
+		return err
+	}
+	defer store.Close(context.Background())
+
+	st, err := store.LoadState(ctx)
+	if errors.Is(err, ErrNoState) {
+		return fmt.Errorf("%w: no state document exists yet; run the bot once or use --init-state to write the baseline", err)
+	}
+	if err != nil {
+		return err
+	}
+	return writeState(w, st, format)
+}
+
+func writeState(w io.Writer, st State, format string) error {
+	switch format {
+	case StateFormatJSON:
+		b, err := json.MarshalIndent(st, "", "  ")
+		if err != nil {
+			return err
+		}
+		_, err = fmt.Fprintln(w, string(b))
+		return err
+	case StateFormatCSV:
+		cw := csv.NewWriter(w)
+		cw.Write([]string{"rank", "symbol", "name", "market_cap"})
+		for _, c := range st.Coins {
+			marketCap := ""
+			if c.MarketCap != nil {
+				marketCap = strconv.FormatFloat(*c.MarketCap, 'f', -1, 64)
+			}
+			cw.Write([]string{strconv.FormatInt(c.Rank, 10), c.Symbol, c.Name, marketCap})
+		}
+		cw.Flush()
+		return cw.Error()
+	}
+	fmt.Fprintf(w, "updated_at: %s\ntop_n: %d\nconvert: %s\n\n", st.UpdatedAt.UTC().Format(time.RFC3339), st.TopN, st.Convert)
+	tw := tabwriter.NewWriter(w, 0, 0, 1, ' ', tabwriter.Debug)
+	fmt.Fprintln(tw, "RANK\t SYMBOL\t NAME\t MARKET_CAP")
+	for _, c := range st.Coins {
+		marketCap := "n/a"
+		if c.MarketCap != nil {
+			marketCap = formatAbbrev(*c.MarketCap)
+		}
+		fmt.Fprintf(tw, "%d\t %s\t %s\t %s\n", c.Rank, c.Symbol, c.Name, marketCap)
+	}
+	return tw.Flush()
+}
@@ -0,0 +1,56 @@
+package bot
+
+import (
+	"bytes"
+	"encoding/json"
+	"strings"
+	"testing"
+	"time"
+)
+
+func testState() State {
+	btcCap, suiCap := 1.5e12, 4.2e9
+	return State{UpdatedAt: time.Date(2026, 3, 2, 9, 30, 0, 0, time.UTC), TopN: 100, Convert: "USD", IDs: []int64{1, 20947}, Coins: []Coin{
+		{ID: 1, Name: "Bitcoin", Symbol: "BTC", Rank: 1, MarketCap: &btcCap},
+		{ID: 20947, Name: "Sui, by Mysten", Symbol: "SUI", Rank: 42, MarketCap: &suiCap},
+		{ID: 74, Name: "Dogecoin", Symbol: "DOGE", Rank: 99},
+	}}
+}
+
+func TestWriteStateTextShowsMetadataAndCoins(t *testing.T) {
+	var buf bytes.Buffer
+	if err := writeState(&buf, testState(), StateFormatText); err != nil {
+		t.Fatalf("writeState error: %v", err)
+	}
+	out := buf.String()
+	for _, want := range []string{"updated_at: 2026-03-02T09:30:00Z\ntop_n: 100\nconvert: USD\n", "| BTC", "| 1.50T", "| 4.20B", "| n/a"} {
+		if !strings.Contains(out, want) {
+			t.Fatalf("text output missing %q:\n%s", want, out)
+		}
+	}
+}
+
+func TestWriteStateCSVQuotesFields(t *testing.T) {
+	var buf bytes.Buffer
+	if err := writeState(&buf, testState(), StateFormatCSV); err != nil {
+		t.Fatalf("writeState error: %v", err)
+	}
+	want := "rank,symbol,name,market_cap\n1,BTC,Bitcoin,1500000000000\n42,SUI,\"Sui, by Mysten\",4200000000\n99,DOGE,Dogecoin,\n"
+	if buf.String() != want {
+		t.Fatalf("unexpected csv:\n%s", buf.String())
+	}
+}
+
+func TestWriteStateJSONDumpsDocument(t *testing.T) {
+	var buf bytes.Buffer
+	if err := writeState(&buf, testState(), StateFormatJSON); err != nil {
+		t.Fatalf("writeState error: %v", err)
+	}
+	var got map[string]any
+	if err := json.Unmarshal(buf.Bytes(), &got); err != nil {
+		t.Fatalf("invalid json: %v\n%s", err, buf.String())
+	}
+	if got["top_n"] != float64(100) || got["convert"] != "USD" || len(got["coins"].([]any)) != 3 {
+		t.Fatalf("unexpected json: %v", got)
+	}
+}
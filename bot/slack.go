@@ -0,0 +1,71 @@
+package bot
+
+import (
+	"context"
+	"encoding/json"
+	"fmt"
+	"io"
+	"log"
+	"net/http"
+	"regexp"
+	"strings"
+)
+
+// slackTextLimit is the length Slack renders in a single text block.
+const slackTextLimit = 3000
+
+// notifySlack cross-posts text to the Slack incoming webhook when requested.
+// Like Discord it never fails the run.
+func notifySlack(ctx context.Context, client *http.Client, cfg Config, opt RunOptions, text string) {
+	if !opt.NotifySlack {
+		return
+	}
+	if cfg.SlackWebhookURL == "" {
+		log.Printf("[Slack] warning: --notify-slack set but SLACK_WEBHOOK_URL is empty; skipping")
+		return
+	}
+	if err := sendSlackMessage(ctx, client, cfg.SlackWebhookURL, text); err != nil {
+		log.Printf("[Slack] warning: failed to send message: %v", err)
+		return
+	}
+	log.Printf("[Slack] message sent successfully")
+}
+
+func sendSlackMessage(ctx context.Context, client *http.Client, webhookURL, text string) error {
+	body, _ := json.Marshal(map[string]any{"text": slackFormat(text)})
+	req, err := http.NewRequestWithContext(ctx, http.MethodPost, webhookURL, strings.NewReader(string(body)))
+	if err != nil {
+		return err
+	}
+	req.Header.Set("Content-Type", "application/json")
+	resp, err := client.Do(req)
+	if err != nil {
+		return upstreamError("slack", err)
+	}
+	defer resp.Body.Close()
+	if resp.StatusCode >= 300 {
+		b, _ := io.ReadAll(resp.Body)
+		return fmt.Errorf("slack error: %s %s", resp.Status, string(b))
+	}
+	return nil
+}
+
+var (
+	slackBoldRe = regexp.MustCompile(`\*\*(.+?)\*\*`)
+	slackLinkRe = regexp.MustCompile(`\[([^\]]+)\]\((https?://[^)\s]+)\)`)
+)
+
+// slackFormat turns the Markdown used by templates and AI output into Slack
+// mrkdwn: `**bold**` becomes `*bold*` (single-asterisk bold is already the
+// same), `[text](url)` becomes `<url|text>`, and &, < and > are escaped as
+// Slack requires. The result is cut to slackTextLimit characters with "…".
+func slackFormat(text string) string {
+	out := strings.TrimSpace(text)
+	out = strings.NewReplacer("&", "&amp;", "<", "&lt;", ">", "&gt;").Replace(out)
+	out = slackBoldRe.ReplaceAllString(out, "*$1*")
+	out = slackLinkRe.ReplaceAllString(out, "<$2|$1>")
+	if r := []rune(out); len(r) > slackTextLimit {
+		out = string(r[:slackTextLimit-1]) + "…"
+	}
+	return out
+}
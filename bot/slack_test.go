@@ -0,0 +1,22 @@
+package bot
+
+import (
+	"strings"
+	"testing"
+	"unicode/utf8"
+)
+
+func TestSlackFormatConvertsMarkdown(t *testing.T) {
+	got := slackFormat("**New** coin [Pepe](https://coinmarketcap.com/currencies/pepe/) <3 & *up*")
+	want := "*New* coin <https://coinmarketcap.com/currencies/pepe/|Pepe> &lt;3 &amp; *up*"
+	if got != want {
+		t.Fatalf("slackFormat = %q, want %q", got, want)
+	}
+}
+
+func TestSlackFormatTruncates(t *testing.T) {
+	got := slackFormat(strings.Repeat("a", slackTextLimit+10))
+	if utf8.RuneCountInString(got) != slackTextLimit || !strings.HasSuffix(got, "…") {
+		t.Fatalf("expected %d characters ending in an ellipsis, got %d", slackTextLimit, utf8.RuneCountInString(got))
+	}
+}
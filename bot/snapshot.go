@@ -0,0 +1,57 @@
+package bot
+
+import (
+	"context"
+	"errors"
+	"log"
+	"time"
+)
+
+// ErrNoSnapshot is returned by LoadSnapshotAt when no snapshot was stored at
+// or before the requested time.
+var ErrNoSnapshot = errors.New("no snapshot")
+
+// Snapshot is the listing one run fetched, stored when RAW_SNAPSHOT_ENABLED is
+// set so a post can later be traced back to the data behind it.
+type Snapshot struct {
+	CreatedAt time.Time `bson:"created_at" json:"created_at"`
+	TopN      int64     `bson:"top_n" json:"top_n"`
+	Convert   string    `bson:"convert" json:"convert"`
+	Provider  string    `bson:"provider,omitempty" json:"provider,omitempty"`
+	Coins     []Coin    `bson:"coins" json:"coins"`
+}
+
+// LoadSnapshotAt returns the latest snapshot taken at or before ts.
+func LoadSnapshotAt(ctx context.Context, store StateStore, ts time.Time) (Snapshot, error) {
+	return store.LoadSnapshotAt(ctx, ts)
+}
+
+// pruneSnapshots drops snapshots older than SNAPSHOT_RETENTION_DAYS; a
+// retention of 0 keeps them forever. A failure is only logged.
+func pruneSnapshots(ctx context.Context, store StateStore, cfg Config, now time.Time) {
+	if !cfg.RawSnapshotEnabled || cfg.SnapshotRetentionDays <= 0 {
+		return
+	}
+	cutoff := now.AddDate(0, 0, -cfg.SnapshotRetentionDays)
+	n, err := store.DeleteSnapshotsBefore(ctx, cutoff)
+	if err != nil {
+		log.Printf("[RunOnce] warning: failed to prune snapshots older than %s: %v", cutoff.Format(time.RFC3339), err)
+		return
+	}
+	if n > 0 {
+		log.Printf("[RunOnce] pruned %d snapshot(s) older than %d day(s)", n, cfg.SnapshotRetentionDays)
+	}
+}
+
+// saveSnapshot stores the fetched listing when RAW_SNAPSHOT_ENABLED is set. A
+// failure is only logged: auditing must not block a post.
+func saveSnapshot(ctx context.Context, store StateStore, cfg Config, snap Snapshot) {
+	if !cfg.RawSnapshotEnabled {
+		return
+	}
+	if err := store.InsertSnapshot(ctx, snap); err != nil {
+		log.Printf("[RunOnce] warning: failed to save snapshot: %v", err)
+		return
+	}
+	log.Printf("[RunOnce] saved snapshot of %d coin(s)", len(snap.Coins))
+}
@@ -0,0 +1,44 @@
+package bot
+
+import (
+	"context"
+	"errors"
+	"testing"
+	"time"
+)
+
+func TestRunOnceStoresSnapshotAndPrunesOldOnes(t *testing.T) {
+	srv := newCMCMockServer(t, listingsBody(1, 2))
+	old := time.Now().UTC().AddDate(0, 0, -40)
+	store := &memoryStore{snapshots: []Snapshot{{CreatedAt: old, TopN: 2, Convert: "USD"}}}
+	cfg := Config{CMCBaseURL: srv.URL, TopN: 2, RawSnapshotEnabled: true, SnapshotRetentionDays: 30}
+
+	if err := RunOnceWithStore(context.Background(), cfg, RunOptions{Convert: "USD"}, store); err != nil {
+		t.Fatalf("RunOnceWithStore error: %v", err)
+	}
+	if len(store.snapshots) != 1 {
+		t.Fatalf("expected the old snapshot to be pruned and a new one stored, got %d", len(store.snapshots))
+	}
+
+	snap, err := LoadSnapshotAt(context.Background(), store, time.Now().UTC())
+	if err != nil {
+		t.Fatalf("LoadSnapshotAt error: %v", err)
+	}
+	if snap.TopN != 2 || snap.Convert != "USD" || snap.Provider != providerCMC || len(snap.Coins) != 2 || snap.Coins[1].ID != 2 {
+		t.Fatalf("unexpected snapshot: %+v", snap)
+	}
+	if _, err := LoadSnapshotAt(context.Background(), store, old); !errors.Is(err, ErrNoSnapshot) {
+		t.Fatalf("expected ErrNoSnapshot before the first snapshot, got %v", err)
+	}
+}
+
+func TestRunOnceSkipsSnapshotsByDefault(t *testing.T) {
+	srv := newCMCMockServer(t, listingsBody(1, 2))
+	store := &memoryStore{}
+	if err := RunOnceWithStore(context.Background(), Config{CMCBaseURL: srv.URL, TopN: 2}, RunOptions{Convert: "USD"}, store); err != nil {
+		t.Fatalf("RunOnceWithStore error: %v", err)
+	}
+	if len(store.snapshots) != 0 {
+		t.Fatalf("RAW_SNAPSHOT_ENABLED unset should not store snapshots, got %d", len(store.snapshots))
+	}
+}
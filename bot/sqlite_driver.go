@@ -0,0 +1,7 @@
+//go:build sqlite
+
+package bot
+
+// The SQLite driver is only linked into builds made with `-tags sqlite`, so
+// Mongo-only deployments (including Netlify) don't carry it.
+import _ "modernc.org/sqlite"
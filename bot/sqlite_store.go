@@ -0,0 +1,446 @@
+package bot
+
+import (
+	"context"
+	"database/sql"
+	"encoding/json"
+	"errors"
+	"fmt"
+	"log"
+	"slices"
+	"strconv"
+	"time"
+)
+
+// sqliteDriverName is registered by sqlite_driver.go (build tag "sqlite").
+const sqliteDriverName = "sqlite"
+
+// sqliteMigrations run in order on connect; PRAGMA user_version records how
+// many of them have already been applied to the database file.
+var sqliteMigrations = []string{
+	`CREATE TABLE IF NOT EXISTS state (
+		id TEXT PRIMARY KEY,
+		updated_at INTEGER NOT NULL,
+		top_n INTEGER NOT NULL,
+		convert TEXT NOT NULL,
+		ids TEXT NOT NULL,
+		coins TEXT NOT NULL
+	)`,
+	`CREATE TABLE IF NOT EXISTS history (
+		id INTEGER PRIMARY KEY AUTOINCREMENT,
+		created_at INTEGER NOT NULL,
+		top_n INTEGER NOT NULL,
+		convert TEXT NOT NULL,
+		new_coin_ids TEXT NOT NULL,
+		text TEXT NOT NULL,
+		mentioned_coins TEXT NOT NULL,
+		telegram_message_id INTEGER
+	)`,
+	`CREATE INDEX IF NOT EXISTS history_created_at ON history (created_at DESC)`,
+	`ALTER TABLE state ADD COLUMN sort TEXT NOT NULL DEFAULT 'market_cap'`,
+	`CREATE TABLE IF NOT EXISTS ath (
+		id INTEGER PRIMARY KEY,
+		max_market_cap REAL NOT NULL,
+		achieved_at INTEGER NOT NULL
+	)`,
+	`ALTER TABLE state ADD COLUMN provider TEXT NOT NULL DEFAULT 'cmc'`,
+	`ALTER TABLE history ADD COLUMN provider TEXT NOT NULL DEFAULT 'cmc'`,
+	`ALTER TABLE state ADD COLUMN credits_used INTEGER NOT NULL DEFAULT 0`,
+	`ALTER TABLE history ADD COLUMN credits_used INTEGER NOT NULL DEFAULT 0`,
+	`ALTER TABLE history ADD COLUMN telegram_message_ids TEXT NOT NULL DEFAULT '[]'`,
+	`CREATE TABLE IF NOT EXISTS raw_listings (
+		id INTEGER PRIMARY KEY AUTOINCREMENT,
+		fetched_at INTEGER NOT NULL,
+		start INTEGER NOT NULL,
+		"limit" INTEGER NOT NULL,
+		status TEXT NOT NULL,
+		data TEXT NOT NULL
+	)`,
+	`CREATE TABLE IF NOT EXISTS snapshots (
+		id INTEGER PRIMARY KEY AUTOINCREMENT,
+		created_at INTEGER NOT NULL,
+		top_n INTEGER NOT NULL,
+		convert TEXT NOT NULL,
+		provider TEXT NOT NULL,
+		coins TEXT NOT NULL
+	)`,
+	`CREATE INDEX IF NOT EXISTS snapshots_created_at ON snapshots (created_at DESC)`,
+	`ALTER TABLE state ADD COLUMN tenure TEXT NOT NULL DEFAULT '{}'`,
+	`ALTER TABLE history ADD COLUMN kind TEXT NOT NULL DEFAULT ''`,
+	`ALTER TABLE history ADD COLUMN forced INTEGER NOT NULL DEFAULT 0`,
+	`ALTER TABLE history ADD COLUMN mastodon_status_id TEXT NOT NULL DEFAULT ''`,
+	`ALTER TABLE state ADD COLUMN watchlist_ids TEXT NOT NULL DEFAULT '[]'`,
+	`ALTER TABLE history ADD COLUMN exited_coin_ids TEXT NOT NULL DEFAULT '[]'`,
+	`CREATE TABLE IF NOT EXISTS run_metrics (
+		id INTEGER PRIMARY KEY AUTOINCREMENT,
+		created_at INTEGER NOT NULL,
+		coins_fetched INTEGER NOT NULL,
+		new_coins INTEGER NOT NULL,
+		exited_coins INTEGER NOT NULL,
+		text_source TEXT NOT NULL,
+		duration_ms INTEGER NOT NULL
+	)`,
+	`ALTER TABLE history ADD COLUMN ai_model_used TEXT NOT NULL DEFAULT ''`,
+	`ALTER TABLE state ADD COLUMN last_run_at INTEGER NOT NULL DEFAULT 0`,
+	`ALTER TABLE run_metrics ADD COLUMN error TEXT NOT NULL DEFAULT ''`,
+	`ALTER TABLE state ADD COLUMN tenure_left_at TEXT NOT NULL DEFAULT '{}'`,
+}
+
+// SqliteStore mirrors the Mongo state and history documents in two tables.
+// Timestamps are stored as unix nanoseconds and coin lists as JSON text.
+type SqliteStore struct {
+	db *sql.DB
+}
+
+var _ StateStore = (*SqliteStore)(nil)
+
+// errSqliteNotCompiledIn is returned for BACKEND=sqlite by a binary built
+// without the sqlite tag.
+var errSqliteNotCompiledIn = errors.New("sqlite backend is not compiled in; rebuild with -tags sqlite")
+
+// sqliteCompiledIn reports whether sqlite_driver.go registered the driver.
+func sqliteCompiledIn() bool {
+	return slices.Contains(sql.Drivers(), sqliteDriverName)
+}
+
+func OpenSqliteStore(ctx context.Context, path string) (*SqliteStore, error) {
+	if !sqliteCompiledIn() {
+		return nil, errSqliteNotCompiledIn
+	}
+	db, err := sql.Open(sqliteDriverName, path)
+	if err != nil {
+		return nil, err
+	}
+	if err := migrateSqlite(ctx, db); err != nil {
+		db.Close()
+		return nil, fmt.Errorf("sqlite migrations failed: %w", err)
+	}
+	log.Printf("[SqliteStore] opened %s", path)
+	return &SqliteStore{db: db}, nil
+}
+
+func migrateSqlite(ctx context.Context, db *sql.DB) error {
+	var version int
+	if err := db.QueryRowContext(ctx, "PRAGMA user_version").Scan(&version); err != nil {
+		return err
+	}
+	for i := version; i < len(sqliteMigrations); i++ {
+		if _, err := db.ExecContext(ctx, sqliteMigrations[i]); err != nil {
+			return fmt.Errorf("migration %d: %w", i+1, err)
+		}
+		if _, err := db.ExecContext(ctx, fmt.Sprintf("PRAGMA user_version = %d", i+1)); err != nil {
+			return err
+		}
+		log.Printf("[SqliteStore] applied migration %d", i+1)
+	}
+	return nil
+}
+
+func (s *SqliteStore) Close(ctx context.Context) error { return s.db.Close() }
+
+func (s *SqliteStore) LoadState(ctx context.Context) (State, error) {
+	var updatedAt, lastRunAt int64
+	var idsJSON, coinsJSON, tenureJSON, leftAtJSON, watchlistJSON string
+	st := State{}
+	err := s.db.QueryRowContext(ctx, `SELECT updated_at, top_n, convert, sort, provider, credits_used, ids, coins, tenure, tenure_left_at, watchlist_ids, last_run_at FROM state WHERE id = ?`, "top").
+		Scan(&updatedAt, &st.TopN, &st.Convert, &st.Sort, &st.Provider, &st.CreditsUsed, &idsJSON, &coinsJSON, &tenureJSON, &leftAtJSON, &watchlistJSON, &lastRunAt)
+	if errors.Is(err, sql.ErrNoRows) {
+		return State{}, ErrNoState
+	}
+	if err != nil {
+		return State{}, err
+	}
+	st.UpdatedAt = time.Unix(0, updatedAt).UTC()
+	if lastRunAt > 0 {
+		st.LastRunAt = time.Unix(0, lastRunAt).UTC()
+	}
+	if err := json.Unmarshal([]byte(idsJSON), &st.IDs); err != nil {
+		return State{}, fmt.Errorf("decode state ids: %w", err)
+	}
+	if err := json.Unmarshal([]byte(coinsJSON), &st.Coins); err != nil {
+		return State{}, fmt.Errorf("decode state coins: %w", err)
+	}
+	if err := json.Unmarshal([]byte(tenureJSON), &st.Tenure); err != nil {
+		return State{}, fmt.Errorf("decode state tenure: %w", err)
+	}
+	if err := json.Unmarshal([]byte(leftAtJSON), &st.TenureLeftAt); err != nil {
+		return State{}, fmt.Errorf("decode state tenure_left_at: %w", err)
+	}
+	if err := json.Unmarshal([]byte(watchlistJSON), &st.WatchlistIDs); err != nil {
+		return State{}, fmt.Errorf("decode state watchlist_ids: %w", err)
+	}
+	return st, nil
+}
+
+func (s *SqliteStore) WriteState(ctx context.Context, st State) error {
+	idsJSON, _ := json.Marshal(coinIDs(st.Coins))
+	coinsJSON, _ := json.Marshal(st.Coins)
+	tenureJSON, _ := json.Marshal(st.Tenure)
+	if st.Tenure == nil {
+		tenureJSON = []byte("{}")
+	}
+	leftAtJSON, _ := json.Marshal(st.TenureLeftAt)
+	if st.TenureLeftAt == nil {
+		leftAtJSON = []byte("{}")
+	}
+	watchlistJSON, _ := json.Marshal(orEmpty(st.WatchlistIDs))
+	now := time.Now().UTC().UnixNano()
+	_, err := s.db.ExecContext(ctx, `INSERT INTO state (id, updated_at, top_n, convert, sort, provider, credits_used, ids, coins, tenure, tenure_left_at, watchlist_ids, last_run_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+		ON CONFLICT (id) DO UPDATE SET updated_at = excluded.updated_at, top_n = excluded.top_n, convert = excluded.convert, sort = excluded.sort, provider = excluded.provider, credits_used = excluded.credits_used, ids = excluded.ids, coins = excluded.coins, tenure = excluded.tenure, tenure_left_at = excluded.tenure_left_at, watchlist_ids = excluded.watchlist_ids, last_run_at = excluded.last_run_at`,
+		"top", now, st.TopN, st.Convert, st.Sort, stateProvider(st), st.CreditsUsed, string(idsJSON), string(coinsJSON), string(tenureJSON), string(leftAtJSON), string(watchlistJSON), now)
+	return err
+}
+
+func (s *SqliteStore) TouchState(ctx context.Context, at time.Time) error {
+	_, err := s.db.ExecContext(ctx, `UPDATE state SET last_run_at = ? WHERE id = ?`, at.UTC().UnixNano(), "top")
+	return err
+}
+
+func (s *SqliteStore) InsertHistory(ctx context.Context, doc HistoryDoc) error {
+	newIDsJSON, _ := json.Marshal(doc.NewCoinIDs)
+	mentionedJSON, _ := json.Marshal(doc.MentionedCoins)
+	msgIDsJSON, _ := json.Marshal(orEmpty(doc.TelegramMessageIDs))
+	exitedJSON, _ := json.Marshal(orEmpty(doc.ExitedCoinIDs))
+	provider := doc.Provider
+	if provider == "" {
+		provider = providerCMC
+	}
+	_, err := s.db.ExecContext(ctx, `INSERT INTO history (created_at, top_n, convert, new_coin_ids, text, mentioned_coins, telegram_message_id, telegram_message_ids, provider, credits_used, kind, forced, mastodon_status_id, exited_coin_ids, ai_model_used) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)`,
+		doc.CreatedAt.UTC().UnixNano(), doc.TopN, doc.Convert, string(newIDsJSON), doc.Text, string(mentionedJSON), doc.TelegramMessageID, string(msgIDsJSON), provider, doc.CreditsUsed, doc.Kind, doc.Forced, doc.MastodonStatusID, string(exitedJSON), doc.AIModelUsed)
+	return err
+}
+
+func (s *SqliteStore) LoadRecentPosts(ctx context.Context, limit int) ([]RecentPost, error) {
+	docs, err := s.queryHistory(ctx, `ORDER BY created_at DESC LIMIT ?`, limit)
+	if err != nil {
+		return nil, err
+	}
+	out := make([]RecentPost, 0, len(docs))
+	for _, d := range docs {
+		out = append(out, recentPostFromHistory(d))
+	}
+	return out, nil
+}
+
+func (s *SqliteStore) LatestHistory(ctx context.Context) (HistoryDoc, error) {
+	docs, err := s.queryHistory(ctx, `WHERE kind != ? ORDER BY created_at DESC LIMIT 1`, HistoryKindDigest)
+	if err != nil {
+		return HistoryDoc{}, err
+	}
+	if len(docs) == 0 {
+		return HistoryDoc{}, ErrNoHistory
+	}
+	return docs[0], nil
+}
+
+func (s *SqliteStore) ListHistory(ctx context.Context, limit int) ([]HistoryDoc, error) {
+	return s.queryHistory(ctx, `ORDER BY created_at DESC LIMIT ?`, limit)
+}
+
+func (s *SqliteStore) LastMentioned(ctx context.Context, ids []int64) (map[int64]time.Time, error) {
+	out := map[int64]time.Time{}
+	if len(ids) == 0 {
+		return out, nil
+	}
+	idsJSON, _ := json.Marshal(ids)
+	rows, err := s.db.QueryContext(ctx, `SELECT json_extract(c.value, '$.id') AS coin_id, MAX(h.created_at) FROM history h, json_each(h.mentioned_coins) c
+		WHERE h.kind != ? AND coin_id IN (SELECT value FROM json_each(?)) GROUP BY coin_id`, HistoryKindDigest, string(idsJSON))
+	if err != nil {
+		return nil, err
+	}
+	defer rows.Close()
+	for rows.Next() {
+		var id, createdAt int64
+		if err := rows.Scan(&id, &createdAt); err != nil {
+			return nil, err
+		}
+		out[id] = time.Unix(0, createdAt).UTC()
+	}
+	return out, rows.Err()
+}
+
+func (s *SqliteStore) AnnouncedSince(ctx context.Context, since time.Time) (map[int64]struct{}, error) {
+	rows, err := s.db.QueryContext(ctx, `SELECT c.value FROM history h, json_each(h.new_coin_ids) c WHERE h.forced = 0 AND h.created_at >= ?
+		UNION SELECT c.value FROM history h, json_each(h.exited_coin_ids) c WHERE h.created_at >= ?`, since.UnixNano(), since.UnixNano())
+	if err != nil {
+		return nil, err
+	}
+	defer rows.Close()
+	out := map[int64]struct{}{}
+	for rows.Next() {
+		var id int64
+		if err := rows.Scan(&id); err != nil {
+			return nil, err
+		}
+		out[id] = struct{}{}
+	}
+	return out, rows.Err()
+}
+
+func (s *SqliteStore) LastEntered(ctx context.Context, ids []int64) (map[int64]time.Time, error) {
+	out := map[int64]time.Time{}
+	if len(ids) == 0 {
+		return out, nil
+	}
+	idsJSON, _ := json.Marshal(ids)
+	rows, err := s.db.QueryContext(ctx, `SELECT c.value AS coin_id, MAX(h.created_at) FROM history h, json_each(h.new_coin_ids) c
+		WHERE h.forced = 0 AND coin_id IN (SELECT value FROM json_each(?)) GROUP BY coin_id`, string(idsJSON))
+	if err != nil {
+		return nil, err
+	}
+	defer rows.Close()
+	for rows.Next() {
+		var id, createdAt int64
+		if err := rows.Scan(&id, &createdAt); err != nil {
+			return nil, err
+		}
+		out[id] = time.Unix(0, createdAt).UTC()
+	}
+	return out, rows.Err()
+}
+
+func (s *SqliteStore) MentionedSince(ctx context.Context, since time.Time) (map[int64]struct{}, error) {
+	rows, err := s.db.QueryContext(ctx, `SELECT DISTINCT json_extract(c.value, '$.id') FROM history h, json_each(h.mentioned_coins) c WHERE h.kind != ? AND h.created_at >= ?`, HistoryKindDigest, since.UnixNano())
+	if err != nil {
+		return nil, err
+	}
+	defer rows.Close()
+	out := map[int64]struct{}{}
+	for rows.Next() {
+		var id int64
+		if err := rows.Scan(&id); err != nil {
+			return nil, err
+		}
+		out[id] = struct{}{}
+	}
+	return out, rows.Err()
+}
+
+func (s *SqliteStore) LoadATHs(ctx context.Context, ids []int64) (map[int64]ATHRecord, error) {
+	out := map[int64]ATHRecord{}
+	if len(ids) == 0 {
+		return out, nil
+	}
+	idsJSON, _ := json.Marshal(ids)
+	rows, err := s.db.QueryContext(ctx, `SELECT id, max_market_cap, achieved_at FROM ath WHERE id IN (SELECT value FROM json_each(?))`, string(idsJSON))
+	if err != nil {
+		return nil, err
+	}
+	defer rows.Close()
+	for rows.Next() {
+		var r ATHRecord
+		var achievedAt int64
+		if err := rows.Scan(&r.ID, &r.MaxMarketCap, &achievedAt); err != nil {
+			return nil, err
+		}
+		r.AchievedAt = time.Unix(0, achievedAt).UTC()
+		out[r.ID] = r
+	}
+	return out, rows.Err()
+}
+
+func (s *SqliteStore) UpsertATHs(ctx context.Context, records []ATHRecord) error {
+	for _, r := range records {
+		_, err := s.db.ExecContext(ctx, `INSERT INTO ath (id, max_market_cap, achieved_at) VALUES (?, ?, ?)
+			ON CONFLICT (id) DO UPDATE SET max_market_cap = excluded.max_market_cap, achieved_at = excluded.achieved_at`,
+			r.ID, r.MaxMarketCap, r.AchievedAt.UTC().UnixNano())
+		if err != nil {
+			return err
+		}
+	}
+	return nil
+}
+
+func (s *SqliteStore) InsertRunMetrics(ctx context.Context, doc RunMetricsDoc) error {
+	_, err := s.db.ExecContext(ctx, `INSERT INTO run_metrics (created_at, coins_fetched, new_coins, exited_coins, text_source, duration_ms, error) VALUES (?, ?, ?, ?, ?, ?, ?)`,
+		doc.CreatedAt.UTC().UnixNano(), doc.CoinsFetched, doc.NewCoins, doc.ExitedCoins, string(doc.TextSource), doc.DurationMs, doc.Error)
+	return err
+}
+
+func (s *SqliteStore) InsertRawListing(ctx context.Context, raw RawListing) error {
+	_, err := s.db.ExecContext(ctx, `INSERT INTO raw_listings (fetched_at, start, "limit", status, data) VALUES (?, ?, ?, ?, ?)`,
+		raw.FetchedAt.UTC().UnixNano(), raw.Start, raw.Limit, string(orNull(raw.Status)), string(orNull(raw.Data)))
+	return err
+}
+
+func (s *SqliteStore) InsertSnapshot(ctx context.Context, snap Snapshot) error {
+	coinsJSON, _ := json.Marshal(orEmpty(snap.Coins))
+	_, err := s.db.ExecContext(ctx, `INSERT INTO snapshots (created_at, top_n, convert, provider, coins) VALUES (?, ?, ?, ?, ?)`,
+		snap.CreatedAt.UTC().UnixNano(), snap.TopN, snap.Convert, snap.Provider, string(coinsJSON))
+	return err
+}
+
+func (s *SqliteStore) DeleteSnapshotsBefore(ctx context.Context, cutoff time.Time) (int64, error) {
+	res, err := s.db.ExecContext(ctx, `DELETE FROM snapshots WHERE created_at < ?`, cutoff.UTC().UnixNano())
+	if err != nil {
+		return 0, err
+	}
+	return res.RowsAffected()
+}
+
+func (s *SqliteStore) LoadSnapshotAt(ctx context.Context, ts time.Time) (Snapshot, error) {
+	return s.loadSnapshot(ctx, `WHERE created_at <= ? ORDER BY created_at DESC LIMIT 1`, ts.UTC().UnixNano())
+}
+
+func (s *SqliteStore) LoadOldestSnapshot(ctx context.Context) (Snapshot, error) {
+	return s.loadSnapshot(ctx, `ORDER BY created_at ASC LIMIT 1`)
+}
+
+// loadSnapshot selects one snapshot row with the given WHERE/ORDER/LIMIT suffix.
+func (s *SqliteStore) loadSnapshot(ctx context.Context, suffix string, args ...any) (Snapshot, error) {
+	var createdAt int64
+	var coinsJSON string
+	snap := Snapshot{}
+	err := s.db.QueryRowContext(ctx, `SELECT created_at, top_n, convert, provider, coins FROM snapshots `+suffix, args...).
+		Scan(&createdAt, &snap.TopN, &snap.Convert, &snap.Provider, &coinsJSON)
+	if errors.Is(err, sql.ErrNoRows) {
+		return Snapshot{}, ErrNoSnapshot
+	}
+	if err != nil {
+		return Snapshot{}, err
+	}
+	snap.CreatedAt = time.Unix(0, createdAt).UTC()
+	if err := json.Unmarshal([]byte(coinsJSON), &snap.Coins); err != nil {
+		return Snapshot{}, fmt.Errorf("decode snapshot coins: %w", err)
+	}
+	return snap, nil
+}
+
+// queryHistory selects history rows with the given WHERE/ORDER/LIMIT suffix.
+func (s *SqliteStore) queryHistory(ctx context.Context, suffix string, args ...any) ([]HistoryDoc, error) {
+	rows, err := s.db.QueryContext(ctx, `SELECT id, created_at, top_n, convert, new_coin_ids, text, mentioned_coins, telegram_message_id, telegram_message_ids, provider, credits_used, kind, forced, mastodon_status_id, exited_coin_ids, ai_model_used FROM history `+suffix, args...)
+	if err != nil {
+		return nil, err
+	}
+	defer rows.Close()
+	out := []HistoryDoc{}
+	for rows.Next() {
+		var d HistoryDoc
+		var rowID, createdAt int64
+		var newIDsJSON, mentionedJSON, msgIDsJSON, exitedJSON string
+		var msgID sql.NullInt64
+		if err := rows.Scan(&rowID, &createdAt, &d.TopN, &d.Convert, &newIDsJSON, &d.Text, &mentionedJSON, &msgID, &msgIDsJSON, &d.Provider, &d.CreditsUsed, &d.Kind, &d.Forced, &d.MastodonStatusID, &exitedJSON, &d.AIModelUsed); err != nil {
+			return nil, err
+		}
+		d.ID = strconv.FormatInt(rowID, 10)
+		d.CreatedAt = time.Unix(0, createdAt).UTC()
+		if err := json.Unmarshal([]byte(newIDsJSON), &d.NewCoinIDs); err != nil {
+			return nil, fmt.Errorf("decode history new_coin_ids: %w", err)
+		}
+		if err := json.Unmarshal([]byte(mentionedJSON), &d.MentionedCoins); err != nil {
+			return nil, fmt.Errorf("decode history mentioned_coins: %w", err)
+		}
+		if err := json.Unmarshal([]byte(msgIDsJSON), &d.TelegramMessageIDs); err != nil {
+			return nil, fmt.Errorf("decode history telegram_message_ids: %w", err)
+		}
+		if err := json.Unmarshal([]byte(exitedJSON), &d.ExitedCoinIDs); err != nil {
+			return nil, fmt.Errorf("decode history exited_coin_ids: %w", err)
+		}
+		if msgID.Valid {
+			v := msgID.Int64
+			d.TelegramMessageID = &v
+		}
+		out = append(out, d)
+	}
+	return out, rows.Err()
+}
@@ -0,0 +1,170 @@
+//go:build sqlite
+
+package bot
+
+import (
+	"context"
+	"database/sql"
+	"fmt"
+	"path/filepath"
+	"testing"
+	"time"
+)
+
+// createSqliteAtVersion writes a database file with only the first version
+// migrations applied, as an older build of the bot would have left it.
+func createSqliteAtVersion(t *testing.T, version int) string {
+	t.Helper()
+	path := filepath.Join(t.TempDir(), "bot.db")
+	db, err := sql.Open(sqliteDriverName, path)
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer db.Close()
+	for _, stmt := range sqliteMigrations[:version] {
+		if _, err := db.Exec(stmt); err != nil {
+			t.Fatalf("migration %q: %v", stmt, err)
+		}
+	}
+	if _, err := db.Exec(fmt.Sprintf("PRAGMA user_version = %d", version)); err != nil {
+		t.Fatal(err)
+	}
+	return path
+}
+
+func TestSqliteStoreUpgradesFromPreviousVersion(t *testing.T) {
+	ctx := context.Background()
+	path := createSqliteAtVersion(t, len(sqliteMigrations)-1)
+	store, err := OpenSqliteStore(ctx, path)
+	if err != nil {
+		t.Fatalf("OpenSqliteStore error: %v", err)
+	}
+	defer store.Close(ctx)
+
+	if err := store.InsertHistory(ctx, HistoryDoc{CreatedAt: time.Now().UTC(), TopN: 100, Convert: "USD", NewCoinIDs: []int64{20947}, Text: "🚀 SUI", AIModelUsed: "gpt-4o"}); err != nil {
+		t.Fatalf("InsertHistory after upgrade: %v", err)
+	}
+	docs, err := store.ListHistory(ctx, 1)
+	if err != nil || len(docs) != 1 || docs[0].AIModelUsed != "gpt-4o" {
+		t.Fatalf("unexpected history after upgrade: %+v %v", docs, err)
+	}
+}
+
+func TestSqliteStoreMigratesFreshDatabase(t *testing.T) {
+	ctx := context.Background()
+	store, err := OpenSqliteStore(ctx, createSqliteAtVersion(t, 0))
+	if err != nil {
+		t.Fatalf("OpenSqliteStore error: %v", err)
+	}
+	defer store.Close(ctx)
+	var version int
+	if err := store.db.QueryRowContext(ctx, "PRAGMA user_version").Scan(&version); err != nil || version != len(sqliteMigrations) {
+		t.Fatalf("user_version = %d (%v), want %d", version, err, len(sqliteMigrations))
+	}
+	if _, err := store.LoadState(ctx); err != ErrNoState {
+		t.Fatalf("expected ErrNoState on a fresh database, got %v", err)
+	}
+}
+
+func TestSqliteStoreRoundTripsStateAndHistory(t *testing.T) {
+	ctx := context.Background()
+	store, err := OpenSqliteStore(ctx, filepath.Join(t.TempDir(), "bot.db"))
+	if err != nil {
+		t.Fatalf("OpenSqliteStore error: %v", err)
+	}
+	defer store.Close(ctx)
+
+	suiCap := 4.2e9
+	st := State{TopN: 100, Convert: "USD", Sort: "market_cap", Provider: providerCMC, CreditsUsed: 2,
+		Coins: []Coin{{ID: 1, Name: "Bitcoin", Symbol: "BTC", Rank: 1}, {ID: 20947, Name: "Sui", Symbol: "SUI", Rank: 42, MarketCap: &suiCap}},
+		Tenure: map[int64]int64{1: 7, 20947: 1}, WatchlistIDs: []int64{74}}
+	if err := store.WriteState(ctx, st); err != nil {
+		t.Fatalf("WriteState error: %v", err)
+	}
+	got, err := store.LoadState(ctx)
+	if err != nil {
+		t.Fatalf("LoadState error: %v", err)
+	}
+	if got.TopN != 100 || got.Convert != "USD" || len(got.Coins) != 2 || *got.Coins[1].MarketCap != suiCap || got.Tenure[1] != 7 || len(got.IDs) != 2 || got.WatchlistIDs[0] != 74 || got.UpdatedAt.IsZero() {
+		t.Fatalf("unexpected state: %+v", got)
+	}
+
+	msgID := int64(99)
+	for i, text := range []string{"first", "second"} {
+		doc := HistoryDoc{CreatedAt: time.Now().UTC().Add(time.Duration(i) * time.Second), TopN: 100, Convert: "USD", NewCoinIDs: []int64{20947}, Text: text,
+			MentionedCoins: []Coin{{ID: 20947, Symbol: "SUI"}}, TelegramMessageID: &msgID, TelegramMessageIDs: []*int64{&msgID}, ExitedCoinIDs: []int64{74}}
+		if err := store.InsertHistory(ctx, doc); err != nil {
+			t.Fatalf("InsertHistory error: %v", err)
+		}
+	}
+	docs, err := store.ListHistory(ctx, 10)
+	if err != nil || len(docs) != 2 {
+		t.Fatalf("ListHistory: %d doc(s), %v", len(docs), err)
+	}
+	if docs[0].Text != "second" || docs[0].ID == "" || *docs[0].TelegramMessageID != 99 || docs[0].MentionedCoins[0].Symbol != "SUI" || docs[0].ExitedCoinIDs[0] != 74 {
+		t.Fatalf("unexpected newest history doc: %+v", docs[0])
+	}
+}
+
+func TestSqliteStoreTouchStateOnlyMovesLastRunAt(t *testing.T) {
+	ctx := context.Background()
+	store, err := OpenSqliteStore(ctx, filepath.Join(t.TempDir(), "bot.db"))
+	if err != nil {
+		t.Fatalf("OpenSqliteStore error: %v", err)
+	}
+	defer store.Close(ctx)
+	if err := store.WriteState(ctx, State{TopN: 1, Convert: "USD", Coins: []Coin{{ID: 1, Symbol: "BTC", Rank: 1}}}); err != nil {
+		t.Fatal(err)
+	}
+	written, _ := store.LoadState(ctx)
+	at := written.UpdatedAt.Add(5 * time.Hour)
+	if err := store.TouchState(ctx, at); err != nil {
+		t.Fatalf("TouchState error: %v", err)
+	}
+	got, _ := store.LoadState(ctx)
+	if !got.LastRunAt.Equal(at) || !got.UpdatedAt.Equal(written.UpdatedAt) || len(got.Coins) != 1 {
+		t.Fatalf("unexpected state after touch: %+v", got)
+	}
+}
+
+func TestSqliteStoreSkipsDigestPostsInMentionLookups(t *testing.T) {
+	ctx := context.Background()
+	store, err := OpenSqliteStore(ctx, filepath.Join(t.TempDir(), "bot.db"))
+	if err != nil {
+		t.Fatalf("OpenSqliteStore error: %v", err)
+	}
+	defer store.Close(ctx)
+
+	postedAt := time.Date(2026, 3, 1, 12, 0, 0, 0, time.UTC)
+	for _, doc := range []HistoryDoc{
+		{CreatedAt: postedAt, Text: "entrants", NewCoinIDs: []int64{5}, MentionedCoins: []Coin{{ID: 5, Symbol: "SHIB"}}},
+		{CreatedAt: postedAt.Add(time.Hour), Text: "digest", Kind: HistoryKindDigest, NewCoinIDs: []int64{}, MentionedCoins: []Coin{{ID: 7, Symbol: "TON"}}},
+	} {
+		if err := store.InsertHistory(ctx, doc); err != nil {
+			t.Fatalf("InsertHistory error: %v", err)
+		}
+	}
+
+	seen, err := store.LastMentioned(ctx, []int64{5, 7})
+	if err != nil || len(seen) != 1 || !seen[5].Equal(postedAt) {
+		t.Fatalf("LastMentioned: %v (%v)", seen, err)
+	}
+	recent, err := store.MentionedSince(ctx, postedAt.Add(-time.Hour))
+	if _, ok := recent[7]; err != nil || ok || len(recent) != 1 {
+		t.Fatalf("MentionedSince: %v (%v)", recent, err)
+	}
+	last, err := store.LatestHistory(ctx)
+	if err != nil || last.Text != "entrants" {
+		t.Fatalf("LatestHistory: %q (%v)", last.Text, err)
+	}
+}
+
+func TestSqliteStoreUpsertATHsInsertsThenUpdates(t *testing.T) {
+	ctx := context.Background()
+	store, err := OpenSqliteStore(ctx, filepath.Join(t.TempDir(), "bot.db"))
+	if err != nil {
+		t.Fatalf("OpenSqliteStore error: %v", err)
+	}
+	defer store.Close(ctx)
+	checkUpsertATHs(t, store)
+}
@@ -0,0 +1,78 @@
+package bot
+
+import (
+	"context"
+	"log"
+	"net/http"
+	"time"
+)
+
+// defaultMaxStateAgeHours is generous enough that a missed cron tick or a
+// quiet weekend never counts as an outage.
+const defaultMaxStateAgeHours = 72
+
+const defaultResumeTemplate = `⏯ Resuming coverage of the CoinMarketCap Top %top_n% (%convert%) after %gap_hours% hours without updates. Changes from the gap are not announced; new entrants are tracked again from now on.`
+
+// resumeTemplatePath is the template of the --notify-resume message.
+const resumeTemplatePath = "templates/telegram_resume.template.md"
+
+// stateAge returns how long ago the last successful run was and whether that
+// is beyond maxAge. UpdatedAt only moves when the top-N changed, so the
+// LastRunAt heartbeat is used when it is newer. A max age of 0 disables the
+// check, and so does a state with neither timestamp.
+func stateAge(prev State, maxAge time.Duration, now time.Time) (time.Duration, bool) {
+	last := prev.UpdatedAt
+	if prev.LastRunAt.After(last) {
+		last = prev.LastRunAt
+	}
+	if maxAge <= 0 || last.IsZero() {
+		return 0, false
+	}
+	age := now.Sub(last)
+	return age, age > maxAge
+}
+
+// touchState records a successful run so quiet stretches without state
+// writes do not look like an outage. A failure is only logged.
+func touchState(ctx context.Context, store StateStore) {
+	if err := store.TouchState(ctx, time.Now().UTC()); err != nil {
+		log.Printf("[RunOnce] warning: failed to record the run in state: %v", err)
+	}
+}
+
+// resumeCoverage handles a run after an outage: a diff against such an old
+// state would announce every change of the gap as news, so the baseline is
+// overwritten instead. With --notify-resume a short notice is posted first.
+// Like the other non-entrant messages it is not recorded in history.
+func resumeCoverage(ctx context.Context, client *http.Client, cfg Config, opt RunOptions, store StateStore, nextState State, age time.Duration) error {
+	renderCtx := map[string]any{
+		"project_name":  "coinmarketcap_top100_bot",
+		"timestamp_utc": time.Now().UTC().Format(time.RFC3339),
+		"top_n":         cfg.notifyTopN(),
+		"convert":       opt.Convert,
+		"gap_hours":     int64(age.Hours()),
+	}
+	var text string
+	if opt.NotifyResume {
+		text = renderTemplateIn(cfg.TemplatesDir, loadTemplateOrDefault(resumeTemplatePath, defaultResumeTemplate), renderCtx)
+	}
+	if opt.DryRun {
+		if text == "" {
+			return nil
+		}
+		return printDryRun(opt, map[string]any{"resumed": true}, renderCtx, text)
+	}
+	if text != "" {
+		if _, err := sendTelegramMessage(ctx, client, cfg, text, "", telegramParseMode(cfg, opt)); err != nil {
+			log.Printf("[RunOnce] failed to send resume notice: %v", err)
+			return err
+		}
+		log.Printf("[RunOnce] resume notice sent")
+	}
+	if err := store.WriteState(ctx, nextState); err != nil {
+		log.Printf("[RunOnce] failed to write state: %v", err)
+		return err
+	}
+	log.Printf("[RunOnce] baseline replaced with %d current coin(s)", len(nextState.Coins))
+	return nil
+}
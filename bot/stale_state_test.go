@@ -0,0 +1,69 @@
+package bot
+
+import (
+	"context"
+	"slices"
+	"strings"
+	"testing"
+	"time"
+)
+
+func TestRunOnceStateAgeThreshold(t *testing.T) {
+	const listings = `{"data":[
+		{"id":1,"name":"Bitcoin","symbol":"BTC","cmc_rank":1},
+		{"id":2,"name":"Ethereum","symbol":"ETH","cmc_rank":2},
+		{"id":5426,"name":"Solana","symbol":"SOL","cmc_rank":3}]}`
+	newStore := func(age time.Duration) *memoryStore {
+		prev := State{UpdatedAt: time.Now().UTC().Add(-age), TopN: 3, Convert: "USD", IDs: []int64{1, 2, 3}, Coins: []Coin{{ID: 1, Rank: 1}, {ID: 2, Rank: 2}, {ID: 3, Rank: 3}}}
+		return &memoryStore{state: &prev}
+	}
+	cfg := Config{CMCBaseURL: newCMCMockServer(t, listings).URL, TopN: 3, RankChangeThreshold: 10, MaxStateAge: 72 * time.Hour}
+
+	under := newStore(72*time.Hour - time.Minute)
+	out := runDry(t, cfg, RunOptions{Convert: "USD", NotifyResume: true}, listings, under)
+	if !strings.Contains(out, "Solana") || strings.Contains(out, "Resuming coverage") {
+		t.Fatalf("state just under MAX_STATE_AGE_HOURS should post entrants: %q", out)
+	}
+
+	over := newStore(72*time.Hour + time.Minute)
+	out = runDry(t, cfg, RunOptions{Convert: "USD", NotifyResume: true}, listings, over)
+	if strings.Contains(out, "Solana") || !strings.Contains(out, "Resuming coverage of the CoinMarketCap Top 3 (USD) after 72 hours") {
+		t.Fatalf("state just over MAX_STATE_AGE_HOURS should only print the resume notice: %q", out)
+	}
+	if over.stateWrites != 0 {
+		t.Fatalf("dry run must not write state")
+	}
+
+	if err := RunOnceWithStore(context.Background(), cfg, RunOptions{Convert: "USD"}, over); err != nil {
+		t.Fatalf("RunOnceWithStore error: %v", err)
+	}
+	if over.stateWrites != 1 || len(over.history) != 0 || !slices.Equal(over.state.IDs, []int64{1, 2, 5426}) {
+		t.Fatalf("stale state should be re-baselined without a post: %d write(s), %d post(s), ids %v", over.stateWrites, len(over.history), over.state.IDs)
+	}
+}
+
+func TestRunOnceQuietStretchDoesNotLookStale(t *testing.T) {
+	prev := State{UpdatedAt: time.Now().UTC().Add(-100 * time.Hour), LastRunAt: time.Now().UTC().Add(-time.Hour), TopN: 3, Convert: "USD", IDs: []int64{1, 2, 3},
+		Coins: []Coin{{ID: 1, Rank: 1}, {ID: 2, Rank: 2}, {ID: 3, Rank: 3}}}
+	store := &memoryStore{state: &prev}
+	cfg := Config{TopN: 3, RankChangeThreshold: 10, MaxStateAge: 72 * time.Hour}
+
+	cfg.CMCBaseURL = newCMCMockServer(t, `{"data":[
+		{"id":1,"name":"Bitcoin","symbol":"BTC","cmc_rank":1},
+		{"id":2,"name":"Ethereum","symbol":"ETH","cmc_rank":2},
+		{"id":3,"name":"Tether","symbol":"USDT","cmc_rank":3}]}`).URL
+	if err := RunOnceWithStore(context.Background(), cfg, RunOptions{Convert: "USD"}, store); err != nil {
+		t.Fatalf("RunOnceWithStore error: %v", err)
+	}
+	if store.stateWrites != 0 || store.stateTouches != 1 || time.Since(store.state.LastRunAt) > time.Minute {
+		t.Fatalf("an unchanged run should only refresh last_run_at: %d write(s), %d touch(es), last_run_at %s", store.stateWrites, store.stateTouches, store.state.LastRunAt)
+	}
+
+	out := runDry(t, cfg, RunOptions{Convert: "USD"}, `{"data":[
+		{"id":1,"name":"Bitcoin","symbol":"BTC","cmc_rank":1},
+		{"id":2,"name":"Ethereum","symbol":"ETH","cmc_rank":2},
+		{"id":5426,"name":"Solana","symbol":"SOL","cmc_rank":3}]}`, store)
+	if !strings.Contains(out, "Solana") {
+		t.Fatalf("an entrant after a quiet stretch should be posted, not re-baselined: %q", out)
+	}
+}
@@ -0,0 +1,185 @@
+package bot
+
+import (
+	"context"
+	"encoding/json"
+	"errors"
+	"fmt"
+	"log"
+	"time"
+)
+
+// Backend selects where state and history are persisted.
+type Backend string
+
+const (
+	BackendMongo  Backend = "mongodb"
+	BackendSqlite Backend = "sqlite"
+)
+
+var (
+	// ErrNoState is returned by LoadState before the first baseline is written.
+	ErrNoState = errors.New("no previous state")
+	// ErrNoHistory is returned by LatestHistory when nothing was published yet.
+	ErrNoHistory = errors.New("no history")
+)
+
+// State is the previously stored top-N snapshot. On write, UpdatedAt and IDs
+// are derived by the store from the current time and Coins. CreditsUsed is the
+// CMC credit usage of the run that wrote it. Tenure maps coin id to the number
+// of consecutive state writes the coin has been part of, and TenureLeftAt maps
+// each departed coin still in Tenure to when it left. WatchlistIDs are the
+// coins fetched just below the top-N (WATCHLIST_MARGIN), in listing order.
+// LastRunAt is set by every write and refreshed by TouchState after each
+// successful run, including runs that changed nothing.
+type State struct {
+	UpdatedAt    time.Time           `json:"updated_at"`
+	TopN         int64               `json:"top_n"`
+	Convert      string              `json:"convert"`
+	Sort         string              `json:"sort,omitempty"`
+	Provider     string              `json:"provider,omitempty"`
+	CreditsUsed  int64               `json:"credits_used"`
+	IDs          []int64             `json:"ids"`
+	Coins        []Coin              `json:"coins"`
+	Tenure       map[int64]int64     `json:"tenure,omitempty"`
+	TenureLeftAt map[int64]time.Time `json:"tenure_left_at,omitempty"`
+	WatchlistIDs []int64             `json:"watchlist_ids,omitempty"`
+	LastRunAt    time.Time           `json:"last_run_at,omitempty"`
+}
+
+// StateStore persists the top-N snapshot and the history of published posts.
+// RunOnceWithStore only touches storage through this interface.
+type StateStore interface {
+	LoadState(ctx context.Context) (State, error)
+	WriteState(ctx context.Context, st State) error
+	// TouchState sets LastRunAt of the stored state to at without changing
+	// anything else; it is a no-op before the first baseline.
+	TouchState(ctx context.Context, at time.Time) error
+	InsertHistory(ctx context.Context, doc HistoryDoc) error
+	LoadRecentPosts(ctx context.Context, limit int) ([]RecentPost, error)
+	// LatestHistory returns the newest post that is not a digest, or
+	// ErrNoHistory.
+	LatestHistory(ctx context.Context) (HistoryDoc, error)
+	// ListHistory returns up to limit published posts, newest first, with
+	// HistoryDoc.ID set.
+	ListHistory(ctx context.Context, limit int) ([]HistoryDoc, error)
+	// LastMentioned returns, for each of ids that any history post other
+	// than a digest mentions, the created_at of its latest such post, in a
+	// single query.
+	LastMentioned(ctx context.Context, ids []int64) (map[int64]time.Time, error)
+	// MentionedSince returns the ids of coins mentioned by posts created at
+	// or after since, digests aside.
+	MentionedSince(ctx context.Context, since time.Time) (map[int64]struct{}, error)
+	// AnnouncedSince returns the ids of coins that posts created at or after
+	// since announced as entrants (forced posts aside) or as exits.
+	AnnouncedSince(ctx context.Context, since time.Time) (map[int64]struct{}, error)
+	// LastEntered returns, for each of ids announced as a new entrant by a
+	// post that was not forced, the created_at of the latest such post.
+	LastEntered(ctx context.Context, ids []int64) (map[int64]time.Time, error)
+	LoadATHs(ctx context.Context, ids []int64) (map[int64]ATHRecord, error)
+	UpsertATHs(ctx context.Context, records []ATHRecord) error
+	// InsertRunMetrics appends the summary of one completed run.
+	InsertRunMetrics(ctx context.Context, doc RunMetricsDoc) error
+	// InsertRawListing keeps a listings response for auditing (SAVE_RAW).
+	InsertRawListing(ctx context.Context, raw RawListing) error
+	// Snapshots of fetched listings (RAW_SNAPSHOT_ENABLED).
+	InsertSnapshot(ctx context.Context, snap Snapshot) error
+	DeleteSnapshotsBefore(ctx context.Context, cutoff time.Time) (int64, error)
+	// LoadSnapshotAt returns the latest snapshot taken at or before ts, or
+	// ErrNoSnapshot.
+	LoadSnapshotAt(ctx context.Context, ts time.Time) (Snapshot, error)
+	// LoadOldestSnapshot returns the earliest stored snapshot, or
+	// ErrNoSnapshot.
+	LoadOldestSnapshot(ctx context.Context) (Snapshot, error)
+	Close(ctx context.Context) error
+}
+
+// RunMetricsDoc summarizes one run for dashboards. TextSource is empty when
+// the run posted nothing; Error holds the error a failed run returned.
+type RunMetricsDoc struct {
+	CreatedAt    time.Time  `bson:"created_at"`
+	CoinsFetched int        `bson:"coins_fetched"`
+	NewCoins     int        `bson:"new_coins"`
+	ExitedCoins  int        `bson:"exited_coins"`
+	TextSource   TextSource `bson:"text_source,omitempty"`
+	DurationMs   int64      `bson:"duration_ms"`
+	Error        string     `bson:"error,omitempty"`
+}
+
+// RawListing is one CMC listings response as received, reduced to its status
+// and data objects.
+type RawListing struct {
+	FetchedAt time.Time
+	Start     int
+	Limit     int
+	Status    json.RawMessage
+	Data      json.RawMessage
+}
+
+// orNull stands in JSON null for a field missing from the response.
+func orNull(m json.RawMessage) json.RawMessage {
+	if len(m) == 0 {
+		return json.RawMessage("null")
+	}
+	return m
+}
+
+// saveRawListings stores the raw pages of this run. Auditing is secondary, so
+// a failure is only logged.
+func saveRawListings(ctx context.Context, store StateStore, raws []RawListing) {
+	for _, raw := range raws {
+		if err := store.InsertRawListing(ctx, raw); err != nil {
+			log.Printf("[RunOnce] warning: failed to save raw listings page start=%d: %v", raw.Start, err)
+			return
+		}
+	}
+	if len(raws) > 0 {
+		log.Printf("[RunOnce] saved %d raw listings page(s)", len(raws))
+	}
+}
+
+// saveRunMetrics records the run summary. Like auditing it is secondary, so a
+// failure is only logged.
+func saveRunMetrics(ctx context.Context, store StateStore, doc RunMetricsDoc) {
+	if err := store.InsertRunMetrics(ctx, doc); err != nil {
+		log.Printf("[RunOnce] warning: failed to save run metrics: %v", err)
+	}
+}
+
+// OpenStateStore connects to the backend selected by cfg.Backend.
+func OpenStateStore(ctx context.Context, cfg Config) (StateStore, error) {
+	switch cfg.Backend {
+	case BackendSqlite:
+		return OpenSqliteStore(ctx, cfg.SqlitePath)
+	case BackendMongo, "":
+		return OpenMongoStore(ctx, cfg)
+	default:
+		return nil, fmt.Errorf("unsupported backend %q", cfg.Backend)
+	}
+}
+
+// checkStateSort rejects diffing against a snapshot taken with a different
+// listings sort; comparing a market-cap top list to a volume one is noise.
+func checkStateSort(prev State, sortBy string) error {
+	prevSort := prev.Sort
+	if prevSort == "" {
+		prevSort = defaultCMCSort
+	}
+	if prevSort != sortBy {
+		return fmt.Errorf("stored state was built with sort=%s but this run uses sort=%s; use a separate database or collection per sort", prevSort, sortBy)
+	}
+	return nil
+}
+
+// stateProvider returns the provider recorded on st; states written before
+// providers were tracked all came from CMC.
+func stateProvider(st State) string {
+	if st.Provider == "" {
+		return providerCMC
+	}
+	return st.Provider
+}
+
+func recentPostFromHistory(d HistoryDoc) RecentPost {
+	return RecentPost{CreatedAtUTC: d.CreatedAt.UTC().Format(time.RFC3339), Text: d.Text, MentionedCoins: d.MentionedCoins}
+}
@@ -0,0 +1,215 @@
+package bot
+
+import (
+	"context"
+	"slices"
+	"time"
+)
+
+// memoryStore is an in-memory StateStore for tests; it records every write.
+type memoryStore struct {
+	state        *State
+	stateWrites  int
+	stateTouches int
+	history      []HistoryDoc
+	aths         map[int64]ATHRecord
+	raws         []RawListing
+	snapshots    []Snapshot
+	runMetrics   []RunMetricsDoc
+	// mentionLookups counts LastMentioned calls.
+	mentionLookups int
+}
+
+var _ StateStore = (*memoryStore)(nil)
+
+func (m *memoryStore) LoadState(ctx context.Context) (State, error) {
+	if m.state == nil {
+		return State{}, ErrNoState
+	}
+	return *m.state, nil
+}
+
+func (m *memoryStore) WriteState(ctx context.Context, st State) error {
+	st.IDs = coinIDs(st.Coins)
+	st.LastRunAt = time.Now().UTC()
+	m.state = &st
+	m.stateWrites++
+	return nil
+}
+
+func (m *memoryStore) TouchState(ctx context.Context, at time.Time) error {
+	if m.state != nil {
+		m.state.LastRunAt = at
+		m.stateTouches++
+	}
+	return nil
+}
+
+func (m *memoryStore) InsertHistory(ctx context.Context, doc HistoryDoc) error {
+	m.history = append(m.history, doc)
+	return nil
+}
+
+func (m *memoryStore) LoadRecentPosts(ctx context.Context, limit int) ([]RecentPost, error) {
+	docs, _ := m.ListHistory(ctx, limit)
+	out := make([]RecentPost, 0, len(docs))
+	for _, d := range docs {
+		out = append(out, recentPostFromHistory(d))
+	}
+	return out, nil
+}
+
+func (m *memoryStore) LatestHistory(ctx context.Context) (HistoryDoc, error) {
+	for i := len(m.history) - 1; i >= 0; i-- {
+		if m.history[i].Kind != HistoryKindDigest {
+			return m.history[i], nil
+		}
+	}
+	return HistoryDoc{}, ErrNoHistory
+}
+
+func (m *memoryStore) ListHistory(ctx context.Context, limit int) ([]HistoryDoc, error) {
+	out := []HistoryDoc{}
+	for i := len(m.history) - 1; i >= 0 && len(out) < limit; i-- {
+		out = append(out, m.history[i])
+	}
+	return out, nil
+}
+
+func (m *memoryStore) LastMentioned(ctx context.Context, ids []int64) (map[int64]time.Time, error) {
+	m.mentionLookups++
+	out := map[int64]time.Time{}
+	for _, d := range m.history {
+		if d.Kind == HistoryKindDigest {
+			continue
+		}
+		for _, c := range d.MentionedCoins {
+			if slices.Contains(ids, c.ID) && d.CreatedAt.After(out[c.ID]) {
+				out[c.ID] = d.CreatedAt
+			}
+		}
+	}
+	return out, nil
+}
+
+func (m *memoryStore) AnnouncedSince(ctx context.Context, since time.Time) (map[int64]struct{}, error) {
+	out := map[int64]struct{}{}
+	for _, d := range m.history {
+		if d.CreatedAt.Before(since) {
+			continue
+		}
+		for _, id := range d.ExitedCoinIDs {
+			out[id] = struct{}{}
+		}
+		if !d.Forced {
+			for _, id := range d.NewCoinIDs {
+				out[id] = struct{}{}
+			}
+		}
+	}
+	return out, nil
+}
+
+func (m *memoryStore) LastEntered(ctx context.Context, ids []int64) (map[int64]time.Time, error) {
+	out := map[int64]time.Time{}
+	for _, d := range m.history {
+		if d.Forced {
+			continue
+		}
+		for _, id := range d.NewCoinIDs {
+			if slices.Contains(ids, id) && d.CreatedAt.After(out[id]) {
+				out[id] = d.CreatedAt
+			}
+		}
+	}
+	return out, nil
+}
+
+func (m *memoryStore) MentionedSince(ctx context.Context, since time.Time) (map[int64]struct{}, error) {
+	out := map[int64]struct{}{}
+	for _, d := range m.history {
+		if d.CreatedAt.Before(since) || d.Kind == HistoryKindDigest {
+			continue
+		}
+		for _, c := range d.MentionedCoins {
+			out[c.ID] = struct{}{}
+		}
+	}
+	return out, nil
+}
+
+func (m *memoryStore) LoadATHs(ctx context.Context, ids []int64) (map[int64]ATHRecord, error) {
+	out := map[int64]ATHRecord{}
+	for _, id := range ids {
+		if r, ok := m.aths[id]; ok {
+			out[id] = r
+		}
+	}
+	return out, nil
+}
+
+func (m *memoryStore) UpsertATHs(ctx context.Context, records []ATHRecord) error {
+	if m.aths == nil {
+		m.aths = map[int64]ATHRecord{}
+	}
+	for _, r := range records {
+		m.aths[r.ID] = r
+	}
+	return nil
+}
+
+func (m *memoryStore) InsertRunMetrics(ctx context.Context, doc RunMetricsDoc) error {
+	m.runMetrics = append(m.runMetrics, doc)
+	return nil
+}
+
+func (m *memoryStore) InsertRawListing(ctx context.Context, raw RawListing) error {
+	m.raws = append(m.raws, raw)
+	return nil
+}
+
+func (m *memoryStore) InsertSnapshot(ctx context.Context, snap Snapshot) error {
+	m.snapshots = append(m.snapshots, snap)
+	return nil
+}
+
+func (m *memoryStore) DeleteSnapshotsBefore(ctx context.Context, cutoff time.Time) (int64, error) {
+	kept := m.snapshots[:0]
+	for _, s := range m.snapshots {
+		if !s.CreatedAt.Before(cutoff) {
+			kept = append(kept, s)
+		}
+	}
+	deleted := int64(len(m.snapshots) - len(kept))
+	m.snapshots = kept
+	return deleted, nil
+}
+
+func (m *memoryStore) LoadSnapshotAt(ctx context.Context, ts time.Time) (Snapshot, error) {
+	found := false
+	var best Snapshot
+	for _, s := range m.snapshots {
+		if !s.CreatedAt.After(ts) && (!found || s.CreatedAt.After(best.CreatedAt)) {
+			best, found = s, true
+		}
+	}
+	if !found {
+		return Snapshot{}, ErrNoSnapshot
+	}
+	return best, nil
+}
+
+func (m *memoryStore) LoadOldestSnapshot(ctx context.Context) (Snapshot, error) {
+	if len(m.snapshots) == 0 {
+		return Snapshot{}, ErrNoSnapshot
+	}
+	oldest := m.snapshots[0]
+	for _, s := range m.snapshots[1:] {
+		if s.CreatedAt.Before(oldest.CreatedAt) {
+			oldest = s
+		}
+	}
+	return oldest, nil
+}
+
+func (m *memoryStore) Close(ctx context.Context) error { return nil }
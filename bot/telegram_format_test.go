@@ -1,6 +1,11 @@
 package bot
 
-import "testing"
+import (
+	"strings"
+	"testing"
+	"time"
+	"unicode/utf8"
+)
 
 func TestFormatTelegramHTML(t *testing.T) {
 	input := "### **Market Context**\nUse <tags> & symbols"
@@ -10,3 +15,130 @@ func TestFormatTelegramHTML(t *testing.T) {
 		t.Fatalf("unexpected output:\nwant: %q\ngot:  %q", want, got)
 	}
 }
+
+func TestFormatTelegramHTMLConvertsLinks(t *testing.T) {
+	got := formatTelegramHTML("• #42 [Shiba Inu](https://coinmarketcap.com/currencies/shiba-inu/) (SHIB) & more")
+	want := `• #42 <a href="https://coinmarketcap.com/currencies/shiba-inu/">Shiba Inu</a> (SHIB) &amp; more`
+	if got != want {
+		t.Fatalf("unexpected output:\nwant: %q\ngot:  %q", want, got)
+	}
+}
+
+func TestCoinCMCPageURL(t *testing.T) {
+	for name, want := range map[string]string{
+		"Bitcoin":            "https://coinmarketcap.com/currencies/bitcoin/",
+		"Shiba Inu":          "https://coinmarketcap.com/currencies/shiba-inu/",
+		"USD Coin (Bridged)": "https://coinmarketcap.com/currencies/usd-coin-bridged/",
+	} {
+		if got := (Coin{Name: name}).CMCPageURL(); got != want {
+			t.Fatalf("%s: got %q want %q", name, got, want)
+		}
+	}
+}
+
+func TestCoinCMCPageURLPrefersListingSlug(t *testing.T) {
+	coins, err := parseCMCListings(strings.NewReader(`{"data":[{"id":3408,"name":"USDC","symbol":"USDC","slug":"usd-coin","cmc_rank":7}]}`), "USD", time.Now())
+	if err != nil {
+		t.Fatalf("parseCMCListings error: %v", err)
+	}
+	if got := withDerivedFields(coins, time.Now())[0].CMCURL; got != "https://coinmarketcap.com/currencies/usd-coin/" {
+		t.Fatalf("expected the CMC slug in the URL, got %q", got)
+	}
+}
+
+func TestPlainTextLinksKeepsNameAndURL(t *testing.T) {
+	text := "• #42 [Shiba Inu](https://coinmarketcap.com/currencies/shiba-inu/) (SHIB)"
+	want := "• #42 Shiba Inu (https://coinmarketcap.com/currencies/shiba-inu/) (SHIB)"
+	if got := plainTextLinks(text); got != want {
+		t.Fatalf("unexpected output:\nwant: %q\ngot:  %q", want, got)
+	}
+	if got := mastodonStatus(text); got != want {
+		t.Fatalf("mastodon status should carry plain links, got %q", got)
+	}
+}
+
+func TestFallbackTemplateLinksCoinNamesWhenParseModeIsSet(t *testing.T) {
+	coins := withDerivedFields([]Coin{{ID: 5994, Name: "Shiba Inu", Symbol: "SHIB", Rank: 42}}, time.Now())
+	ctx := map[string]any{"top_n": 100, "convert": "USD", "debut_coins": coins, "parse_mode": "HTML"}
+	if out := RenderTemplate(defaultFallbackTemplate, ctx); !strings.Contains(out, "[Shiba Inu](https://coinmarketcap.com/currencies/shiba-inu/) (SHIB)") {
+		t.Fatalf("expected a linked name: %q", out)
+	}
+	ctx["parse_mode"] = ""
+	if out := RenderTemplate(defaultFallbackTemplate, ctx); !strings.Contains(out, "• #42 Shiba Inu (SHIB)") {
+		t.Fatalf("expected a plain name without parse mode: %q", out)
+	}
+}
+
+func TestEscapeMarkdownV2EscapesEveryReservedChar(t *testing.T) {
+	for _, r := range "_*[]()~`>#+-=|{}.!\\" {
+		got := escapeMarkdownV2(string(r))
+		if want := "\\" + string(r); got != want {
+			t.Fatalf("escape %q: got %q want %q", r, got, want)
+		}
+	}
+}
+
+func TestEscapeMarkdownV2KeepsLinkSyntax(t *testing.T) {
+	got := escapeMarkdownV2("New: [Shiba-Inu](https://coinmarketcap.com/currencies/shiba-inu/) up 1.5!")
+	want := `New: [Shiba\-Inu](https://coinmarketcap.com/currencies/shiba-inu/) up 1\.5\!`
+	if got != want {
+		t.Fatalf("unexpected output:\nwant: %q\ngot:  %q", want, got)
+	}
+}
+
+func TestEscapeMarkdownV2KeepsBackslashEscapes(t *testing.T) {
+	got := escapeMarkdownV2(`AI says \_not italic\_ and C:\ [x\y](https://example.com/a)`)
+	want := `AI says \_not italic\_ and C:\\ [x\\y](https://example.com/a)`
+	if got != want {
+		t.Fatalf("unexpected output:\nwant: %q\ngot:  %q", want, got)
+	}
+}
+
+func TestEscapeMarkdownV2KeepsAuthoredFormatting(t *testing.T) {
+	got := escapeMarkdownV2("**Top 100** _new_ `SHIB_USD` at 1.5! 2*3 coinmarketcap_top100_bot https://x.com/a_b_c *open")
+	want := "*Top 100* _new_ `SHIB_USD` at 1\\.5\\! 2\\*3 coinmarketcap\\_top100\\_bot https://x\\.com/a\\_b\\_c \\*open"
+	if got != want {
+		t.Fatalf("unexpected output:\nwant: %q\ngot:  %q", want, got)
+	}
+}
+
+func TestMarkdownEscapeDirectiveIsNotEscapedTwice(t *testing.T) {
+	ctx := map[string]any{"name": "Shiba_Inu v2.0*", "symbol": "SHIB", "parse_mode": parseModeMarkdownV2}
+	rendered := RenderTemplate("*New:* %MARKDOWN_ESCAPE:name% (%symbol%)", ctx)
+	if rendered != `*New:* Shiba\_Inu v2\.0\* (SHIB)` {
+		t.Fatalf("unexpected render: %q", rendered)
+	}
+	ctx["parse_mode"] = "HTML"
+	if rendered := RenderTemplate("%MARKDOWN_ESCAPE:name%", ctx); rendered != "Shiba_Inu v2.0*" {
+		t.Fatalf("expected the value as is outside MarkdownV2, got %q", rendered)
+	}
+	got, mode := formatTelegramText(`*New:* Shiba\_Inu v2\.0\* (SHIB)`, parseModeMarkdownV2)
+	want := `*New:* Shiba\_Inu v2\.0\* \(SHIB\)`
+	if got != want || mode != parseModeMarkdownV2 {
+		t.Fatalf("unexpected output:\nwant: %q\ngot:  %q (%s)", want, got, mode)
+	}
+}
+
+func TestSplitTelegramTextPrefersParagraphBoundaries(t *testing.T) {
+	paragraph := strings.Repeat("x", 98)
+	text := strings.TrimSuffix(strings.Repeat(paragraph+"\n\n", 90), "\n\n") // 8998 chars
+	chunks := splitTelegramText(text, telegramMessageLimit)
+	if len(chunks) < 3 {
+		t.Fatalf("expected at least 3 chunks, got %d", len(chunks))
+	}
+	for i, c := range chunks {
+		if n := utf8.RuneCountInString(c); n > telegramMessageLimit || n == 0 {
+			t.Fatalf("chunk %d has invalid length %d", i, n)
+		}
+		if strings.HasPrefix(c, "\n") || strings.HasSuffix(c, "\n") || !strings.HasSuffix(c, paragraph) {
+			t.Fatalf("chunk %d was not split on a paragraph boundary: %q...", i, c[:20])
+		}
+	}
+}
+
+func TestSplitTelegramTextHardSplitsLongLines(t *testing.T) {
+	chunks := splitTelegramText(strings.Repeat("é", 9000), telegramMessageLimit)
+	if len(chunks) != 3 || utf8.RuneCountInString(chunks[0]) != telegramMessageLimit || utf8.RuneCountInString(chunks[2]) != 9000-2*telegramMessageLimit {
+		t.Fatalf("unexpected hard split: %d chunks", len(chunks))
+	}
+}
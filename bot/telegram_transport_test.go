@@ -4,10 +4,13 @@ import (
 	"bytes"
 	"context"
 	"encoding/json"
+	"fmt"
 	"io"
 	"net/http"
+	"net/http/httptest"
 	"strings"
 	"testing"
+	"time"
 )
 
 type captureRoundTripper struct {
@@ -37,6 +40,19 @@ func (c *captureRoundTripper) RoundTrip(req *http.Request) (*http.Response, erro
 	}, nil
 }
 
+// newTelegramMockServer accepts every Bot API call with message_id 42 and
+// counts the calls.
+func newTelegramMockServer(t *testing.T) (*httptest.Server, *int) {
+	t.Helper()
+	calls := 0
+	srv := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		calls++
+		fmt.Fprint(w, `{"ok":true,"result":{"message_id":42}}`)
+	}))
+	t.Cleanup(srv.Close)
+	return srv, &calls
+}
+
 func TestTelegramPayloadsUseHTMLParseMode(t *testing.T) {
 	msg := telegramSendMessagePayload("chat", "<b>hello</b>")
 	if msg["parse_mode"] != "HTML" {
@@ -55,7 +71,7 @@ func TestSendTelegramPhotoLongCaptionFallsBackWithoutDoubleEscaping(t *testing.T
 	cfg := Config{TelegramToken: "token", TelegramChannelID: "channel"}
 	longCaption := strings.Repeat("A", 1100) + " **Bold**"
 
-	_, err := sendTelegramPhoto(context.Background(), client, cfg, "https://example.com/img.png", longCaption)
+	_, err := sendTelegramPhoto(context.Background(), client, cfg, "channel", "https://example.com/img.png", longCaption, "")
 	if err != nil {
 		t.Fatalf("sendTelegramPhoto error: %v", err)
 	}
@@ -78,3 +94,64 @@ func TestSendTelegramPhotoLongCaptionFallsBackWithoutDoubleEscaping(t *testing.T
 		t.Fatalf("fallback text was double-escaped: %q", text)
 	}
 }
+
+func TestSendTelegramMessageHonorsRetryAfterOn429(t *testing.T) {
+	calls := 0
+	client := &http.Client{Transport: roundTripFunc(func(req *http.Request) (*http.Response, error) {
+		calls++
+		if calls == 1 {
+			return jsonResponse(http.StatusTooManyRequests, `{"ok":false,"error_code":429,"description":"Too Many Requests: retry after 1","parameters":{"retry_after":1}}`), nil
+		}
+		return jsonResponse(http.StatusOK, `{"ok":true,"result":{"message_id":7}}`), nil
+	})}
+	cfg := Config{TelegramToken: "token", TelegramChannelID: "channel", CMCMaxRetries: 3, CMCRetryBase: time.Millisecond}
+
+	start := time.Now()
+	msgID, err := sendTelegramMessageFormatted(context.Background(), client, cfg, "channel", "hello", "HTML")
+	if err != nil {
+		t.Fatalf("sendTelegramMessageFormatted error: %v", err)
+	}
+	if calls != 2 || msgID == nil || *msgID != 7 {
+		t.Fatalf("expected success on second call, got calls=%d msgID=%v", calls, msgID)
+	}
+	if elapsed := time.Since(start); elapsed < time.Second {
+		t.Fatalf("expected to wait retry_after=1s, waited %s", elapsed)
+	}
+}
+
+func TestSendTelegramMessageBroadcastsToAllChannels(t *testing.T) {
+	var chats []string
+	client := &http.Client{Transport: roundTripFunc(func(req *http.Request) (*http.Response, error) {
+		var payload map[string]any
+		_ = json.NewDecoder(req.Body).Decode(&payload)
+		chat, _ := payload["chat_id"].(string)
+		chats = append(chats, chat)
+		if chat == "@backup" {
+			return jsonResponse(http.StatusBadRequest, `{"ok":false,"description":"Bad Request: chat not found"}`), nil
+		}
+		return jsonResponse(http.StatusOK, `{"ok":true,"result":{"message_id":5}}`), nil
+	})}
+	cfg := Config{TelegramToken: "token", TelegramChannelIDs: []string{"@main", "@backup", "@archive"}}
+
+	ids, err := sendTelegramMessage(context.Background(), client, cfg, "hello", "", "")
+	if err != nil {
+		t.Fatalf("a failing non-primary channel should not fail the send: %v", err)
+	}
+	if strings.Join(chats, ",") != "@main,@backup,@archive" {
+		t.Fatalf("unexpected channel order: %v", chats)
+	}
+	if len(ids) != 3 || ids[0] == nil || *ids[0] != 5 || ids[1] != nil || ids[2] == nil {
+		t.Fatalf("unexpected message ids: %v", ids)
+	}
+
+	cfg.TelegramChannelIDs = []string{"@backup", "@main"}
+	ids, err = sendTelegramMessage(context.Background(), client, cfg, "hello", "", "")
+	if err != nil || ids[0] != nil || firstMessageID(ids) == nil || *firstMessageID(ids) != 5 {
+		t.Fatalf("a failing first channel should not fail the send: ids=%v err=%v", ids, err)
+	}
+
+	cfg.TelegramChannelIDs = []string{"@backup", "@backup"}
+	if _, err := sendTelegramMessage(context.Background(), client, cfg, "hello", "", ""); err == nil {
+		t.Fatalf("expected an error when every channel fails")
+	}
+}
@@ -1,6 +1,10 @@
 package bot
 
-import "testing"
+import (
+	"os"
+	"path/filepath"
+	"testing"
+)
 
 func TestTemplateFeaturesWork(t *testing.T) {
 	ctx := map[string]any{
@@ -8,8 +12,8 @@ func TestTemplateFeaturesWork(t *testing.T) {
 		"items": []any{map[string]any{"name": "BTC"}, map[string]any{"name": "ETH"}},
 		"empty": "",
 	}
-	tpl := "hi %name|X% %% %missing|d% %IF name%ok%END_IF%%IF empty%bad%END_IF% %EACH items%[%name%]%END_EACH%"
-	if got := RenderTemplate(tpl, ctx); got != "hi Alice % d ok [BTC][ETH]" {
+	tpl := "hi %name|X% %% %missing|d% %IF name%ok%END_IF%%IF empty%bad%END_IF%%UNLESS empty%!%END_UNLESS% %EACH items%[%name%]%END_EACH%"
+	if got := RenderTemplate(tpl, ctx); got != "hi Alice % d ok! [BTC][ETH]" {
 		t.Fatalf("unexpected output: %q", got)
 	}
 }
@@ -33,3 +37,252 @@ func TestTemplateIfTreatsEmptySlicesAsFalse(t *testing.T) {
 		t.Fatalf("unexpected output: %q", got)
 	}
 }
+
+func TestTemplateIfElseRendersMatchingBranch(t *testing.T) {
+	ctx := map[string]any{"new_coins": []Coin{{Symbol: "BTC"}}, "exited_coins": []Coin{}}
+	tpl := "%IF new_coins%new%ELSE%none%END_IF% %IF exited_coins%exited%ELSE%No movement today%END_IF%"
+	if got := RenderTemplate(tpl, ctx); got != "new No movement today" {
+		t.Fatalf("unexpected output: %q", got)
+	}
+}
+
+func TestTemplateIfElseSupportsNestedIfInsideElse(t *testing.T) {
+	tpl := "%IF a%A%ELSE%%IF b%B%ELSE%C%END_IF%!%END_IF%"
+	cases := []struct {
+		ctx  map[string]any
+		want string
+	}{
+		{map[string]any{"a": "1", "b": "1"}, "A"},
+		{map[string]any{"b": "1"}, "B!"},
+		{map[string]any{}, "C!"},
+	}
+	for _, tc := range cases {
+		if got := RenderTemplate(tpl, tc.ctx); got != tc.want {
+			t.Fatalf("ctx %v: got %q want %q", tc.ctx, got, tc.want)
+		}
+	}
+}
+
+func TestTemplateElseBindsToInnermostIf(t *testing.T) {
+	tpl := "%IF a%[%IF b%B%ELSE%notB%END_IF%]%ELSE%notA%END_IF%"
+	cases := []struct {
+		ctx  map[string]any
+		want string
+	}{
+		{map[string]any{"a": "1", "b": "1"}, "[B]"},
+		{map[string]any{"a": "1"}, "[notB]"},
+		{map[string]any{"b": "1"}, "notA"},
+	}
+	for _, tc := range cases {
+		if got := RenderTemplate(tpl, tc.ctx); got != tc.want {
+			t.Fatalf("ctx %v: got %q want %q", tc.ctx, got, tc.want)
+		}
+	}
+}
+
+func TestTemplateIfElseInsideEachUsesLoopItem(t *testing.T) {
+	ctx := map[string]any{
+		"has_exits": false,
+		"new_coins": []Coin{{Symbol: "BTC", ImageURL: "x"}, {Symbol: "ETH"}},
+	}
+	tpl := "%EACH new_coins%%symbol%:%IF image_url%logo%ELSE%none%END_IF% %END_EACH%%IF has_exits%exits%ELSE%No exits this run.%END_IF%"
+	if got := RenderTemplate(tpl, ctx); got != "BTC:logo ETH:none No exits this run." {
+		t.Fatalf("unexpected output: %q", got)
+	}
+}
+
+func TestTemplateUnlessRendersWhenFalsy(t *testing.T) {
+	ctx := map[string]any{
+		"empty_list": []Coin{},
+		"null":       nil,
+		"text":       "hello",
+	}
+	cases := []struct {
+		tpl  string
+		want string
+	}{
+		{"%UNLESS empty_list%none%END_UNLESS%", "none"},
+		{"%UNLESS null%none%END_UNLESS%", "none"},
+		{"%UNLESS missing%none%END_UNLESS%", "none"},
+		{"%UNLESS text%none%END_UNLESS%", ""},
+		{"%UNLESS text%none%ELSE%%text%%END_UNLESS%", "hello"},
+		{"%UNLESS missing%%IF text%a%ELSE%b%END_IF%%ELSE%c%END_UNLESS%", "a"},
+		{"%IF text%%UNLESS text%x%ELSE%y%END_UNLESS%%ELSE%z%END_IF%", "y"},
+	}
+	for _, tc := range cases {
+		if got := RenderTemplate(tc.tpl, ctx); got != tc.want {
+			t.Fatalf("%q: got %q want %q", tc.tpl, got, tc.want)
+		}
+	}
+}
+
+func TestTemplateFormatsPercentChangeWithArrow(t *testing.T) {
+	ctx := map[string]any{"up": 12.345, "down": -3.2, "flat": 0}
+	if got := RenderTemplate("%up:arrow% %down:arrow% %flat:arrow%", ctx); got != "▲12.35% ▼3.20% 0.00%" {
+		t.Fatalf("unexpected output: %q", got)
+	}
+}
+
+func TestTemplateAbbreviatesLargeAmounts(t *testing.T) {
+	ctx := map[string]any{"t": 2.5e12, "b": 1234567890, "m": -4.2e6, "small": 12.5}
+	if got := RenderTemplate("%t:abbrev% %b:abbrev% %m:abbrev% %small:abbrev%", ctx); got != "2.50T 1.23B -4.20M 12.50" {
+		t.Fatalf("unexpected output: %q", got)
+	}
+}
+
+func TestTemplateFormatNumberDirective(t *testing.T) {
+	ctx := map[string]any{"m": 4567890.4, "b": 12345678901.23, "t": 2.5e12, "small": 512.6}
+	got := RenderTemplate("%FORMAT_NUMBER:m% %FORMAT_NUMBER:b% %FORMAT_NUMBER:t% %FORMAT_NUMBER:small% %FORMAT_NUMBER:missing|n/a%", ctx)
+	if got != "4,567,890 12,345,678,901 2,500,000,000,000 513 n/a" {
+		t.Fatalf("unexpected output: %q", got)
+	}
+}
+
+func TestTemplateFormatCurrencyDirective(t *testing.T) {
+	ctx := map[string]any{"convert": "USD", "m": 4567890.4, "b": 12345678901.23, "t": 2.5e12, "small": 512.6}
+	got := RenderTemplate("%FORMAT_CURRENCY:m% %FORMAT_CURRENCY:b% %FORMAT_CURRENCY:t% %FORMAT_CURRENCY:small%", ctx)
+	if got != "$4.6M $12.3B $2.5T $512.60" {
+		t.Fatalf("unexpected output: %q", got)
+	}
+
+	ctx["convert"] = "EUR"
+	if got := RenderTemplate("%EACH coins%%FORMAT_CURRENCY:market_cap%%END_EACH%", map[string]any{"convert": "CHF", "coins": []any{map[string]any{"market_cap": 3.2e9}}}); got != "CHF 3.2B" {
+		t.Fatalf("unexpected output for a currency without a symbol: %q", got)
+	}
+	if got := RenderTemplate("%FORMAT_CURRENCY:b%", ctx); got != "€12.3B" {
+		t.Fatalf("unexpected EUR output: %q", got)
+	}
+}
+
+func TestFormatCurrencyMovesUpAUnitWhenRoundingReachesAThousand(t *testing.T) {
+	for _, tc := range []struct {
+		in   float64
+		want string
+	}{{999950, "$1.0M"}, {999.996, "$1.0K"}, {999949, "$999.9K"}, {-999950, "-$1.0M"}, {999.99e9, "$1.0T"}} {
+		if got := formatCurrency(tc.in, "USD"); got != tc.want {
+			t.Fatalf("formatCurrency(%v): got %q want %q", tc.in, got, tc.want)
+		}
+	}
+	if got := formatAbbrev(999999); got != "1.00M" {
+		t.Fatalf("formatAbbrev(999999): got %q", got)
+	}
+}
+
+func TestTemplateResolvesDottedKeyPaths(t *testing.T) {
+	ctx := map[string]any{
+		"coin": map[string]any{"market_cap": 1500},
+		"top_movers": []any{
+			map[string]any{"symbol": "BTC", "quote": map[string]any{"USD": map[string]any{"price": 65000}}},
+		},
+	}
+	tpl := "%coin.market_cap% %EACH top_movers%%symbol%=%quote.USD.price%%END_EACH% %coin.quote.price|n/a%"
+	if got := RenderTemplate(tpl, ctx); got != "1500 BTC=65000 n/a" {
+		t.Fatalf("unexpected output: %q", got)
+	}
+}
+
+func TestTemplateFormatsNumbersWithSpec(t *testing.T) {
+	ctx := map[string]any{"market_cap": 1234567890.12345, "price": 0.4321, "small": -999.5, "name": "BTC"}
+	tpl := "%market_cap:,.0f% %market_cap:,.2f% %price:.2f% %small:,% %name:,.0f% %missing:,.0f|n/a%"
+	want := "1,234,567,890 1,234,567,890.12 0.43 -999.5 BTC n/a"
+	if got := RenderTemplate(tpl, ctx); got != want {
+		t.Fatalf("unexpected output: got %q want %q", got, want)
+	}
+}
+
+func TestTemplateEachLimit(t *testing.T) {
+	ctx := map[string]any{"items": []any{
+		map[string]any{"name": "A"}, map[string]any{"name": "B"}, map[string]any{"name": "C"},
+	}}
+	cases := []struct{ tpl, want string }{
+		{"%EACH items%%name%%IF _remaining%+%_remaining%%END_IF%;%END_EACH%", "A;B;C;"},
+		{"%EACH items LIMIT 2%%name%%IF _remaining%+%_remaining%%END_IF%;%END_EACH%", "A+1;B+1;"},
+		{"%EACH items LIMIT 10%%name%%IF _remaining%+%_remaining%%END_IF%;%END_EACH%", "A;B;C;"},
+		{"%EACH items LIMIT 0%%name%%END_EACH%", ""},
+	}
+	for _, tc := range cases {
+		if got := RenderTemplate(tc.tpl, ctx); got != tc.want {
+			t.Fatalf("%q: got %q want %q", tc.tpl, got, tc.want)
+		}
+	}
+	if _, ok := ctx["items"].([]any)[0].(map[string]any)["_remaining"]; ok {
+		t.Fatal("LIMIT must not modify the context items")
+	}
+}
+
+func TestTemplateNestedEach(t *testing.T) {
+	ctx := map[string]any{"groups": []any{
+		map[string]any{"title": "g1", "coins": []any{map[string]any{"symbol": "BTC"}, map[string]any{"symbol": "ETH"}}},
+		map[string]any{"title": "g2", "coins": []any{map[string]any{"symbol": "SOL"}}},
+	}}
+	got := RenderTemplate("%EACH groups%%title%:[%EACH coins%%symbol% %END_EACH%] %END_EACH%", ctx)
+	if got != "g1:[BTC ETH ] g2:[SOL ] " {
+		t.Fatalf("unexpected output: %q", got)
+	}
+}
+
+func TestTemplateEachExposesLoopPosition(t *testing.T) {
+	ctx := map[string]any{"coins": []any{map[string]any{"symbol": "BTC"}, map[string]any{"symbol": "ETH"}, map[string]any{"symbol": "SOL"}}}
+	cases := []struct{ tpl, want string }{
+		{"%EACH coins%%_number%. %symbol%%UNLESS _last%, %END_UNLESS%%END_EACH%", "1. BTC, 2. ETH, 3. SOL"},
+		{"%EACH coins%%_index%%IF _first%*%END_IF%%IF _last%!%END_IF% %END_EACH%", "0* 1 2! "},
+		{"%EACH coins LIMIT 2%%symbol%%IF _last% (+%_remaining%)%ELSE%,%END_IF%%END_EACH%", "BTC,ETH (+1)"},
+	}
+	for _, tc := range cases {
+		if got := RenderTemplate(tc.tpl, ctx); got != tc.want {
+			t.Fatalf("%q: got %q want %q", tc.tpl, got, tc.want)
+		}
+	}
+}
+
+func TestTemplateIncludeRendersPartialWithLoopItem(t *testing.T) {
+	dir := t.TempDir()
+	if err := os.MkdirAll(filepath.Join(dir, "partials"), 0o755); err != nil {
+		t.Fatal(err)
+	}
+	if err := os.WriteFile(filepath.Join(dir, "partials", "coin_row.md"), []byte("• #%rank% %symbol% (top %top_n%)\n"), 0o644); err != nil {
+		t.Fatal(err)
+	}
+	ctx := map[string]any{"top_n": 100, "new_coins": []Coin{{Symbol: "SUI", Rank: 42}, {Symbol: "TON", Rank: 57}}}
+	tpl := "%EACH new_coins%%INCLUDE partials/coin_row.md%%END_EACH%%INCLUDE missing.md%done"
+	if got := renderTemplateIn(dir, tpl, ctx); got != "• #42 SUI (top 100)\n• #57 TON (top 100)\ndone" {
+		t.Fatalf("unexpected output: %q", got)
+	}
+}
+
+func TestTemplateIncludeStopsAtDepthLimit(t *testing.T) {
+	dir := t.TempDir()
+	if err := os.WriteFile(filepath.Join(dir, "loop.md"), []byte("x%INCLUDE loop.md%"), 0o644); err != nil {
+		t.Fatal(err)
+	}
+	want := ""
+	for i := 0; i < maxIncludeDepth; i++ {
+		want += "x"
+	}
+	if got := renderTemplateIn(dir, "%INCLUDE loop.md%!", nil); got != want+"!" {
+		t.Fatalf("unexpected output: %q", got)
+	}
+}
+
+func TestTemplateUnlessNestsWithEachAndIf(t *testing.T) {
+	ctx := map[string]any{
+		"new_coins":    []Coin{{Symbol: "SUI", ImageURL: "https://example.com/sui.png"}, {Symbol: "TON"}},
+		"exited_coins": []Coin{},
+		"quiet":        false,
+	}
+	cases := []struct {
+		tpl  string
+		want string
+	}{
+		{"%EACH new_coins%%symbol%%UNLESS image_url% (no logo)%END_UNLESS%;%END_EACH%", "SUI;TON (no logo);"},
+		{"%EACH new_coins%%UNLESS image_url%%IF _last%last:%END_IF%%symbol%%ELSE%-%END_UNLESS%%END_EACH%", "-last:TON"},
+		{"%UNLESS quiet%%EACH new_coins%[%symbol%]%END_EACH%%END_UNLESS%", "[SUI][TON]"},
+		{"%UNLESS exited_coins%No exits.%UNLESS quiet% Still watching.%END_UNLESS%%END_UNLESS%", "No exits. Still watching."},
+		{"%IF new_coins%%EACH new_coins%%UNLESS _first%, %END_UNLESS%%symbol%%END_EACH%%END_IF%", "SUI, TON"},
+	}
+	for _, tc := range cases {
+		if got := RenderTemplate(tc.tpl, ctx); got != tc.want {
+			t.Fatalf("%q: got %q want %q", tc.tpl, got, tc.want)
+		}
+	}
+}
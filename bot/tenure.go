@@ -0,0 +1,69 @@
+package bot
+
+import "time"
+
+// defaultTenureRetentionDays bounds how long a departed coin is remembered, so
+// the tenure map does not grow with every coin that ever passed through.
+const defaultTenureRetentionDays = 30
+
+// updateTenure returns the tenure map to store with the next state and the
+// ids of returning veterans. A coin in the previous snapshot gets one more
+// run; a coin that wasn't starts again at 1, unless its remembered tenure is
+// above threshold (a long-standing member that briefly dropped out, or a state
+// that lost it on a restart): such a veteran keeps counting and is not a new
+// entrant. Departed coins keep their last count so they can be recognised;
+// ageOutTenure forgets them after TENURE_RETENTION_DAYS.
+// A threshold <= 0 disables the veteran rule. TenureRuns is set on current.
+func updateTenure(prev map[int64]int64, prevIDs map[int64]struct{}, current []Coin, threshold int64) (map[int64]int64, map[int64]struct{}) {
+	next := make(map[int64]int64, len(prev)+len(current))
+	for id, n := range prev {
+		next[id] = n
+	}
+	veterans := map[int64]struct{}{}
+	for i, c := range current {
+		n := prev[c.ID]
+		_, present := prevIDs[c.ID]
+		switch {
+		case present:
+			// States written before tenure was tracked have no count yet.
+			next[c.ID] = max(n, 1) + 1
+		case threshold > 0 && n > threshold:
+			veterans[c.ID] = struct{}{}
+			next[c.ID] = n + 1
+		default:
+			next[c.ID] = 1
+		}
+		current[i].TenureRuns = next[c.ID]
+	}
+	return next, veterans
+}
+
+// ageOutTenure returns tenure without the coins that have been out of the
+// top-N for longer than retention, so a coin that left long ago is a new
+// entrant again instead of a veteran, and the departure times to store with
+// the next state: when each remembered coin absent from current was first
+// seen missing. A retention <= 0 keeps departed coins forever.
+func ageOutTenure(tenure map[int64]int64, leftAt map[int64]time.Time, current []Coin, now time.Time, retention time.Duration) (map[int64]int64, map[int64]time.Time) {
+	present := make(map[int64]struct{}, len(current))
+	for _, c := range current {
+		present[c.ID] = struct{}{}
+	}
+	kept := make(map[int64]int64, len(tenure))
+	nextLeftAt := map[int64]time.Time{}
+	for id, n := range tenure {
+		left, recorded := leftAt[id]
+		if recorded && retention > 0 && now.Sub(left) > retention {
+			continue
+		}
+		kept[id] = n
+		if _, ok := present[id]; ok {
+			continue
+		}
+		if !recorded {
+			// Coins that left before departures were recorded start now.
+			left = now
+		}
+		nextLeftAt[id] = left
+	}
+	return kept, nextLeftAt
+}
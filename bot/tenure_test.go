@@ -0,0 +1,86 @@
+package bot
+
+import (
+	"context"
+	"strings"
+	"testing"
+	"time"
+)
+
+func TestUpdateTenure(t *testing.T) {
+	prev := map[int64]int64{1: 5, 3: 40, 4: 2}
+	prevIDs := map[int64]struct{}{1: {}, 2: {}}
+	current := []Coin{{ID: 1}, {ID: 2}, {ID: 3}, {ID: 4}, {ID: 5}}
+
+	next, veterans := updateTenure(prev, prevIDs, current, 10)
+	want := map[int64]int64{1: 6, 2: 2, 3: 41, 4: 1, 5: 1}
+	for id, n := range want {
+		if next[id] != n {
+			t.Fatalf("tenure of %d: got %d want %d (all: %v)", id, next[id], n, next)
+		}
+	}
+	if _, ok := veterans[3]; !ok || len(veterans) != 1 {
+		t.Fatalf("expected only coin 3 to be a veteran, got %v", veterans)
+	}
+	if current[0].TenureRuns != 6 || current[4].TenureRuns != 1 {
+		t.Fatalf("TenureRuns not set on coins: %+v", current)
+	}
+}
+
+func TestAgeOutTenure(t *testing.T) {
+	now := time.Date(2024, 6, 1, 0, 0, 0, 0, time.UTC)
+	tenure := map[int64]int64{1: 5, 2: 40, 3: 7, 4: 3}
+	leftAt := map[int64]time.Time{2: now.Add(-48 * time.Hour), 3: now.Add(-time.Hour)}
+	current := []Coin{{ID: 1}, {ID: 2}}
+
+	kept, nextLeftAt := ageOutTenure(tenure, leftAt, current, now, 24*time.Hour)
+	if len(kept) != 3 || kept[1] != 5 || kept[3] != 7 || kept[4] != 3 {
+		t.Fatalf("expected coin 2 to age out even though it is back, got %v", kept)
+	}
+	if len(nextLeftAt) != 2 || !nextLeftAt[3].Equal(now.Add(-time.Hour)) || !nextLeftAt[4].Equal(now) {
+		t.Fatalf("unexpected departure times: %v", nextLeftAt)
+	}
+	if len(tenure) != 4 {
+		t.Fatalf("the input tenure must not be modified: %v", tenure)
+	}
+
+	kept, _ = ageOutTenure(tenure, leftAt, current, now, 0)
+	if len(kept) != 4 {
+		t.Fatalf("a zero retention should keep every coin, got %v", kept)
+	}
+}
+
+func TestRunOnceAnnouncesVeteranAbsentBeyondTenureRetention(t *testing.T) {
+	srv := newCMCMockServer(t, listingsBody(1, 2))
+	leftAt := map[int64]time.Time{2: time.Now().UTC().Add(-72 * time.Hour)}
+	store := &memoryStore{state: &State{TopN: 2, Convert: "USD", IDs: []int64{1, 3}, Coins: []Coin{{ID: 1, Rank: 1}, {ID: 3, Rank: 2}}, Tenure: map[int64]int64{1: 200, 2: 150, 3: 1}, TenureLeftAt: leftAt}}
+	cfg := Config{CMCBaseURL: srv.URL, TopN: 2, TenureThreshold: 100, TenureRetention: 48 * time.Hour}
+
+	out := captureStdout(t, func() {
+		if err := RunOnceWithStore(context.Background(), cfg, RunOptions{Convert: "USD", DryRun: true}, store); err != nil {
+			t.Fatalf("RunOnceWithStore error: %v", err)
+		}
+	})
+	if !strings.Contains(out, "Coin 2 (C2)") {
+		t.Fatalf("a coin gone longer than the retention should be announced again, got %q", out)
+	}
+}
+
+func TestRunOnceDoesNotAnnounceReturningVeteran(t *testing.T) {
+	srv := newCMCMockServer(t, listingsBody(1, 2))
+	store := &memoryStore{state: &State{TopN: 2, Convert: "USD", IDs: []int64{1, 3}, Coins: []Coin{{ID: 1, Rank: 1}, {ID: 3, Rank: 2}}, Tenure: map[int64]int64{1: 200, 2: 150, 3: 1}}}
+	cfg := Config{CMCBaseURL: srv.URL, TopN: 2, TenureThreshold: 100}
+
+	if err := RunOnceWithStore(context.Background(), cfg, RunOptions{Convert: "USD"}, store); err != nil {
+		t.Fatalf("RunOnceWithStore error: %v", err)
+	}
+	if len(store.history) != 0 {
+		t.Fatalf("a returning veteran should not be announced, got %d post(s)", len(store.history))
+	}
+	if store.stateWrites != 1 || store.state.Tenure[1] != 201 || store.state.Tenure[2] != 151 || store.state.Tenure[3] != 1 {
+		t.Fatalf("unexpected tenure after %d write(s): %v", store.stateWrites, store.state.Tenure)
+	}
+	if _, ok := store.state.TenureLeftAt[3]; !ok || len(store.state.TenureLeftAt) != 1 {
+		t.Fatalf("expected only the departed coin 3 to get a departure time, got %v", store.state.TenureLeftAt)
+	}
+}
@@ -0,0 +1,35 @@
+package bot
+
+import (
+	"context"
+	"errors"
+	"fmt"
+	"log"
+	"time"
+)
+
+// postHoldReason returns why a post announcing newCoins should wait, or ""
+// when it can go out: fewer entrants than MIN_NEW_COINS, or a previous post
+// within MIN_POST_INTERVAL_MINUTES of now. Runs without entrants (renames,
+// ATHs, movers) are only held by the interval. A failed history lookup only
+// logs a warning and lets the post through.
+func postHoldReason(ctx context.Context, store StateStore, cfg Config, newCoins []Coin, now time.Time) string {
+	if len(newCoins) > 0 && len(newCoins) < cfg.MinNewCoins {
+		return fmt.Sprintf("%d new coin(s) is below MIN_NEW_COINS %d", len(newCoins), cfg.MinNewCoins)
+	}
+	if cfg.MinPostInterval <= 0 {
+		return ""
+	}
+	last, err := store.LatestHistory(ctx)
+	if errors.Is(err, ErrNoHistory) {
+		return ""
+	}
+	if err != nil {
+		log.Printf("[RunOnce] warning: failed to load the latest post for MIN_POST_INTERVAL_MINUTES: %v", err)
+		return ""
+	}
+	if since := now.Sub(last.CreatedAt); since < cfg.MinPostInterval {
+		return fmt.Sprintf("last post was %s ago, inside MIN_POST_INTERVAL_MINUTES %d", since.Round(time.Second), int(cfg.MinPostInterval.Minutes()))
+	}
+	return ""
+}
@@ -0,0 +1,57 @@
+package bot
+
+import (
+	"context"
+	"strings"
+	"testing"
+	"time"
+)
+
+func TestRunOnceBelowMinNewCoinsKeepsEntrantsForNextRun(t *testing.T) {
+	prev := State{TopN: 3, Convert: "USD", IDs: []int64{1, 2, 3}, Coins: []Coin{{ID: 1, Rank: 1}, {ID: 2, Rank: 2}, {ID: 3, Rank: 3}}}
+	store := &memoryStore{state: &prev}
+	cfg := Config{TopN: 3, RankChangeThreshold: 10, MinNewCoins: 2}
+
+	cfg.CMCBaseURL = newCMCMockServer(t, `{"data":[
+		{"id":1,"name":"Bitcoin","symbol":"BTC","cmc_rank":1},
+		{"id":2,"name":"Ethereum","symbol":"ETH","cmc_rank":2},
+		{"id":5426,"name":"Solana","symbol":"SOL","cmc_rank":3}]}`).URL
+	if err := RunOnceWithStore(context.Background(), cfg, RunOptions{Convert: "USD"}, store); err != nil {
+		t.Fatalf("RunOnceWithStore error: %v", err)
+	}
+	if store.stateWrites != 0 || len(store.history) != 0 {
+		t.Fatalf("held run must not touch state or history: %d state write(s), %d post(s)", store.stateWrites, len(store.history))
+	}
+
+	out := runDry(t, cfg, RunOptions{Convert: "USD"}, `{"data":[
+		{"id":1,"name":"Bitcoin","symbol":"BTC","cmc_rank":1},
+		{"id":74,"name":"Dogecoin","symbol":"DOGE","cmc_rank":2},
+		{"id":5426,"name":"Solana","symbol":"SOL","cmc_rank":3}]}`, store)
+	if !strings.Contains(out, "Solana") || !strings.Contains(out, "Dogecoin") {
+		t.Fatalf("expected the held SOL entrant alongside DOGE: %q", out)
+	}
+}
+
+func TestRunOnceInsidePostIntervalKeepsEntrantsForNextRun(t *testing.T) {
+	const listings = `{"data":[
+		{"id":1,"name":"Bitcoin","symbol":"BTC","cmc_rank":1},
+		{"id":2,"name":"Ethereum","symbol":"ETH","cmc_rank":2},
+		{"id":5426,"name":"Solana","symbol":"SOL","cmc_rank":3}]}`
+	srv := newCMCMockServer(t, listings)
+	prev := State{TopN: 3, Convert: "USD", IDs: []int64{1, 2, 3}, Coins: []Coin{{ID: 1, Rank: 1}, {ID: 2, Rank: 2}, {ID: 3, Rank: 3}}}
+	store := &memoryStore{state: &prev, history: []HistoryDoc{{CreatedAt: time.Now().UTC().Add(-10 * time.Minute)}}}
+	cfg := Config{CMCBaseURL: srv.URL, TopN: 3, RankChangeThreshold: 10, MinPostInterval: 30 * time.Minute}
+
+	if err := RunOnceWithStore(context.Background(), cfg, RunOptions{Convert: "USD"}, store); err != nil {
+		t.Fatalf("RunOnceWithStore error: %v", err)
+	}
+	if store.stateWrites != 0 || len(store.history) != 1 {
+		t.Fatalf("held run must not touch state or history: %d state write(s), %d post(s)", store.stateWrites, len(store.history))
+	}
+
+	store.history[0].CreatedAt = time.Now().UTC().Add(-time.Hour)
+	out := runDry(t, cfg, RunOptions{Convert: "USD"}, listings, store)
+	if !strings.Contains(out, "Solana") {
+		t.Fatalf("expected SOL once the interval passed: %q", out)
+	}
+}
@@ -0,0 +1,67 @@
+package bot
+
+import (
+	"context"
+	"net/http"
+)
+
+// fetchWithWatchlist fetches the top-N and the WATCHLIST_MARGIN coins below
+// the headline cutoff (NOTIFY_TOP_N) in the same listings request and splits
+// off that band. The request only goes deeper than TopN when the band reaches
+// past it.
+func fetchWithWatchlist(ctx context.Context, client *http.Client, cfg Config, opt RunOptions, metrics *RunMetrics) ([]Coin, []Coin, string, error) {
+	deeper := cfg
+	deeper.TopN = max(cfg.TopN, cfg.notifyTopN()+cfg.WatchlistMargin)
+	coins, provider, err := fetchTopN(ctx, client, deeper, opt, metrics)
+	if err != nil {
+		return nil, nil, "", err
+	}
+	current, band := splitWatchlist(coins, cfg.TopN, cfg.notifyTopN(), cfg.WatchlistMargin)
+	return current, band, provider, nil
+}
+
+// splitWatchlist cuts a listing fetched for the watchlist into the top-N
+// proper and the band of up to margin coins just below the headline cutoff.
+// With NOTIFY_TOP_N below TopN the band lies within the top-N.
+func splitWatchlist(coins []Coin, topN, headline, margin int) ([]Coin, []Coin) {
+	from := min(headline, len(coins))
+	band := coins[from:min(headline+margin, len(coins))]
+	return coins[:min(topN, len(coins))], band
+}
+
+// approachingCoins returns the band coins that were neither in the previous
+// band nor inside the previous headline (prevHeadline), ie coins climbing
+// towards the cutoff rather than falling out of it. A previous state without
+// a band (the margin was just enabled) yields none, so the first run does not
+// flag the whole band.
+func approachingCoins(band []Coin, prevBand []int64, prevHeadline map[int64]struct{}) []Coin {
+	out := []Coin{}
+	if len(prevBand) == 0 {
+		return out
+	}
+	seen := make(map[int64]struct{}, len(prevBand))
+	for _, id := range prevBand {
+		seen[id] = struct{}{}
+	}
+	for _, c := range band {
+		if _, ok := seen[c.ID]; ok {
+			continue
+		}
+		if _, ok := prevHeadline[c.ID]; ok {
+			continue
+		}
+		out = append(out, c)
+	}
+	return out
+}
+
+// headlineIDs returns the ids of the coins ranked within the headline.
+func headlineIDs(coins []Coin, headline int64) map[int64]struct{} {
+	out := make(map[int64]struct{}, len(coins))
+	for _, c := range coins {
+		if withinHeadline(c.Rank, headline) {
+			out[c.ID] = struct{}{}
+		}
+	}
+	return out
+}
@@ -0,0 +1,70 @@
+package bot
+
+import (
+	"context"
+	"strings"
+	"testing"
+)
+
+func TestApproachingCoinsSkipsKnownAndFallingCoins(t *testing.T) {
+	band := []Coin{{ID: 10, Symbol: "NEW"}, {ID: 11, Symbol: "STAY"}, {ID: 12, Symbol: "FELL"}}
+	prevSet := map[int64]struct{}{12: {}}
+
+	got := approachingCoins(band, []int64{11}, prevSet)
+	if len(got) != 1 || got[0].Symbol != "NEW" {
+		t.Fatalf("unexpected approaching coins: %+v", got)
+	}
+	if got := approachingCoins(band, nil, prevSet); len(got) != 0 {
+		t.Fatalf("a state without a band should flag nothing, got %+v", got)
+	}
+}
+
+func TestRunOnceAttachesWatchlistOnlyToEntrantPosts(t *testing.T) {
+	listing := `{"data":[
+		{"id":1,"name":"Bitcoin","symbol":"BTC","cmc_rank":1},
+		{"id":5426,"name":"Solana","symbol":"SOL","cmc_rank":2},
+		{"id":74,"name":"Dogecoin","symbol":"DOGE","cmc_rank":3}]}`
+	cfg := Config{TopN: 2, WatchlistMargin: 1, RankChangeThreshold: 10}
+	run := func(prevIDs []int64) string {
+		cfg.CMCBaseURL = newCMCMockServer(t, listing).URL
+		var prevCoins []Coin
+		for i, id := range prevIDs {
+			prevCoins = append(prevCoins, Coin{ID: id, Rank: int64(i + 1)})
+		}
+		store := &memoryStore{state: &State{TopN: 2, Convert: "USD", IDs: prevIDs, Coins: prevCoins, WatchlistIDs: []int64{2}}}
+		return captureStdout(t, func() {
+			if err := RunOnceWithStore(context.Background(), cfg, RunOptions{Convert: "USD", DryRun: true}, store); err != nil {
+				t.Fatalf("RunOnceWithStore error: %v", err)
+			}
+		})
+	}
+
+	out := run([]int64{1, 2})
+	if !strings.Contains(out, "Solana") || !strings.Contains(out, "Knocking on the door:\n• #3 Dogecoin (DOGE)") {
+		t.Fatalf("expected SOL as entrant and DOGE on the watchlist, got %q", out)
+	}
+	if out := run([]int64{1, 5426}); out != "" {
+		t.Fatalf("the watchlist alone should not post, got %q", out)
+	}
+}
+
+func TestRunOnceWatchlistSitsBelowNotifyTopN(t *testing.T) {
+	cfg := Config{TopN: 4, NotifyTopN: 2, WatchlistMargin: 1, RankChangeThreshold: 10}
+	cfg.CMCBaseURL = newCMCMockServer(t, `{"data":[
+		{"id":1,"name":"Bitcoin","symbol":"BTC","cmc_rank":1},
+		{"id":5426,"name":"Solana","symbol":"SOL","cmc_rank":2},
+		{"id":2010,"name":"Cardano","symbol":"ADA","cmc_rank":3},
+		{"id":1027,"name":"Ethereum","symbol":"ETH","cmc_rank":4}]}`).URL
+	prevCoins := []Coin{{ID: 1, Rank: 1}, {ID: 1027, Rank: 2}, {ID: 74, Rank: 3}, {ID: 2010, Rank: 4}}
+	store := &memoryStore{state: &State{TopN: 4, Convert: "USD", IDs: coinIDs(prevCoins), Coins: prevCoins, WatchlistIDs: []int64{74}}}
+
+	out := captureStdout(t, func() {
+		if err := RunOnceWithStore(context.Background(), cfg, RunOptions{Convert: "USD", DryRun: true}, store); err != nil {
+			t.Fatalf("RunOnceWithStore error: %v", err)
+		}
+	})
+	// ADA was tracked but outside the top 2, so reaching rank 3 is approaching.
+	if !strings.Contains(out, "Knocking on the door:\n• #3 Cardano (ADA)") {
+		t.Fatalf("expected ADA just below the top 2 on the watchlist, got %q", out)
+	}
+}
@@ -5,6 +5,7 @@ go 1.22
 require (
 	github.com/aws/aws-lambda-go v1.52.0
 	go.mongodb.org/mongo-driver v1.17.9
+	modernc.org/sqlite v1.33.1
 )
 
 require (
@@ -5,26 +5,239 @@ import (
 	"flag"
 	"fmt"
 	"os"
+	"os/signal"
+	"syscall"
+	"time"
 
 	"coinmarketcap_top100_bot/bot"
 )
 
 func main() {
+	if len(os.Args) > 1 {
+		switch os.Args[1] {
+		case "list-history":
+			listHistory(os.Args[2:])
+			return
+		case "compare":
+			compare(os.Args[2:])
+			return
+		case "show-state":
+			showState(os.Args[2:])
+			return
+		}
+	}
+	configPath := flag.String("config", "", "optional TOML config file; env vars take precedence over its values")
 	dryRun := flag.Bool("dry-run", false, "print final message without sending")
+	dryRunFormat := flag.String("format", "text", "what --dry-run prints: text, or json with the new, exited and moved coins next to the text; markdown makes --output-file hold the raw text")
+	outputFile := flag.String("output-file", "", "with --dry-run, write the output to this file instead of stdout: JSON with the text, coins and render context, or the raw text for .md files and --format markdown")
 	notifyExits := flag.Bool("notify-exits", false, "include exited coins in context")
-	convert := flag.String("convert", "USD", "currency for market cap")
+	convert := flag.String("convert", "USD", "currency for market cap; a comma-separated list such as USD,EUR adds per-coin quotes, the first one stays primary")
+	sortBy := flag.String("sort", "market_cap", "CMC listings sort field, eg market_cap, volume_24h, price")
 	skipMongo := flag.Bool("skip-mongo", false, "test posting flow without MongoDB state/history")
 	testMessage := flag.String("test-message", "", "custom message for posting flow test (works with --skip-mongo)")
 	testImageURL := flag.String("test-image-url", "", "optional image URL for --test-message")
+	parseMode := flag.String("parse-mode", "", "Telegram parse mode: empty for HTML (default) or MarkdownV2")
+	notifyRenames := flag.Bool("notify-renames", false, "announce coins whose name or symbol changed since the previous snapshot")
+	minMarketCap := flag.Float64("min-market-cap", 0, "ignore new entrants whose market cap is below this value (0 disables)")
+	rankJumpThreshold := flag.Int64("rank-jump-threshold", 0, "post when a coin climbs at least this many places (0 disables)")
+	interval := flag.Int("interval", 0, "run continuously, waiting this many seconds between runs (0 runs once)")
+	initState := flag.Bool("init-state", false, "fetch the current top-N, overwrite the stored state baseline and exit without posting")
+	rssOutput := flag.String("rss-output", "", "write an RSS 2.0 feed of published posts to this path and exit")
+	notifyDiscord := flag.Bool("notify-discord", false, "also post the message to DISCORD_WEBHOOK_URL")
+	notifyEmail := flag.Bool("notify-email", false, "also email the message via SMTP_HOST to SMTP_TO")
+	notifySlack := flag.Bool("notify-slack", false, "also post the message to SLACK_WEBHOOK_URL")
+	notifyMastodon := flag.Bool("notify-mastodon", false, "also post the message as a public status to MASTODON_INSTANCE_URL")
+	notifyMovers := flag.Bool("notify-movers", false, "announce coins whose rank moved by more than MOVER_THRESHOLD places, even without new entrants")
+	forceRun := flag.Bool("force-run", false, "post even when no new entrants were found, using every current coin as the new list (history marks it forced)")
+	metricsPort := flag.Int("metrics-port", 0, "serve Prometheus metrics on this port at /metrics while running (0 disables; most useful with --interval)")
+	notifyResume := flag.Bool("notify-resume", false, "when the stored state is older than MAX_STATE_AGE_HOURS, post a short resuming coverage message before re-baselining")
+	mode := flag.String("mode", "entrants", "entrants posts top-N entries and exits; digest posts the 7d gainers and losers without touching state")
 	flag.Parse()
+	if err := bot.SetupLogging(os.Getenv("LOG_FORMAT")); err != nil {
+		fmt.Fprintln(os.Stderr, err)
+		os.Exit(1)
+	}
+	if *initState && *dryRun {
+		fmt.Fprintln(os.Stderr, "--init-state and --dry-run are mutually exclusive")
+		os.Exit(1)
+	}
+	if *mode != "entrants" && *mode != bot.ModeDigest {
+		fmt.Fprintf(os.Stderr, "unsupported --mode value %q\n", *mode)
+		os.Exit(1)
+	}
+	if *mode == bot.ModeDigest && (*skipMongo || *initState) {
+		fmt.Fprintln(os.Stderr, "--mode digest cannot be combined with --skip-mongo or --init-state")
+		os.Exit(1)
+	}
+	if *dryRunFormat != string(bot.DryRunText) && *dryRunFormat != string(bot.DryRunJSON) && *dryRunFormat != string(bot.DryRunMarkdown) {
+		fmt.Fprintf(os.Stderr, "unsupported --format value %q\n", *dryRunFormat)
+		os.Exit(1)
+	}
+	if *outputFile != "" && !*dryRun {
+		fmt.Fprintln(os.Stderr, "--output-file requires --dry-run")
+		os.Exit(1)
+	}
+	if *interval < 0 {
+		fmt.Fprintln(os.Stderr, "--interval must be >= 0")
+		os.Exit(1)
+	}
+	primaryConvert, extraConverts, err := bot.ParseConverts(*convert)
+	if err != nil {
+		fmt.Fprintln(os.Stderr, err)
+		os.Exit(1)
+	}
+	if !bot.ValidCMCSort(*sortBy) {
+		fmt.Fprintf(os.Stderr, "unsupported --sort value %q\n", *sortBy)
+		os.Exit(1)
+	}
+
+	opt := bot.RunOptions{DryRun: *dryRun, NotifyExits: *notifyExits, Convert: primaryConvert, SkipMongo: *skipMongo, TestMessage: *testMessage, TestImageURL: *testImageURL, NotifyDiscord: *notifyDiscord, NotifySlack: *notifySlack, NotifyEmail: *notifyEmail, ParseMode: *parseMode, Sort: *sortBy, NotifyRenames: *notifyRenames, NotifyMovers: *notifyMovers, ForceRun: *forceRun, DryRunFormat: bot.DryRunFormat(*dryRunFormat)}
+	opt.ExtraConverts = extraConverts
+	opt.NotifyMastodon = *notifyMastodon
+	opt.OutputFile = *outputFile
+	opt.NotifyResume = *notifyResume
+	if *mode == bot.ModeDigest {
+		opt.Mode = bot.ModeDigest
+	}
+	if *minMarketCap > 0 {
+		opt.MinMarketCap = minMarketCap
+	}
+	if *rankJumpThreshold > 0 {
+		opt.RankJumpThreshold = rankJumpThreshold
+	}
+
+	// Telegram credentials are only needed by commands that can post.
+	telegramOptional := *dryRun || *initState || *rssOutput != ""
+	var cfg bot.Config
+	if *configPath != "" {
+		cfg, err = bot.ConfigFromFile(*configPath, telegramOptional, *skipMongo)
+	} else {
+		cfg, err = bot.ConfigFromEnv(telegramOptional, *skipMongo)
+	}
+	if err != nil {
+		fmt.Fprintln(os.Stderr, err)
+		os.Exit(1)
+	}
+	if *initState {
+		if err := bot.InitState(context.Background(), cfg, opt); err != nil {
+			fmt.Fprintln(os.Stderr, err)
+			os.Exit(1)
+		}
+		return
+	}
+	if *rssOutput != "" {
+		if err := bot.WriteRSSFeed(context.Background(), cfg, *rssOutput); err != nil {
+			fmt.Fprintln(os.Stderr, err)
+			os.Exit(1)
+		}
+		return
+	}
+	if *metricsPort > 0 {
+		bot.ServeMetrics(fmt.Sprintf(":%d", *metricsPort))
+	}
+	if *interval > 0 {
+		ctx, stop := signal.NotifyContext(context.Background(), os.Interrupt, syscall.SIGTERM)
+		defer stop()
+		bot.RunLoop(ctx, time.Duration(*interval)*time.Second, func(ctx context.Context) error {
+			return bot.RunOnce(ctx, cfg, opt)
+		})
+		return
+	}
+	if err := bot.RunOnce(context.Background(), cfg, opt); err != nil {
+		fmt.Fprintln(os.Stderr, err)
+		os.Exit(1)
+	}
+}
 
-	cfg, err := bot.ConfigFromEnv(*dryRun, *skipMongo)
+// listHistory runs the list-history subcommand: it prints the latest posts
+// from history and exits without fetching or posting.
+func listHistory(args []string) {
+	fs := flag.NewFlagSet("list-history", flag.ExitOnError)
+	configPath := fs.String("config", "", "optional TOML config file; env vars take precedence over its values")
+	limit := fs.Int("limit", 10, "how many posts to list, newest first")
+	format := fs.String("format", bot.HistoryFormatTable, "table prints created_at, new coin symbols and a text preview; json prints the full history documents")
+	fs.Parse(args)
+	if err := bot.SetupLogging(os.Getenv("LOG_FORMAT")); err != nil {
+		fmt.Fprintln(os.Stderr, err)
+		os.Exit(1)
+	}
+	if *limit <= 0 {
+		fmt.Fprintln(os.Stderr, "--limit must be > 0")
+		os.Exit(1)
+	}
+	cfg, err := loadReadOnlyConfig(*configPath)
+	if err == nil {
+		err = bot.ListHistory(context.Background(), cfg, *limit, *format, os.Stdout)
+	}
 	if err != nil {
 		fmt.Fprintln(os.Stderr, err)
 		os.Exit(1)
 	}
-	if err := bot.RunOnce(context.Background(), cfg, bot.RunOptions{DryRun: *dryRun, NotifyExits: *notifyExits, Convert: *convert, SkipMongo: *skipMongo, TestMessage: *testMessage, TestImageURL: *testImageURL}); err != nil {
+}
+
+// compare runs the compare subcommand: it diffs the snapshots nearest to
+// --from and --to and prints the report without fetching or posting.
+func compare(args []string) {
+	fs := flag.NewFlagSet("compare", flag.ExitOnError)
+	configPath := fs.String("config", "", "optional TOML config file; env vars take precedence over its values")
+	fromRaw := fs.String("from", "", "start of the comparison: a date (2006-01-02, end of that day UTC) or an RFC 3339 timestamp")
+	toRaw := fs.String("to", "", "end of the comparison, same formats as --from")
+	output := fs.String("output", bot.CompareOutputText, "text prints a readable report; json prints entered, exited and moved coins")
+	fs.Parse(args)
+	if err := bot.SetupLogging(os.Getenv("LOG_FORMAT")); err != nil {
 		fmt.Fprintln(os.Stderr, err)
 		os.Exit(1)
 	}
+	if *fromRaw == "" || *toRaw == "" {
+		fmt.Fprintln(os.Stderr, "compare requires --from and --to")
+		os.Exit(1)
+	}
+	from, err := bot.ParseCompareTime(*fromRaw)
+	if err != nil {
+		fmt.Fprintln(os.Stderr, "--from:", err)
+		os.Exit(1)
+	}
+	to, err := bot.ParseCompareTime(*toRaw)
+	if err != nil {
+		fmt.Fprintln(os.Stderr, "--to:", err)
+		os.Exit(1)
+	}
+	cfg, err := loadReadOnlyConfig(*configPath)
+	if err == nil {
+		err = bot.Compare(context.Background(), cfg, from, to, *output, os.Stdout)
+	}
+	if err != nil {
+		fmt.Fprintln(os.Stderr, err)
+		os.Exit(1)
+	}
+}
+
+// showState runs the show-state subcommand: it prints the stored state and
+// exits with an error when none has been written yet.
+func showState(args []string) {
+	fs := flag.NewFlagSet("show-state", flag.ExitOnError)
+	configPath := fs.String("config", "", "optional TOML config file; env vars take precedence over its values")
+	format := fs.String("format", bot.StateFormatText, "text prints the metadata and a rank | symbol | name | market_cap table; json prints the whole state; csv prints RFC 4180 rows")
+	fs.Parse(args)
+	if err := bot.SetupLogging(os.Getenv("LOG_FORMAT")); err != nil {
+		fmt.Fprintln(os.Stderr, err)
+		os.Exit(1)
+	}
+	cfg, err := loadReadOnlyConfig(*configPath)
+	if err == nil {
+		err = bot.ShowState(context.Background(), cfg, *format, os.Stdout)
+	}
+	if err != nil {
+		fmt.Fprintln(os.Stderr, err)
+		os.Exit(1)
+	}
+}
+
+// loadReadOnlyConfig loads the config of a subcommand that only reads the
+// store, so the Telegram env vars are not required.
+func loadReadOnlyConfig(configPath string) (bot.Config, error) {
+	if configPath != "" {
+		return bot.ConfigFromFile(configPath, true, false)
+	}
+	return bot.ConfigFromEnv(true, false)
 }
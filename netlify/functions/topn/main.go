@@ -3,10 +3,12 @@ package main
 import (
 	"context"
 	_ "embed"
+	"encoding/base64"
 	"encoding/json"
 	"fmt"
 	"log"
 	"os"
+	"strings"
 
 	"coinmarketcap_top100_bot/bot"
 	"github.com/aws/aws-lambda-go/events"
@@ -37,6 +39,22 @@ func handler(ctx context.Context, req events.APIGatewayProxyRequest) (events.API
 		}, nil
 	}
 
+	if req.HTTPMethod == "GET" && req.Path == "/health" {
+		return healthResponse(), nil
+	}
+
+	if req.HTTPMethod == "GET" && req.Path == "/metrics" {
+		var body strings.Builder
+		if err := bot.Metrics.WritePrometheus(&body); err != nil {
+			return jsonError(500, err), nil
+		}
+		return events.APIGatewayProxyResponse{
+			StatusCode: 200,
+			Headers:    map[string]string{"Content-Type": "text/plain; version=0.0.4; charset=utf-8"},
+			Body:       body.String(),
+		}, nil
+	}
+
 	if req.HTTPMethod == "POST" && req.Path == "/api/v1/tick" {
 		cfg, err := bot.ConfigFromEnv(false, false)
 		if err != nil {
@@ -58,13 +76,20 @@ func handler(ctx context.Context, req events.APIGatewayProxyRequest) (events.API
 		return events.APIGatewayProxyResponse{StatusCode: 200, Headers: map[string]string{"Content-Type": "application/json"}, Body: string(body)}, nil
 	}
 
+	if !isScheduledInvocation(req) {
+		log.Printf("[topn.handler] no route for %s %s; not running the job", req.HTTPMethod, req.Path)
+		return jsonError(404, fmt.Errorf("no route for %s %s", req.HTTPMethod, req.Path)), nil
+	}
+
 	log.Printf("[topn.handler] invocation started")
-	log.Printf("[topn.handler] env presence: CMC_API_KEY=%t TELEGRAM_COINMARKETCAP_TOP_100_BOT_TOKEN=%t TELEGRAM_COINMARKETCAP_TOP_100_CHANNEL_ID=%t MONGODB_CONNECTION_STRING=%t GEMINI_API_KEY=%t",
+	log.Printf("[topn.handler] env presence: CMC_API_KEY=%t TELEGRAM_COINMARKETCAP_TOP_100_BOT_TOKEN=%t TELEGRAM_COINMARKETCAP_TOP_100_CHANNEL_ID=%t MONGODB_CONNECTION_STRING=%t GEMINI_API_KEY=%t OPENAI_API_KEY=%t ANTHROPIC_API_KEY=%t",
 		os.Getenv("CMC_API_KEY") != "",
 		os.Getenv("TELEGRAM_COINMARKETCAP_TOP_100_BOT_TOKEN") != "",
 		os.Getenv("TELEGRAM_COINMARKETCAP_TOP_100_CHANNEL_ID") != "",
 		os.Getenv("MONGODB_CONNECTION_STRING") != "",
 		os.Getenv("GEMINI_API_KEY") != "",
+		os.Getenv("OPENAI_API_KEY") != "",
+		os.Getenv("ANTHROPIC_API_KEY") != "",
 	)
 
 	cfg, err := bot.ConfigFromEnv(false, false)
@@ -77,8 +102,13 @@ func handler(ctx context.Context, req events.APIGatewayProxyRequest) (events.API
 	if convert == "" {
 		convert = "USD"
 	}
+	primaryConvert, extraConverts, err := bot.ParseConverts(convert)
+	if err != nil {
+		log.Printf("[topn.handler] invalid CONVERT: %v", err)
+		return events.APIGatewayProxyResponse{StatusCode: 500, Body: err.Error()}, nil
+	}
 	log.Printf("[topn.handler] executing RunOnce with convert=%s", convert)
-	if err := bot.RunOnce(ctx, cfg, bot.RunOptions{DryRun: false, NotifyExits: false, Convert: convert}); err != nil {
+	if err := runOnce(ctx, cfg, bot.RunOptions{DryRun: false, NotifyExits: false, Convert: primaryConvert, ExtraConverts: extraConverts}); err != nil {
 		log.Printf("[topn.handler] RunOnce failed: %v", err)
 		return events.APIGatewayProxyResponse{StatusCode: 500, Body: err.Error()}, nil
 	}
@@ -86,6 +116,46 @@ func handler(ctx context.Context, req events.APIGatewayProxyRequest) (events.API
 	return events.APIGatewayProxyResponse{StatusCode: 200, Body: "ok"}, nil
 }
 
+// runOnce runs the job; tests replace it.
+var runOnce = bot.RunOnce
+
+// scheduledPaths are the paths the scheduler invokes the function with.
+var scheduledPaths = map[string]bool{"": true, "/": true, "/.netlify/functions/topn": true}
+
+// isScheduledInvocation reports whether req comes from the scheduler and so
+// runs the job: one of scheduledPaths, or a POST whose JSON body carries the
+// next_run Netlify sends with every scheduled invocation, whatever the path.
+func isScheduledInvocation(req events.APIGatewayProxyRequest) bool {
+	if scheduledPaths[req.Path] {
+		return true
+	}
+	if req.HTTPMethod != "POST" {
+		return false
+	}
+	body := []byte(req.Body)
+	if req.IsBase64Encoded {
+		decoded, err := base64.StdEncoding.DecodeString(req.Body)
+		if err != nil {
+			return false
+		}
+		body = decoded
+	}
+	var event struct {
+		NextRun string `json:"next_run"`
+	}
+	return json.Unmarshal(body, &event) == nil && event.NextRun != ""
+}
+
+// healthResponse validates the env config without calling CMC, Mongo or
+// Telegram: 200 when it loads, 503 with the reason otherwise.
+func healthResponse() events.APIGatewayProxyResponse {
+	if _, err := bot.ConfigFromEnv(false, false); err != nil {
+		body, _ := json.Marshal(map[string]string{"status": "unhealthy", "error": err.Error()})
+		return events.APIGatewayProxyResponse{StatusCode: 503, Headers: map[string]string{"Content-Type": "application/json"}, Body: string(body)}
+	}
+	return events.APIGatewayProxyResponse{StatusCode: 200, Headers: map[string]string{"Content-Type": "application/json"}, Body: `{"status":"ok"}`}
+}
+
 func readSwaggerSpec() ([]byte, error) {
 	if len(embeddedSwaggerSpec) > 0 {
 		return embeddedSwaggerSpec, nil
@@ -134,5 +204,8 @@ func jsonError(status int, err error) events.APIGatewayProxyResponse {
 }
 
 func main() {
+	if err := bot.SetupLogging(os.Getenv("LOG_FORMAT")); err != nil {
+		log.Fatalf("[topn.main] %v", err)
+	}
 	lambda.Start(handler)
 }
@@ -5,6 +5,7 @@ import (
 	"strings"
 	"testing"
 
+	"coinmarketcap_top100_bot/bot"
 	"github.com/aws/aws-lambda-go/events"
 )
 
@@ -39,3 +40,74 @@ func TestHandlerSwaggerEndpoint(t *testing.T) {
 		t.Fatalf("swagger response does not look like OpenAPI json")
 	}
 }
+
+func TestHandlerMetricsEndpoint(t *testing.T) {
+	resp, err := handler(context.Background(), events.APIGatewayProxyRequest{HTTPMethod: "GET", Path: "/metrics"})
+	if err != nil {
+		t.Fatalf("handler returned error: %v", err)
+	}
+	if resp.StatusCode != 200 || !strings.HasPrefix(resp.Headers["Content-Type"], "text/plain") {
+		t.Fatalf("unexpected response: %d %v", resp.StatusCode, resp.Headers)
+	}
+	if !strings.Contains(resp.Body, "# TYPE ai_calls_total counter") {
+		t.Fatalf("metrics body is missing ai_calls_total: %s", resp.Body)
+	}
+}
+
+func TestHandlerHealthEndpoint(t *testing.T) {
+	t.Setenv("CMC_API_KEY", "test")
+	t.Setenv("TELEGRAM_COINMARKETCAP_TOP_100_BOT_TOKEN", "test")
+	t.Setenv("TELEGRAM_COINMARKETCAP_TOP_100_CHANNEL_ID", "@test")
+	t.Setenv("MONGODB_CONNECTION_STRING", "mongodb://localhost:27017")
+
+	resp, err := handler(context.Background(), events.APIGatewayProxyRequest{HTTPMethod: "GET", Path: "/health"})
+	if err != nil {
+		t.Fatalf("handler returned error: %v", err)
+	}
+	if resp.StatusCode != 200 || resp.Body != `{"status":"ok"}` {
+		t.Fatalf("unexpected health response: %d %s", resp.StatusCode, resp.Body)
+	}
+
+	t.Setenv("CMC_API_KEY", "")
+	resp, _ = handler(context.Background(), events.APIGatewayProxyRequest{HTTPMethod: "GET", Path: "/health"})
+	if resp.StatusCode != 503 || !strings.Contains(resp.Body, "CMC_API_KEY") {
+		t.Fatalf("expected 503 naming the missing var, got %d %s", resp.StatusCode, resp.Body)
+	}
+}
+
+func TestHandlerUnknownPathDoesNotRunJob(t *testing.T) {
+	resp, err := handler(context.Background(), events.APIGatewayProxyRequest{HTTPMethod: "GET", Path: "/favicon.ico"})
+	if err != nil {
+		t.Fatalf("handler returned error: %v", err)
+	}
+	if resp.StatusCode != 404 {
+		t.Fatalf("unexpected status: %d, body: %s", resp.StatusCode, resp.Body)
+	}
+}
+
+func TestHandlerScheduledEventRunsJob(t *testing.T) {
+	t.Setenv("CMC_API_KEY", "test")
+	t.Setenv("TELEGRAM_COINMARKETCAP_TOP_100_BOT_TOKEN", "test")
+	t.Setenv("TELEGRAM_COINMARKETCAP_TOP_100_CHANNEL_ID", "@test")
+	t.Setenv("MONGODB_CONNECTION_STRING", "mongodb://localhost:27017")
+	runs := 0
+	runOnce = func(ctx context.Context, cfg bot.Config, opt bot.RunOptions) error {
+		runs++
+		return nil
+	}
+	t.Cleanup(func() { runOnce = bot.RunOnce })
+
+	req := events.APIGatewayProxyRequest{HTTPMethod: "POST", Path: "/.netlify/functions/topn-background", Body: `{"next_run":"2026-10-16T12:05:00.000Z"}`}
+	resp, err := handler(context.Background(), req)
+	if err != nil {
+		t.Fatalf("handler returned error: %v", err)
+	}
+	if resp.StatusCode != 200 || runs != 1 {
+		t.Fatalf("expected the scheduled event to run the job once, got %d runs and %d %s", runs, resp.StatusCode, resp.Body)
+	}
+
+	req.Body = `{"hello":"world"}`
+	if resp, _ := handler(context.Background(), req); resp.StatusCode != 404 || runs != 1 {
+		t.Fatalf("a POST without next_run must not run the job, got %d runs and status %d", runs, resp.StatusCode)
+	}
+}